@@ -6,6 +6,7 @@ use axum::{
     extract::FromRequestParts,
     http::{header::AUTHORIZATION, request::Parts},
 };
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
 use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey, Header, Validation, decode, encode};
 
 /// Secret key for HS256 (for demonstration purposes only)
@@ -68,6 +69,65 @@ pub fn validate_token_vulnerable(token: &str) -> Result<UserClaims, AppError> {
     Ok(token_data.claims)
 }
 
+/// Algorithms the server is willing to verify; anything else is rejected before decoding
+pub const ALLOWED_ALGORITHMS: &[Algorithm] = &[Algorithm::HS256];
+
+/// Read the `alg` field from a token's header without verifying anything
+fn raw_header_alg(token: &str) -> Result<String, AppError> {
+    let header_segment = token.split('.').next().ok_or(AppError::Unauthorized)?;
+    let header_bytes = URL_SAFE_NO_PAD
+        .decode(header_segment)
+        .map_err(|_| AppError::Unauthorized)?;
+    let header: serde_json::Value =
+        serde_json::from_slice(&header_bytes).map_err(|_| AppError::Unauthorized)?;
+
+    header
+        .get("alg")
+        .and_then(|alg| alg.as_str())
+        .map(str::to_string)
+        .ok_or(AppError::Unauthorized)
+}
+
+/// Build an unsigned token (`alg: none`, empty signature) as an attacker would
+pub fn craft_unsigned_token(claims: &UserClaims) -> Result<String, AppError> {
+    let header = URL_SAFE_NO_PAD.encode(br#"{"alg":"none","typ":"JWT"}"#);
+    let payload = serde_json::to_vec(claims)
+        .map_err(|e| AppError::Internal(format!("Failed to encode claims: {}", e)))?;
+    Ok(format!("{}.{}.", header, URL_SAFE_NO_PAD.encode(payload)))
+}
+
+/// Validate a JWT token that trusts the header's `alg` (vulnerable version)
+/// This demonstrates the `alg: none` attack - unsigned tokens are accepted as-is
+pub fn validate_token_alg_none_vulnerable(token: &str) -> Result<UserClaims, AppError> {
+    // VULNERABLE: The token decides how (and whether) it gets verified
+    if raw_header_alg(token)?.eq_ignore_ascii_case("none") {
+        let payload_segment = token.split('.').nth(1).ok_or(AppError::Unauthorized)?;
+        let payload = URL_SAFE_NO_PAD
+            .decode(payload_segment)
+            .map_err(|_| AppError::Unauthorized)?;
+        return serde_json::from_slice(&payload).map_err(|_| AppError::Unauthorized);
+    }
+
+    validate_token_hs256(token)
+}
+
+/// Validate a JWT token that explicitly forbids `alg: none` (secure version)
+/// The algorithm must be on the server-side allowlist and the signature is always verified
+pub fn validate_token_rejecting_none(token: &str) -> Result<UserClaims, AppError> {
+    let alg = raw_header_alg(token)?;
+    if alg.eq_ignore_ascii_case("none") {
+        tracing::warn!("Rejected unsigned token (alg: {})", alg);
+        return Err(AppError::Unauthorized);
+    }
+
+    let alg: Algorithm = alg.parse().map_err(|_| AppError::Unauthorized)?;
+    if !ALLOWED_ALGORITHMS.contains(&alg) {
+        return Err(AppError::Unauthorized);
+    }
+
+    validate_token_hs256(token)
+}
+
 /// Extractor for authenticated user claims (secure version)
 #[derive(Debug, Clone)]
 pub struct AuthenticatedUser(pub UserClaims);
@@ -175,6 +235,57 @@ mod tests {
         assert_eq!(validated.sub, "user123");
     }
 
+    fn valid_claims() -> UserClaims {
+        UserClaims {
+            sub: "attacker".to_string(),
+            permissions: vec!["read".to_string(), "admin".to_string()],
+            exp: (chrono::Utc::now() + chrono::Duration::hours(1)).timestamp() as usize,
+            iat: chrono::Utc::now().timestamp() as usize,
+            aud: Some(JWT_AUDIENCE.to_string()),
+            iss: Some(JWT_ISSUER.to_string()),
+        }
+    }
+
+    #[test]
+    fn test_alg_none_token_accepted_by_vulnerable_validator() {
+        let token = craft_unsigned_token(&valid_claims()).unwrap();
+        assert!(token.ends_with('.'));
+
+        let claims = validate_token_alg_none_vulnerable(&token).unwrap();
+        assert_eq!(claims.sub, "attacker");
+        assert!(is_admin(&claims));
+    }
+
+    #[test]
+    fn test_alg_none_token_rejected_by_secure_validator() {
+        let token = craft_unsigned_token(&valid_claims()).unwrap();
+        assert!(matches!(
+            validate_token_rejecting_none(&token),
+            Err(AppError::Unauthorized)
+        ));
+
+        // Case variations and a bogus signature segment are rejected as well
+        let payload = URL_SAFE_NO_PAD.encode(serde_json::to_vec(&valid_claims()).unwrap());
+        for alg in ["None", "NONE", "nOnE"] {
+            let header = URL_SAFE_NO_PAD.encode(format!(r#"{{"alg":"{}","typ":"JWT"}}"#, alg));
+            let token = format!("{}.{}.", header, payload);
+            assert!(validate_token_alg_none_vulnerable(&token).is_ok());
+            assert!(validate_token_rejecting_none(&token).is_err());
+
+            let token = format!("{}.{}.c2lnbmF0dXJl", header, payload);
+            assert!(validate_token_rejecting_none(&token).is_err());
+        }
+    }
+
+    #[test]
+    fn test_secure_validator_accepts_signed_token() {
+        let token = generate_token_hs256(&valid_claims()).unwrap();
+        assert_eq!(
+            validate_token_rejecting_none(&token).unwrap().sub,
+            "attacker"
+        );
+    }
+
     #[test]
     fn test_extract_bearer_token() {
         assert_eq!(extract_bearer_token("Bearer abc123"), Some("abc123"));
@@ -20,9 +20,19 @@
 //!
 //!   # Test secure endpoint (rejects invalid tokens)
 //!   curl -H "Authorization: Bearer <expired_token>" http://localhost:8080/validate
+//!
+//!   # Forge an unsigned `alg: none` token with admin permissions
+//!   curl http://localhost:8080/token/alg-none
+//!
+//!   # Vulnerable endpoint trusts the header's alg and accepts it; secure one rejects it
+//!   curl -H "Authorization: Bearer <none_token>" http://localhost:8080/vulnerable/validate/alg-none
+//!   curl -H "Authorization: Bearer <none_token>" http://localhost:8080/validate/alg-none
 
 use api_security_demo::{
-    auth::{JWT_AUDIENCE, JWT_ISSUER, JWT_SECRET},
+    auth::{
+        JWT_AUDIENCE, JWT_ISSUER, JWT_SECRET, craft_unsigned_token,
+        validate_token_alg_none_vulnerable, validate_token_rejecting_none,
+    },
     error::AppError,
     models::UserClaims,
 };
@@ -64,6 +74,12 @@ async fn main() {
         .route("/vulnerable/validate", get(vulnerable_validate_token))
         // Secure validation endpoint
         .route("/validate", get(secure_validate_token))
+        // `alg: none` attack pair
+        .route(
+            "/vulnerable/validate/alg-none",
+            get(vulnerable_validate_alg_none),
+        )
+        .route("/validate/alg-none", get(secure_validate_alg_none))
         // Subtle vulnerabilities
         .route("/subtle/validate/alg-confusion", get(subtle_alg_confusion))
         .route("/subtle/validate/kid-injection", get(subtle_kid_injection))
@@ -83,10 +99,13 @@ async fn main() {
     tracing::info!("  GET /token/expired         - Expired token (1 hour ago)");
     tracing::info!("  GET /token/wrong-audience  - Token with wrong audience");
     tracing::info!("  GET /token/wrong-issuer    - Token with wrong issuer");
+    tracing::info!("  GET /token/alg-none        - Unsigned admin token (alg: none)");
     tracing::info!("");
     tracing::info!("Validation endpoints:");
     tracing::info!("  GET /vulnerable/validate   - VULNERABLE: Only checks signature");
     tracing::info!("  GET /validate              - SECURE: Checks all claims");
+    tracing::info!("  GET /vulnerable/validate/alg-none - VULNERABLE: Accepts unsigned tokens");
+    tracing::info!("  GET /validate/alg-none            - SECURE: Forbids alg none");
     tracing::info!("");
     tracing::info!("Subtle vulnerability endpoints:");
     tracing::info!("  GET /subtle/validate/alg-confusion - Algorithm confusion attack");
@@ -144,9 +163,28 @@ async fn generate_test_token(Path(token_type): Path<String>) -> Result<Json<Toke
             };
             (claims, "Token with wrong issuer")
         }
+        "alg-none" => {
+            let claims = UserClaims {
+                sub: "attacker".to_string(),
+                permissions: vec!["read".to_string(), "admin".to_string()], // Escalated!
+                exp: (Utc::now() + Duration::hours(1)).timestamp() as usize,
+                iat: Utc::now().timestamp() as usize,
+                aud: Some(JWT_AUDIENCE.to_string()),
+                iss: Some(JWT_ISSUER.to_string()),
+            };
+
+            // No secret needed - the token is simply not signed
+            return Ok(Json(TokenInfo {
+                access_token: craft_unsigned_token(&claims)?,
+                token_type: "Bearer".to_string(),
+                description: "Unsigned token (alg: none) with forged admin permission".to_string(),
+                claims,
+            }));
+        }
         _ => {
             return Err(AppError::BadRequest(
-                "Unknown token type. Use: valid, expired, wrong-audience, wrong-issuer".to_string(),
+                "Unknown token type. Use: valid, expired, wrong-audience, wrong-issuer, alg-none"
+                    .to_string(),
             ));
         }
     };
@@ -300,6 +338,73 @@ async fn secure_validate_token(
     }
 }
 
+/// VULNERABLE: Lets the token header choose the verification algorithm
+///
+/// - `alg: none` tokens are accepted without any signature ✗
+/// - Anyone can forge arbitrary claims (e.g. admin permission) ✗
+async fn vulnerable_validate_alg_none(
+    headers: axum::http::HeaderMap,
+) -> Result<Json<TokenValidationResponse>, AppError> {
+    let token = bearer_token(&headers)?;
+
+    tracing::warn!("VULNERABLE validation - trusting the token's alg header!");
+
+    let claims = validate_token_alg_none_vulnerable(token)?;
+    // jsonwebtoken cannot even parse `alg: none`, so only signed tokens decode here
+    let signature_valid = jsonwebtoken::decode_header(token).is_ok();
+
+    tracing::warn!(
+        sub = claims.sub,
+        signature_valid,
+        "VULNERABLE: Accepted token whose algorithm came from the token itself"
+    );
+
+    Ok(Json(TokenValidationResponse {
+        valid: true,
+        claims: Some(claims),
+        validation_checks: ValidationChecks {
+            signature_valid,
+            expiration_checked: signature_valid,
+            audience_checked: signature_valid,
+            issuer_checked: signature_valid,
+        },
+    }))
+}
+
+/// SECURE: Explicitly forbids `alg: none` and requires an allowlisted algorithm
+///
+/// - Rejects unsigned tokens regardless of `none` casing ✓
+/// - Verifies the signature with a server-chosen algorithm ✓
+/// - Checks expiration, audience and issuer ✓
+async fn secure_validate_alg_none(
+    headers: axum::http::HeaderMap,
+) -> Result<Json<TokenValidationResponse>, AppError> {
+    let token = bearer_token(&headers)?;
+
+    tracing::info!("Secure validation - alg none forbidden, HS256 required");
+
+    let claims = validate_token_rejecting_none(token)?;
+
+    Ok(Json(TokenValidationResponse {
+        valid: true,
+        claims: Some(claims),
+        validation_checks: ValidationChecks {
+            signature_valid: true,
+            expiration_checked: true,
+            audience_checked: true,
+            issuer_checked: true,
+        },
+    }))
+}
+
+fn bearer_token(headers: &axum::http::HeaderMap) -> Result<&str, AppError> {
+    headers
+        .get(AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .ok_or(AppError::Unauthorized)
+}
+
 // ============ SUBTLE VULNERABILITIES ============
 
 /// SUBTLE VULNERABILITY #1: Algorithm Confusion
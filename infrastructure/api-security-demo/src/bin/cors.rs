@@ -0,0 +1,88 @@
+//! CORS Misconfiguration Demonstration
+//!
+//! This example demonstrates:
+//! - Vulnerable endpoint: Reflects any `Origin` and allows credentials, so a
+//!   malicious site can read the victim's data using their session cookie
+//! - Secure endpoint: Only answers an explicit origin allowlist and never
+//!   combines credentials with a wildcard
+//!
+//! Run: cargo run --bin cors-demo
+//! Test:
+//!   # Vulnerable: The attacker's origin is echoed back with credentials allowed
+//!   curl -i http://localhost:8080/vulnerable/profile \
+//!     -H "Origin: https://evil.example" -H "Cookie: session=victim"
+//!
+//!   # Secure: No Access-Control-Allow-Origin for an unknown origin
+//!   curl -i http://localhost:8080/profile \
+//!     -H "Origin: https://evil.example" -H "Cookie: session=victim"
+//!
+//!   # Secure: Preflight from an allowed origin succeeds
+//!   curl -i -X OPTIONS http://localhost:8080/profile \
+//!     -H "Origin: http://localhost:3000" \
+//!     -H "Access-Control-Request-Method: GET"
+
+use api_security_demo::cors::{secure_cors_layer, vulnerable_cors_layer};
+use axum::{Json, Router, routing::get};
+use serde::Serialize;
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+
+/// Frontends that may call the API with the user's cookies
+const ALLOWED_ORIGINS: &[&str] = &["http://localhost:3000", "https://app.example.com"];
+
+#[derive(Serialize)]
+struct Profile {
+    user_id: &'static str,
+    email: &'static str,
+    api_key: &'static str,
+}
+
+#[tokio::main]
+async fn main() {
+    tracing_subscriber::registry()
+        .with(
+            tracing_subscriber::EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| "cors_demo=debug,tower_http=debug".into()),
+        )
+        .with(tracing_subscriber::fmt::layer())
+        .init();
+
+    let app = create_app();
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:8080")
+        .await
+        .unwrap();
+
+    tracing::info!("CORS demonstration server running on http://127.0.0.1:8080");
+    tracing::info!("");
+    tracing::info!("Available endpoints:");
+    tracing::info!("  GET /vulnerable/profile  - VULNERABLE: Reflects any origin with credentials");
+    tracing::info!("  GET /profile             - SECURE: Origin allowlist");
+    tracing::info!("");
+    tracing::info!("Allowed origins:");
+    for origin in ALLOWED_ORIGINS {
+        tracing::info!("  - {}", origin);
+    }
+
+    axum::serve(listener, app).await.unwrap();
+}
+
+fn create_app() -> Router {
+    // Layers only wrap routes added before them, so each policy gets its own router
+    let vulnerable = Router::new()
+        .route("/vulnerable/profile", get(profile))
+        .layer(vulnerable_cors_layer());
+    let secure = Router::new()
+        .route("/profile", get(profile))
+        .layer(secure_cors_layer(ALLOWED_ORIGINS).expect("ALLOWED_ORIGINS must be valid origins"));
+
+    vulnerable.merge(secure)
+}
+
+/// Sensitive data that a cookie-authenticated user would see
+async fn profile() -> Json<Profile> {
+    Json(Profile {
+        user_id: "alice",
+        email: "alice@example.com",
+        api_key: "sk_live_demo_1234",
+    })
+}
@@ -0,0 +1,472 @@
+//! File Upload Validation Demonstration
+//!
+//! This example demonstrates:
+//! - Vulnerable endpoint: Trusts the client-provided filename and content type,
+//!   has no size limit, and writes wherever the filename points
+//! - Secure endpoint: Detects the file type from its magic bytes, sanitizes the
+//!   filename, enforces a size cap, and only writes inside the upload directory
+//!
+//! Run: cargo run --bin file-upload-demo
+//! Test:
+//!   # Vulnerable: Path traversal writes outside the upload directory
+//!   curl -X POST http://localhost:8080/vulnerable/upload \
+//!     -F 'file=@shell.php;filename=../../tmp/shell.php;type=image/png'
+//!
+//!   # Secure: A script disguised as a PNG is rejected
+//!   curl -X POST http://localhost:8080/upload \
+//!     -F 'file=@shell.php;filename=avatar.png;type=image/png'
+//!
+//!   # Secure: A real PNG is stored under a sanitized name
+//!   curl -X POST http://localhost:8080/upload \
+//!     -F 'file=@avatar.png;filename=../../avatar.png'
+
+use api_security_demo::error::AppError;
+use axum::{
+    Json, Router,
+    extract::{DefaultBodyLimit, Multipart, State, multipart::Field},
+    http::StatusCode,
+    routing::post,
+};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+use uuid::Uuid;
+
+/// Directory that uploaded files are written to
+const UPLOAD_DIR: &str = "uploads";
+
+/// Maximum size of an uploaded file (1 MiB)
+const MAX_UPLOAD_SIZE: usize = 1024 * 1024;
+
+/// Request body limit for the secure endpoint (file plus multipart headers)
+const MAX_REQUEST_SIZE: usize = MAX_UPLOAD_SIZE + 16 * 1024;
+
+/// Maximum length of the sanitized filename stem
+const MAX_STEM_LENGTH: usize = 64;
+
+/// File types accepted by the secure endpoint, identified by their magic bytes
+const ALLOWED_FILE_TYPES: &[FileType] = &[
+    FileType {
+        magic: b"\x89PNG\r\n\x1a\n",
+        extension: "png",
+        mime: "image/png",
+    },
+    FileType {
+        magic: b"\xff\xd8\xff",
+        extension: "jpg",
+        mime: "image/jpeg",
+    },
+    FileType {
+        magic: b"%PDF-",
+        extension: "pdf",
+        mime: "application/pdf",
+    },
+];
+
+struct FileType {
+    magic: &'static [u8],
+    extension: &'static str,
+    mime: &'static str,
+}
+
+#[derive(Clone)]
+struct AppState {
+    upload_dir: PathBuf,
+}
+
+#[tokio::main]
+async fn main() {
+    tracing_subscriber::registry()
+        .with(
+            tracing_subscriber::EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| "file_upload_demo=debug,tower_http=debug".into()),
+        )
+        .with(tracing_subscriber::fmt::layer())
+        .init();
+
+    let app = create_app(AppState {
+        upload_dir: PathBuf::from(UPLOAD_DIR),
+    });
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:8080")
+        .await
+        .unwrap();
+
+    tracing::info!("File upload demonstration server running on http://127.0.0.1:8080");
+    tracing::info!("");
+    tracing::info!("Available endpoints:");
+    tracing::info!("  POST /vulnerable/upload  - VULNERABLE: Trusts filename and content type");
+    tracing::info!("  POST /upload             - SECURE: Validates content, name and size");
+    tracing::info!("");
+    tracing::info!("Upload directory: {}", UPLOAD_DIR);
+    tracing::info!("Allowed file types:");
+    for file_type in ALLOWED_FILE_TYPES {
+        tracing::info!("  - {} (.{})", file_type.mime, file_type.extension);
+    }
+
+    axum::serve(listener, app).await.unwrap();
+}
+
+fn create_app(state: AppState) -> Router {
+    Router::new()
+        // Vulnerable endpoint - no validation, no size limit
+        .route(
+            "/vulnerable/upload",
+            post(vulnerable_upload).layer(DefaultBodyLimit::disable()),
+        )
+        // Secure endpoint - content, name and size validation
+        .route(
+            "/upload",
+            post(secure_upload).layer(DefaultBodyLimit::max(MAX_REQUEST_SIZE)),
+        )
+        .with_state(state)
+}
+
+#[derive(Serialize)]
+struct UploadResponse {
+    file_name: String,
+    content_type: String,
+    size: usize,
+    validated: bool,
+}
+
+/// Return the first multipart field named `file`
+async fn file_field(multipart: &mut Multipart) -> Result<Field<'_>, AppError> {
+    while let Some(field) = multipart.next_field().await.map_err(multipart_error)? {
+        if field.name() == Some("file") {
+            return Ok(field);
+        }
+    }
+    Err(AppError::BadRequest("Missing 'file' field".to_string()))
+}
+
+fn multipart_error(e: axum::extract::multipart::MultipartError) -> AppError {
+    if e.status() == StatusCode::PAYLOAD_TOO_LARGE {
+        AppError::PayloadTooLarge(format!("Uploads are limited to {} bytes", MAX_UPLOAD_SIZE))
+    } else {
+        AppError::BadRequest(format!("Invalid multipart body: {}", e))
+    }
+}
+
+/// VULNERABLE: Stores the upload exactly as the client describes it
+///
+/// An attacker can:
+/// - Write outside the upload directory with `../` or an absolute filename
+/// - Upload a script or HTML page labelled as `image/png`
+/// - Exhaust disk space with an arbitrarily large file
+async fn vulnerable_upload(
+    State(state): State<AppState>,
+    mut multipart: Multipart,
+) -> Result<Json<UploadResponse>, AppError> {
+    let field = file_field(&mut multipart).await?;
+
+    // VULNERABLE: Client-controlled filename and content type are trusted
+    let file_name = field.file_name().unwrap_or("upload").to_string();
+    let content_type = field
+        .content_type()
+        .unwrap_or("application/octet-stream")
+        .to_string();
+    let data = field.bytes().await.map_err(multipart_error)?;
+
+    // VULNERABLE: Joining an untrusted name lets it escape the directory
+    let path = state.upload_dir.join(&file_name);
+    tracing::warn!(
+        file_name = file_name,
+        path = %path.display(),
+        content_type = content_type,
+        "VULNERABLE: Writing upload to client-controlled path!"
+    );
+
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent)
+            .await
+            .map_err(|e| AppError::Internal(format!("Failed to create directory: {}", e)))?;
+    }
+    tokio::fs::write(&path, &data)
+        .await
+        .map_err(|e| AppError::Internal(format!("Failed to write file: {}", e)))?;
+
+    Ok(Json(UploadResponse {
+        file_name: path.display().to_string(),
+        content_type,
+        size: data.len(),
+        validated: false,
+    }))
+}
+
+/// SECURE: Validates the upload before storing it
+///
+/// This demonstrates proper upload handling:
+/// - Enforces a size cap while reading, not after buffering everything
+/// - Detects the type from the magic bytes and checks it against an allowlist
+/// - Ignores the client's content type and extension
+/// - Stores under a generated, sanitized name inside a fixed directory
+async fn secure_upload(
+    State(state): State<AppState>,
+    mut multipart: Multipart,
+) -> Result<Json<UploadResponse>, AppError> {
+    let mut field = file_field(&mut multipart).await?;
+    let original_name = field.file_name().unwrap_or_default().to_string();
+
+    let mut data = Vec::new();
+    while let Some(chunk) = field.chunk().await.map_err(multipart_error)? {
+        if data.len() + chunk.len() > MAX_UPLOAD_SIZE {
+            tracing::warn!(
+                file_name = original_name,
+                "Blocked: Upload exceeds size limit"
+            );
+            return Err(AppError::PayloadTooLarge(format!(
+                "Uploads are limited to {} bytes",
+                MAX_UPLOAD_SIZE
+            )));
+        }
+        data.extend_from_slice(&chunk);
+    }
+
+    let Some(file_type) = detect_file_type(&data) else {
+        tracing::warn!(
+            file_name = original_name,
+            claimed_type = field.content_type(),
+            "Blocked: File content does not match an allowed type"
+        );
+        return Err(AppError::BadRequest(
+            "Unsupported file type. Allowed types: PNG, JPEG, PDF".to_string(),
+        ));
+    };
+
+    // The extension comes from the detected type, never from the client
+    let stored_name = format!(
+        "{}-{}.{}",
+        Uuid::new_v4(),
+        sanitize_file_stem(&original_name),
+        file_type.extension
+    );
+    tokio::fs::create_dir_all(&state.upload_dir)
+        .await
+        .map_err(|e| AppError::Internal(format!("Failed to create directory: {}", e)))?;
+    tokio::fs::write(state.upload_dir.join(&stored_name), &data)
+        .await
+        .map_err(|e| AppError::Internal(format!("Failed to write file: {}", e)))?;
+
+    tracing::info!(
+        original_name = original_name,
+        stored_name = stored_name,
+        content_type = file_type.mime,
+        size = data.len(),
+        "Upload validated and stored"
+    );
+
+    Ok(Json(UploadResponse {
+        file_name: stored_name,
+        content_type: file_type.mime.to_string(),
+        size: data.len(),
+        validated: true,
+    }))
+}
+
+/// Identify the file type from its leading bytes
+fn detect_file_type(data: &[u8]) -> Option<&'static FileType> {
+    ALLOWED_FILE_TYPES
+        .iter()
+        .find(|file_type| data.starts_with(file_type.magic))
+}
+
+/// Reduce a client-provided filename to a safe stem
+///
+/// Drops any directory components (both `/` and `\`), the extension, and every
+/// character outside `[A-Za-z0-9_-]`, so the result can never traverse paths or
+/// be mistaken for a hidden file.
+fn sanitize_file_stem(file_name: &str) -> String {
+    let base = file_name.rsplit(['/', '\\']).next().unwrap_or_default();
+    let stem = match base.rsplit_once('.') {
+        Some((stem, _)) => stem,
+        None => base,
+    };
+
+    let sanitized: String = stem
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || *c == '_' || *c == '-')
+        .take(MAX_STEM_LENGTH)
+        .collect();
+
+    if sanitized.is_empty() {
+        "upload".to_string()
+    } else {
+        sanitized
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{
+        body::Body,
+        http::{Request, header},
+    };
+    use tower::ServiceExt;
+
+    const PNG_BYTES: &[u8] = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+
+    /// Temporary directory with an `uploads` subdirectory, removed on drop
+    struct TestDir(PathBuf);
+
+    impl TestDir {
+        fn new() -> Self {
+            let root = std::env::temp_dir().join(format!("file-upload-demo-{}", Uuid::new_v4()));
+            std::fs::create_dir_all(root.join("uploads")).unwrap();
+            Self(root)
+        }
+
+        fn upload_dir(&self) -> PathBuf {
+            self.0.join("uploads")
+        }
+    }
+
+    impl Drop for TestDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn upload_request(
+        uri: &str,
+        file_name: &str,
+        content_type: &str,
+        data: &[u8],
+    ) -> Request<Body> {
+        let boundary = "demo-boundary";
+        let mut body = format!(
+            "--{boundary}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"{file_name}\"\r\nContent-Type: {content_type}\r\n\r\n"
+        )
+        .into_bytes();
+        body.extend_from_slice(data);
+        body.extend_from_slice(format!("\r\n--{boundary}--\r\n").as_bytes());
+
+        Request::builder()
+            .method("POST")
+            .uri(uri)
+            .header(
+                header::CONTENT_TYPE,
+                format!("multipart/form-data; boundary={boundary}"),
+            )
+            .body(Body::from(body))
+            .unwrap()
+    }
+
+    async fn json_body(response: axum::response::Response) -> serde_json::Value {
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    fn stored_files(dir: &Path) -> Vec<String> {
+        std::fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_secure_upload_rejects_spoofed_extension() {
+        let dir = TestDir::new();
+        let app = create_app(AppState {
+            upload_dir: dir.upload_dir(),
+        });
+
+        // A PHP script claiming to be a PNG image
+        let response = app
+            .oneshot(upload_request(
+                "/upload",
+                "avatar.png",
+                "image/png",
+                b"<?php system($_GET['cmd']); ?>",
+            ))
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert!(stored_files(&dir.upload_dir()).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_secure_upload_sanitizes_path_traversal_filename() {
+        let dir = TestDir::new();
+        let app = create_app(AppState {
+            upload_dir: dir.upload_dir(),
+        });
+
+        let response = app
+            .oneshot(upload_request(
+                "/upload",
+                "../../evil.php",
+                "application/x-php",
+                PNG_BYTES,
+            ))
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let json = json_body(response).await;
+        let stored_name = json["file_name"].as_str().unwrap();
+        assert!(stored_name.ends_with("-evil.png"), "{}", stored_name);
+        assert!(!stored_name.contains(".."));
+        assert_eq!(json["content_type"], "image/png");
+
+        // Nothing escaped the upload directory
+        assert_eq!(
+            stored_files(&dir.upload_dir()),
+            vec![stored_name.to_string()]
+        );
+        assert_eq!(stored_files(&dir.0), vec!["uploads".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_secure_upload_enforces_size_cap() {
+        let dir = TestDir::new();
+        let app = create_app(AppState {
+            upload_dir: dir.upload_dir(),
+        });
+
+        let mut data = PNG_BYTES.to_vec();
+        data.resize(MAX_UPLOAD_SIZE + 1, 0);
+        let response = app
+            .oneshot(upload_request("/upload", "big.png", "image/png", &data))
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        assert!(stored_files(&dir.upload_dir()).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_vulnerable_upload_allows_path_traversal() {
+        let dir = TestDir::new();
+        let app = create_app(AppState {
+            upload_dir: dir.upload_dir(),
+        });
+
+        let response = app
+            .oneshot(upload_request(
+                "/vulnerable/upload",
+                "../escaped.php",
+                "image/png",
+                b"<?php echo 'pwned'; ?>",
+            ))
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        // The file landed next to, not inside, the upload directory
+        assert!(dir.0.join("escaped.php").exists());
+    }
+
+    #[test]
+    fn test_sanitize_file_stem() {
+        assert_eq!(sanitize_file_stem("../../etc/passwd"), "passwd");
+        assert_eq!(sanitize_file_stem("..\\..\\boot.ini"), "boot");
+        assert_eq!(sanitize_file_stem("my photo (1).jpg"), "myphoto1");
+        assert_eq!(sanitize_file_stem(".htaccess"), "upload");
+        assert_eq!(sanitize_file_stem(""), "upload");
+    }
+}
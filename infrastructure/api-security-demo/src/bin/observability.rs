@@ -16,7 +16,11 @@
 //!
 //!   # Failed authentication
 //!   curl -H "Authorization: Bearer invalid" http://localhost:8080/api/protected
+//!
+//!   # Every response carries the request id found in that request's log lines
+//!   curl -i http://localhost:8080/api/data
 
+use api_security_demo::request_id::with_request_tracing;
 use axum::{
     Json, Router,
     extract::{ConnectInfo, Query, State},
@@ -128,6 +132,8 @@ async fn main() {
             security_logging_middleware,
         ))
         .with_state(state);
+    // Outermost layer, so the security middleware logs inside the request span
+    let app = with_request_tracing(app);
 
     let listener = tokio::net::TcpListener::bind("127.0.0.1:8080")
         .await
@@ -153,6 +159,7 @@ async fn main() {
     tracing::info!("  - Authentication failures");
     tracing::info!("  - Suspicious query patterns (SQLi, XSS)");
     tracing::info!("  - Blocked requests");
+    tracing::info!("All log lines carry the request_id returned in the x-request-id header");
 
     axum::serve(
         listener,
@@ -0,0 +1,115 @@
+//! Regular Expression Denial of Service (ReDoS) Demonstration
+//!
+//! This example demonstrates:
+//! - Vulnerable endpoint: Runs the client's regex with a backtracking engine,
+//!   so one request with nested quantifiers pins a worker thread
+//! - Secure endpoint: Caps the pattern length and size and uses the
+//!   linear-time `regex` crate, so every search finishes quickly
+//!
+//! Run: cargo run --bin redos-demo
+//! Test:
+//!   # Vulnerable: This request never returns (and blocks a runtime worker)
+//!   curl -G http://localhost:8080/vulnerable/search \
+//!     --data-urlencode 'q=^(a+)+$' --data-urlencode 'text=aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa!'
+//!
+//!   # Secure: The same request is answered immediately
+//!   curl -G http://localhost:8080/search \
+//!     --data-urlencode 'q=^(a+)+$' --data-urlencode 'text=aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa!'
+//!
+//!   # Secure: Ordinary searches over the catalog still work
+//!   curl -G http://localhost:8080/search --data-urlencode 'q=(pie|tart)$'
+
+use api_security_demo::error::AppError;
+use api_security_demo::search::{SearchError, secure_search, vulnerable_search};
+use axum::{Json, Router, extract::Query, routing::get};
+use serde::{Deserialize, Serialize};
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+
+/// Items searched when the request does not supply its own `text`
+const CATALOG: &[&str] = &[
+    "apple pie",
+    "banana split",
+    "cherry tart",
+    "apple crumble",
+    "lemon tart",
+];
+
+#[derive(Deserialize)]
+struct SearchQuery {
+    /// Regular expression supplied by the client
+    q: String,
+    /// Search this text instead of the catalog
+    text: Option<String>,
+}
+
+#[derive(Serialize)]
+struct SearchResponse {
+    pattern: String,
+    matches: Vec<String>,
+}
+
+#[tokio::main]
+async fn main() {
+    tracing_subscriber::registry()
+        .with(
+            tracing_subscriber::EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| "redos_demo=debug,tower_http=debug".into()),
+        )
+        .with(tracing_subscriber::fmt::layer())
+        .init();
+
+    let app = create_app();
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:8080")
+        .await
+        .unwrap();
+
+    tracing::info!("ReDoS demonstration server running on http://127.0.0.1:8080");
+    tracing::info!("");
+    tracing::info!("Available endpoints:");
+    tracing::info!("  GET /vulnerable/search?q=...  - VULNERABLE: Backtracking regex engine");
+    tracing::info!("  GET /search?q=...             - SECURE: Bounded, linear-time regex");
+
+    axum::serve(listener, app).await.unwrap();
+}
+
+fn create_app() -> Router {
+    Router::new()
+        .route("/vulnerable/search", get(vulnerable_search_handler))
+        .route("/search", get(secure_search_handler))
+}
+
+/// VULNERABLE: Matches on the async worker itself, with no bound on the work
+async fn vulnerable_search_handler(
+    Query(query): Query<SearchQuery>,
+) -> Result<Json<SearchResponse>, AppError> {
+    tracing::warn!(pattern = %query.q, "Running client regex with a backtracking engine");
+    run_search(query, vulnerable_search)
+}
+
+/// SECURE: Linear-time matching of a length- and size-limited pattern
+async fn secure_search_handler(
+    Query(query): Query<SearchQuery>,
+) -> Result<Json<SearchResponse>, AppError> {
+    run_search(query, secure_search)
+}
+
+fn run_search(
+    query: SearchQuery,
+    search: for<'a> fn(&str, &[&'a str]) -> Result<Vec<&'a str>, SearchError>,
+) -> Result<Json<SearchResponse>, AppError> {
+    let items: Vec<&str> = match &query.text {
+        Some(text) => vec![text.as_str()],
+        None => CATALOG.to_vec(),
+    };
+    let matches = search(&query.q, &items)
+        .map_err(|e| AppError::BadRequest(e.to_string()))?
+        .into_iter()
+        .map(str::to_string)
+        .collect();
+
+    Ok(Json(SearchResponse {
+        pattern: query.q,
+        matches,
+    }))
+}
@@ -8,6 +8,8 @@
 //! Run: cargo run --bin ch12-security-test
 //! Or run tests: cargo test --bin ch12-security-test
 
+use api_security_demo::negotiate::{Negotiated, ResponseFormat};
+use api_security_demo::validated::Validated;
 use axum::{
     Json, Router,
     extract::{Path, Query, State},
@@ -16,10 +18,12 @@ use axum::{
 };
 use serde::{Deserialize, Serialize};
 use std::{
+    borrow::Cow,
     collections::HashMap,
     sync::{Arc, RwLock},
 };
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+use validator::{Validate, ValidationError};
 
 /// In-memory database for testing
 #[derive(Debug, Default)]
@@ -226,7 +230,10 @@ async fn vulnerable_get_product(
 // ============ SECURE ENDPOINTS ============
 
 /// SECURE: Returns only public user data
-async fn secure_list_users(State(state): State<AppState>) -> Json<Vec<UserPublic>> {
+async fn secure_list_users(
+    State(state): State<AppState>,
+    format: ResponseFormat,
+) -> Negotiated<Vec<UserPublic>> {
     let users = state.db.users.read().unwrap();
     let public_users: Vec<UserPublic> = users
         .values()
@@ -236,7 +243,7 @@ async fn secure_list_users(State(state): State<AppState>) -> Json<Vec<UserPublic
             email: u.email.clone(),
         })
         .collect();
-    Json(public_users)
+    format.respond(public_users)
 }
 
 #[derive(Debug, Serialize)]
@@ -247,33 +254,66 @@ struct UserPublic {
     // No password_hash, role, or ssn
 }
 
-/// SECURE: Input validation and filtered response
-async fn secure_get_user(
-    State(state): State<AppState>,
-    Path(id): Path<String>,
-) -> Result<Json<UserPublic>, (StatusCode, String)> {
-    // SECURE: Validate input format
+/// Path parameters for `/api/users/{id}`
+#[derive(Debug, Deserialize, Validate)]
+struct UserIdPath {
+    /// SECURE: digits only, within 1..=1_000_000
+    #[validate(length(min = 1, max = 7), custom(function = validate_user_id))]
+    id: String,
+}
+
+fn validate_user_id(id: &str) -> Result<(), ValidationError> {
     if !id.chars().all(|c| c.is_ascii_digit()) {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            "Invalid user ID format".to_string(),
-        ));
+        return Err(
+            ValidationError::new("format").with_message(Cow::Borrowed("Invalid user ID format"))
+        );
     }
 
-    let id: i64 = id
-        .parse()
-        .map_err(|_| (StatusCode::BAD_REQUEST, "Invalid user ID".to_string()))?;
+    match id.parse::<i64>() {
+        Ok(n) if (1..=1_000_000).contains(&n) => Ok(()),
+        _ => Err(ValidationError::new("range").with_message(Cow::Borrowed("User ID out of range"))),
+    }
+}
+
+/// Query parameters for `/api/search`
+#[derive(Debug, Deserialize, Validate)]
+struct SearchParams {
+    /// SECURE: bounded length and a whitelist of characters
+    #[serde(default)]
+    #[validate(length(max = 100), custom(function = validate_search_chars))]
+    q: String,
+}
 
-    // SECURE: Validate range
-    if !(1..=1_000_000).contains(&id) {
-        return Err((StatusCode::BAD_REQUEST, "User ID out of range".to_string()));
+fn validate_search_chars(query: &str) -> Result<(), ValidationError> {
+    // SECURE: Only allow alphanumeric and common characters
+    if query
+        .chars()
+        .all(|c| c.is_alphanumeric() || c == ' ' || c == '-' || c == '_' || c == '@' || c == '.')
+    {
+        Ok(())
+    } else {
+        Err(ValidationError::new("charset")
+            .with_message(Cow::Borrowed("Invalid characters in query")))
     }
+}
+
+/// SECURE: Input validation and filtered response
+async fn secure_get_user(
+    State(state): State<AppState>,
+    format: ResponseFormat,
+    Validated(Path(path)): Validated<Path<UserIdPath>>,
+) -> Result<Negotiated<UserPublic>, (StatusCode, String)> {
+    // The ID has already been validated declaratively
+    let id: i64 = path
+        .id
+        .parse()
+        .map_err(|_| (StatusCode::BAD_REQUEST, "Invalid user ID".to_string()))?;
 
     let users = state.db.users.read().unwrap();
     users
         .get(&id)
         .map(|u| {
-            Json(UserPublic {
+            format.respond(UserPublic {
                 id: u.id,
                 username: u.username.clone(),
                 email: u.email.clone(),
@@ -285,25 +325,10 @@ async fn secure_get_user(
 /// SECURE: Parameterized search (simulated)
 async fn secure_search(
     State(state): State<AppState>,
-    Query(params): Query<HashMap<String, String>>,
-) -> Result<Json<SearchResponse>, (StatusCode, String)> {
-    let query = params.get("q").cloned().unwrap_or_default();
-
-    // SECURE: Validate and sanitize input
-    if query.len() > 100 {
-        return Err((StatusCode::BAD_REQUEST, "Query too long".to_string()));
-    }
-
-    // SECURE: Only allow alphanumeric and common characters
-    if !query
-        .chars()
-        .all(|c| c.is_alphanumeric() || c == ' ' || c == '-' || c == '_' || c == '@' || c == '.')
-    {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            "Invalid characters in query".to_string(),
-        ));
-    }
+    format: ResponseFormat,
+    Validated(Query(params)): Validated<Query<SearchParams>>,
+) -> Result<Negotiated<SearchResponse>, (StatusCode, String)> {
+    let query = params.q;
 
     let users = state.db.users.read().unwrap();
     let results: Vec<UserPublic> = users
@@ -319,7 +344,7 @@ async fn secure_search(
         })
         .collect();
 
-    Ok(Json(SearchResponse {
+    Ok(format.respond(SearchResponse {
         query,
         results,
         vulnerable: false,
@@ -336,13 +361,14 @@ struct SearchResponse {
 /// SECURE: Returns only public product data
 async fn secure_get_product(
     State(state): State<AppState>,
+    format: ResponseFormat,
     Path(id): Path<i64>,
-) -> Result<Json<ProductPublic>, StatusCode> {
+) -> Result<Negotiated<ProductPublic>, StatusCode> {
     let products = state.db.products.read().unwrap();
     products
         .get(&id)
         .map(|p| {
-            Json(ProductPublic {
+            format.respond(ProductPublic {
                 id: p.id,
                 name: p.name.clone(),
                 price: p.price,
@@ -460,7 +486,7 @@ mod tests {
     use super::*;
     use axum::{
         body::Body,
-        http::{Request, StatusCode},
+        http::{Request, StatusCode, header},
     };
     use tower::ServiceExt;
 
@@ -552,4 +578,132 @@ mod tests {
 
         assert_eq!(response.status(), StatusCode::BAD_REQUEST);
     }
+
+    #[tokio::test]
+    async fn test_secure_search_rejects_too_long_query() {
+        let app = create_app();
+        let long_query = "a".repeat(101);
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri(format!("/api/search?q={}", long_query))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["fields"]["q"][0]["code"], "length");
+    }
+
+    #[tokio::test]
+    async fn test_secure_user_invalid_id_format_reports_field() {
+        let app = create_app();
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/api/users/12a")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["fields"]["id"][0]["code"], "format");
+        assert_eq!(body["fields"]["id"][0]["message"], "Invalid user ID format");
+    }
+
+    #[tokio::test]
+    async fn test_secure_valid_input_passes() {
+        let app = create_app();
+
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri("/api/users/2")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/api/search?q=user@example.com")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    async fn get_with_accept(app: Router, uri: &str, accept: &str) -> axum::response::Response {
+        app.oneshot(
+            Request::builder()
+                .uri(uri)
+                .header(header::ACCEPT, accept)
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_secure_user_negotiates_json() {
+        let response = get_with_accept(create_app(), "/api/users/2", "application/json").await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "application/json");
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["id"], 2);
+        assert!(body.get("ssn").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_secure_user_negotiates_msgpack() {
+        let response = get_with_accept(create_app(), "/api/users/2", "application/msgpack").await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()[header::CONTENT_TYPE],
+            "application/msgpack"
+        );
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = rmp_serde::from_slice(&body).unwrap();
+        assert_eq!(body["id"], 2);
+        assert!(body.get("ssn").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_secure_users_unsupported_accept_is_406() {
+        let response = get_with_accept(create_app(), "/api/users", "text/html").await;
+
+        assert_eq!(response.status(), StatusCode::NOT_ACCEPTABLE);
+    }
 }
@@ -0,0 +1,212 @@
+//! CORS policies: a reflected-origin configuration and an allowlist-based one
+//!
+//! `vulnerable_cors_layer` echoes whatever `Origin` the browser sends and
+//! allows credentials, so any website can make authenticated requests with the
+//! victim's cookies and read the response. `secure_cors_layer` only answers an
+//! explicit list of origins and refuses to combine credentials with a wildcard.
+
+use axum::http::{HeaderValue, Method, header};
+use std::time::Duration;
+use thiserror::Error;
+use tower_http::cors::{AllowHeaders, AllowMethods, AllowOrigin, CorsLayer};
+
+/// How long browsers may cache a preflight response
+pub const PREFLIGHT_MAX_AGE: Duration = Duration::from_secs(600);
+
+#[derive(Error, Debug, PartialEq)]
+pub enum CorsConfigError {
+    #[error("At least one allowed origin is required")]
+    NoOrigins,
+
+    #[error("Wildcard origin cannot be combined with credentials")]
+    Wildcard,
+
+    #[error("Invalid origin {0:?}: expected scheme://host[:port]")]
+    InvalidOrigin(String),
+}
+
+/// VULNERABLE: Reflect any origin and allow credentials
+///
+/// tower-http rejects `Access-Control-Allow-Origin: *` with credentials, but
+/// mirroring the request's origin has the same effect and is not caught.
+pub fn vulnerable_cors_layer() -> CorsLayer {
+    CorsLayer::new()
+        .allow_origin(AllowOrigin::mirror_request())
+        .allow_methods(AllowMethods::mirror_request())
+        .allow_headers(AllowHeaders::mirror_request())
+        .allow_credentials(true)
+}
+
+/// SECURE: Allow credentialed requests only from the listed origins
+///
+/// Origins must be exactly what browsers send in the `Origin` header
+/// (e.g. `https://app.example.com`, no path or trailing slash). Requests from
+/// other origins get no `Access-Control-Allow-Origin` header, so the browser
+/// blocks the response from reaching the page.
+pub fn secure_cors_layer(allowed_origins: &[&str]) -> Result<CorsLayer, CorsConfigError> {
+    if allowed_origins.is_empty() {
+        return Err(CorsConfigError::NoOrigins);
+    }
+    let origins = allowed_origins
+        .iter()
+        .map(|origin| parse_origin(origin))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(CorsLayer::new()
+        .allow_origin(AllowOrigin::list(origins))
+        .allow_methods([Method::GET, Method::POST])
+        .allow_headers([header::CONTENT_TYPE, header::AUTHORIZATION])
+        .allow_credentials(true)
+        .max_age(PREFLIGHT_MAX_AGE))
+}
+
+/// Validate an allowlist entry as a serialized origin
+fn parse_origin(origin: &str) -> Result<HeaderValue, CorsConfigError> {
+    if origin == "*" {
+        return Err(CorsConfigError::Wildcard);
+    }
+    let invalid = || CorsConfigError::InvalidOrigin(origin.to_string());
+
+    // The Origin header is compared byte for byte, so the entry must already be
+    // in the browser's serialized form
+    let url = url::Url::parse(origin).map_err(|_| invalid())?;
+    if !matches!(url.scheme(), "http" | "https") || url.origin().ascii_serialization() != origin {
+        return Err(invalid());
+    }
+
+    HeaderValue::from_str(origin).map_err(|_| invalid())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{
+        Router,
+        body::Body,
+        http::{Request, Response, StatusCode},
+        routing::get,
+    };
+    use tower::ServiceExt;
+
+    const ALLOWED: &str = "https://app.example.com";
+    const EVIL: &str = "https://evil.example";
+
+    fn app(cors: CorsLayer) -> Router {
+        Router::new()
+            .route("/api/profile", get(|| async { "secret profile" }))
+            .layer(cors)
+    }
+
+    fn preflight(origin: &str) -> Request<Body> {
+        Request::builder()
+            .method("OPTIONS")
+            .uri("/api/profile")
+            .header(header::ORIGIN, origin)
+            .header(header::ACCESS_CONTROL_REQUEST_METHOD, "GET")
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    fn get_from(origin: &str) -> Request<Body> {
+        Request::builder()
+            .uri("/api/profile")
+            .header(header::ORIGIN, origin)
+            .header(header::COOKIE, "session=victim")
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    fn allow_origin<B>(response: &Response<B>) -> Option<&str> {
+        response
+            .headers()
+            .get(header::ACCESS_CONTROL_ALLOW_ORIGIN)
+            .map(|value| value.to_str().unwrap())
+    }
+
+    fn allows_credentials<B>(response: &Response<B>) -> bool {
+        response
+            .headers()
+            .get(header::ACCESS_CONTROL_ALLOW_CREDENTIALS)
+            .is_some_and(|value| value == "true")
+    }
+
+    fn secure() -> CorsLayer {
+        secure_cors_layer(&[ALLOWED, "http://localhost:3000"]).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_vulnerable_layer_reflects_any_origin_with_credentials() {
+        let response = app(vulnerable_cors_layer())
+            .oneshot(get_from(EVIL))
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(allow_origin(&response), Some(EVIL));
+        assert!(allows_credentials(&response));
+
+        let response = app(vulnerable_cors_layer())
+            .oneshot(preflight(EVIL))
+            .await
+            .unwrap();
+        assert_eq!(allow_origin(&response), Some(EVIL));
+    }
+
+    #[tokio::test]
+    async fn test_secure_layer_rejects_disallowed_origin() {
+        let response = app(secure()).oneshot(preflight(EVIL)).await.unwrap();
+        assert_eq!(allow_origin(&response), None);
+
+        // The handler still runs, but without the header the browser hides the response
+        let response = app(secure()).oneshot(get_from(EVIL)).await.unwrap();
+        assert_eq!(allow_origin(&response), None);
+
+        // A lookalike origin is not a prefix match
+        let response = app(secure())
+            .oneshot(get_from("https://app.example.com.evil.example"))
+            .await
+            .unwrap();
+        assert_eq!(allow_origin(&response), None);
+    }
+
+    #[tokio::test]
+    async fn test_secure_layer_allows_listed_origin() {
+        let response = app(secure()).oneshot(preflight(ALLOWED)).await.unwrap();
+        assert_eq!(allow_origin(&response), Some(ALLOWED));
+        assert!(allows_credentials(&response));
+        assert_eq!(
+            response.headers()[header::ACCESS_CONTROL_MAX_AGE],
+            PREFLIGHT_MAX_AGE.as_secs().to_string()
+        );
+
+        let response = app(secure()).oneshot(get_from(ALLOWED)).await.unwrap();
+        assert_eq!(allow_origin(&response), Some(ALLOWED));
+    }
+
+    #[test]
+    fn test_secure_layer_rejects_unsafe_allowlists() {
+        assert_eq!(
+            secure_cors_layer(&[]).unwrap_err(),
+            CorsConfigError::NoOrigins
+        );
+        assert_eq!(
+            secure_cors_layer(&[ALLOWED, "*"]).unwrap_err(),
+            CorsConfigError::Wildcard
+        );
+        for origin in [
+            "null",
+            "app.example.com",
+            "https://app.example.com/",
+            "https://app.example.com/api",
+            "https://APP.example.com",
+            "ftp://files.example.com",
+        ] {
+            assert_eq!(
+                secure_cors_layer(&[origin]).unwrap_err(),
+                CorsConfigError::InvalidOrigin(origin.to_string()),
+                "{}",
+                origin
+            );
+        }
+    }
+}
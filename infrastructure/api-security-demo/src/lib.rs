@@ -3,8 +3,13 @@
 //! Shared library code for all chapter examples.
 
 pub mod auth;
+pub mod cors;
 pub mod db;
 pub mod error;
 pub mod models;
+pub mod negotiate;
+pub mod request_id;
+pub mod search;
+pub mod validated;
 
 pub use error::AppError;
@@ -0,0 +1,177 @@
+//! `Accept`-header driven response negotiation
+//!
+//! `ResponseFormat` is an extractor that picks JSON or MessagePack from the
+//! request's `Accept` header (JSON when absent) and rejects anything else with
+//! 406. `Negotiated<T>` serializes the same model in the chosen format.
+
+use axum::{
+    extract::FromRequestParts,
+    http::{HeaderValue, StatusCode, header, request::Parts},
+    response::{IntoResponse, Response},
+};
+use serde::Serialize;
+
+pub const JSON: &str = "application/json";
+pub const MSGPACK: &str = "application/msgpack";
+
+/// Wire format selected from the `Accept` header
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResponseFormat {
+    Json,
+    MsgPack,
+}
+
+impl ResponseFormat {
+    /// Choose the best supported format, or `None` if nothing acceptable is supported
+    pub fn from_accept(accept: Option<&str>) -> Option<Self> {
+        let accept = match accept.map(str::trim) {
+            None | Some("") => return Some(Self::Json),
+            Some(accept) => accept,
+        };
+
+        let mut candidates: Vec<(f32, usize, Self)> = accept
+            .split(',')
+            .enumerate()
+            .filter_map(|(position, range)| {
+                let mut parts = range.split(';');
+                let media_type = parts.next()?.trim().to_ascii_lowercase();
+                let quality = parts
+                    .filter_map(|param| param.trim().strip_prefix("q="))
+                    .find_map(|q| q.parse::<f32>().ok())
+                    .unwrap_or(1.0);
+                if quality <= 0.0 {
+                    return None;
+                }
+
+                let format = match media_type.as_str() {
+                    JSON | "application/*" | "*/*" => Self::Json,
+                    MSGPACK | "application/x-msgpack" => Self::MsgPack,
+                    _ => return None,
+                };
+                Some((quality, position, format))
+            })
+            .collect();
+
+        // Highest quality wins; ties go to the earliest listed range
+        candidates.sort_by(|a, b| b.0.total_cmp(&a.0).then(a.1.cmp(&b.1)));
+        candidates.first().map(|(_, _, format)| *format)
+    }
+
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Self::Json => JSON,
+            Self::MsgPack => MSGPACK,
+        }
+    }
+
+    /// Wrap a value so it is serialized in this format
+    pub fn respond<T: Serialize>(self, value: T) -> Negotiated<T> {
+        Negotiated {
+            format: self,
+            value,
+        }
+    }
+}
+
+/// Rejection for an `Accept` header that allows no supported format
+#[derive(Debug)]
+pub struct NotAcceptable;
+
+impl IntoResponse for NotAcceptable {
+    fn into_response(self) -> Response {
+        (
+            StatusCode::NOT_ACCEPTABLE,
+            format!("Supported response types: {}, {}", JSON, MSGPACK),
+        )
+            .into_response()
+    }
+}
+
+impl<S> FromRequestParts<S> for ResponseFormat
+where
+    S: Send + Sync,
+{
+    type Rejection = NotAcceptable;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let accept = parts
+            .headers
+            .get(header::ACCEPT)
+            .map(|value| value.to_str().map_err(|_| NotAcceptable))
+            .transpose()?;
+        Self::from_accept(accept).ok_or(NotAcceptable)
+    }
+}
+
+/// A value serialized as JSON or MessagePack depending on the negotiated format
+#[derive(Debug)]
+pub struct Negotiated<T> {
+    pub format: ResponseFormat,
+    pub value: T,
+}
+
+impl<T: Serialize> IntoResponse for Negotiated<T> {
+    fn into_response(self) -> Response {
+        let body = match self.format {
+            ResponseFormat::Json => serde_json::to_vec(&self.value).map_err(|e| e.to_string()),
+            // Named encoding keeps field names, so clients see the same shape as JSON
+            ResponseFormat::MsgPack => {
+                rmp_serde::to_vec_named(&self.value).map_err(|e| e.to_string())
+            }
+        };
+
+        match body {
+            Ok(body) => (
+                [(
+                    header::CONTENT_TYPE,
+                    HeaderValue::from_static(self.format.content_type()),
+                )],
+                body,
+            )
+                .into_response(),
+            Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_missing_or_wildcard_accept_defaults_to_json() {
+        assert_eq!(
+            ResponseFormat::from_accept(None),
+            Some(ResponseFormat::Json)
+        );
+        assert_eq!(
+            ResponseFormat::from_accept(Some("")),
+            Some(ResponseFormat::Json)
+        );
+        assert_eq!(
+            ResponseFormat::from_accept(Some("*/*")),
+            Some(ResponseFormat::Json)
+        );
+    }
+
+    #[test]
+    fn test_quality_values_are_respected() {
+        assert_eq!(
+            ResponseFormat::from_accept(Some("application/json;q=0.5, application/msgpack")),
+            Some(ResponseFormat::MsgPack)
+        );
+        assert_eq!(
+            ResponseFormat::from_accept(Some("application/msgpack;q=0, */*;q=0.1")),
+            Some(ResponseFormat::Json)
+        );
+    }
+
+    #[test]
+    fn test_unsupported_accept_is_rejected() {
+        assert_eq!(ResponseFormat::from_accept(Some("text/html")), None);
+        assert_eq!(
+            ResponseFormat::from_accept(Some("application/json;q=0")),
+            None
+        );
+    }
+}
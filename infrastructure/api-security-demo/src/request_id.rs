@@ -0,0 +1,223 @@
+//! Request ID propagation for end-to-end tracing
+//!
+//! `with_request_tracing` gives every request an `x-request-id` (a UUID v4, or
+//! the one the client sent if it is well formed), opens a `request` span
+//! carrying it so every log line emitted while handling the request includes
+//! the id, and echoes the id in the response header for client-side correlation.
+//!
+//! A client-supplied id is only kept when it is at most `MAX_REQUEST_ID_LEN`
+//! characters of `[A-Za-z0-9-]`. Anything else is replaced by a fresh UUID, so
+//! clients cannot inject newlines or other markup into the logs or flood them
+//! with oversized values.
+
+use axum::{Router, extract::Request, middleware};
+use tower::ServiceBuilder;
+use tower_http::{
+    request_id::{MakeRequestUuid, PropagateRequestIdLayer, RequestId, SetRequestIdLayer},
+    trace::TraceLayer,
+};
+use tracing::Span;
+
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Longest client-supplied request id that is kept (a UUID is 36 characters)
+pub const MAX_REQUEST_ID_LEN: usize = 64;
+
+/// Whether a client-supplied request id is safe to log and echo
+pub fn is_valid_request_id(id: &[u8]) -> bool {
+    !id.is_empty()
+        && id.len() <= MAX_REQUEST_ID_LEN
+        && id.iter().all(|&b| b.is_ascii_alphanumeric() || b == b'-')
+}
+
+/// Drop a malformed client `x-request-id` so `SetRequestIdLayer` generates one
+async fn discard_invalid_request_id(mut request: Request) -> Request {
+    let headers = request.headers_mut();
+    if headers
+        .get_all(REQUEST_ID_HEADER)
+        .iter()
+        .any(|value| !is_valid_request_id(value.as_bytes()))
+    {
+        headers.remove(REQUEST_ID_HEADER);
+    }
+    request
+}
+
+/// Build the per-request span from the id assigned by `SetRequestIdLayer`
+pub fn make_request_span(request: &Request) -> Span {
+    let request_id = request
+        .extensions()
+        .get::<RequestId>()
+        .and_then(|id| id.header_value().to_str().ok())
+        .unwrap_or("unknown");
+
+    tracing::info_span!(
+        "request",
+        request_id = %request_id,
+        method = %request.method(),
+        path = %request.uri().path(),
+    )
+}
+
+/// Wrap all routes so requests are traced under a span with their request id
+///
+/// Apply this last, so middleware added to the router also logs inside the span.
+pub fn with_request_tracing<S>(router: Router<S>) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    router.layer(
+        ServiceBuilder::new()
+            .layer(middleware::map_request(discard_invalid_request_id))
+            .layer(SetRequestIdLayer::x_request_id(MakeRequestUuid))
+            .layer(TraceLayer::new_for_http().make_span_with(make_request_span))
+            .layer(PropagateRequestIdLayer::x_request_id()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, routing::get};
+    use std::{
+        io::Write,
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+    use tower::ServiceExt;
+    use tracing_subscriber::fmt::MakeWriter;
+
+    /// Collects formatted log output in memory
+    #[derive(Clone, Default)]
+    struct CapturedLogs(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for CapturedLogs {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl<'a> MakeWriter<'a> for CapturedLogs {
+        type Writer = Self;
+
+        fn make_writer(&'a self) -> Self::Writer {
+            self.clone()
+        }
+    }
+
+    fn app() -> Router {
+        with_request_tracing(Router::new().route(
+            "/",
+            get(|| async {
+                tracing::info!("handler started");
+                // Yield so the two requests interleave
+                tokio::time::sleep(Duration::from_millis(20)).await;
+                tracing::info!("handler finished");
+                "ok"
+            }),
+        ))
+    }
+
+    fn request_id(response: &axum::response::Response) -> String {
+        response
+            .headers()
+            .get(REQUEST_ID_HEADER)
+            .expect("x-request-id header missing")
+            .to_str()
+            .unwrap()
+            .to_string()
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_requests_log_distinct_ids() {
+        let logs = CapturedLogs::default();
+        let subscriber = tracing_subscriber::fmt()
+            .with_writer(logs.clone())
+            .with_ansi(false)
+            .finish();
+        // Single-threaded test runtime, so the thread-local default covers both requests
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let request = || Request::builder().uri("/").body(Body::empty()).unwrap();
+        let (first, second) = tokio::join!(app().oneshot(request()), app().oneshot(request()));
+        let first_id = request_id(&first.unwrap());
+        let second_id = request_id(&second.unwrap());
+        assert_ne!(first_id, second_id);
+
+        let output = String::from_utf8(logs.0.lock().unwrap().clone()).unwrap();
+        for id in [&first_id, &second_id] {
+            let lines: Vec<&str> = output
+                .lines()
+                .filter(|line| line.contains(id.as_str()))
+                .collect();
+            assert!(
+                lines.iter().any(|line| line.contains("handler started"))
+                    && lines.iter().any(|line| line.contains("handler finished")),
+                "logs for {} missing:\n{}",
+                id,
+                output
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_client_request_id_is_echoed() {
+        let response = app()
+            .oneshot(
+                Request::builder()
+                    .uri("/")
+                    .header(REQUEST_ID_HEADER, "client-supplied-id")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(request_id(&response), "client-supplied-id");
+    }
+
+    #[tokio::test]
+    async fn test_malformed_client_request_id_is_replaced() {
+        let too_long = "a".repeat(MAX_REQUEST_ID_LEN + 1);
+        for bad in [
+            "ok level=ERROR msg=forged",
+            "<script>",
+            "id_with_underscore",
+            too_long.as_str(),
+        ] {
+            let request = Request::builder()
+                .uri("/")
+                .header(REQUEST_ID_HEADER, bad)
+                .body(Body::empty())
+                .unwrap();
+
+            let id = request_id(&app().oneshot(request).await.unwrap());
+            assert_ne!(id, bad);
+            assert!(
+                uuid::Uuid::parse_str(&id).is_ok(),
+                "not a generated id: {}",
+                id
+            );
+        }
+    }
+
+    #[test]
+    fn test_request_id_validation() {
+        assert!(is_valid_request_id(b"client-supplied-id"));
+        assert!(is_valid_request_id(b"67e55044-10b1-426f-9247-bb680e5fe0c8"));
+        assert!(!is_valid_request_id(b""));
+        assert!(!is_valid_request_id(b"id\nforged"));
+        assert!(!is_valid_request_id(b"id_with_underscore"));
+        assert!(!is_valid_request_id(
+            "a".repeat(MAX_REQUEST_ID_LEN + 1).as_bytes()
+        ));
+        assert!(is_valid_request_id(
+            "a".repeat(MAX_REQUEST_ID_LEN).as_bytes()
+        ));
+    }
+}
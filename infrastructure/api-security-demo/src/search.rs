@@ -0,0 +1,374 @@
+//! Searching with user-supplied regular expressions (ReDoS)
+//!
+//! `vulnerable_search` compiles the pattern for a backtracking engine, the
+//! kind used by many regex libraries. Nested quantifiers such as `(a+)+$` make
+//! it try every way of splitting the input between the loops, so a short
+//! request like `aaaa…a!` keeps a worker busy for minutes. `secure_search`
+//! caps the pattern length and uses the `regex` crate, whose automata-based
+//! engine runs in time linear in the input for every pattern.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use regex::{Regex, RegexBuilder};
+use thiserror::Error;
+
+/// Longest pattern a client may send
+pub const MAX_PATTERN_LEN: usize = 100;
+
+/// Upper bound on the compiled program, so patterns like `a{1000}{1000}` are rejected
+pub const REGEX_SIZE_LIMIT: usize = 1 << 16;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum SearchError {
+    #[error("Pattern is too long ({0} characters, at most {MAX_PATTERN_LEN} allowed)")]
+    PatternTooLong(usize),
+
+    #[error("Invalid pattern: {0}")]
+    InvalidPattern(String),
+}
+
+/// VULNERABLE: Match every item against the pattern with a backtracking engine
+///
+/// Supports literals, `.`, `^`, `$`, groups, `|`, `*`, `+`, `?` and `\` escapes.
+/// There is no limit on the pattern or on the matching work.
+pub fn vulnerable_search<'a>(
+    pattern: &str,
+    items: &[&'a str],
+) -> Result<Vec<&'a str>, SearchError> {
+    let never = AtomicBool::new(false);
+    Ok(backtracking_search(pattern, items, &never)?.expect("search is never cancelled"))
+}
+
+/// Backtracking search that gives up with `None` once `cancel` is set
+fn backtracking_search<'a>(
+    pattern: &str,
+    items: &[&'a str],
+    cancel: &AtomicBool,
+) -> Result<Option<Vec<&'a str>>, SearchError> {
+    let regex = Backtracking::compile(pattern)?;
+    let mut matches = Vec::new();
+    for &item in items {
+        let matched = regex.is_match(item, cancel);
+        if cancel.load(Ordering::Relaxed) {
+            return Ok(None);
+        }
+        if matched {
+            matches.push(item);
+        }
+    }
+    Ok(Some(matches))
+}
+
+/// SECURE: Linear-time matching with bounded patterns
+pub fn secure_search<'a>(pattern: &str, items: &[&'a str]) -> Result<Vec<&'a str>, SearchError> {
+    let regex = compile_user_pattern(pattern)?;
+    Ok(items
+        .iter()
+        .copied()
+        .filter(|item| regex.is_match(item))
+        .collect())
+}
+
+/// Compile a client's pattern with length and size limits
+pub fn compile_user_pattern(pattern: &str) -> Result<Regex, SearchError> {
+    let len = pattern.chars().count();
+    if len > MAX_PATTERN_LEN {
+        return Err(SearchError::PatternTooLong(len));
+    }
+    RegexBuilder::new(pattern)
+        .size_limit(REGEX_SIZE_LIMIT)
+        .dfa_size_limit(REGEX_SIZE_LIMIT)
+        .build()
+        .map_err(|e| SearchError::InvalidPattern(e.to_string()))
+}
+
+#[derive(Debug)]
+enum Node {
+    Char(char),
+    Any,
+    Start,
+    End,
+    Concat(Vec<Node>),
+    Alternate(Vec<Node>),
+    Repeat {
+        node: Box<Node>,
+        min: usize,
+        max: Option<usize>,
+    },
+}
+
+/// Naive backtracking regex engine (the vulnerable part of the demo)
+struct Backtracking {
+    root: Node,
+}
+
+/// Text being matched, and the flag that stops the search early
+struct Input<'a> {
+    text: &'a [char],
+    cancel: &'a AtomicBool,
+}
+
+impl Backtracking {
+    fn compile(pattern: &str) -> Result<Self, SearchError> {
+        let chars: Vec<char> = pattern.chars().collect();
+        let mut pos = 0;
+        let root = parse_alternation(&chars, &mut pos)?;
+        if pos < chars.len() {
+            return Err(SearchError::InvalidPattern(format!(
+                "unmatched ')' at position {}",
+                pos
+            )));
+        }
+        Ok(Self { root })
+    }
+
+    /// Unanchored search: try a match starting at every position
+    fn is_match(&self, text: &str, cancel: &AtomicBool) -> bool {
+        let text: Vec<char> = text.chars().collect();
+        let input = Input {
+            text: &text,
+            cancel,
+        };
+        (0..=text.len()).any(|start| match_node(&self.root, &input, start, &mut |_| true))
+    }
+}
+
+fn parse_alternation(chars: &[char], pos: &mut usize) -> Result<Node, SearchError> {
+    let mut branches = vec![parse_concat(chars, pos)?];
+    while chars.get(*pos) == Some(&'|') {
+        *pos += 1;
+        branches.push(parse_concat(chars, pos)?);
+    }
+    Ok(if branches.len() == 1 {
+        branches.remove(0)
+    } else {
+        Node::Alternate(branches)
+    })
+}
+
+fn parse_concat(chars: &[char], pos: &mut usize) -> Result<Node, SearchError> {
+    let mut nodes = Vec::new();
+    while let Some(&c) = chars.get(*pos) {
+        let atom = match c {
+            '|' | ')' => break,
+            '(' => {
+                *pos += 1;
+                let inner = parse_alternation(chars, pos)?;
+                if chars.get(*pos) != Some(&')') {
+                    return Err(SearchError::InvalidPattern("unclosed group".into()));
+                }
+                inner
+            }
+            '*' | '+' | '?' => {
+                return Err(SearchError::InvalidPattern(format!(
+                    "nothing to repeat at position {}",
+                    pos
+                )));
+            }
+            '.' => Node::Any,
+            '^' => Node::Start,
+            '$' => Node::End,
+            '\\' => {
+                *pos += 1;
+                match chars.get(*pos) {
+                    Some(&escaped) => Node::Char(escaped),
+                    None => return Err(SearchError::InvalidPattern("trailing '\\'".into())),
+                }
+            }
+            c => Node::Char(c),
+        };
+        *pos += 1;
+
+        let (min, max) = match chars.get(*pos) {
+            Some('*') => (0, None),
+            Some('+') => (1, None),
+            Some('?') => (0, Some(1)),
+            _ => {
+                nodes.push(atom);
+                continue;
+            }
+        };
+        *pos += 1;
+        nodes.push(Node::Repeat {
+            node: Box::new(atom),
+            min,
+            max,
+        });
+    }
+    Ok(Node::Concat(nodes))
+}
+
+/// Match `node` at `pos`, then hand each possible end position to `next`
+///
+/// Every alternative is retried on failure, which is what makes nested
+/// quantifiers exponential.
+fn match_node(node: &Node, input: &Input, pos: usize, next: &mut dyn FnMut(usize) -> bool) -> bool {
+    let text = input.text;
+    match node {
+        Node::Char(c) => text.get(pos) == Some(c) && next(pos + 1),
+        Node::Any => pos < text.len() && next(pos + 1),
+        Node::Start => pos == 0 && next(pos),
+        Node::End => pos == text.len() && next(pos),
+        Node::Concat(nodes) => match_sequence(nodes, input, pos, next),
+        Node::Alternate(branches) => branches
+            .iter()
+            .any(|branch| match_node(branch, input, pos, next)),
+        Node::Repeat { node, min, max } => match_repeat(node, *min, *max, 0, input, pos, next),
+    }
+}
+
+fn match_sequence(
+    nodes: &[Node],
+    input: &Input,
+    pos: usize,
+    next: &mut dyn FnMut(usize) -> bool,
+) -> bool {
+    match nodes.split_first() {
+        None => next(pos),
+        Some((first, rest)) => match_node(first, input, pos, &mut |end| {
+            match_sequence(rest, input, end, next)
+        }),
+    }
+}
+
+/// Greedy repetition: try one more iteration first, then stop here
+///
+/// Every backtracking path goes through here, so this is where a cancelled
+/// search stops.
+fn match_repeat(
+    node: &Node,
+    min: usize,
+    max: Option<usize>,
+    count: usize,
+    input: &Input,
+    pos: usize,
+    next: &mut dyn FnMut(usize) -> bool,
+) -> bool {
+    if input.cancel.load(Ordering::Relaxed) {
+        return false;
+    }
+    if max.is_none_or(|max| count < max) {
+        // Only continue after progress, so a repeat that matches "" cannot loop forever
+        let more = match_node(node, input, pos, &mut |end| {
+            end > pos && match_repeat(node, min, max, count + 1, input, end, next)
+        });
+        if more {
+            return true;
+        }
+    }
+    count >= min && next(pos)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, mpsc};
+    use std::time::{Duration, Instant};
+
+    /// Nested quantifiers: every split of the `a`s between the loops is tried
+    const EVIL_PATTERN: &str = "^(a+)+$";
+
+    fn evil_input(len: usize) -> String {
+        format!("{}!", "a".repeat(len))
+    }
+
+    const ITEMS: &[&str] = &["apple pie", "banana split", "cherry tart", "apple crumble"];
+
+    #[test]
+    fn both_engines_agree_on_ordinary_patterns() {
+        for pattern in [
+            "apple",
+            "^b",
+            "t$",
+            "(pie|tart)$",
+            "an+a",
+            "cr.m",
+            "x?apple c",
+        ] {
+            assert_eq!(
+                vulnerable_search(pattern, ITEMS).unwrap(),
+                secure_search(pattern, ITEMS).unwrap(),
+                "pattern {:?}",
+                pattern
+            );
+        }
+        assert_eq!(
+            secure_search("(pie|tart)$", ITEMS).unwrap(),
+            ["apple pie", "cherry tart"]
+        );
+    }
+
+    #[test]
+    fn vulnerable_search_hangs_on_catastrophic_pattern() {
+        // 28 `a`s already mean about 2^28 attempts. Run it on another thread,
+        // wait a bounded time, then cancel so it does not keep a core busy.
+        let cancel = Arc::new(AtomicBool::new(false));
+        let (tx, rx) = mpsc::channel();
+        let worker = {
+            let cancel = cancel.clone();
+            std::thread::spawn(move || {
+                let input = evil_input(28);
+                let matches = backtracking_search(EVIL_PATTERN, &[input.as_str()], &cancel)
+                    .map(|m| m.map(|m| m.len()));
+                let _ = tx.send(matches);
+            })
+        };
+
+        let finished = rx.recv_timeout(Duration::from_millis(500));
+        cancel.store(true, Ordering::Relaxed);
+        worker.join().unwrap();
+
+        assert!(
+            finished.is_err(),
+            "backtracking engine finished a catastrophic search"
+        );
+        assert_eq!(rx.recv().unwrap(), Ok(None));
+    }
+
+    #[test]
+    fn secure_search_stays_responsive_on_catastrophic_pattern() {
+        let input = evil_input(100_000);
+        let start = Instant::now();
+        let matches = secure_search(EVIL_PATTERN, &[input.as_str()]).unwrap();
+
+        assert!(matches.is_empty());
+        assert!(
+            start.elapsed() < Duration::from_millis(500),
+            "took {:?}",
+            start.elapsed()
+        );
+    }
+
+    #[test]
+    fn secure_search_limits_patterns() {
+        let long = "a".repeat(MAX_PATTERN_LEN + 1);
+        assert_eq!(
+            secure_search(&long, ITEMS),
+            Err(SearchError::PatternTooLong(MAX_PATTERN_LEN + 1))
+        );
+
+        // Short, but huge once the repetitions are expanded
+        assert!(matches!(
+            secure_search("(a{100}){100}", ITEMS),
+            Err(SearchError::InvalidPattern(_))
+        ));
+        assert!(matches!(
+            secure_search("(unclosed", ITEMS),
+            Err(SearchError::InvalidPattern(_))
+        ));
+    }
+
+    #[test]
+    fn backtracking_engine_rejects_malformed_patterns() {
+        for pattern in ["(a", "a)", "*a", "a\\"] {
+            assert!(
+                matches!(
+                    vulnerable_search(pattern, ITEMS),
+                    Err(SearchError::InvalidPattern(_))
+                ),
+                "pattern {:?}",
+                pattern
+            );
+        }
+    }
+}
@@ -0,0 +1,213 @@
+//! Declarative input validation for axum extractors
+//!
+//! `Validated<E>` wraps `Json<T>`, `Query<T>` or `Path<T>` and runs the
+//! `validator` rules declared on `T` after deserialization. Failures are
+//! rejected with 400 and per-field error details.
+
+use axum::{
+    Json,
+    extract::{FromRequest, FromRequestParts, Path, Query, Request},
+    http::{StatusCode, request::Parts},
+    response::{IntoResponse, Response},
+};
+use serde_json::{Value, json};
+use validator::{Validate, ValidationErrors};
+
+/// Extractor that deserializes with `E` and then validates the inner value
+#[derive(Debug, Clone)]
+pub struct Validated<E>(pub E);
+
+/// Access to the value that should be validated inside an extractor
+pub trait HasValidate {
+    type Validate: Validate;
+
+    fn get_validate(&self) -> &Self::Validate;
+}
+
+impl<T: Validate> HasValidate for Json<T> {
+    type Validate = T;
+
+    fn get_validate(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: Validate> HasValidate for Query<T> {
+    type Validate = T;
+
+    fn get_validate(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: Validate> HasValidate for Path<T> {
+    type Validate = T;
+
+    fn get_validate(&self) -> &T {
+        &self.0
+    }
+}
+
+/// Rejection returned by `Validated`
+#[derive(Debug)]
+pub enum ValidationRejection {
+    /// The inner extractor failed (e.g. malformed JSON)
+    Extractor(Response),
+    /// The value was deserialized but violates a declared rule
+    Invalid(ValidationErrors),
+}
+
+impl IntoResponse for ValidationRejection {
+    fn into_response(self) -> Response {
+        match self {
+            ValidationRejection::Extractor(response) => response,
+            ValidationRejection::Invalid(errors) => {
+                let body = Json(json!({
+                    "error": "Validation failed",
+                    "fields": field_errors_json(&errors),
+                }));
+                (StatusCode::BAD_REQUEST, body).into_response()
+            }
+        }
+    }
+}
+
+/// Convert validation errors to `{ field: [{ code, message }] }`
+fn field_errors_json(errors: &ValidationErrors) -> Value {
+    let fields = errors
+        .field_errors()
+        .into_iter()
+        .map(|(field, errors)| {
+            let details: Vec<Value> = errors
+                .iter()
+                .map(|e| {
+                    json!({
+                        "code": e.code,
+                        "message": e.message.as_ref().map(|m| m.to_string()),
+                    })
+                })
+                .collect();
+            (field.to_string(), Value::Array(details))
+        })
+        .collect();
+    Value::Object(fields)
+}
+
+fn validate_inner<E: HasValidate>(extracted: E) -> Result<Validated<E>, ValidationRejection> {
+    extracted
+        .get_validate()
+        .validate()
+        .map_err(ValidationRejection::Invalid)?;
+    Ok(Validated(extracted))
+}
+
+impl<S, E> FromRequestParts<S> for Validated<E>
+where
+    S: Send + Sync,
+    E: FromRequestParts<S> + HasValidate,
+    E::Rejection: IntoResponse,
+{
+    type Rejection = ValidationRejection;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let extracted = E::from_request_parts(parts, state)
+            .await
+            .map_err(|e| ValidationRejection::Extractor(e.into_response()))?;
+        validate_inner(extracted)
+    }
+}
+
+impl<S, E> FromRequest<S> for Validated<E>
+where
+    S: Send + Sync,
+    E: FromRequest<S> + HasValidate,
+    E::Rejection: IntoResponse,
+{
+    type Rejection = ValidationRejection;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let extracted = E::from_request(req, state)
+            .await
+            .map_err(|e| ValidationRejection::Extractor(e.into_response()))?;
+        validate_inner(extracted)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{Router, body::Body, http::Request, routing::get};
+    use serde::Deserialize;
+    use tower::ServiceExt;
+
+    #[derive(Debug, Deserialize, Validate)]
+    struct SearchParams {
+        #[validate(length(max = 10))]
+        q: String,
+    }
+
+    #[derive(Debug, Deserialize, Validate)]
+    struct IdPath {
+        #[validate(range(min = 1, max = 100))]
+        id: i64,
+    }
+
+    fn app() -> Router {
+        Router::new()
+            .route(
+                "/search",
+                get(|Validated(Query(params)): Validated<Query<SearchParams>>| async move {
+                    params.q
+                }),
+            )
+            .route(
+                "/items/{id}",
+                get(|Validated(Path(path)): Validated<Path<IdPath>>| async move {
+                    path.id.to_string()
+                }),
+            )
+    }
+
+    async fn get_response(uri: &str) -> (StatusCode, String) {
+        let response = app()
+            .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_rule_violation_returns_field_details() {
+        let (status, body) = get_response("/search?q=aaaaaaaaaaaaaaaa").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let body: Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(body["error"], "Validation failed");
+        assert_eq!(body["fields"]["q"][0]["code"], "length");
+    }
+
+    #[tokio::test]
+    async fn test_extractor_failure_is_passed_through() {
+        let (status, _) = get_response("/items/abc").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let (status, body) = get_response("/items/500").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(body.contains("range"));
+    }
+
+    #[tokio::test]
+    async fn test_valid_input_passes() {
+        let (status, body) = get_response("/search?q=rust").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, "rust");
+
+        let (status, body) = get_response("/items/42").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, "42");
+    }
+}
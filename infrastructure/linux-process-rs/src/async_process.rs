@@ -0,0 +1,114 @@
+//! Async process handle for tokio services (requires the `tokio` feature)
+//!
+//! `ProcessBuilder::spawn_async` returns an `AsyncProcess` wrapping
+//! `tokio::process::Child`. Waiting does not block a runtime thread, and exit
+//! statuses are collected through tokio's SIGCHLD handling, so children are
+//! reaped even when the handle is dropped early.
+
+use crate::errors::{ProcessError, ProcessResult};
+use crate::process::{exit_output, ProcessOutput};
+use crate::signal::SignalType;
+use nix::sys::signal::{kill, Signal};
+use nix::unistd::Pid;
+use std::time::{Duration, Instant};
+use tokio::process::{Child, ChildStderr, ChildStdin, ChildStdout};
+
+/// A child process spawned on the tokio runtime
+///
+/// The builder's `timeout`, `kill_grace` and `treat_signals_as_success`
+/// settings apply to `wait` the same way they do for `ProcessGuard::wait`.
+#[derive(Debug)]
+pub struct AsyncProcess {
+    child: Child,
+    label: Option<String>,
+    timeout: Option<Duration>,
+    kill_grace: Duration,
+    success_signals: Vec<SignalType>,
+}
+
+impl AsyncProcess {
+    pub(crate) fn new(
+        child: Child,
+        label: Option<String>,
+        timeout: Option<Duration>,
+        kill_grace: Duration,
+        success_signals: Vec<SignalType>,
+    ) -> Self {
+        Self {
+            child,
+            label,
+            timeout,
+            kill_grace,
+            success_signals,
+        }
+    }
+
+    /// PID of the process, or `None` once it has been reaped
+    pub fn pid(&self) -> Option<u32> {
+        self.child.id()
+    }
+
+    /// Label set on the builder, if any
+    pub fn label(&self) -> Option<&str> {
+        self.label.as_deref()
+    }
+
+    /// Take the child's stdin, if it was configured with `Stdio::piped()`
+    pub fn take_stdin(&mut self) -> Option<ChildStdin> {
+        self.child.stdin.take()
+    }
+
+    /// Take the child's stdout, if it was configured with `Stdio::piped()`
+    pub fn take_stdout(&mut self) -> Option<ChildStdout> {
+        self.child.stdout.take()
+    }
+
+    /// Take the child's stderr, if it was configured with `Stdio::piped()`
+    pub fn take_stderr(&mut self) -> Option<ChildStderr> {
+        self.child.stderr.take()
+    }
+
+    /// Wait for the process to exit without blocking the runtime
+    ///
+    /// If the builder set a `timeout` and it expires, the child is sent
+    /// SIGTERM, then SIGKILL after `kill_grace`, and is reaped before
+    /// `ProcessError::Timeout` is returned.
+    pub async fn wait(&mut self) -> ProcessResult<ProcessOutput> {
+        let status = match self.timeout {
+            Some(timeout) => {
+                let start = Instant::now();
+                match tokio::time::timeout(timeout, self.child.wait()).await {
+                    Ok(status) => status?,
+                    Err(_) => {
+                        let elapsed = start.elapsed();
+                        self.terminate().await?;
+                        return Err(ProcessError::Timeout { elapsed });
+                    }
+                }
+            }
+            None => self.child.wait().await?,
+        };
+
+        exit_output(status, &self.success_signals)
+    }
+
+    /// Kill the process with SIGKILL and wait until it has been reaped
+    pub async fn kill(&mut self) -> ProcessResult<()> {
+        self.child.kill().await?;
+        Ok(())
+    }
+
+    /// SIGTERMを送り、猶予期間内に終了しなければSIGKILLする（どちらも回収まで待つ）
+    async fn terminate(&mut self) -> ProcessResult<()> {
+        if let Some(pid) = self.child.id() {
+            let _ = kill(Pid::from_raw(pid as i32), Signal::SIGTERM);
+        }
+        if tokio::time::timeout(self.kill_grace, self.child.wait())
+            .await
+            .is_err()
+        {
+            self.child.kill().await?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,184 @@
+/// サーキットブレーカー - 連続した起動失敗時に新規起動を一時停止する
+///
+/// ディスクフルやexecエラーなどで起動が失敗し続ける場合に、
+/// 無駄な再試行を止めるための状態機械。
+/// Closed → (連続失敗) → Open → (クールダウン経過) → HalfOpen → Closed/Open
+use std::time::{Duration, Instant};
+
+/// サーキットの状態
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitState {
+    /// 通常どおり起動を受け付ける
+    Closed,
+    /// 起動を拒否している（クールダウン中）
+    Open,
+    /// 復旧確認のため次の起動を試行として受け付ける
+    HalfOpen,
+}
+
+/// サーキットブレーカーの設定
+#[derive(Debug, Clone)]
+pub struct CircuitBreakerConfig {
+    /// サーキットを開くまでの連続失敗回数
+    pub failure_threshold: u32,
+    /// 連続失敗を数える時間窓（最初の失敗からこの時間を過ぎるとカウントをリセット）
+    pub window: Duration,
+    /// Openのまま起動を拒否する時間
+    pub cooldown: Duration,
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        Self {
+            failure_threshold: 5,
+            window: Duration::from_secs(30),
+            cooldown: Duration::from_secs(10),
+        }
+    }
+}
+
+/// 起動の成否を記録してサーキットの状態を管理する
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    config: CircuitBreakerConfig,
+    consecutive_failures: u32,
+    first_failure_at: Option<Instant>,
+    opened_at: Option<Instant>,
+}
+
+impl CircuitBreaker {
+    pub fn new(config: CircuitBreakerConfig) -> Self {
+        Self {
+            config,
+            consecutive_failures: 0,
+            first_failure_at: None,
+            opened_at: None,
+        }
+    }
+
+    /// 現在の状態
+    pub fn state(&self) -> CircuitState {
+        self.state_at(Instant::now())
+    }
+
+    /// 起動を試みてよいか判定する。拒否する場合は再試行までの残り時間を返す
+    pub fn check(&self) -> Result<(), Duration> {
+        self.check_at(Instant::now())
+    }
+
+    /// 起動成功を記録（HalfOpenからはClosedに戻る）
+    pub fn record_success(&mut self) {
+        self.consecutive_failures = 0;
+        self.first_failure_at = None;
+        self.opened_at = None;
+    }
+
+    /// 起動失敗を記録
+    pub fn record_failure(&mut self) {
+        self.record_failure_at(Instant::now());
+    }
+
+    fn state_at(&self, now: Instant) -> CircuitState {
+        match self.opened_at {
+            None => CircuitState::Closed,
+            Some(opened_at) if now.duration_since(opened_at) < self.config.cooldown => {
+                CircuitState::Open
+            }
+            Some(_) => CircuitState::HalfOpen,
+        }
+    }
+
+    fn check_at(&self, now: Instant) -> Result<(), Duration> {
+        match (self.state_at(now), self.opened_at) {
+            (CircuitState::Open, Some(opened_at)) => {
+                Err(self.config.cooldown - now.duration_since(opened_at))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn record_failure_at(&mut self, now: Instant) {
+        // HalfOpenでの試行失敗は即座に再オープン
+        if self.opened_at.is_some() {
+            self.opened_at = Some(now);
+            return;
+        }
+
+        // 時間窓を過ぎた失敗は連続とみなさない
+        match self.first_failure_at {
+            Some(first) if now.duration_since(first) <= self.config.window => {
+                self.consecutive_failures += 1;
+            }
+            _ => {
+                self.first_failure_at = Some(now);
+                self.consecutive_failures = 1;
+            }
+        }
+
+        if self.consecutive_failures >= self.config.failure_threshold {
+            self.opened_at = Some(now);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn breaker() -> CircuitBreaker {
+        CircuitBreaker::new(CircuitBreakerConfig {
+            failure_threshold: 3,
+            window: Duration::from_secs(10),
+            cooldown: Duration::from_secs(5),
+        })
+    }
+
+    #[test]
+    fn test_opens_after_consecutive_failures() {
+        let mut cb = breaker();
+        let now = Instant::now();
+
+        cb.record_failure_at(now);
+        cb.record_failure_at(now + Duration::from_secs(1));
+        assert_eq!(cb.state_at(now + Duration::from_secs(1)), CircuitState::Closed);
+
+        cb.record_failure_at(now + Duration::from_secs(2));
+        assert_eq!(cb.state_at(now + Duration::from_secs(2)), CircuitState::Open);
+        assert_eq!(
+            cb.check_at(now + Duration::from_secs(4)),
+            Err(Duration::from_secs(3))
+        );
+    }
+
+    #[test]
+    fn test_failures_outside_window_do_not_open() {
+        let mut cb = breaker();
+        let now = Instant::now();
+
+        cb.record_failure_at(now);
+        cb.record_failure_at(now + Duration::from_secs(1));
+        cb.record_failure_at(now + Duration::from_secs(20));
+        assert_eq!(cb.state_at(now + Duration::from_secs(20)), CircuitState::Closed);
+    }
+
+    #[test]
+    fn test_half_open_after_cooldown() {
+        let mut cb = breaker();
+        let now = Instant::now();
+        for _ in 0..3 {
+            cb.record_failure_at(now);
+        }
+
+        let later = now + Duration::from_secs(5);
+        assert_eq!(cb.state_at(later), CircuitState::HalfOpen);
+        assert!(cb.check_at(later).is_ok());
+
+        // 試行が失敗すると再びOpen
+        cb.record_failure_at(later);
+        assert_eq!(cb.state_at(later), CircuitState::Open);
+
+        // 試行が成功するとClosed
+        cb.record_success();
+        assert_eq!(cb.state_at(later), CircuitState::Closed);
+    }
+}
@@ -25,9 +25,9 @@ pub enum ProcessError {
     #[error("Signal handling error: {0}")]
     SignalError(String),
 
-    /// Process timeout
-    #[error("Process timed out after {seconds} seconds")]
-    TimeoutError { seconds: u64 },
+    /// Process exceeded its time limit and was killed
+    #[error("Process timed out after {elapsed:?}")]
+    Timeout { elapsed: std::time::Duration },
 
     /// Permission denied
     #[error("Permission denied: {context}")]
@@ -42,11 +42,227 @@ pub enum ProcessError {
     #[error("Resource limit error: {message}")]
     ResourceLimitError { message: String },
 
+    /// Process was killed by a signal that is not treated as success
+    #[error("Process terminated by signal {signal}")]
+    TerminatedBySignal { signal: i32 },
+
+    /// Failed to open or write the log file
+    #[error("Log file error at {path}: {source}")]
+    LogFileError {
+        path: String,
+        #[source]
+        source: io::Error,
+    },
+
+    /// Atomic file replacement failed; the target keeps its previous content
+    #[error("Atomic write to {path} failed: {reason}")]
+    AtomicWriteError { path: String, reason: String },
+
+    /// The cgroup a child should be placed into is missing or not writable
+    #[error("Cgroup error at {path}: {reason}")]
+    CgroupError { path: String, reason: String },
+
+    /// The child could not be moved into the requested namespaces
+    #[error("Failed to unshare namespaces {namespaces}: {reason}")]
+    NamespaceError { namespaces: String, reason: String },
+
+    /// Spawning is suspended after repeated failures
+    #[error("Circuit open for pool '{pool}', retry after {retry_after:?}")]
+    CircuitOpen {
+        pool: String,
+        retry_after: std::time::Duration,
+    },
+
     /// Process already terminated
     #[error("Process {pid} already terminated")]
     ProcessTerminated { pid: u32 },
 }
 
+/// Stable, tagged wire representation of `ProcessError`
+///
+/// `io::Error` and `nix::Error` are carried as errno plus message so the
+/// receiving side rebuilds an equivalent error.
+#[cfg(feature = "serde")]
+mod serde_impl {
+    use super::ProcessError;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use std::io;
+    use std::time::Duration;
+
+    #[derive(Serialize, Deserialize)]
+    #[serde(tag = "kind", rename_all = "snake_case")]
+    enum Repr {
+        Io {
+            errno: Option<i32>,
+            message: String,
+        },
+        Spawn {
+            reason: String,
+        },
+        InvalidInput {
+            message: String,
+        },
+        Signal {
+            message: String,
+        },
+        Timeout {
+            elapsed: Duration,
+        },
+        PermissionDenied {
+            context: String,
+        },
+        #[cfg(unix)]
+        Fork {
+            errno: i32,
+            name: String,
+        },
+        ResourceLimit {
+            message: String,
+        },
+        TerminatedBySignal {
+            signal: i32,
+        },
+        LogFile {
+            path: String,
+            errno: Option<i32>,
+            message: String,
+        },
+        AtomicWrite {
+            path: String,
+            reason: String,
+        },
+        Cgroup {
+            path: String,
+            reason: String,
+        },
+        Namespace {
+            namespaces: String,
+            reason: String,
+        },
+        CircuitOpen {
+            pool: String,
+            retry_after: Duration,
+        },
+        ProcessTerminated {
+            pid: u32,
+        },
+    }
+
+    fn to_io_error(errno: Option<i32>, message: String) -> io::Error {
+        match errno {
+            Some(errno) => io::Error::from_raw_os_error(errno),
+            None => io::Error::new(io::ErrorKind::Other, message),
+        }
+    }
+
+    impl From<&ProcessError> for Repr {
+        fn from(error: &ProcessError) -> Self {
+            match error {
+                ProcessError::Io(e) => Repr::Io {
+                    errno: e.raw_os_error(),
+                    message: e.to_string(),
+                },
+                ProcessError::SpawnError { reason } => Repr::Spawn {
+                    reason: reason.clone(),
+                },
+                ProcessError::InvalidInput(message) => Repr::InvalidInput {
+                    message: message.clone(),
+                },
+                ProcessError::SignalError(message) => Repr::Signal {
+                    message: message.clone(),
+                },
+                ProcessError::Timeout { elapsed } => Repr::Timeout { elapsed: *elapsed },
+                ProcessError::PermissionDenied { context } => Repr::PermissionDenied {
+                    context: context.clone(),
+                },
+                #[cfg(unix)]
+                ProcessError::ForkError(errno) => Repr::Fork {
+                    errno: *errno as i32,
+                    name: format!("{:?}", errno),
+                },
+                ProcessError::ResourceLimitError { message } => Repr::ResourceLimit {
+                    message: message.clone(),
+                },
+                ProcessError::TerminatedBySignal { signal } => {
+                    Repr::TerminatedBySignal { signal: *signal }
+                }
+                ProcessError::LogFileError { path, source } => Repr::LogFile {
+                    path: path.clone(),
+                    errno: source.raw_os_error(),
+                    message: source.to_string(),
+                },
+                ProcessError::AtomicWriteError { path, reason } => Repr::AtomicWrite {
+                    path: path.clone(),
+                    reason: reason.clone(),
+                },
+                ProcessError::CgroupError { path, reason } => Repr::Cgroup {
+                    path: path.clone(),
+                    reason: reason.clone(),
+                },
+                ProcessError::NamespaceError { namespaces, reason } => Repr::Namespace {
+                    namespaces: namespaces.clone(),
+                    reason: reason.clone(),
+                },
+                ProcessError::CircuitOpen { pool, retry_after } => Repr::CircuitOpen {
+                    pool: pool.clone(),
+                    retry_after: *retry_after,
+                },
+                ProcessError::ProcessTerminated { pid } => Repr::ProcessTerminated { pid: *pid },
+            }
+        }
+    }
+
+    impl From<Repr> for ProcessError {
+        fn from(repr: Repr) -> Self {
+            match repr {
+                Repr::Io { errno, message } => ProcessError::Io(to_io_error(errno, message)),
+                Repr::Spawn { reason } => ProcessError::SpawnError { reason },
+                Repr::InvalidInput { message } => ProcessError::InvalidInput(message),
+                Repr::Signal { message } => ProcessError::SignalError(message),
+                Repr::Timeout { elapsed } => ProcessError::Timeout { elapsed },
+                Repr::PermissionDenied { context } => ProcessError::PermissionDenied { context },
+                #[cfg(unix)]
+                Repr::Fork { errno, .. } => {
+                    ProcessError::ForkError(nix::errno::Errno::from_i32(errno))
+                }
+                Repr::ResourceLimit { message } => ProcessError::ResourceLimitError { message },
+                Repr::TerminatedBySignal { signal } => ProcessError::TerminatedBySignal { signal },
+                Repr::LogFile {
+                    path,
+                    errno,
+                    message,
+                } => ProcessError::LogFileError {
+                    path,
+                    source: to_io_error(errno, message),
+                },
+                Repr::AtomicWrite { path, reason } => {
+                    ProcessError::AtomicWriteError { path, reason }
+                }
+                Repr::Cgroup { path, reason } => ProcessError::CgroupError { path, reason },
+                Repr::Namespace { namespaces, reason } => {
+                    ProcessError::NamespaceError { namespaces, reason }
+                }
+                Repr::CircuitOpen { pool, retry_after } => {
+                    ProcessError::CircuitOpen { pool, retry_after }
+                }
+                Repr::ProcessTerminated { pid } => ProcessError::ProcessTerminated { pid },
+            }
+        }
+    }
+
+    impl Serialize for ProcessError {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            Repr::from(self).serialize(serializer)
+        }
+    }
+
+    impl<'de> Deserialize<'de> for ProcessError {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            Repr::deserialize(deserializer).map(ProcessError::from)
+        }
+    }
+}
+
 /// Result type alias for process operations
 pub type ProcessResult<T> = Result<T, ProcessError>;
 
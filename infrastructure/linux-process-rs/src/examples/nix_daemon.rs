@@ -1,3 +1,4 @@
+use linux_process_rs::utils::write_atomic;
 use nix::sys::stat::{umask, Mode};
 use nix::unistd::{chdir, close, dup2, fork, setsid, ForkResult};
 use std::fs::{File, OpenOptions};
@@ -159,8 +160,8 @@ fn daemon_main() -> Result<(), Box<dyn std::error::Error>> {
 
 /// Creates a PID file for the daemon
 fn create_pid_file(path: &str) -> Result<(), Box<dyn std::error::Error>> {
-    let mut pid_file = File::create(path)?;
-    writeln!(pid_file, "{}", std::process::id())?;
+    // 途中で落ちても不完全なPIDファイルが残らないようにする
+    write_atomic(path, format!("{}\n", std::process::id()).as_bytes())?;
     Ok(())
 }
 
@@ -3,16 +3,31 @@
 //! This library provides comprehensive tools and examples for managing Linux processes
 //! in Rust, including process spawning, signal handling, zombie prevention, and more.
 
+#[cfg(feature = "tokio")]
+pub mod async_process;
+pub mod circuit_breaker;
 pub mod errors;
+pub mod namespace;
+pub mod pipeline;
+pub mod pool_state;
 pub mod process;
 pub mod process_guard;
 pub mod process_pool;
+pub mod pty;
+pub mod restart_policy;
 pub mod signal;
 pub mod utils;
 
 // Re-export commonly used types
+#[cfg(feature = "tokio")]
+pub use async_process::AsyncProcess;
+pub use circuit_breaker::{CircuitBreakerConfig, CircuitState};
 pub use errors::{ProcessError, ProcessResult};
-pub use process::ProcessBuilder;
+pub use namespace::NamespaceFlags;
+pub use pipeline::{Pipeline, PipelineOutput};
+pub use process::{CapturedOutput, ProcessBuilder, ProcessScope, Rlimit, SchedPolicy};
 pub use process_guard::ProcessGuard;
-pub use process_pool::ProcessPool;
-pub use signal::{SignalHandler, SignalType};
+pub use process_pool::{JobHandle, PoolMetrics, ProcessPool, ShutdownMode, ShutdownReport};
+pub use pty::{Pty, WindowSize};
+pub use restart_policy::{RestartPolicy, RestartStats};
+pub use signal::{HandlerId, SignalHandler, SignalType};
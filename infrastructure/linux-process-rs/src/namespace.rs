@@ -0,0 +1,293 @@
+//! Linux namespace isolation for spawned processes (`ProcessBuilder::unshare`)
+//!
+//! The child calls `unshare(2)` between fork and exec. A new PID namespace
+//! only applies to processes created *after* the call, so for
+//! `NamespaceFlags::PID` the child forks once more: the grandchild becomes
+//! PID 1 of the new namespace and execs the command, while the intermediate
+//! process stays behind as a supervisor. The supervisor forwards common
+//! signals to the command, exits with the command's status (re-raising the
+//! signal that killed it), and takes the command down with it if it is
+//! killed itself, so callers can treat it like the command.
+
+use crate::errors::ProcessError;
+use std::fmt;
+use std::io;
+use std::ops::{BitOr, BitOrAssign};
+use std::process::Command;
+use std::sync::atomic::{AtomicI32, Ordering};
+
+/// Set of namespaces a child should be moved into with `ProcessBuilder::unshare`
+///
+/// Combine flags with `|`, e.g. `NamespaceFlags::PID | NamespaceFlags::UTS`.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct NamespaceFlags(libc::c_int);
+
+impl NamespaceFlags {
+    /// Own mount table, made private so mounts never propagate back (`CLONE_NEWNS`)
+    pub const MOUNT: Self = Self(libc::CLONE_NEWNS);
+    /// Own hostname and domain name (`CLONE_NEWUTS`)
+    pub const UTS: Self = Self(libc::CLONE_NEWUTS);
+    /// Own System V IPC objects and POSIX message queues (`CLONE_NEWIPC`)
+    pub const IPC: Self = Self(libc::CLONE_NEWIPC);
+    /// Own network stack with only a down loopback interface (`CLONE_NEWNET`)
+    pub const NETWORK: Self = Self(libc::CLONE_NEWNET);
+    /// Own PID numbering; the command runs as PID 1 (`CLONE_NEWPID`)
+    pub const PID: Self = Self(libc::CLONE_NEWPID);
+    /// Own user and group IDs, allowing the other namespaces without privileges (`CLONE_NEWUSER`)
+    pub const USER: Self = Self(libc::CLONE_NEWUSER);
+    /// Own view of the cgroup hierarchy (`CLONE_NEWCGROUP`)
+    pub const CGROUP: Self = Self(libc::CLONE_NEWCGROUP);
+
+    const NAMED: [(Self, &'static str); 7] = [
+        (Self::MOUNT, "MOUNT"),
+        (Self::UTS, "UTS"),
+        (Self::IPC, "IPC"),
+        (Self::NETWORK, "NETWORK"),
+        (Self::PID, "PID"),
+        (Self::USER, "USER"),
+        (Self::CGROUP, "CGROUP"),
+    ];
+
+    /// No namespaces
+    pub const fn empty() -> Self {
+        Self(0)
+    }
+
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Whether every namespace in `other` is also in `self`
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    fn bits(self) -> libc::c_int {
+        self.0
+    }
+}
+
+impl BitOr for NamespaceFlags {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for NamespaceFlags {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+impl fmt::Display for NamespaceFlags {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names: Vec<&str> = Self::NAMED
+            .iter()
+            .filter(|(flag, _)| self.contains(*flag))
+            .map(|&(_, name)| name)
+            .collect();
+        if names.is_empty() {
+            f.write_str("(empty)")
+        } else {
+            f.write_str(&names.join(" | "))
+        }
+    }
+}
+
+impl fmt::Debug for NamespaceFlags {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "NamespaceFlags({})", self)
+    }
+}
+
+/// Move the child into new namespaces before exec
+///
+/// Must be registered after every pre-exec step that should run in the
+/// supervisor too, and before steps that need the new namespaces (dropping
+/// privileges comes after, since `unshare` itself needs them).
+pub(crate) fn unshare(cmd: &mut Command, flags: NamespaceFlags) {
+    use std::os::unix::process::CommandExt;
+
+    unsafe {
+        cmd.pre_exec(move || {
+            if libc::unshare(flags.bits()) == -1 {
+                return Err(io::Error::last_os_error());
+            }
+            if flags.contains(NamespaceFlags::MOUNT) {
+                // 共有マウントのままだと子のマウントが親の名前空間にも伝播する
+                let root = b"/\0".as_ptr() as *const libc::c_char;
+                let ret = libc::mount(
+                    std::ptr::null(),
+                    root,
+                    std::ptr::null(),
+                    libc::MS_REC | libc::MS_PRIVATE,
+                    std::ptr::null(),
+                );
+                if ret == -1 {
+                    return Err(io::Error::last_os_error());
+                }
+            }
+            if flags.contains(NamespaceFlags::PID) {
+                fork_into_pid_namespace()?;
+            }
+            Ok(())
+        });
+    }
+}
+
+/// Attribute a spawn failure to `unshare` when namespaces were requested
+///
+/// The pre-exec error only carries an errno, so this maps the errors
+/// `unshare(2)` reports for missing privileges or exhausted namespace limits.
+pub(crate) fn unshare_error(flags: NamespaceFlags, error: &io::Error) -> Option<ProcessError> {
+    if flags.is_empty() {
+        return None;
+    }
+    let reason = match error.raw_os_error()? {
+        libc::EPERM => format!(
+            "{} (needs CAP_SYS_ADMIN, or NamespaceFlags::USER where unprivileged user namespaces are allowed)",
+            error
+        ),
+        libc::EINVAL | libc::ENOSPC | libc::EUSERS => error.to_string(),
+        _ => return None,
+    };
+    Some(ProcessError::NamespaceError {
+        namespaces: flags.to_string(),
+        reason,
+    })
+}
+
+/// Signals the supervisor passes on to the command
+const FORWARDED_SIGNALS: [libc::c_int; 7] = [
+    libc::SIGTERM,
+    libc::SIGINT,
+    libc::SIGHUP,
+    libc::SIGQUIT,
+    libc::SIGUSR1,
+    libc::SIGUSR2,
+    libc::SIGWINCH,
+];
+
+// fork後の監督プロセスにだけ設定される（シグナルハンドラから参照する）
+static SUPERVISED_PID: AtomicI32 = AtomicI32::new(0);
+
+extern "C" fn forward_signal(signal: libc::c_int) {
+    let pid = SUPERVISED_PID.load(Ordering::Relaxed);
+    if pid > 0 {
+        unsafe {
+            let errno = *libc::__errno_location();
+            libc::kill(pid, signal);
+            *libc::__errno_location() = errno;
+        }
+    }
+}
+
+/// Fork after `unshare(CLONE_NEWPID)`; returns only in the grandchild (PID 1)
+///
+/// Runs between fork and exec, so it only uses async-signal-safe calls.
+unsafe fn fork_into_pid_namespace() -> io::Result<()> {
+    // ハンドラを設定し終えるまでに届いたシグナルを取りこぼさないようブロックしておく
+    let mut forwarded: libc::sigset_t = std::mem::zeroed();
+    let mut previous: libc::sigset_t = std::mem::zeroed();
+    libc::sigemptyset(&mut forwarded);
+    for signal in FORWARDED_SIGNALS {
+        libc::sigaddset(&mut forwarded, signal);
+    }
+    libc::sigprocmask(libc::SIG_BLOCK, &forwarded, &mut previous);
+
+    match libc::fork() {
+        -1 => {
+            let error = io::Error::last_os_error();
+            libc::sigprocmask(libc::SIG_SETMASK, &previous, std::ptr::null_mut());
+            Err(error)
+        }
+        0 => {
+            libc::sigprocmask(libc::SIG_SETMASK, &previous, std::ptr::null_mut());
+            // 監督プロセスがSIGKILLされたらコマンドも道連れにする
+            if libc::prctl(libc::PR_SET_PDEATHSIG, libc::SIGKILL) == -1 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(())
+        }
+        pid => supervise(pid, &previous),
+    }
+}
+
+/// Wait for the command in the PID namespace and exit the same way it did
+unsafe fn supervise(pid: libc::pid_t, previous_mask: &libc::sigset_t) -> ! {
+    SUPERVISED_PID.store(pid, Ordering::Relaxed);
+    let mut action: libc::sigaction = std::mem::zeroed();
+    action.sa_sigaction = forward_signal as extern "C" fn(libc::c_int) as libc::sighandler_t;
+    action.sa_flags = libc::SA_RESTART;
+    libc::sigemptyset(&mut action.sa_mask);
+    for signal in FORWARDED_SIGNALS {
+        libc::sigaction(signal, &action, std::ptr::null_mut());
+    }
+    libc::sigprocmask(libc::SIG_SETMASK, previous_mask, std::ptr::null_mut());
+
+    // exec失敗の通知用パイプやstdioを持ち続けると、親のspawnや読み込みが終わらない
+    close_all_fds();
+
+    let mut status = 0;
+    while libc::waitpid(pid, &mut status, 0) == -1 {
+        if *libc::__errno_location() != libc::EINTR {
+            libc::_exit(127);
+        }
+    }
+
+    if libc::WIFSIGNALED(status) {
+        let signal = libc::WTERMSIG(status);
+        libc::signal(signal, libc::SIG_DFL);
+        let mut unblock: libc::sigset_t = std::mem::zeroed();
+        libc::sigemptyset(&mut unblock);
+        libc::sigaddset(&mut unblock, signal);
+        libc::sigprocmask(libc::SIG_UNBLOCK, &unblock, std::ptr::null_mut());
+        libc::kill(libc::getpid(), signal);
+        libc::_exit(128 + signal);
+    }
+    libc::_exit(libc::WEXITSTATUS(status))
+}
+
+unsafe fn close_all_fds() {
+    if libc::syscall(libc::SYS_close_range, 0, libc::c_uint::MAX, 0) == 0 {
+        return;
+    }
+    // close_rangeのないカーネル（5.9未満）
+    let max = match libc::sysconf(libc::_SC_OPEN_MAX) {
+        n if n > 0 => n as libc::c_int,
+        _ => 1024,
+    };
+    for fd in 0..max {
+        libc::close(fd);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flags_combine_and_display() {
+        let flags = NamespaceFlags::PID | NamespaceFlags::UTS;
+        assert!(flags.contains(NamespaceFlags::PID));
+        assert!(!flags.contains(NamespaceFlags::NETWORK));
+        assert_eq!(flags.to_string(), "UTS | PID");
+        assert_eq!(NamespaceFlags::empty().to_string(), "(empty)");
+    }
+
+    #[test]
+    fn test_unshare_errors_are_attributed_to_namespaces() {
+        let eperm = io::Error::from_raw_os_error(libc::EPERM);
+        let error = unshare_error(NamespaceFlags::NETWORK, &eperm).unwrap();
+        assert!(matches!(
+            error,
+            ProcessError::NamespaceError { ref namespaces, .. } if namespaces == "NETWORK"
+        ));
+
+        let enoent = io::Error::from_raw_os_error(libc::ENOENT);
+        assert!(unshare_error(NamespaceFlags::NETWORK, &enoent).is_none());
+        assert!(unshare_error(NamespaceFlags::empty(), &eperm).is_none());
+    }
+}
@@ -0,0 +1,103 @@
+//! Shell-style pipelines (`cmd1 | cmd2 | ...`) without a shell
+
+use crate::errors::{ProcessError, ProcessResult};
+use crate::process::ProcessBuilder;
+use crate::utils::output_pipe;
+use std::io::Read;
+use std::process::{Child, ExitStatus, Stdio};
+
+/// Commands whose stdout feeds the stdin of the next one
+///
+/// Build one with `ProcessBuilder::pipe` or `Pipeline::new`. All stages run
+/// concurrently; each reads from a pipe the previous stage writes to, and the
+/// last stage's stdout is captured. stderr is not redirected (inherited unless
+/// set on a stage), and per-stage timeouts are not enforced.
+#[derive(Debug)]
+pub struct Pipeline {
+    stages: Vec<ProcessBuilder>,
+}
+
+/// Result of `Pipeline::output`
+#[derive(Debug, Clone)]
+pub struct PipelineOutput {
+    /// Everything the last stage wrote to stdout
+    pub stdout: Vec<u8>,
+    /// Exit status of every stage, in pipeline order
+    pub statuses: Vec<ExitStatus>,
+}
+
+impl PipelineOutput {
+    /// Whether every stage exited successfully (like bash's `pipefail`)
+    pub fn success(&self) -> bool {
+        self.statuses.iter().all(ExitStatus::success)
+    }
+}
+
+impl Pipeline {
+    /// Start a pipeline with `first` as its only stage
+    pub fn new(first: ProcessBuilder) -> Self {
+        Self {
+            stages: vec![first],
+        }
+    }
+
+    /// Append a stage that reads the current last stage's stdout
+    pub fn pipe(mut self, next: ProcessBuilder) -> Self {
+        self.stages.push(next);
+        self
+    }
+
+    /// Run all stages, collect the last stage's stdout and wait for every stage
+    ///
+    /// The first stage keeps its own stdin setting. If a stage fails to spawn,
+    /// the stages already running are killed and reaped before the error is
+    /// returned.
+    pub fn output(self) -> ProcessResult<PipelineOutput> {
+        let last = self.stages.len() - 1;
+        let mut children: Vec<Child> = Vec::with_capacity(self.stages.len());
+        let mut upstream = None;
+
+        for (i, mut stage) in self.stages.into_iter().enumerate() {
+            if let Some(reader) = upstream.take() {
+                stage = stage.stdin(Stdio::from(reader));
+            }
+            // 書き込み端はspawn後にbuilderと一緒に破棄され、親には残らない。
+            // 残ると後段が前段の終了を検知できず（EOFにならず）止まる
+            let spawned = if i == last {
+                stage.stdout(Stdio::piped()).spawn_child()
+            } else {
+                output_pipe().and_then(|(reader, writer)| {
+                    upstream = Some(reader);
+                    stage.stdout(Stdio::from(writer)).spawn_child()
+                })
+            };
+
+            match spawned {
+                Ok(child) => children.push(child),
+                Err(e) => {
+                    drop(upstream);
+                    for mut child in children {
+                        let _ = child.kill();
+                        let _ = child.wait();
+                    }
+                    return Err(e);
+                }
+            }
+        }
+
+        // 最終段の出力を読み切ってから全段の終了を待つ（パイプ詰まりを防ぐ）
+        let mut stdout = Vec::new();
+        let read = match children[last].stdout.take() {
+            Some(mut out) => out.read_to_end(&mut stdout).map(|_| ()),
+            None => Ok(()),
+        };
+
+        let mut statuses = Vec::with_capacity(children.len());
+        for child in &mut children {
+            statuses.push(child.wait()?);
+        }
+        read.map_err(ProcessError::Io)?;
+
+        Ok(PipelineOutput { stdout, statuses })
+    }
+}
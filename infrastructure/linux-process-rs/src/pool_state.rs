@@ -0,0 +1,264 @@
+/// プールの状態ファイル - 再起動をまたいでワーカーを引き継ぐ
+///
+/// `ProcessPool::with_state_file`で使われる。1行1ワーカーのタブ区切りで
+/// PID・起動時刻・重み・シャットダウン優先度・ラベル・コマンドを保存する。
+/// 起動時刻は`/proc/<pid>/stat`のstarttimeで、PIDが別プロセスに再利用されて
+/// いても誤って引き継がないよう照合に使う。
+use crate::errors::ProcessResult;
+use crate::utils::write_atomic;
+use nix::sys::signal::{kill, Signal};
+use nix::sys::wait::{waitpid, WaitPidFlag};
+use nix::unistd::Pid;
+use std::fmt::Write as _;
+use std::io;
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// 状態ファイルに記録されたワーカー
+#[derive(Debug, Clone, PartialEq)]
+pub struct PersistedWorker {
+    pub pid: Pid,
+    /// `/proc/<pid>/stat`のstarttime（ブートからのクロックティック数）
+    pub start_time: u64,
+    pub weight: usize,
+    pub shutdown_priority: i32,
+    pub label: Option<String>,
+    pub command: String,
+}
+
+impl PersistedWorker {
+    fn to_line(&self) -> String {
+        // 区切り文字を含む値は1行に収まるよう空白に置き換える
+        let clean = |s: &str| s.replace(['\t', '\n', '\r'], " ");
+        format!(
+            "{}\t{}\t{}\t{}\t{}\t{}",
+            self.pid,
+            self.start_time,
+            self.weight,
+            self.shutdown_priority,
+            clean(self.label.as_deref().unwrap_or("")),
+            clean(&self.command)
+        )
+    }
+
+    fn from_line(line: &str) -> Option<Self> {
+        let mut fields = line.splitn(6, '\t');
+        let pid = fields.next()?.parse().ok()?;
+        let start_time = fields.next()?.parse().ok()?;
+        let weight = fields.next()?.parse().ok()?;
+        let shutdown_priority = fields.next()?.parse().ok()?;
+        let label = fields.next()?;
+        let command = fields.next()?;
+        Some(Self {
+            pid: Pid::from_raw(pid),
+            start_time,
+            weight,
+            shutdown_priority,
+            label: (!label.is_empty()).then(|| label.to_string()),
+            command: command.to_string(),
+        })
+    }
+}
+
+/// 状態ファイルを読み込む（ファイルがなければ空）
+///
+/// 解釈できない行は警告を出して読み飛ばす。
+pub fn load<P: AsRef<Path>>(path: P) -> ProcessResult<Vec<PersistedWorker>> {
+    let path = path.as_ref();
+    let content = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+
+    let mut workers = Vec::new();
+    for (lineno, line) in content.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        match PersistedWorker::from_line(line) {
+            Some(worker) => workers.push(worker),
+            None => eprintln!(
+                "{}:{}: 解釈できない行を読み飛ばします",
+                path.display(),
+                lineno + 1
+            ),
+        }
+    }
+    Ok(workers)
+}
+
+/// 状態ファイルをアトミックに書き換える
+pub fn save<'a, P, I>(path: P, workers: I) -> ProcessResult<()>
+where
+    P: AsRef<Path>,
+    I: IntoIterator<Item = &'a PersistedWorker>,
+{
+    let mut content = String::new();
+    for worker in workers {
+        let _ = writeln!(content, "{}", worker.to_line());
+    }
+    write_atomic(path, content.as_bytes())
+}
+
+/// `/proc/<pid>/stat`から状態文字と起動時刻を読む
+pub(crate) fn proc_stat(pid: Pid) -> Option<(char, u64)> {
+    let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+    // commは空白や括弧を含みうるので、最後の')'より後ろを数える
+    let rest = &stat[stat.rfind(')')? + 1..];
+    let mut fields = rest.split_whitespace();
+    let state = fields.next()?.chars().next()?;
+    // stateがフィールド3、starttimeがフィールド22
+    let start_time = fields.nth(18)?.parse().ok()?;
+    Some((state, start_time))
+}
+
+/// `/proc/<pid>/stat`から親プロセスのPIDを読む
+pub(crate) fn proc_ppid(pid: Pid) -> Option<Pid> {
+    let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+    let rest = &stat[stat.rfind(')')? + 1..];
+    // ppidはフィールド4（stateの次）
+    let ppid = rest.split_whitespace().nth(1)?.parse().ok()?;
+    Some(Pid::from_raw(ppid))
+}
+
+/// プロセスの経過時間（/proc/uptimeとstarttimeから算出）
+pub(crate) fn proc_elapsed(start_time: u64) -> Option<Duration> {
+    let uptime = std::fs::read_to_string("/proc/uptime").ok()?;
+    let uptime: f64 = uptime.split_whitespace().next()?.parse().ok()?;
+    let ticks = unsafe { libc::sysconf(libc::_SC_CLK_TCK) };
+    if ticks <= 0 {
+        return None;
+    }
+    let started = start_time as f64 / ticks as f64;
+    Some(Duration::from_secs_f64((uptime - started).max(0.0)))
+}
+
+/// 以前のプールから引き継いだプロセス
+///
+/// 自分の子とは限らないため`Child`は持たず、PIDと起動時刻で追跡する。
+/// `ProcessGuard`と同じく、Drop時に実行中ならSIGTERM→SIGKILLで終了させる。
+#[derive(Debug)]
+pub(crate) struct AdoptedProcess {
+    pid: Pid,
+    start_time: u64,
+}
+
+impl AdoptedProcess {
+    /// 記録と一致するプロセスが生きていれば引き継ぐ
+    ///
+    /// 終了済み（ゾンビを含む）やPIDが再利用されている場合は`None`。
+    /// 自分の子のゾンビであればここで回収する。
+    pub(crate) fn adopt(worker: &PersistedWorker) -> Option<Self> {
+        let adopted = Self {
+            pid: worker.pid,
+            start_time: worker.start_time,
+        };
+        if adopted.is_running() {
+            Some(adopted)
+        } else {
+            None
+        }
+    }
+
+    pub(crate) fn pid(&self) -> Pid {
+        self.pid
+    }
+
+    pub(crate) fn start_time(&self) -> u64 {
+        self.start_time
+    }
+
+    pub(crate) fn is_running(&self) -> bool {
+        match proc_stat(self.pid) {
+            Some((state, start_time)) if start_time == self.start_time => {
+                if state == 'Z' || state == 'X' {
+                    // 自分の子であれば回収する（他人の子ならECHILDで何もしない）
+                    let _ = waitpid(self.pid, Some(WaitPidFlag::WNOHANG));
+                    false
+                } else {
+                    true
+                }
+            }
+            _ => false,
+        }
+    }
+
+    /// 終了を待つ。時間内に終了すれば`true`
+    pub(crate) fn wait_timeout(&self, timeout: Duration) -> bool {
+        let start = Instant::now();
+        while self.is_running() {
+            if start.elapsed() >= timeout {
+                return false;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        true
+    }
+}
+
+impl Drop for AdoptedProcess {
+    fn drop(&mut self) {
+        if !self.is_running() {
+            return;
+        }
+        eprintln!("AdoptedProcess: PID {} を終了します", self.pid);
+
+        if let Err(e) = kill(self.pid, Signal::SIGTERM) {
+            eprintln!("AdoptedProcess: SIGTERM送信失敗: {}", e);
+        }
+        if !self.wait_timeout(Duration::from_millis(500)) {
+            eprintln!("AdoptedProcess: プロセスがまだ実行中、SIGKILLで強制終了");
+            if let Err(e) = kill(self.pid, Signal::SIGKILL) {
+                eprintln!("AdoptedProcess: SIGKILL失敗: {}", e);
+            }
+            self.wait_timeout(Duration::from_millis(500));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_state_file_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("pool.state");
+        let workers = vec![
+            PersistedWorker {
+                pid: Pid::from_raw(1234),
+                start_time: 5678,
+                weight: 2,
+                shutdown_priority: -1,
+                label: Some("image\tresizer".into()),
+                command: "sleep 10".into(),
+            },
+            PersistedWorker {
+                pid: Pid::from_raw(42),
+                start_time: 1,
+                weight: 1,
+                shutdown_priority: 0,
+                label: None,
+                command: "worker --flag value".into(),
+            },
+        ];
+
+        save(&path, &workers).unwrap();
+        let loaded = load(&path).unwrap();
+
+        assert_eq!(loaded.len(), 2);
+        assert_eq!(loaded[0].label.as_deref(), Some("image resizer"));
+        assert_eq!(loaded[1], workers[1]);
+        assert!(load(dir.path().join("missing")).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_proc_stat_reads_own_process() {
+        let (state, start_time) = proc_stat(Pid::this()).unwrap();
+        assert_ne!(state, 'Z');
+        assert!(start_time > 0);
+        assert!(proc_elapsed(start_time).is_some());
+    }
+}
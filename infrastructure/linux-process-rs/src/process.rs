@@ -1,21 +1,159 @@
 //! Process management module with ownership and lifetime best practices
 
 use crate::errors::{ProcessError, ProcessResult};
+use crate::namespace::{self, NamespaceFlags};
+use crate::pty::{Pty, WindowSize};
+use crate::signal::SignalType;
+use nix::sys::resource::{getrlimit, setrlimit, Resource};
+use nix::unistd::Uid;
 use std::io;
-use std::process::{Child, Command, Stdio};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, Command, ExitStatus, Stdio};
+use std::thread::JoinHandle;
 use std::time::Duration;
 
+/// Lowest value accepted by `/proc/<pid>/oom_score_adj` (never OOM-killed)
+pub const OOM_SCORE_ADJ_MIN: i32 = -1000;
+
+/// Highest value accepted by `/proc/<pid>/oom_score_adj` (killed first)
+pub const OOM_SCORE_ADJ_MAX: i32 = 1000;
+
+/// Highest scheduling priority accepted by `ProcessBuilder::nice`
+pub const NICE_MIN: i32 = -20;
+
+/// Lowest scheduling priority accepted by `ProcessBuilder::nice`
+pub const NICE_MAX: i32 = 19;
+
+/// Default time a timed-out child gets between SIGTERM and SIGKILL
+pub const DEFAULT_KILL_GRACE: Duration = Duration::from_secs(2);
+
+/// Default time a child gets between the drop signal and SIGKILL when its guard is dropped
+pub const DEFAULT_DROP_GRACE: Duration = Duration::from_millis(100);
+
+/// Resource limits that can be applied to a child with `ProcessBuilder::rlimit`
+///
+/// Linux-only: each variant maps to the `setrlimit(2)` resource of the same
+/// name and follows Linux semantics.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rlimit {
+    /// Maximum size of the virtual address space in bytes (`RLIMIT_AS`)
+    AddressSpace,
+    /// CPU time in seconds: SIGXCPU at the soft limit, SIGKILL at the hard limit (`RLIMIT_CPU`)
+    Cpu,
+    /// One more than the highest file descriptor number that can be opened (`RLIMIT_NOFILE`)
+    NoFile,
+    /// Maximum number of processes for the child's real user ID (`RLIMIT_NPROC`)
+    NProc,
+}
+
+impl Rlimit {
+    fn resource(self) -> Resource {
+        match self {
+            Self::AddressSpace => Resource::RLIMIT_AS,
+            Self::Cpu => Resource::RLIMIT_CPU,
+            Self::NoFile => Resource::RLIMIT_NOFILE,
+            Self::NProc => Resource::RLIMIT_NPROC,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::AddressSpace => "RLIMIT_AS",
+            Self::Cpu => "RLIMIT_CPU",
+            Self::NoFile => "RLIMIT_NOFILE",
+            Self::NProc => "RLIMIT_NPROC",
+        }
+    }
+}
+
+/// Scheduling policies that can be applied to a child with `ProcessBuilder::sched_policy`
+///
+/// Linux-only: each variant maps to the `sched_setscheduler(2)` policy of the
+/// same name. The real-time policies carry a static priority (1-99) and
+/// require `CAP_SYS_NICE` unless `RLIMIT_RTPRIO` allows the priority.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SchedPolicy {
+    /// Default time-sharing policy (`SCHED_OTHER`)
+    Other,
+    /// CPU-bound batch work, scheduled with a small penalty (`SCHED_BATCH`)
+    Batch,
+    /// Runs only when nothing else wants the CPU (`SCHED_IDLE`)
+    Idle,
+    /// Real-time first-in first-out with the given priority (`SCHED_FIFO`)
+    Fifo(u8),
+    /// Real-time round-robin with the given priority (`SCHED_RR`)
+    RoundRobin(u8),
+}
+
+impl SchedPolicy {
+    fn policy(self) -> libc::c_int {
+        match self {
+            Self::Other => libc::SCHED_OTHER,
+            Self::Batch => libc::SCHED_BATCH,
+            Self::Idle => libc::SCHED_IDLE,
+            Self::Fifo(_) => libc::SCHED_FIFO,
+            Self::RoundRobin(_) => libc::SCHED_RR,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Other => "SCHED_OTHER",
+            Self::Batch => "SCHED_BATCH",
+            Self::Idle => "SCHED_IDLE",
+            Self::Fifo(_) => "SCHED_FIFO",
+            Self::RoundRobin(_) => "SCHED_RR",
+        }
+    }
+
+    /// Static priority: 0 for the time-sharing policies
+    fn priority(self) -> u8 {
+        match self {
+            Self::Fifo(priority) | Self::RoundRobin(priority) => priority,
+            Self::Other | Self::Batch | Self::Idle => 0,
+        }
+    }
+}
+
+/// Output of `ProcessBuilder::output_string`, decoded as (lossy) UTF-8
+#[derive(Debug, Clone)]
+pub struct CapturedOutput {
+    /// Standard output, or both streams in write order with `merge_stderr(true)`
+    pub stdout: String,
+    /// Standard error (empty with `merge_stderr(true)`)
+    pub stderr: String,
+    pub status: ExitStatus,
+}
+
 /// Builder pattern for creating processes with validation
 #[derive(Debug)]
 pub struct ProcessBuilder {
     command: String,
     args: Vec<String>,
     env_vars: Vec<(String, String)>,
-    working_dir: Option<String>,
+    inherit_env: bool,
+    env_inherit_patterns: Vec<String>,
+    working_dir: Option<PathBuf>,
     stdin: Option<Stdio>,
+    stdin_data: Option<Vec<u8>>,
     stdout: Option<Stdio>,
     stderr: Option<Stdio>,
     timeout: Option<Duration>,
+    kill_grace: Duration,
+    success_signals: Vec<SignalType>,
+    pty: Option<WindowSize>,
+    label: Option<String>,
+    oom_score_adj: Option<i32>,
+    nice: Option<i32>,
+    sched_policy: Option<SchedPolicy>,
+    rlimits: Vec<(Rlimit, u64, u64)>,
+    cgroup: Option<PathBuf>,
+    uid: Option<u32>,
+    gid: Option<u32>,
+    supplementary_groups: Option<Vec<u32>>,
+    namespaces: NamespaceFlags,
+    merge_stderr: bool,
+    process_group: bool,
 }
 
 impl ProcessBuilder {
@@ -25,11 +163,29 @@ impl ProcessBuilder {
             command: command.into(),
             args: Vec::new(),
             env_vars: Vec::new(),
+            inherit_env: true,
+            env_inherit_patterns: Vec::new(),
             working_dir: None,
             stdin: None,
+            stdin_data: None,
             stdout: None,
             stderr: None,
             timeout: None,
+            kill_grace: DEFAULT_KILL_GRACE,
+            success_signals: Vec::new(),
+            pty: None,
+            label: None,
+            oom_score_adj: None,
+            nice: None,
+            sched_policy: None,
+            rlimits: Vec::new(),
+            cgroup: None,
+            uid: None,
+            gid: None,
+            supplementary_groups: None,
+            namespaces: NamespaceFlags::empty(),
+            merge_stderr: false,
+            process_group: false,
         }
     }
 
@@ -39,18 +195,214 @@ impl ProcessBuilder {
         self
     }
 
-    /// Set timeout for the process
+    /// Set a wall-clock limit for the process
+    ///
+    /// When waiting exceeds the limit the child is sent SIGTERM, then SIGKILL
+    /// if it is still running after `kill_grace`, and is reaped before
+    /// `ProcessError::Timeout` is returned.
     pub fn timeout(mut self, duration: Duration) -> Self {
         self.timeout = Some(duration);
         self
     }
 
+    /// Time a timed-out child gets to exit after SIGTERM before SIGKILL
+    /// (default: `DEFAULT_KILL_GRACE`)
+    pub fn kill_grace(mut self, grace: Duration) -> Self {
+        self.kill_grace = grace;
+        self
+    }
+
+    /// Attach a human-readable label (e.g. "image-resizer") to the process
+    ///
+    /// The label is carried to the spawned `ProcessGuard` and into `ProcessPool`
+    /// worker info, so logs and status reports can name the worker instead of a PID.
+    pub fn label<S: Into<String>>(mut self, label: S) -> Self {
+        self.label = Some(label.into());
+        self
+    }
+
+    /// Adjust how likely the OOM killer is to pick the child
+    ///
+    /// The value is written to the child's `oom_score_adj` between fork and
+    /// exec, so it is in effect before the command runs. Positive values make
+    /// the child a preferred victim; negative values protect it, which requires
+    /// `CAP_SYS_RESOURCE` (otherwise spawning fails with a permission error).
+    /// Values outside `OOM_SCORE_ADJ_MIN..=OOM_SCORE_ADJ_MAX` are rejected at spawn.
+    pub fn oom_score_adj(mut self, score: i32) -> Self {
+        self.oom_score_adj = Some(score);
+        self
+    }
+
+    /// Set the child's nice value (`NICE_MIN..=NICE_MAX`, higher is lower priority)
+    ///
+    /// The value is applied with `setpriority` in the child between fork and
+    /// exec. Lowering the priority (a value at or above the caller's own nice)
+    /// needs no privilege; raising it needs root or `CAP_SYS_NICE` unless
+    /// `RLIMIT_NICE` allows it. Out-of-range values are rejected as
+    /// `ProcessError::InvalidInput` and a missing privilege as
+    /// `ProcessError::PermissionDenied` at spawn.
+    pub fn nice(mut self, nice: i32) -> Self {
+        self.nice = Some(nice);
+        self
+    }
+
+    /// Run the child under a scheduling policy (Linux-only)
+    ///
+    /// The policy is applied with `sched_setscheduler` in the child between
+    /// fork and exec, before `nice`. `Batch` and `Idle` need no privilege;
+    /// `Fifo` and `RoundRobin` take a priority of 1-99 and need root or
+    /// `CAP_SYS_NICE` unless `RLIMIT_RTPRIO` allows it, otherwise spawning
+    /// fails with `ProcessError::PermissionDenied`.
+    pub fn sched_policy(mut self, policy: SchedPolicy) -> Self {
+        self.sched_policy = Some(policy);
+        self
+    }
+
+    /// Limit a resource for the child (Linux-only)
+    ///
+    /// The limit is applied with `setrlimit` in the child between fork and exec,
+    /// so the command starts already constrained and the parent is unaffected.
+    /// Setting the same resource again replaces the earlier limit. A soft limit
+    /// above the hard limit, or raising the hard limit above the caller's own
+    /// without root, is reported as `ProcessError::ResourceLimitError` at spawn.
+    pub fn rlimit(mut self, resource: Rlimit, soft: u64, hard: u64) -> Self {
+        self.rlimits.retain(|(r, _, _)| *r != resource);
+        self.rlimits.push((resource, soft, hard));
+        self
+    }
+
+    /// Place the child into a cgroup v2 directory (e.g. `/sys/fs/cgroup/app.slice/worker`)
+    ///
+    /// The child moves itself into the cgroup by writing to `cgroup.procs`
+    /// between fork and exec, so the command never runs outside of it. A
+    /// directory without a `cgroup.procs` file or one the caller cannot write
+    /// is reported as `ProcessError::CgroupError` at spawn.
+    pub fn cgroup<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.cgroup = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Run the child as this user ID (requires root)
+    ///
+    /// Privileges are dropped between fork and exec with `setgroups`, `setgid`
+    /// and then `setuid`, after the other pre-exec steps (cgroup, rlimits,
+    /// OOM score) that may still need them. Setting a user also replaces the
+    /// caller's supplementary groups with those given to
+    /// `supplementary_groups` (none by default), so root's groups are not
+    /// inherited. If any call fails the exec is aborted and spawning fails.
+    pub fn uid(mut self, uid: u32) -> Self {
+        self.uid = Some(uid);
+        self
+    }
+
+    /// Run the child with this primary group ID (requires root)
+    pub fn gid(mut self, gid: u32) -> Self {
+        self.gid = Some(gid);
+        self
+    }
+
+    /// Set the child's supplementary groups (requires root)
+    pub fn supplementary_groups(mut self, groups: &[u32]) -> Self {
+        self.supplementary_groups = Some(groups.to_vec());
+        self
+    }
+
+    /// Run the child in new Linux namespaces (requires CAP_SYS_ADMIN unless `USER` is included)
+    ///
+    /// `unshare(2)` is called between fork and exec, after the cgroup, rlimit
+    /// and OOM score steps and before privileges are dropped. Calling this
+    /// again adds to the namespaces already requested.
+    ///
+    /// With `NamespaceFlags::PID` the command runs as PID 1 of the new
+    /// namespace. Since only processes forked after `unshare` enter it, the
+    /// child forks once more and stays behind as a supervisor: the PID seen by
+    /// the caller is the supervisor's, which forwards SIGTERM, SIGINT, SIGHUP,
+    /// SIGQUIT, SIGUSR1, SIGUSR2 and SIGWINCH to the command and exits with its
+    /// status. As for any init process, signals the command has no handler for
+    /// are ignored (SIGKILL aside), so a timeout may only end it after
+    /// `kill_grace`. `/proc` is not remounted; add `MOUNT` and mount it in the
+    /// command to see the new namespace there.
+    ///
+    /// With `USER` no UID/GID mappings are written, so the command runs as the
+    /// overflow user (`nobody`). Failures to create the namespaces are reported
+    /// as `ProcessError::NamespaceError` at spawn.
+    pub fn unshare(mut self, flags: NamespaceFlags) -> Self {
+        self.namespaces |= flags;
+        self
+    }
+
+    /// Send stderr into the stdout capture of `output_string` (like `2>&1`)
+    ///
+    /// Both streams share one pipe, so the lines keep the order the child wrote them.
+    pub fn merge_stderr(mut self, merge: bool) -> Self {
+        self.merge_stderr = merge;
+        self
+    }
+
+    /// Run the process as the leader of a new process group (`setpgid(0, 0)`)
+    ///
+    /// Everything the command forks stays in that group, so the guard returned
+    /// by `spawn` signals the whole group on drop (`kill(-pgid, sig)`) and
+    /// grandchildren are cleaned up with the command. A process spawned
+    /// `with_pty` or `spawn_detached` already leads its own session and group.
+    pub fn new_process_group(mut self, new_group: bool) -> Self {
+        self.process_group = new_group;
+        self
+    }
+
+    /// Treat termination by any of these signals as a successful exit
+    ///
+    /// Useful for graceful-shutdown flows where e.g. SIGTERM is expected.
+    pub fn treat_signals_as_success(mut self, signals: &[SignalType]) -> Self {
+        self.success_signals = signals.to_vec();
+        self
+    }
+
+    /// Run the process on a newly allocated pseudo-terminal
+    ///
+    /// The PTY becomes the child's controlling terminal and replaces any stdio
+    /// configuration; the master side is available via `ProcessGuard::pty`.
+    pub fn with_pty(mut self) -> Self {
+        self.pty.get_or_insert_with(WindowSize::default);
+        self
+    }
+
+    /// Choose whether the process runs on a pseudo-terminal
+    ///
+    /// `true` is the same as `with_pty`; `false` drops a PTY requested earlier
+    /// (including one implied by `pty_size`), so the stdio settings apply again.
+    pub fn allocate_pty(mut self, allocate: bool) -> Self {
+        if allocate {
+            self.pty.get_or_insert_with(WindowSize::default);
+        } else {
+            self.pty = None;
+        }
+        self
+    }
+
+    /// Set the initial PTY window size (implies `with_pty`)
+    pub fn pty_size(mut self, rows: u16, cols: u16) -> Self {
+        self.pty = Some(WindowSize { rows, cols });
+        self
+    }
+
     /// Configure stdin
     pub fn stdin(mut self, cfg: Stdio) -> Self {
         self.stdin = Some(cfg);
         self
     }
 
+    /// Write `data` to the child's stdin, then close it
+    ///
+    /// The child gets a piped stdin that a dedicated thread fills while the
+    /// caller reads the output, so inputs larger than the pipe buffer cannot
+    /// deadlock. A child that exits without reading everything is not an
+    /// error. Cannot be combined with `stdin`, a PTY or `spawn_async`.
+    pub fn stdin_bytes(mut self, data: Vec<u8>) -> Self {
+        self.stdin_data = Some(data);
+        self
+    }
+
     /// Configure stdout
     pub fn stdout(mut self, cfg: Stdio) -> Self {
         self.stdout = Some(cfg);
@@ -73,8 +425,56 @@ impl ProcessBuilder {
         self
     }
 
-    /// Set working directory
-    pub fn current_dir<P: Into<String>>(mut self, dir: P) -> Self {
+    /// Set several environment variables
+    pub fn envs<I, K, V>(mut self, vars: I) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: Into<String>,
+        V: Into<String>,
+    {
+        self.env_vars
+            .extend(vars.into_iter().map(|(k, v)| (k.into(), v.into())));
+        self
+    }
+
+    /// Start the child with an empty environment
+    ///
+    /// Like `Command::env_clear`, this also drops variables added with `env`
+    /// or `envs` so far; add the whitelisted ones afterwards. The command
+    /// itself is still looked up on the parent's `PATH`.
+    pub fn env_clear(mut self) -> Self {
+        self.env_vars.clear();
+        self.env_inherit_patterns.clear();
+        self.inherit_env = false;
+        self
+    }
+
+    /// Choose whether the child inherits the parent's environment (the default)
+    ///
+    /// Unlike `env_clear`, variables set with `env`/`envs` are kept either way.
+    pub fn inherit_env(mut self, inherit: bool) -> Self {
+        self.inherit_env = inherit;
+        self
+    }
+
+    /// Inherit only the parent's variables whose names match `pattern`
+    ///
+    /// `pattern` is a glob where `*` matches any run of characters and `?`
+    /// a single one, e.g. `MYAPP_*`; a prefix is just a pattern ending in
+    /// `*`. Calling this again allows more names, and variables set with
+    /// `env`/`envs` are always added on top. Implies `inherit_env(true)`;
+    /// a later `env_clear` or `inherit_env(false)` drops the inherited ones.
+    pub fn env_inherit_filter<S: Into<String>>(mut self, pattern: S) -> Self {
+        self.env_inherit_patterns.push(pattern.into());
+        self.inherit_env = true;
+        self
+    }
+
+    /// Set the directory the child starts in
+    ///
+    /// The directory is checked at spawn: a missing path or one that is not a
+    /// directory is reported as `ProcessError::InvalidInput` naming the path.
+    pub fn current_dir<P: Into<PathBuf>>(mut self, dir: P) -> Self {
         self.working_dir = Some(dir.into());
         self
     }
@@ -98,6 +498,27 @@ impl ProcessBuilder {
         }
 
         // Set environment variables with validation
+        if !self.inherit_env {
+            cmd.env_clear();
+        } else if !self.env_inherit_patterns.is_empty() {
+            if self.env_inherit_patterns.iter().any(String::is_empty) {
+                return Err(ProcessError::InvalidInput(
+                    "Environment filter pattern cannot be empty".into(),
+                ));
+            }
+            cmd.env_clear();
+            // 名前がUTF-8でない変数はパターンと照合できないので引き継がない
+            for (key, value) in std::env::vars_os() {
+                let Some(name) = key.to_str() else { continue };
+                if self
+                    .env_inherit_patterns
+                    .iter()
+                    .any(|pattern| glob_match(pattern, name))
+                {
+                    cmd.env(&key, value);
+                }
+            }
+        }
         for (key, value) in &self.env_vars {
             validate_env_var(key, value)?;
             cmd.env(key, value);
@@ -105,11 +526,19 @@ impl ProcessBuilder {
 
         // Set working directory
         if let Some(ref dir) = self.working_dir {
-            validate_path(dir)?;
+            validate_working_dir(dir)?;
             cmd.current_dir(dir);
         }
 
         // Configure stdio
+        if self.stdin_data.is_some() {
+            if self.stdin.is_some() || self.pty.is_some() {
+                return Err(ProcessError::InvalidInput(
+                    "stdin_bytes cannot be combined with stdin or a PTY".into(),
+                ));
+            }
+            cmd.stdin(Stdio::piped());
+        }
         if let Some(stdin) = self.stdin.take() {
             cmd.stdin(stdin);
         }
@@ -120,27 +549,624 @@ impl ProcessBuilder {
             cmd.stderr(stderr);
         }
 
+        if let Some(score) = self.oom_score_adj {
+            set_oom_score_adj(&mut cmd, score)?;
+        }
+
+        // 子側でRLIMIT_NICEを下げると優先度を上げられなくなるため、rlimitより先に行う
+        if self.nice.is_some() || self.sched_policy.is_some() {
+            set_scheduling(&mut cmd, self.nice, self.sched_policy)?;
+        }
+
+        if !self.rlimits.is_empty() {
+            set_rlimits(&mut cmd, &self.rlimits)?;
+        }
+
+        if let Some(ref cgroup) = self.cgroup {
+            set_cgroup(&mut cmd, cgroup)?;
+        }
+
+        // PTYや切り離しではsetsidで新しいグループを作るため、setpgidすると失敗する
+        if self.process_group && self.pty.is_none() {
+            use std::os::unix::process::CommandExt;
+            cmd.process_group(0);
+        }
+
+        // 名前空間の作成にも権限が要るため、権限を落とす前に行う
+        if !self.namespaces.is_empty() {
+            namespace::unshare(&mut cmd, self.namespaces);
+        }
+
+        // 権限が必要な他の設定を済ませてから、最後に権限を落とす
+        if self.uid.is_some() || self.gid.is_some() || self.supplementary_groups.is_some() {
+            set_credentials(
+                &mut cmd,
+                self.uid,
+                self.gid,
+                self.supplementary_groups.as_deref(),
+            );
+        }
+
         Ok(cmd)
     }
 
+    /// Attribute a spawn failure to the requested namespaces when possible
+    fn spawn_error(&self, error: io::Error) -> ProcessError {
+        namespace::unshare_error(self.namespaces, &error)
+            .or_else(|| self.scheduling_error(&error))
+            .unwrap_or(ProcessError::Io(error))
+    }
+
+    /// Report EPERM as a permission error when the child was asked to raise its priority
+    ///
+    /// The parent-side check only sees the effective UID, so a root caller
+    /// without `CAP_SYS_NICE` (e.g. in a container) fails in the child instead.
+    fn scheduling_error(&self, error: &io::Error) -> Option<ProcessError> {
+        if error.raw_os_error() != Some(libc::EPERM) {
+            return None;
+        }
+        let context = priority_raise(self.nice, self.sched_policy)?;
+        Some(ProcessError::PermissionDenied {
+            context: format!("{} requires CAP_SYS_NICE", context),
+        })
+    }
+
+    /// Label set with `label`, if any
+    pub(crate) fn label_ref(&self) -> Option<&str> {
+        self.label.as_deref()
+    }
+
+    /// Pipe stdout/stderr unless they were redirected, as `output` does
+    pub(crate) fn capture_output(mut self) -> Self {
+        self.stdout.get_or_insert_with(Stdio::piped);
+        self.stderr.get_or_insert_with(Stdio::piped);
+        self
+    }
+
+    /// Command and arguments as a single display string
+    pub(crate) fn command_line(&self) -> String {
+        std::iter::once(self.command.as_str())
+            .chain(self.args.iter().map(String::as_str))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
     /// Spawn the process and return a handle
     pub fn spawn(mut self) -> ProcessResult<ProcessGuard> {
         let name = self.command.clone();
+        let label = self.label.take();
         let timeout = self.timeout;
+        let kill_grace = self.kill_grace;
+        let success_signals = std::mem::take(&mut self.success_signals);
+        let process_group = self.process_group || self.pty.is_some();
         let mut cmd = self.build_command()?;
-        let child = cmd.spawn()?;
+        let pty = match self.pty {
+            Some(size) => Some(Pty::attach(&mut cmd, size)?),
+            None => None,
+        };
+        let mut child = cmd.spawn().map_err(|e| self.spawn_error(e))?;
+        self.feed_stdin(&mut child)?;
+        // 親側のslaveはCommandの破棄で閉じられ、子の終了時にmasterがEOFを返す
+        drop(cmd);
 
         Ok(ProcessGuard {
             child: Some(child),
             name,
+            label,
             timeout,
+            kill_grace,
+            success_signals,
+            pty,
+            drop_signal: SignalType::Terminate,
+            drop_grace: DEFAULT_DROP_GRACE,
+            kill_on_drop: true,
+            process_group,
         })
     }
 
+    /// Spawn the process and wait for it, enforcing `timeout` if set
+    pub fn spawn_and_wait(self) -> ProcessResult<ProcessOutput> {
+        self.spawn()?.wait()
+    }
+
+    /// Spawn the process on the tokio runtime (requires the `tokio` feature)
+    ///
+    /// Must be called from within a tokio runtime. The child is reaped by
+    /// tokio's SIGCHLD handling, so no zombie is left even if the returned
+    /// `AsyncProcess` is dropped without waiting; like `ProcessGuard`, dropping
+    /// it kills a still-running child. PTYs are not supported.
+    #[cfg(feature = "tokio")]
+    pub fn spawn_async(mut self) -> ProcessResult<crate::async_process::AsyncProcess> {
+        if self.pty.is_some() {
+            return Err(ProcessError::InvalidInput(
+                "spawn_async does not support PTYs".into(),
+            ));
+        }
+        if self.stdin_data.is_some() {
+            return Err(ProcessError::InvalidInput(
+                "spawn_async does not support stdin_bytes; write to take_stdin() instead".into(),
+            ));
+        }
+
+        let label = self.label.take();
+        let success_signals = std::mem::take(&mut self.success_signals);
+        let mut cmd = tokio::process::Command::from(self.build_command()?);
+        cmd.kill_on_drop(true);
+        let child = cmd.spawn().map_err(|e| self.spawn_error(e))?;
+
+        Ok(crate::async_process::AsyncProcess::new(
+            child,
+            label,
+            self.timeout,
+            self.kill_grace,
+            success_signals,
+        ))
+    }
+
+    /// Start a pipeline that feeds this process's stdout into `next`'s stdin
+    ///
+    /// Chain further stages with `Pipeline::pipe` and run it with `Pipeline::output`.
+    pub fn pipe(self, next: ProcessBuilder) -> crate::pipeline::Pipeline {
+        crate::pipeline::Pipeline::new(self).pipe(next)
+    }
+
+    /// Spawn a detached process that outlives its parent (nohup semantics)
+    ///
+    /// The child starts a new session (no controlling terminal), ignores SIGHUP,
+    /// and has any stdio not explicitly configured redirected to `/dev/null`.
+    /// No guard is returned: the process is not killed when the caller exits.
+    /// Only the PID is returned; the child is reaped in the background if it
+    /// exits while the caller is still running.
+    pub fn spawn_detached(mut self) -> ProcessResult<u32> {
+        use std::os::unix::process::CommandExt;
+
+        if self.pty.is_some() {
+            return Err(ProcessError::InvalidInput(
+                "A detached process cannot own a PTY".into(),
+            ));
+        }
+
+        // 端末から切り離すため、未指定のstdioは/dev/nullに向ける
+        if self.stdin_data.is_none() {
+            self.stdin.get_or_insert_with(Stdio::null);
+        }
+        self.stdout.get_or_insert_with(Stdio::null);
+        self.stderr.get_or_insert_with(Stdio::null);
+        // setsidが新しいグループも作る
+        self.process_group = false;
+
+        let mut cmd = self.build_command()?;
+        // exec前に子プロセス側で実行される
+        unsafe {
+            cmd.pre_exec(|| {
+                if libc::setsid() == -1 {
+                    return Err(io::Error::last_os_error());
+                }
+                if libc::signal(libc::SIGHUP, libc::SIG_IGN) == libc::SIG_ERR {
+                    return Err(io::Error::last_os_error());
+                }
+                Ok(())
+            });
+        }
+
+        let mut child = cmd.spawn().map_err(|e| self.spawn_failed(e))?;
+        self.feed_stdin(&mut child)?;
+        let pid = child.id();
+
+        // 親が生きている間に終了した場合のゾンビ化を防ぐ。
+        // 親が先に終了すれば子はinitに引き取られる
+        std::thread::Builder::new()
+            .name(format!("reaper-{}", pid))
+            .spawn(move || {
+                let _ = child.wait();
+            })?;
+
+        Ok(pid)
+    }
+
+    /// Spawn the process without a guard (for callers that manage the child themselves)
+    pub(crate) fn spawn_child(mut self) -> ProcessResult<Child> {
+        let mut cmd = self.build_command()?;
+        let mut child = cmd.spawn().map_err(|e| self.spawn_failed(e))?;
+        self.feed_stdin(&mut child)?;
+        Ok(child)
+    }
+
+    /// Start writing the `stdin_bytes` data to the child, if any
+    ///
+    /// The returned handle can be joined to learn whether the write failed;
+    /// dropping it lets the writer finish in the background.
+    fn feed_stdin(
+        &mut self,
+        child: &mut Child,
+    ) -> ProcessResult<Option<JoinHandle<io::Result<()>>>> {
+        let (Some(data), Some(stdin)) = (self.stdin_data.take(), child.stdin.take()) else {
+            return Ok(None);
+        };
+        let handle = std::thread::Builder::new()
+            .name(format!("stdin-{}", child.id()))
+            .spawn(move || write_stdin(stdin, &data))?;
+        Ok(Some(handle))
+    }
+
+    /// Like `spawn_error`, but reports other failures as `SpawnError` with the command
+    fn spawn_failed(&self, error: io::Error) -> ProcessError {
+        namespace::unshare_error(self.namespaces, &error)
+            .or_else(|| self.scheduling_error(&error))
+            .unwrap_or_else(|| ProcessError::SpawnError {
+                reason: format!("{}: {}", self.command, error),
+            })
+    }
+
     /// Execute with output capture
     pub fn output(mut self) -> ProcessResult<std::process::Output> {
+        if self.stdin_data.is_none() {
+            let mut cmd = self.build_command()?;
+            return cmd.output().map_err(|e| self.spawn_error(e));
+        }
+
+        // Command::outputと同じく、未指定のstdout/stderrは取り込む
+        self.stdout.get_or_insert_with(Stdio::piped);
+        self.stderr.get_or_insert_with(Stdio::piped);
+        let mut cmd = self.build_command()?;
+        let mut child = cmd.spawn().map_err(|e| self.spawn_error(e))?;
+        let writer = self.feed_stdin(&mut child)?;
+        // 書き込みと並行して出力を読むので、どちらのパイプも詰まらない
+        let output = child.wait_with_output()?;
+        join_stdin_writer(writer)?;
+        Ok(output)
+    }
+
+    /// Run to completion and capture stdout/stderr as strings
+    ///
+    /// Invalid UTF-8 is replaced with U+FFFD instead of failing. With
+    /// `merge_stderr(true)` both streams are interleaved into `stdout`.
+    pub fn output_string(mut self) -> ProcessResult<CapturedOutput> {
+        use std::io::Read;
+
+        if !self.merge_stderr {
+            let output = self.output()?;
+            return Ok(CapturedOutput {
+                stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+                stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+                status: output.status,
+            });
+        }
+
+        let (mut reader, writer) = crate::utils::output_pipe()?;
+        self.stdout = Some(Stdio::from(writer.try_clone()?));
+        self.stderr = Some(Stdio::from(writer));
         let mut cmd = self.build_command()?;
-        Ok(cmd.output()?)
+        let mut child = cmd.spawn().map_err(|e| self.spawn_error(e))?;
+        let writer = self.feed_stdin(&mut child)?;
+        // 親側の書き込み端を閉じないと、子が終了しても読み込みがEOFにならない
+        drop(cmd);
+
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+        let status = child.wait()?;
+        join_stdin_writer(writer)?;
+        Ok(CapturedOutput {
+            stdout: String::from_utf8_lossy(&bytes).into_owned(),
+            stderr: String::new(),
+            status,
+        })
+    }
+}
+
+/// Write all of `data` to the child's stdin and close it
+fn write_stdin(mut stdin: ChildStdin, data: &[u8]) -> io::Result<()> {
+    use std::io::Write;
+
+    match stdin.write_all(data) {
+        // 読み切らずに終了した子は失敗扱いにしない
+        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+        result => result,
+    }
+}
+
+/// Wait for the stdin writer thread and surface its error
+fn join_stdin_writer(writer: Option<JoinHandle<io::Result<()>>>) -> ProcessResult<()> {
+    let Some(writer) = writer else {
+        return Ok(());
+    };
+    writer
+        .join()
+        .map_err(|_| ProcessError::InvalidInput("stdin writer thread panicked".into()))??;
+    Ok(())
+}
+
+/// Write `score` to the child's `/proc/self/oom_score_adj` before exec
+fn set_oom_score_adj(cmd: &mut Command, score: i32) -> ProcessResult<()> {
+    use std::os::unix::process::CommandExt;
+
+    if !(OOM_SCORE_ADJ_MIN..=OOM_SCORE_ADJ_MAX).contains(&score) {
+        return Err(ProcessError::InvalidInput(format!(
+            "oom_score_adj must be between {} and {}, got {}",
+            OOM_SCORE_ADJ_MIN, OOM_SCORE_ADJ_MAX, score
+        )));
+    }
+
+    // fork後はメモリ確保できないため、書き込む内容は親側で用意しておく
+    let value = score.to_string();
+    unsafe {
+        cmd.pre_exec(move || {
+            let fd = libc::open(
+                b"/proc/self/oom_score_adj\0".as_ptr() as *const libc::c_char,
+                libc::O_WRONLY | libc::O_CLOEXEC,
+            );
+            if fd == -1 {
+                return Err(io::Error::last_os_error());
+            }
+            let written = libc::write(fd, value.as_ptr() as *const libc::c_void, value.len());
+            let result = if written == -1 {
+                Err(io::Error::last_os_error())
+            } else {
+                Ok(())
+            };
+            libc::close(fd);
+            result
+        });
+    }
+    Ok(())
+}
+
+/// Nice value of the calling process
+fn current_nice() -> i32 {
+    // -1も正当な値なので、errnoで失敗を判別する
+    nix::errno::Errno::clear();
+    let nice = unsafe { libc::getpriority(libc::PRIO_PROCESS, 0) };
+    if nice == -1 && nix::errno::Errno::last() != nix::errno::Errno::UnknownErrno {
+        return 0;
+    }
+    nice
+}
+
+/// Describe the requested priority increase, if any, for permission errors
+fn priority_raise(nice: Option<i32>, policy: Option<SchedPolicy>) -> Option<String> {
+    if let Some(policy @ (SchedPolicy::Fifo(_) | SchedPolicy::RoundRobin(_))) = policy {
+        return Some(format!(
+            "{} with priority {}",
+            policy.name(),
+            policy.priority()
+        ));
+    }
+    let current = current_nice();
+    nice.filter(|&nice| nice < current)
+        .map(|nice| format!("raising the nice value from {} to {}", current, nice))
+}
+
+/// Validate the policy and nice value in the parent, then apply them in the child before exec
+fn set_scheduling(
+    cmd: &mut Command,
+    nice: Option<i32>,
+    policy: Option<SchedPolicy>,
+) -> ProcessResult<()> {
+    use std::os::unix::process::CommandExt;
+
+    if let Some(nice) = nice {
+        if !(NICE_MIN..=NICE_MAX).contains(&nice) {
+            return Err(ProcessError::InvalidInput(format!(
+                "nice must be between {} and {}, got {}",
+                NICE_MIN, NICE_MAX, nice
+            )));
+        }
+    }
+    if let Some(policy) = policy {
+        let realtime = matches!(policy, SchedPolicy::Fifo(_) | SchedPolicy::RoundRobin(_));
+        if realtime && !(1..=99).contains(&policy.priority()) {
+            return Err(ProcessError::InvalidInput(format!(
+                "{} priority must be between 1 and 99, got {}",
+                policy.name(),
+                policy.priority()
+            )));
+        }
+    }
+
+    // 優先度を下げるのは誰でもできるが、上げるにはCAP_SYS_NICEかリソース制限での許可が要る
+    if let Some(context) = priority_raise(nice, policy) {
+        if !Uid::effective().is_root() && !rlimit_allows(nice, policy) {
+            return Err(ProcessError::PermissionDenied {
+                context: format!("{} requires root or CAP_SYS_NICE", context),
+            });
+        }
+    }
+
+    unsafe {
+        cmd.pre_exec(move || {
+            if let Some(policy) = policy {
+                let param = libc::sched_param {
+                    sched_priority: policy.priority() as libc::c_int,
+                };
+                if libc::sched_setscheduler(0, policy.policy(), &param) == -1 {
+                    return Err(io::Error::last_os_error());
+                }
+            }
+            if let Some(nice) = nice {
+                if libc::setpriority(libc::PRIO_PROCESS, 0, nice) == -1 {
+                    return Err(io::Error::last_os_error());
+                }
+            }
+            Ok(())
+        });
+    }
+    Ok(())
+}
+
+/// Whether `RLIMIT_NICE` / `RLIMIT_RTPRIO` let an unprivileged caller raise the priority
+fn rlimit_allows(nice: Option<i32>, policy: Option<SchedPolicy>) -> bool {
+    let soft = |resource| getrlimit(resource).map(|(soft, _)| soft).unwrap_or(0);
+    // RLIMIT_NICEの値rは、nice値を 20 - r まで上げてよいことを表す
+    let nice_ok = nice.map_or(true, |nice| {
+        nice >= current_nice() || i64::from(nice) >= 20 - soft(Resource::RLIMIT_NICE).min(40) as i64
+    });
+    let rtprio_ok = match policy {
+        Some(policy @ (SchedPolicy::Fifo(_) | SchedPolicy::RoundRobin(_))) => {
+            u64::from(policy.priority()) <= soft(Resource::RLIMIT_RTPRIO)
+        }
+        _ => true,
+    };
+    nice_ok && rtprio_ok
+}
+
+/// Match `name` against a glob where `*` is any run of characters and `?` one character
+fn glob_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    // 直前の'*'の位置と、それに吸収させた文字の終端（バックトラック用）
+    let mut star: Option<(usize, usize)> = None;
+
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                Some((star_p, star_n)) => {
+                    p = star_p + 1;
+                    n = star_n + 1;
+                    star = Some((star_p, star_n + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Validate the limits in the parent, then apply them in the child before exec
+fn set_rlimits(cmd: &mut Command, rlimits: &[(Rlimit, u64, u64)]) -> ProcessResult<()> {
+    use std::os::unix::process::CommandExt;
+
+    for &(resource, soft, hard) in rlimits {
+        if soft > hard {
+            return Err(ProcessError::ResourceLimitError {
+                message: format!(
+                    "{}: soft limit {} exceeds hard limit {}",
+                    resource.name(),
+                    soft,
+                    hard
+                ),
+            });
+        }
+
+        // 非特権プロセスはハードリミットを引き上げられない
+        let (_, current_hard) =
+            getrlimit(resource.resource()).map_err(|e| ProcessError::ResourceLimitError {
+                message: format!("{}: getrlimit failed: {}", resource.name(), e),
+            })?;
+        if hard > current_hard && !Uid::effective().is_root() {
+            return Err(ProcessError::ResourceLimitError {
+                message: format!(
+                    "{}: hard limit {} exceeds the current hard limit {}",
+                    resource.name(),
+                    hard,
+                    current_hard
+                ),
+            });
+        }
+    }
+
+    let limits: Vec<(Resource, u64, u64)> = rlimits
+        .iter()
+        .map(|&(resource, soft, hard)| (resource.resource(), soft, hard))
+        .collect();
+    unsafe {
+        cmd.pre_exec(move || {
+            for &(resource, soft, hard) in &limits {
+                setrlimit(resource, soft, hard).map_err(io::Error::from)?;
+            }
+            Ok(())
+        });
+    }
+    Ok(())
+}
+
+/// Check the cgroup in the parent, then move the child into it before exec
+fn set_cgroup(cmd: &mut Command, cgroup: &Path) -> ProcessResult<()> {
+    use nix::unistd::{access, AccessFlags};
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+    use std::os::unix::process::CommandExt;
+
+    let cgroup_error = |reason: String| ProcessError::CgroupError {
+        path: cgroup.display().to_string(),
+        reason,
+    };
+
+    if !cgroup.is_dir() {
+        return Err(cgroup_error("no such cgroup directory".into()));
+    }
+    let procs = cgroup.join("cgroup.procs");
+    if !procs.is_file() {
+        return Err(cgroup_error(
+            "cgroup.procs not found (not a cgroup v2 directory?)".into(),
+        ));
+    }
+    access(&procs, AccessFlags::W_OK)
+        .map_err(|e| cgroup_error(format!("cgroup.procs is not writable: {}", e)))?;
+
+    // cgroup.procsに"0"を書くと書き込んだプロセス自身が移動する
+    let procs = CString::new(procs.as_os_str().as_bytes())
+        .map_err(|_| cgroup_error("path contains a NUL byte".into()))?;
+    unsafe {
+        cmd.pre_exec(move || {
+            let fd = libc::open(procs.as_ptr(), libc::O_WRONLY | libc::O_CLOEXEC);
+            if fd == -1 {
+                return Err(io::Error::last_os_error());
+            }
+            let written = libc::write(fd, b"0".as_ptr() as *const libc::c_void, 1);
+            let result = if written == -1 {
+                Err(io::Error::last_os_error())
+            } else {
+                Ok(())
+            };
+            libc::close(fd);
+            result
+        });
+    }
+    Ok(())
+}
+
+/// Drop to the given groups and user in the child before exec
+///
+/// The order is fixed: supplementary groups and the primary group can only be
+/// changed while still root, so `setuid` must come last. Any error aborts the
+/// exec instead of letting the command run with the caller's privileges.
+fn set_credentials(cmd: &mut Command, uid: Option<u32>, gid: Option<u32>, groups: Option<&[u32]>) {
+    use std::os::unix::process::CommandExt;
+
+    // ユーザーを切り替えるときは補助グループも必ず置き換える（rootのグループを残さない）
+    let groups: Option<Vec<libc::gid_t>> = match (groups, uid) {
+        (Some(groups), _) => Some(groups.to_vec()),
+        (None, Some(_)) => Some(Vec::new()),
+        (None, None) => None,
+    };
+    unsafe {
+        cmd.pre_exec(move || {
+            if let Some(ref groups) = groups {
+                if libc::setgroups(groups.len() as _, groups.as_ptr()) == -1 {
+                    return Err(io::Error::last_os_error());
+                }
+            }
+            if let Some(gid) = gid {
+                if libc::setgid(gid) == -1 {
+                    return Err(io::Error::last_os_error());
+                }
+            }
+            if let Some(uid) = uid {
+                if libc::setuid(uid) == -1 {
+                    return Err(io::Error::last_os_error());
+                }
+            }
+            Ok(())
+        });
     }
 }
 
@@ -148,65 +1174,321 @@ impl ProcessBuilder {
 pub struct ProcessGuard {
     child: Option<Child>,
     name: String,
+    label: Option<String>,
     timeout: Option<Duration>,
+    kill_grace: Duration,
+    success_signals: Vec<SignalType>,
+    pty: Option<Pty>,
+    drop_signal: SignalType,
+    drop_grace: Duration,
+    kill_on_drop: bool,
+    process_group: bool,
 }
 
 impl ProcessGuard {
+    /// Signal sent first when the guard is dropped (default `SignalType::Terminate`)
+    ///
+    /// The process is still killed with SIGKILL if it outlives the signal by
+    /// the grace period (`set_grace`); `SignalType::Kill` skips the grace period.
+    pub fn with_signal(mut self, signal: SignalType) -> Self {
+        self.drop_signal = signal;
+        self
+    }
+
+    /// Time the process gets to exit after the drop signal before SIGKILL
+    /// (default: `DEFAULT_DROP_GRACE`)
+    ///
+    /// Drop polls for the exit, so a process that exits promptly does not
+    /// hold it up for the whole grace period.
+    pub fn set_grace(&mut self, grace: Duration) {
+        self.drop_grace = grace;
+    }
+
+    /// Whether dropping the guard terminates the process (default `true`)
+    ///
+    /// With `false` no signal is sent: the process keeps running and is
+    /// reaped by a background thread once it exits, so drop never blocks.
+    pub fn kill_on_drop(mut self, kill_on_drop: bool) -> Self {
+        self.kill_on_drop = kill_on_drop;
+        self
+    }
+
+    /// Get the PID of the running process
+    pub fn pid(&self) -> Option<u32> {
+        self.child.as_ref().map(|c| c.id())
+    }
+
+    /// Process group the process leads, if it was spawned in its own group
+    ///
+    /// Set with `ProcessBuilder::new_process_group` (or implied by a PTY); the
+    /// group ID is the PID. Drop signals the whole group.
+    pub fn pgid(&self) -> Option<u32> {
+        self.pid().filter(|_| self.process_group)
+    }
+
+    /// Label set on the builder, if any
+    pub fn label(&self) -> Option<&str> {
+        self.label.as_deref()
+    }
+
+    /// Master side of the PTY, if the process was spawned `with_pty`
+    pub fn pty(&mut self) -> Option<&mut Pty> {
+        self.pty.as_mut()
+    }
+
+    /// Take ownership of the PTY master, e.g. to move it into a reader thread
+    pub fn take_pty(&mut self) -> Option<Pty> {
+        self.pty.take()
+    }
+
     /// Wait for the process to finish
     pub fn wait(&mut self) -> ProcessResult<ProcessOutput> {
-        if let Some(mut child) = self.child.take() {
-            let status = if let Some(timeout) = self.timeout {
-                match wait_with_timeout(&mut child, timeout) {
-                    Ok(status) => status,
-                    Err(_) => {
-                        child.kill()?;
-                        child.wait()?; // 必ず待機してゾンビプロセスを防ぐ
-                        return Err(ProcessError::TimeoutError {
-                            seconds: timeout.as_secs(),
-                        });
-                    }
-                }
-            } else {
-                child.wait()?
-            };
+        let status = self.wait_child()?;
+        exit_output(status, &self.success_signals)
+    }
 
-            Ok(ProcessOutput {
-                status: status.code(),
-                success: status.success(),
-            })
+    /// Wait for the process and return its raw exit status, consuming the guard
+    ///
+    /// Unlike `wait`, a process killed by a signal is not an error: the status
+    /// reports the signal (`ExitStatusExt::signal`), and
+    /// `treat_signals_as_success` does not apply. `timeout` is still enforced.
+    /// The child is reaped here, so dropping the guard afterwards does nothing.
+    pub fn wait_status(mut self) -> ProcessResult<ExitStatus> {
+        self.wait_child()
+    }
+
+    /// Reap the child, enforcing `timeout`; leaves nothing for Drop to clean up
+    fn wait_child(&mut self) -> ProcessResult<ExitStatus> {
+        let Some(mut child) = self.child.take() else {
+            return Err(ProcessError::ProcessTerminated { pid: 0 });
+        };
+
+        if let Some(timeout) = self.timeout {
+            let start = std::time::Instant::now();
+            match wait_with_timeout(&mut child, timeout)? {
+                Some(status) => Ok(status),
+                None => {
+                    let elapsed = start.elapsed();
+                    terminate_child(&mut child, self.kill_grace)?;
+                    Err(ProcessError::Timeout { elapsed })
+                }
+            }
         } else {
-            Err(ProcessError::ProcessTerminated { pid: 0 })
+            Ok(child.wait()?)
         }
     }
 }
 
+/// Convert an exit status into `ProcessOutput`, honouring `treat_signals_as_success`
+pub(crate) fn exit_output(
+    status: ExitStatus,
+    success_signals: &[SignalType],
+) -> ProcessResult<ProcessOutput> {
+    // シグナルによる終了の扱い
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+
+        if let Some(signal) = status.signal() {
+            if success_signals.iter().any(|s| s.to_signal() == signal) {
+                return Ok(ProcessOutput {
+                    status: None,
+                    success: true,
+                });
+            }
+            return Err(ProcessError::TerminatedBySignal { signal });
+        }
+    }
+
+    Ok(ProcessOutput {
+        status: status.code(),
+        success: status.success(),
+    })
+}
+
 impl Drop for ProcessGuard {
     fn drop(&mut self) {
         if let Some(mut child) = self.child.take() {
             // Try graceful termination first
-            if child.try_wait().ok().and_then(|s| s).is_none() {
+            if !self.drop_finished(&mut child) {
+                if !self.kill_on_drop {
+                    // spawn_detachedと同様、終了を待つだけのスレッドに回収を任せる
+                    let _ = std::thread::Builder::new()
+                        .name(format!("reaper-{}", child.id()))
+                        .spawn(move || {
+                            let _ = child.wait();
+                        });
+                    return;
+                }
+
                 eprintln!("ProcessGuard: Terminating process '{}'", self.name);
 
-                // まずSIGTERMを送信（Unix系）
-                #[cfg(unix)]
-                {
-                    let pid = nix::unistd::Pid::from_raw(child.id() as i32);
-                    let _ = nix::sys::signal::kill(pid, nix::sys::signal::Signal::SIGTERM);
-                    std::thread::sleep(Duration::from_millis(100));
-                }
+                // まず指定のシグナル（デフォルトはSIGTERM）を送信し、猶予の間終了を待つ
+                // Dropはasyncにできないため、上限付きのポーリングで待つ
+                let exited = self.send_drop_signal(&child) && self.wait_for_drop(&mut child);
 
-                // まだ生きていればSIGKILL
-                if child.try_wait().ok().and_then(|s| s).is_none() {
-                    let _ = child.kill();
+                // まだ生きていればSIGKILL（グループなら残っている孫にも）
+                if !exited {
+                    self.force_kill(&mut child);
                 }
 
-                // 必ず待機してゾンビプロセスを防ぐ
+                // 必ず待機してゾンビプロセスを防ぐ（終了済みならwaitは保存済みの状態を返す）
                 let _ = child.wait();
             }
         }
     }
 }
 
+impl ProcessGuard {
+    /// Send the drop signal; `false` if the process should be killed right away
+    fn send_drop_signal(&self, child: &Child) -> bool {
+        #[cfg(unix)]
+        {
+            self.drop_signal != SignalType::Kill
+                && if self.process_group {
+                    crate::signal::send_signal_to_group(child.id(), self.drop_signal).is_ok()
+                } else {
+                    crate::signal::send_signal(child.id(), self.drop_signal).is_ok()
+                }
+        }
+        #[cfg(not(unix))]
+        {
+            let _ = child;
+            false
+        }
+    }
+
+    /// Whether the child has exited and, for a group, no other member is left
+    fn drop_finished(&self, child: &mut Child) -> bool {
+        // 回収できない場合も待ちようがないので終了扱いにする
+        let exited = !matches!(child.try_wait(), Ok(None));
+        exited && !(self.process_group && group_alive(child.id()))
+    }
+
+    /// Poll `drop_finished` for up to the drop grace period
+    fn wait_for_drop(&self, child: &mut Child) -> bool {
+        let start = std::time::Instant::now();
+        loop {
+            if self.drop_finished(child) {
+                return true;
+            }
+            if start.elapsed() >= self.drop_grace {
+                return false;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    /// SIGKILL the process, or every process left in its group
+    fn force_kill(&self, child: &mut Child) {
+        #[cfg(unix)]
+        if self.process_group {
+            let _ = crate::signal::send_signal_to_group(child.id(), SignalType::Kill);
+        }
+        let _ = child.kill();
+    }
+}
+
+/// Whether any process is left in the group `pgid` (zombies included)
+fn group_alive(pgid: u32) -> bool {
+    // シグナル0は存在確認だけを行う。リーダーが回収済みでも、メンバーが残る間はPGIDは再利用されない
+    unsafe { libc::kill(-(pgid as libc::pid_t), 0) == 0 }
+}
+
+/// Owns several `ProcessGuard`s and terminates them together on drop
+///
+/// Drop signals every process first and then waits for all of them at once,
+/// so the whole scope takes at most the longest grace period instead of the
+/// sum of them. Each guard keeps its own drop signal and grace period; guards
+/// with `kill_on_drop(false)` are left running as usual.
+#[derive(Default)]
+pub struct ProcessScope {
+    guards: Vec<ProcessGuard>,
+}
+
+impl ProcessScope {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Take ownership of an already spawned guard
+    pub fn add(&mut self, guard: ProcessGuard) -> &mut ProcessGuard {
+        self.guards.push(guard);
+        self.guards.last_mut().unwrap()
+    }
+
+    /// Spawn `builder` and keep the resulting guard in this scope
+    pub fn spawn(&mut self, builder: ProcessBuilder) -> ProcessResult<&mut ProcessGuard> {
+        let guard = builder.spawn()?;
+        Ok(self.add(guard))
+    }
+
+    /// PIDs of the processes that have not been reaped yet
+    pub fn pids(&self) -> Vec<u32> {
+        self.guards.iter().filter_map(ProcessGuard::pid).collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.guards.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.guards.is_empty()
+    }
+}
+
+impl Drop for ProcessScope {
+    fn drop(&mut self) {
+        let start = std::time::Instant::now();
+        // 先に全プロセスへシグナルを送り、期限付きで並行して終了を待つ
+        let mut pending: Vec<(usize, Child, Option<std::time::Instant>)> = Vec::new();
+
+        for (index, guard) in self.guards.iter_mut().enumerate() {
+            // kill_on_drop(false) のガードは通常どおりのDropに任せる
+            if !guard.kill_on_drop {
+                continue;
+            }
+            let Some(mut child) = guard.child.take() else {
+                continue;
+            };
+            if guard.drop_finished(&mut child) {
+                continue;
+            }
+
+            eprintln!("ProcessScope: Terminating process '{}'", guard.name);
+            if guard.send_drop_signal(&child) {
+                pending.push((index, child, Some(start + guard.drop_grace)));
+            } else {
+                guard.force_kill(&mut child);
+                pending.push((index, child, None));
+            }
+        }
+
+        let guards = &self.guards;
+        while !pending.is_empty() {
+            let now = std::time::Instant::now();
+            pending.retain_mut(|(index, child, deadline)| {
+                let guard = &guards[*index];
+                match deadline {
+                    // 猶予の間はグループのメンバーも含めて終了を待つ
+                    Some(_) if guard.drop_finished(child) => return false,
+                    Some(deadline) if now >= *deadline => {
+                        // 猶予を過ぎたらSIGKILLし、以降は回収だけを待つ
+                        guard.force_kill(child);
+                    }
+                    Some(_) => return true,
+                    None => {}
+                }
+                *deadline = None;
+                matches!(child.try_wait(), Ok(None))
+            });
+            if !pending.is_empty() {
+                std::thread::sleep(Duration::from_millis(10));
+            }
+        }
+    }
+}
+
 /// Process execution output
 #[derive(Debug, Clone)]
 pub struct ProcessOutput {
@@ -214,23 +1496,38 @@ pub struct ProcessOutput {
     pub success: bool,
 }
 
-/// Helper function for timeout implementation
-fn wait_with_timeout(child: &mut Child, timeout: Duration) -> io::Result<std::process::ExitStatus> {
+/// Helper function for timeout implementation; `None` if the child is still running
+fn wait_with_timeout(
+    child: &mut Child,
+    timeout: Duration,
+) -> io::Result<Option<std::process::ExitStatus>> {
     let start = std::time::Instant::now();
 
     loop {
         match child.try_wait()? {
-            Some(status) => return Ok(status),
+            Some(status) => return Ok(Some(status)),
             None => {
                 if start.elapsed() >= timeout {
-                    return Err(io::Error::new(io::ErrorKind::TimedOut, "Process timed out"));
+                    return Ok(None);
                 }
-                std::thread::sleep(Duration::from_millis(100));
+                std::thread::sleep(Duration::from_millis(10));
             }
         }
     }
 }
 
+/// SIGTERMを送り、`grace`以内に終了しなければSIGKILLする。どちらの場合も回収まで行う
+fn terminate_child(child: &mut Child, grace: Duration) -> io::Result<()> {
+    let pid = nix::unistd::Pid::from_raw(child.id() as i32);
+    let _ = nix::sys::signal::kill(pid, nix::sys::signal::Signal::SIGTERM);
+
+    if wait_with_timeout(child, grace)?.is_none() {
+        child.kill()?;
+    }
+    child.wait()?; // 必ず待機してゾンビプロセスを防ぐ
+    Ok(())
+}
+
 /// Input validator for security
 pub fn validate_input(input: &str) -> ProcessResult<&str> {
     // 危険な文字のチェック（2025年のベストプラクティス）
@@ -316,18 +1613,22 @@ fn validate_env_var(key: &str, value: &str) -> ProcessResult<()> {
 }
 
 /// Validate path
-fn validate_path(path: &str) -> ProcessResult<()> {
-    validate_input(path)?;
+fn validate_working_dir(dir: &Path) -> ProcessResult<()> {
+    validate_input(&dir.to_string_lossy())?;
 
-    // 追加のパス検証
-    if !std::path::Path::new(path).exists() {
-        return Err(ProcessError::InvalidInput(format!(
-            "Path does not exist: {}",
-            path
-        )));
+    // 存在しないディレクトリはspawn時にパスの分からないENOENTになるため、先に確認する
+    match std::fs::metadata(dir) {
+        Ok(metadata) if metadata.is_dir() => Ok(()),
+        Ok(_) => Err(ProcessError::InvalidInput(format!(
+            "Working directory is not a directory: {}",
+            dir.display()
+        ))),
+        Err(e) => Err(ProcessError::InvalidInput(format!(
+            "Working directory {} is not accessible: {}",
+            dir.display(),
+            e
+        ))),
     }
-
-    Ok(())
 }
 
 #[cfg(test)]
@@ -351,4 +1652,16 @@ mod tests {
         assert!(validate_env_var("MY-VAR", "value").is_err());
         assert!(validate_env_var("MYVAR", "value\0").is_err());
     }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("MYAPP_*", "MYAPP_TOKEN"));
+        assert!(glob_match("MYAPP_*", "MYAPP_"));
+        assert!(!glob_match("MYAPP_*", "OTHER_MYAPP_TOKEN"));
+        assert!(glob_match("*_URL", "DATABASE_URL"));
+        assert!(glob_match("LC_?", "LC_A"));
+        assert!(!glob_match("LC_?", "LC_ALL"));
+        assert!(glob_match("*A*B", "xxAyyAzzB"));
+        assert!(!glob_match("HOME", "HOMEDIR"));
+    }
 }
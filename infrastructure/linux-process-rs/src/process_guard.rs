@@ -3,16 +3,21 @@
 /// プロセスのライフサイクルを確実に管理するための構造体。
 /// Dropトレイトを実装することで、スコープを抜ける際に
 /// 自動的にプロセスをクリーンアップします。
-use nix::sys::signal::{kill, Signal};
-use nix::unistd::Pid;
+use crate::signal::{send_signal, SignalType};
 use std::process::{Child, Command};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
+
+/// Drop時にシグナルを送ってからSIGKILLするまでの猶予のデフォルト
+pub const DEFAULT_GRACE: Duration = Duration::from_millis(500);
 
 /// プロセスの自動クリーンアップを保証する構造体
 pub struct ProcessGuard {
     child: Option<Child>,
     name: String,
+    signal: SignalType,
+    grace: Duration,
+    kill_on_drop: bool,
 }
 
 impl ProcessGuard {
@@ -29,8 +34,12 @@ impl ProcessGuard {
     /// # Example
     /// 
     /// ```
+    /// # use linux_process_rs::ProcessGuard;
+    /// # fn main() -> std::io::Result<()> {
     /// let guard = ProcessGuard::new("sleep")?;
     /// // guardがスコープを抜けると自動的にプロセスが終了
+    /// # Ok(())
+    /// # }
     /// ```
     pub fn new(command: &str) -> std::io::Result<Self> {
         println!("ProcessGuard: '{}' を起動", command);
@@ -41,6 +50,9 @@ impl ProcessGuard {
         Ok(Self {
             child: Some(child),
             name: command.to_string(),
+            signal: SignalType::Terminate,
+            grace: DEFAULT_GRACE,
+            kill_on_drop: true,
         })
     }
     
@@ -56,9 +68,49 @@ impl ProcessGuard {
         Ok(Self {
             child: Some(child),
             name: format!("{} {:?}", command, args),
+            signal: SignalType::Terminate,
+            grace: DEFAULT_GRACE,
+            kill_on_drop: true,
         })
     }
     
+    /// 起動済みの子プロセスをProcessGuardでラップする
+    pub(crate) fn from_child(child: Child, name: String) -> Self {
+        println!("ProcessGuard: PID {} で起動しました", child.id());
+        Self {
+            child: Some(child),
+            name,
+            signal: SignalType::Terminate,
+            grace: DEFAULT_GRACE,
+            kill_on_drop: true,
+        }
+    }
+    
+    /// Drop時に最初に送るシグナルを指定する（デフォルトはSIGTERM）
+    /// 
+    /// 猶予（`set_grace`、デフォルトは500ms）以内に終了しなければSIGKILLで強制終了する。
+    /// `SignalType::Kill`を指定すると猶予なしで強制終了する。
+    pub fn with_signal(mut self, signal: SignalType) -> Self {
+        self.signal = signal;
+        self
+    }
+    
+    /// Drop時にシグナルを送ってからSIGKILLするまでの猶予を設定する
+    /// 
+    /// Dropは終了をポーリングで待つため、すぐに終了すれば猶予の全体は待たない。
+    pub fn set_grace(&mut self, grace: Duration) {
+        self.grace = grace;
+    }
+    
+    /// Drop時にプロセスを終了させるかを指定する（デフォルトは`true`）
+    /// 
+    /// `false`の場合はシグナルを送らず、プロセスが自分で終了するのを待って回収するだけになる。
+    /// Dropがブロックしないよう、回収はバックグラウンドのスレッドで行う。
+    pub fn kill_on_drop(mut self, kill_on_drop: bool) -> Self {
+        self.kill_on_drop = kill_on_drop;
+        self
+    }
+    
     /// プロセスの終了を待つ
     pub fn wait(&mut self) -> std::io::Result<std::process::ExitStatus> {
         if let Some(mut child) = self.child.take() {
@@ -74,18 +126,45 @@ impl ProcessGuard {
         }
     }
     
+    /// タイムアウト付きでプロセスの終了を待つ
+    /// 
+    /// 時間内に終了した場合は`Some(status)`、タイムアウトした場合は`None`を返す。
+    /// タイムアウト時はプロセスを保持したままなので、Dropで強制終了される。
+    pub fn wait_timeout(
+        &mut self,
+        timeout: Duration,
+    ) -> std::io::Result<Option<std::process::ExitStatus>> {
+        let start = std::time::Instant::now();
+        while let Some(child) = self.child.as_mut() {
+            if let Some(status) = child.try_wait()? {
+                self.child = None;
+                return Ok(Some(status));
+            }
+            if start.elapsed() >= timeout {
+                return Ok(None);
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        Err(std::io::Error::new(
+            std::io::ErrorKind::Other,
+            "Process already terminated"
+        ))
+    }
+    
     /// プロセスがまだ実行中かチェック
+    /// 
+    /// 終了して回収待ちのゾンビは実行中とみなさない
     pub fn is_running(&self) -> bool {
         if let Some(ref child) = self.child {
-            // try_wait()はmutableな参照が必要ないはず
-            // 直接ステータスを確認する別の方法を使用
             #[cfg(unix)]
             {
-                use nix::sys::signal::kill;
+                use nix::sys::wait::{waitid, Id, WaitPidFlag, WaitStatus};
                 use nix::unistd::Pid;
                 let pid = Pid::from_raw(child.id() as i32);
-                // シグナル0を送信してプロセスの存在を確認
-                kill(pid, None).is_ok()
+                // kill(pid, 0)はゾンビにも成功するため、終了したかをwaitidで調べる。
+                // WNOWAITで回収はせず、終了ステータスはwait()やDropのために残しておく
+                let flags = WaitPidFlag::WEXITED | WaitPidFlag::WNOHANG | WaitPidFlag::WNOWAIT;
+                matches!(waitid(Id::Pid(pid), flags), Ok(WaitStatus::StillAlive))
             }
             #[cfg(not(unix))]
             {
@@ -106,27 +185,51 @@ impl ProcessGuard {
 impl Drop for ProcessGuard {
     /// ProcessGuardがスコープを抜ける際に自動的に呼ばれる
     /// 
-    /// 1. まずSIGTERMで優雅に終了を試みる
-    /// 2. 500ms待つ
+    /// 1. まず`with_signal`のシグナル（デフォルトはSIGTERM）で優雅に終了を試みる
+    /// 2. 終了するまで最大`set_grace`の猶予（デフォルトは500ms）だけ待つ
     /// 3. まだ生きていればSIGKILLで強制終了
     /// 4. 必ずwait()してゾンビプロセスを防ぐ
+    /// 
+    /// `kill_on_drop(false)`の場合は1〜3を行わず、終了を待つスレッドに回収を任せる
     fn drop(&mut self) {
         if let Some(mut child) = self.child.take() {
             // まだ実行中かチェック
             if child.try_wait().ok().flatten().is_none() {
+                if !self.kill_on_drop {
+                    eprintln!("ProcessGuard: プロセス '{}' の終了をバックグラウンドで待ちます", self.name);
+                    let name = self.name.clone();
+                    let reaper = thread::Builder::new()
+                        .name(format!("reaper-{}", child.id()))
+                        .spawn(move || {
+                            if let Ok(status) = child.wait() {
+                                eprintln!("ProcessGuard: プロセス '{}' 終了: {:?}", name, status);
+                            }
+                        });
+                    if let Err(e) = reaper {
+                        eprintln!("ProcessGuard: 回収スレッドの起動失敗: {}", e);
+                    }
+                    return;
+                }
+                
                 eprintln!("ProcessGuard: プロセス '{}' を終了します", self.name);
                 
-                let pid = Pid::from_raw(child.id() as i32);
+                // 範囲外の実時間シグナルが指定されていればSIGTERMで代用する
+                let signal = self.signal.checked().unwrap_or(SignalType::Terminate);
                 
-                // まずSIGTERMで優雅に終了を試みる
-                if let Err(e) = kill(pid, Signal::SIGTERM) {
-                    eprintln!("ProcessGuard: SIGTERM送信失敗: {}", e);
+                // まず指定のシグナルで優雅に終了を試みる
+                if let Err(e) = send_signal(child.id(), signal) {
+                    eprintln!("ProcessGuard: {:?}送信失敗: {}", signal, e);
                 } else {
-                    eprintln!("ProcessGuard: SIGTERMを送信しました");
+                    eprintln!("ProcessGuard: {:?}を送信しました", signal);
                 }
                 
-                // 少し待つ（優雅な終了のため）
-                thread::sleep(Duration::from_millis(500));
+                // 猶予の間、終了をポーリングで待つ（Dropはasyncにできない）。SIGKILLなら待つ必要はない
+                if signal != SignalType::Kill {
+                    let start = Instant::now();
+                    while start.elapsed() < self.grace && child.try_wait().ok().flatten().is_none() {
+                        thread::sleep(Duration::from_millis(10));
+                    }
+                }
                 
                 // まだ生きていればSIGKILL
                 if child.try_wait().ok().flatten().is_none() {
@@ -185,4 +288,49 @@ mod tests {
         thread::sleep(Duration::from_millis(200));
         assert!(!guard.is_running());
     }
+    
+    #[test]
+    fn test_process_guard_with_sigkill_skips_grace_period() {
+        let start = Instant::now();
+        
+        {
+            let _guard = ProcessGuard::new_with_args("sleep", &["10"])
+                .unwrap()
+                .with_signal(SignalType::Kill);
+        }
+        
+        // SIGTERM後の500msの待機がない
+        assert!(start.elapsed() < Duration::from_millis(500));
+    }
+    
+    #[test]
+    fn test_process_guard_kills_process_ignoring_sigterm_after_grace() {
+        let mut guard = ProcessGuard::new_with_args("sh", &["-c", "trap '' TERM; sleep 10"]).unwrap();
+        guard.set_grace(Duration::from_millis(300));
+        // シェルがtrapを設定するまで待つ
+        thread::sleep(Duration::from_millis(100));
+        
+        let start = Instant::now();
+        drop(guard);
+        let elapsed = start.elapsed();
+        assert!(elapsed >= Duration::from_millis(300));
+        assert!(elapsed < Duration::from_secs(2));
+    }
+    
+    #[test]
+    fn test_process_guard_without_kill_on_drop_lets_process_finish() {
+        let dir = tempfile::tempdir().unwrap();
+        let marker = dir.path().join("done");
+        let script = format!("sleep 0.2; touch {}", marker.display());
+        
+        {
+            let _guard = ProcessGuard::new_with_args("sh", &["-c", &script])
+                .unwrap()
+                .kill_on_drop(false);
+        }
+        
+        assert!(!marker.exists());
+        thread::sleep(Duration::from_millis(600));
+        assert!(marker.exists());
+    }
 }
\ No newline at end of file
@@ -2,11 +2,37 @@
 /// 
 /// 複数のワーカープロセスを管理し、タスクを分散処理するための構造体。
 /// プロセス数の制限、自動クリーンアップ、状態監視などの機能を提供します。
+use crate::circuit_breaker::{CircuitBreaker, CircuitBreakerConfig, CircuitState};
 use crate::errors::{ProcessError, ProcessResult};
+use crate::pool_state::{self, AdoptedProcess, PersistedWorker};
+use crate::process::ProcessBuilder;
 use crate::process_guard::ProcessGuard;
+use crate::restart_policy::{RestartPolicy, RestartStats};
+use crate::signal::{send_signal, SignalType};
+use nix::sys::signal::{kill, Signal};
 use nix::unistd::Pid;
-use std::collections::HashMap;
-use std::sync::{Arc, Mutex};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::process::Output;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError, TryRecvError};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+use std::process::ExitStatus;
+use std::time::{Duration, Instant};
+
+/// 子孫プロセス数の上限を確認する間隔
+const DESCENDANT_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+
+/// 再起動ポリシーのためにワーカーの終了を確認する間隔
+const RESTART_CHECK_INTERVAL: Duration = Duration::from_millis(50);
+
+/// 待機中のジョブがワーカーの終了で空いたスロットを確認する間隔
+const JOB_DISPATCH_INTERVAL: Duration = Duration::from_millis(50);
+
+/// `submit`で投入したジョブの投入元ID
+pub const DEFAULT_SUBMITTER: &str = "default";
 
 /// ワーカープロセスの状態
 #[derive(Debug, Clone, PartialEq)]
@@ -23,13 +49,287 @@ pub struct WorkerInfo {
     pub state: WorkerState,
     pub command: String,
     pub started_at: std::time::Instant,
+    /// シャットダウン優先度（大きいほど先に終了される）
+    pub shutdown_priority: i32,
+    /// `ProcessBuilder::label`で付けた識別用のラベル
+    pub label: Option<String>,
+    /// このワーカーが占有するプールのスロット数
+    pub weight: usize,
+    /// 状態ファイルから引き継いだワーカーか（自分の子ではない）
+    pub adopted: bool,
+    /// 再起動ポリシーで起動し直した回数
+    pub restarts: u32,
+}
+
+/// ログやレポート向けの表示（ラベルがあれば"worker: <label>"、なければPID）
+impl fmt::Display for WorkerInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.label {
+            Some(label) => write!(f, "worker: {} (PID: {})", label, self.pid),
+            None => write!(f, "PID: {}", self.pid),
+        }
+    }
+}
+
+/// 終了したワーカーを同じコマンドで起動し直す関数
+type Respawn = Arc<dyn Fn() -> ProcessResult<ProcessGuard> + Send + Sync>;
+
+/// プールが保持するワーカープロセス
+enum Worker {
+    /// このプールが起動した子プロセス（起動し直せる場合はその関数）
+    Owned(ProcessGuard, Option<Respawn>),
+    /// 状態ファイルから引き継いだプロセス
+    Adopted(AdoptedProcess),
+}
+
+impl Worker {
+    fn is_running(&self) -> bool {
+        match self {
+            Worker::Owned(guard, _) => guard.is_running(),
+            Worker::Adopted(process) => process.is_running(),
+        }
+    }
+
+    /// 終了を待つ。時間内に終了すれば`true`
+    fn wait_timeout(&mut self, timeout: Duration) -> ProcessResult<bool> {
+        match self {
+            Worker::Owned(guard, _) => Ok(guard.wait_timeout(timeout)?.is_some()),
+            Worker::Adopted(process) => Ok(process.wait_timeout(timeout)),
+        }
+    }
+
+    /// 状態ファイルに記録する起動時刻
+    fn start_time(&self, pid: Pid) -> Option<u64> {
+        match self {
+            Worker::Owned(..) => pool_state::proc_stat(pid).map(|(_, start_time)| start_time),
+            Worker::Adopted(process) => Some(process.start_time()),
+        }
+    }
+}
+
+type Workers = Arc<Mutex<HashMap<Pid, (Worker, WorkerInfo)>>>;
+
+/// 子孫プロセス数や再起動を監視するバックグラウンドスレッド（Dropで停止する）
+struct Monitor {
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Drop for Monitor {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// ジョブの実行結果（取り込んだ標準出力・標準エラーと終了ステータス）
+pub type JobResult = ProcessResult<Output>;
+
+/// `ProcessPool::submit`で投入したジョブの結果を受け取るハンドル
+pub struct JobHandle {
+    id: u64,
+    result: mpsc::Receiver<JobResult>,
+}
+
+impl JobHandle {
+    /// プール内でのジョブの通し番号
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+    
+    /// ジョブが終了するまで待って結果を受け取る
+    /// 
+    /// 実行前にプールが破棄されたジョブはエラーになる。
+    pub fn recv(self) -> JobResult {
+        self.result.recv().unwrap_or_else(|_| Err(job_cancelled(self.id)))
+    }
+    
+    /// 終了していれば結果を受け取る（実行中・待機中なら`None`）
+    pub fn try_recv(&self) -> Option<JobResult> {
+        match self.result.try_recv() {
+            Ok(result) => Some(result),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => Some(Err(job_cancelled(self.id))),
+        }
+    }
+    
+    /// `timeout`まで待って結果を受け取る（時間内に終わらなければ`None`）
+    pub fn recv_timeout(&self, timeout: Duration) -> Option<JobResult> {
+        match self.result.recv_timeout(timeout) {
+            Ok(result) => Some(result),
+            Err(RecvTimeoutError::Timeout) => None,
+            Err(RecvTimeoutError::Disconnected) => Some(Err(job_cancelled(self.id))),
+        }
+    }
+}
+
+fn job_cancelled(id: u64) -> ProcessError {
+    ProcessError::InvalidInput(format!("Job {} was cancelled because its pool was dropped", id))
+}
+
+/// 実行を待っているジョブ
+struct Job {
+    id: u64,
+    submitter: String,
+    builder: ProcessBuilder,
+    /// 占有するスロット数
+    weight: usize,
+    result: mpsc::Sender<JobResult>,
+}
+
+/// 実行中のジョブ
+struct RunningJob {
+    /// 占有しているスロット数
+    weight: usize,
+    /// 起動前は`None`
+    pid: Option<Pid>,
+}
+
+/// `ProcessPool::shutdown`でのジョブの扱い
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShutdownMode {
+    /// 待機中のジョブも含めて全て実行し終えてから停止する
+    Drain,
+    /// 実行中のジョブは終わるまで待ち、待機中のジョブは取り消す
+    Graceful,
+    /// 待機中のジョブを取り消し、実行中のジョブとワーカーをSIGKILLする
+    Immediate,
+}
+
+/// `ProcessPool::shutdown`の結果
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ShutdownReport {
+    /// 停止を始めてから最後まで実行されたジョブの数
+    pub completed_jobs: usize,
+    /// 実行されずに取り消されたジョブの数
+    pub cancelled_jobs: usize,
+    /// 実行中にSIGKILLしたジョブの数
+    pub killed_jobs: usize,
+    /// 終了させて回収したワーカーの数
+    pub terminated_workers: usize,
+}
+
+/// `ProcessPool::metrics`で取得するプールの状態
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PoolMetrics {
+    /// 動作中のプロセス数（ワーカーと実行中のジョブ）
+    pub active_workers: usize,
+    /// 空いているスロット数
+    pub idle_workers: usize,
+    /// スロットの空きを待っているジョブ数
+    pub queued_tasks: usize,
+    /// 終了ステータス0で終わったジョブの累計
+    pub completed_tasks: usize,
+    /// 起動に失敗したか、0以外のステータスかシグナルで終わったジョブの累計
+    pub failed_tasks: usize,
+    /// 終わったジョブ（成功・失敗とも）の起動から終了までの平均時間
+    pub average_task_duration: Duration,
+}
+
+#[derive(Default)]
+struct JobState {
+    pending: VecDeque<Job>,
+    /// 公平モードでジョブを取り出す投入元の順番（待機中のジョブがある投入元だけ）
+    rotation: VecDeque<String>,
+    fair: bool,
+    /// 実行中のジョブ
+    running: HashMap<u64, RunningJob>,
+    next_id: u64,
+    /// 最後まで実行されたジョブの数
+    finished: usize,
+    /// 実行中にSIGKILLしたジョブの数
+    killed: usize,
+    /// 終了ステータス0で終わったジョブの数
+    succeeded: usize,
+    /// 起動に失敗したか、0以外のステータスかシグナルで終わったジョブの数
+    failed: usize,
+    /// 終わったジョブの実行時間の合計
+    task_time: Duration,
+    /// SIGKILLを送った（送る予定の）実行中のジョブ
+    aborting: HashSet<u64>,
+    /// 停止中で、新しいジョブを受け付けない
+    shutting_down: bool,
+    closed: bool,
+    dispatcher: Option<JoinHandle<()>>,
+    runners: Vec<JoinHandle<()>>,
+}
+
+impl JobState {
+    fn push(&mut self, job: Job) {
+        if !self.rotation.contains(&job.submitter) {
+            self.rotation.push_back(job.submitter.clone());
+        }
+        self.pending.push_back(job);
+    }
+    
+    /// 待機中のジョブを全て取り消し、取り消した数を返す
+    /// 
+    /// Senderを破棄するので、ハンドル側には取り消しのエラーが届く。
+    fn cancel_pending(&mut self) -> usize {
+        self.rotation.clear();
+        self.pending.drain(..).count()
+    }
+    
+    /// 実行中のジョブを全てSIGKILLする（起動中のジョブはPIDが分かった時点で止める）
+    fn kill_running(&mut self) {
+        for (id, job) in &self.running {
+            self.aborting.insert(*id);
+            if let Some(pid) = job.pid {
+                let _ = kill(pid, Signal::SIGKILL);
+            }
+        }
+    }
+    
+    /// 実行中のジョブが占有しているスロット数
+    fn running_weight(&self) -> usize {
+        self.running.values().map(|job| job.weight).sum()
+    }
+    
+    /// 次に起動するジョブ（公平モードでは投入元を順に回り、各投入元の中では投入順）
+    fn next_job(&self) -> Option<&Job> {
+        let submitter = if self.fair {
+            self.rotation.front()?
+        } else {
+            &self.pending.front()?.submitter
+        };
+        self.pending.iter().find(|job| job.submitter == *submitter)
+    }
+    
+    /// `next_job`のジョブを取り出す
+    fn take_next_job(&mut self) -> Option<Job> {
+        let id = self.next_job()?.id;
+        let index = self.pending.iter().position(|job| job.id == id)?;
+        let job = self.pending.remove(index)?;
+        // まだ待機中のジョブがあれば順番の最後に回す
+        self.rotation.retain(|s| *s != job.submitter);
+        if self.pending.iter().any(|pending| pending.submitter == job.submitter) {
+            self.rotation.push_back(job.submitter.clone());
+        }
+        Some(job)
+    }
+}
+
+/// 投入されたジョブのキュー（空きスロットができ次第ディスパッチャーが起動する）
+#[derive(Default)]
+struct JobQueue {
+    state: Mutex<JobState>,
+    changed: Condvar,
 }
 
 /// プロセスプール - 複数のワーカープロセスを管理
 pub struct ProcessPool {
-    workers: Arc<Mutex<HashMap<Pid, (ProcessGuard, WorkerInfo)>>>,
+    workers: Workers,
     max_workers: usize,
     name: String,
+    breaker: Mutex<Option<CircuitBreaker>>,
+    state_file: Option<PathBuf>,
+    descendant_monitor: Option<Monitor>,
+    restart_policy: RestartPolicy,
+    restart_stats: Arc<Mutex<RestartStats>>,
+    restart_monitor: Mutex<Option<Monitor>>,
+    jobs: Arc<JobQueue>,
 }
 
 impl ProcessPool {
@@ -43,6 +343,7 @@ impl ProcessPool {
     /// # Example
     /// 
     /// ```
+    /// # use linux_process_rs::ProcessPool;
     /// let pool = ProcessPool::new("MyPool", 5);
     /// ```
     pub fn new(name: impl Into<String>, max_workers: usize) -> Self {
@@ -52,9 +353,173 @@ impl ProcessPool {
             workers: Arc::new(Mutex::new(HashMap::new())),
             max_workers,
             name,
+            breaker: Mutex::new(None),
+            state_file: None,
+            descendant_monitor: None,
+            restart_policy: RestartPolicy::Never,
+            restart_stats: Arc::default(),
+            restart_monitor: Mutex::new(None),
+            jobs: Arc::default(),
+        }
+    }
+    
+    /// サーキットブレーカーを有効にする
+    /// 
+    /// 時間窓内で連続して起動に失敗するとサーキットが開き、クールダウンの間は
+    /// 新規起動を`ProcessError::CircuitOpen`で拒否する。クールダウン後の最初の
+    /// 起動が試行となり、成功すれば通常状態に戻る。
+    pub fn with_circuit_breaker(self, config: CircuitBreakerConfig) -> Self {
+        *self.breaker.lock().unwrap() = Some(CircuitBreaker::new(config));
+        self
+    }
+    
+    /// ワーカーごとの子孫プロセス数に上限を設ける（フォーク爆弾対策）
+    /// 
+    /// バックグラウンドのスレッドが`/proc`を定期的に走査し、子・孫などの子孫の数が
+    /// `max_descendants`を超えたワーカーをプロセスツリーごとSIGKILLで終了させる。
+    /// 増殖中のツリーを取りこぼさないよう、先に全体をSIGSTOPで止めてから終了させる。
+    /// 終了させたワーカーは回収され、次の`active_workers`でプールから外れる。
+    /// 親が先に終了してツリーから外れたプロセス（ダブルフォークなど）は数えられない。
+    pub fn with_descendant_limit(mut self, max_descendants: usize) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let handle = {
+            let stop = Arc::clone(&stop);
+            let workers = Arc::clone(&self.workers);
+            let name = self.name.clone();
+            thread::Builder::new()
+                .name(format!("{}-descendants", self.name))
+                .spawn(move || {
+                    while !stop.load(Ordering::Relaxed) {
+                        thread::sleep(DESCENDANT_CHECK_INTERVAL);
+                        enforce_descendant_limit(&name, &workers, max_descendants);
+                    }
+                })
+        };
+        match handle {
+            Ok(handle) => {
+                // 置き換えた古い監視スレッドはDropで停止する
+                self.descendant_monitor = Some(Monitor {
+                    stop,
+                    handle: Some(handle),
+                });
+            }
+            Err(e) => eprintln!("ProcessPool '{}': 子孫プロセス監視スレッドの起動に失敗: {}",
+                                self.name, e),
+        }
+        self
+    }
+    
+    /// 予期せず終了したワーカーを起動し直す
+    /// 
+    /// 監視スレッドがワーカーの終了を検出し、ポリシーが再起動を求めていれば
+    /// 待ち時間（再起動のたびに倍になる）の後に同じコマンドで起動し直す。
+    /// 再起動回数の上限に達したワーカーはプールから外す。対象は
+    /// `spawn_worker`系で起動したワーカーだけで、ProcessBuilderのワーカーは
+    /// ビルダーを起動時に消費するため起動し直せない。`terminate_worker`などで
+    /// 終了させたワーカーは再起動しない。再起動の回数は`restart_stats`と
+    /// `WorkerInfo::restarts`で確認できる。
+    pub fn with_restart_policy(mut self, policy: RestartPolicy) -> Self {
+        self.restart_policy = policy;
+        self
+    }
+    
+    /// これまでの再起動の集計
+    pub fn restart_stats(&self) -> RestartStats {
+        *self.restart_stats.lock().unwrap()
+    }
+    
+    /// 再起動の監視スレッドを起動する（起動済みなら何もしない）
+    fn ensure_restart_monitor(&self) {
+        let mut monitor = self.restart_monitor.lock().unwrap();
+        if monitor.is_some() || self.restart_policy == RestartPolicy::Never {
+            return;
+        }
+        let stop = Arc::new(AtomicBool::new(false));
+        let supervisor = RestartSupervisor {
+            name: self.name.clone(),
+            workers: Arc::clone(&self.workers),
+            jobs: Arc::clone(&self.jobs),
+            max_workers: self.max_workers,
+            policy: self.restart_policy,
+            stats: Arc::clone(&self.restart_stats),
+            state_file: self.state_file.clone(),
+        };
+        let handle = {
+            let stop = Arc::clone(&stop);
+            thread::Builder::new()
+                .name(format!("{}-restarts", self.name))
+                .spawn(move || supervisor.run(&stop))
+        };
+        match handle {
+            Ok(handle) => {
+                *monitor = Some(Monitor {
+                    stop,
+                    handle: Some(handle),
+                });
+            }
+            Err(e) => eprintln!("ProcessPool '{}': 再起動の監視スレッドの起動に失敗: {}",
+                                self.name, e),
         }
     }
     
+    /// ワーカーのPIDとラベルを状態ファイルに保存し、以前のワーカーを引き継ぐ
+    /// 
+    /// ワーカーの起動・終了のたびに状態ファイルをアトミックに書き換える。
+    /// スーパーバイザーがクラッシュして再起動した場合、ファイルに残っている
+    /// プロセスのうち`/proc`で生存と起動時刻の一致を確認できたものを
+    /// ワーカーとして引き継ぎ、終了済みのものは回収して記録から外す。
+    /// 引き継いだワーカーも通常のワーカーと同様にスロットを占有し、
+    /// プールの破棄時に終了される。
+    pub fn with_state_file(mut self, path: impl AsRef<Path>) -> ProcessResult<Self> {
+        let path = path.as_ref().to_path_buf();
+        {
+            let mut workers = self.workers.lock().unwrap();
+            for persisted in pool_state::load(&path)? {
+                let Some(process) = AdoptedProcess::adopt(&persisted) else {
+                    println!("ProcessPool '{}': 終了済みのワーカー PID: {} を記録から外します",
+                             self.name, persisted.pid);
+                    continue;
+                };
+                
+                let started_at = pool_state::proc_elapsed(persisted.start_time)
+                    .and_then(|elapsed| std::time::Instant::now().checked_sub(elapsed))
+                    .unwrap_or_else(std::time::Instant::now);
+                let info = WorkerInfo {
+                    pid: process.pid(),
+                    state: WorkerState::Running,
+                    command: persisted.command,
+                    started_at,
+                    shutdown_priority: persisted.shutdown_priority,
+                    label: persisted.label,
+                    weight: persisted.weight.max(1),
+                    adopted: true,
+                    restarts: 0,
+                };
+                println!("ProcessPool '{}': ワーカーを引き継ぎました - {}, Command: {}",
+                         self.name, info, info.command);
+                workers.insert(info.pid, (Worker::Adopted(process), info));
+            }
+        }
+        
+        self.state_file = Some(path);
+        self.persist(&self.workers.lock().unwrap());
+        Ok(self)
+    }
+    
+    /// 状態ファイルを現在のワーカー一覧で書き換える（失敗はログのみ）
+    fn persist(&self, workers: &HashMap<Pid, (Worker, WorkerInfo)>) {
+        persist_workers(&self.name, self.state_file.as_deref(), workers);
+    }
+    
+    /// サーキットの現在の状態（ブレーカー未設定の場合は常にClosed）
+    pub fn circuit_state(&self) -> CircuitState {
+        self.breaker
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map_or(CircuitState::Closed, CircuitBreaker::state)
+    }
+    
     /// ワーカープロセスを起動
     /// 
     /// # Arguments
@@ -70,24 +535,304 @@ impl ProcessPool {
     
     /// 引数付きでワーカープロセスを起動
     pub fn spawn_worker_with_args(&self, command: &str, args: &[&str]) -> ProcessResult<Pid> {
+        self.spawn_worker_with_priority(command, args, 0)
+    }
+    
+    /// シャットダウン優先度を指定してワーカープロセスを起動
+    /// 
+    /// `shutdown_gracefully`は優先度の高いワーカーから順に終了させる。
+    /// 最後まで残したいワーカー（DBプロキシなど）には低い優先度を指定する。
+    pub fn spawn_worker_with_priority(
+        &self,
+        command: &str,
+        args: &[&str],
+        shutdown_priority: i32,
+    ) -> ProcessResult<Pid> {
+        let command_line = if args.is_empty() {
+            command.to_string()
+        } else {
+            format!("{} {}", command, args.join(" "))
+        };
+        let command = command.to_string();
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        let respawn: Respawn = Arc::new(move || {
+            let spawned = if args.is_empty() {
+                ProcessGuard::new(&command)
+            } else {
+                let args: Vec<&str> = args.iter().map(String::as_str).collect();
+                ProcessGuard::new_with_args(&command, &args)
+            };
+            spawned.map_err(ProcessError::Io)
+        });
+        self.spawn_inner(command_line, None, shutdown_priority, 1, Some(Arc::clone(&respawn)), || respawn())
+    }
+    
+    /// ProcessBuilderで設定したプロセスをワーカーとして起動
+    /// 
+    /// 引数・環境変数・作業ディレクトリなどはビルダーの設定がそのまま使われ、
+    /// `ProcessBuilder::label`で付けたラベルはワーカー情報とステータス表示に引き継がれる。
+    pub fn spawn_process(&self, builder: ProcessBuilder) -> ProcessResult<Pid> {
+        self.spawn_process_with_priority(builder, 0)
+    }
+    
+    /// シャットダウン優先度を指定してProcessBuilderのプロセスを起動
+    pub fn spawn_process_with_priority(
+        &self,
+        builder: ProcessBuilder,
+        shutdown_priority: i32,
+    ) -> ProcessResult<Pid> {
+        let command_line = builder.command_line();
+        let label = builder.label_ref().map(str::to_string);
+        let name = command_line.clone();
+        self.spawn_inner(command_line, label, shutdown_priority, 1, None, || {
+            builder
+                .spawn_child()
+                .map(|child| ProcessGuard::from_child(child, name))
+        })
+    }
+    
+    /// 重み付きでジョブを投入する
+    /// 
+    /// メモリを多く使うジョブなどは`weight`個分のスロットを占有する。`submit`と同じく
+    /// 空きスロットが足りなければ待機し、使用中のスロットの合計が最大ワーカー数を
+    /// 超えない範囲で投入順に起動する（重いジョブが先頭にいる間は、後ろの軽いジョブも
+    /// 追い越さずに待つ）。最大ワーカー数より重いジョブは最大ワーカー数に丸められ、
+    /// プールが空いたときに単独で実行される。
+    pub fn submit_weighted(&self, builder: ProcessBuilder, weight: usize) -> JobHandle {
+        let weight = weight.clamp(1, self.max_workers.max(1));
+        self.enqueue(DEFAULT_SUBMITTER.to_string(), builder, weight)
+    }
+    
+    /// 使用中のスロット数（稼働中ワーカーと実行中ジョブの重みの合計）
+    pub fn used_slots(&self) -> usize {
+        let workers = self.workers.lock().unwrap();
+        let jobs = self.jobs.state.lock().unwrap().running_weight();
+        workers.values().map(|(_, info)| info.weight).sum::<usize>() + jobs
+    }
+    
+    /// プールの状態のスナップショット
+    /// 
+    /// ジョブの状態の移り変わりと集計は同じロックの中で行われるため、
+    /// 値は全て同じ時点のものになる（実行中から完了に移る途中の値は見えない）。
+    pub fn metrics(&self) -> PoolMetrics {
+        let workers = self.workers.lock().unwrap();
+        let state = self.jobs.state.lock().unwrap();
+        let weights: usize = workers.values().map(|(_, info)| info.weight).sum();
+        let used = weights + state.running_weight();
+        let done = state.succeeded + state.failed;
+        PoolMetrics {
+            active_workers: workers.len() + state.running.len(),
+            idle_workers: self.max_workers.saturating_sub(used),
+            queued_tasks: state.pending.len(),
+            completed_tasks: state.succeeded,
+            failed_tasks: state.failed,
+            average_task_duration: if done == 0 {
+                Duration::ZERO
+            } else {
+                state.task_time.div_f64(done as f64)
+            },
+        }
+    }
+    
+    /// ジョブを投入元ごとに順番に起動する（公平モード）
+    /// 
+    /// 通常は投入順に起動するため、大量のジョブを投入した投入元がいると
+    /// 後から来た他の投入元のジョブはそれが捌けるまで待たされる。公平モードでは
+    /// `submit_as`で指定した投入元ごとにキューを分け、空いたスロットを投入元の間で
+    /// ラウンドロビンに割り当てる。同じ投入元のジョブは投入順に起動する。
+    pub fn with_fair_queuing(self) -> Self {
+        self.jobs.state.lock().unwrap().fair = true;
+        self
+    }
+    
+    /// ProcessBuilderのプロセスをジョブとして投入し、結果を受け取るハンドルを返す
+    /// 
+    /// ジョブはワーカーと同じスロットを1つずつ使い、空きがなければ投入順に待機する。
+    /// 標準出力と標準エラーは（ビルダーでリダイレクトしていなければ）取り込まれ、
+    /// 終了ステータスと一緒に`JobHandle`に届く。起動に失敗した場合はそのエラーが届く。
+    /// プールが破棄されると待機中のジョブは取り消され、実行中のジョブはSIGKILLされる。
+    /// 投入元は`DEFAULT_SUBMITTER`になる。
+    pub fn submit(&self, builder: ProcessBuilder) -> JobHandle {
+        self.submit_as(DEFAULT_SUBMITTER, builder)
+    }
+    
+    /// 投入元を指定してジョブを投入する
+    /// 
+    /// 投入元は`with_fair_queuing`での順番と`pending_by_submitter`の集計に使われる。
+    pub fn submit_as(&self, submitter: impl Into<String>, builder: ProcessBuilder) -> JobHandle {
+        self.enqueue(submitter.into(), builder, 1)
+    }
+    
+    /// ジョブを待機列に入れ、必要ならディスパッチャーを起動する
+    fn enqueue(&self, submitter: String, builder: ProcessBuilder, weight: usize) -> JobHandle {
+        let (sender, receiver) = mpsc::channel();
+        let mut state = self.jobs.state.lock().unwrap();
+        let id = state.next_id;
+        state.next_id += 1;
+        // 停止後に投入されたジョブは、Senderを破棄してすぐに取り消す
+        if state.shutting_down || state.closed {
+            return JobHandle { id, result: receiver };
+        }
+        state.push(Job {
+            id,
+            submitter,
+            builder,
+            weight,
+            result: sender,
+        });
+        
+        // ディスパッチャーは最初のジョブで起動する
+        if state.dispatcher.is_none() {
+            let jobs = Arc::clone(&self.jobs);
+            let workers = Arc::clone(&self.workers);
+            let max_workers = self.max_workers;
+            let spawned = thread::Builder::new()
+                .name(format!("{}-jobs", self.name))
+                .spawn(move || dispatch_jobs(&jobs, &workers, max_workers));
+            match spawned {
+                Ok(handle) => state.dispatcher = Some(handle),
+                Err(e) => eprintln!("ProcessPool '{}': ジョブのディスパッチャーの起動に失敗: {}",
+                                    self.name, e),
+            }
+        }
+        self.jobs.changed.notify_all();
+        
+        JobHandle { id, result: receiver }
+    }
+    
+    /// スロットの空きを待っているジョブ数
+    pub fn queued_jobs(&self) -> usize {
+        self.jobs.state.lock().unwrap().pending.len()
+    }
+    
+    /// 投入元ごとの待機中のジョブ数（待機中のジョブがない投入元は含まない）
+    pub fn pending_by_submitter(&self) -> BTreeMap<String, usize> {
+        let state = self.jobs.state.lock().unwrap();
+        let mut counts = BTreeMap::new();
+        for job in &state.pending {
+            *counts.entry(job.submitter.clone()).or_insert(0) += 1;
+        }
+        counts
+    }
+    
+    /// 実行中のジョブ数
+    pub fn running_jobs(&self) -> usize {
+        self.jobs.state.lock().unwrap().running.len()
+    }
+    
+    /// 待機中のジョブを取り消し、実行中のジョブをSIGKILLして終了を待つ
+    fn close_jobs(&self) {
+        let (dispatcher, runners) = {
+            let mut state = self.jobs.state.lock().unwrap();
+            state.closed = true;
+            let cancelled = state.cancel_pending();
+            if cancelled > 0 {
+                println!("ProcessPool '{}': 待機中のジョブ{}個を取り消しました", self.name, cancelled);
+            }
+            state.kill_running();
+            self.jobs.changed.notify_all();
+            (state.dispatcher.take(), std::mem::take(&mut state.runners))
+        };
+        for handle in dispatcher.into_iter().chain(runners) {
+            let _ = handle.join();
+        }
+    }
+    
+    /// プールを停止し、全てのジョブとワーカーの終了を待つ
+    /// 
+    /// 停止を始めた後に投入されたジョブはすぐに取り消される。ジョブを`mode`に
+    /// 従って片付けた後、ワーカーは`Drain`と`Graceful`ではSIGTERM（猶予の後に
+    /// SIGKILL）、`Immediate`ではSIGKILLで終了させ、全て回収してから戻る。
+    /// 再起動ポリシーによる再起動も止まる。`Drain`ではワーカーがスロットを
+    /// 埋めている間は待機中のジョブを起動できないため、空きができるまで戻らない。
+    pub fn shutdown(&self, mode: ShutdownMode) -> ProcessResult<ShutdownReport> {
+        println!("ProcessPool '{}': {:?}で停止します", self.name, mode);
+        *self.restart_monitor.lock().unwrap() = None;
+        
+        let mut report = ShutdownReport::default();
+        let (finished, killed) = {
+            let mut state = self.jobs.state.lock().unwrap();
+            state.shutting_down = true;
+            let counts = (state.finished, state.killed);
+            match mode {
+                ShutdownMode::Drain => {}
+                ShutdownMode::Graceful => report.cancelled_jobs = state.cancel_pending(),
+                ShutdownMode::Immediate => {
+                    report.cancelled_jobs = state.cancel_pending();
+                    state.kill_running();
+                }
+            }
+            
+            // 待機中のジョブはディスパッチャーが空いたスロットで起動し続ける
+            while !state.pending.is_empty() || !state.running.is_empty() {
+                state = self.jobs.changed.wait_timeout(state, JOB_DISPATCH_INTERVAL).unwrap().0;
+            }
+            counts
+        };
+        self.close_jobs();
+        {
+            let state = self.jobs.state.lock().unwrap();
+            report.completed_jobs = state.finished - finished;
+            report.killed_jobs = state.killed - killed;
+        }
+        
+        let workers: Vec<Pid> = self.workers.lock().unwrap().keys().copied().collect();
+        if mode == ShutdownMode::Immediate {
+            for pid in &workers {
+                let _ = kill(*pid, Signal::SIGKILL);
+            }
+        }
+        self.terminate_all()?;
+        report.terminated_workers = workers.len();
+        
+        println!("ProcessPool '{}': 停止しました - {:?}", self.name, report);
+        Ok(report)
+    }
+    
+    fn spawn_inner(
+        &self,
+        command: String,
+        label: Option<String>,
+        shutdown_priority: i32,
+        weight: usize,
+        respawn: Option<Respawn>,
+        spawn: impl FnOnce() -> ProcessResult<ProcessGuard>,
+    ) -> ProcessResult<Pid> {
         let mut workers = self.workers.lock().unwrap();
+        let mut breaker = self.breaker.lock().unwrap();
         
-        // 最大数チェック
-        if workers.len() >= self.max_workers {
+        // サーキットが開いている間は起動を試みない
+        if let Some(Err(retry_after)) = breaker.as_ref().map(CircuitBreaker::check) {
+            return Err(ProcessError::CircuitOpen {
+                pool: self.name.clone(),
+                retry_after,
+            });
+        }
+        
+        // 最大数チェック（ワーカーと実行中のジョブの重みの合計で判定）
+        let jobs = self.jobs.state.lock().unwrap().running_weight();
+        let used = workers.values().map(|(_, info)| info.weight).sum::<usize>() + jobs;
+        if used + weight > self.max_workers {
             return Err(ProcessError::InvalidInput(format!(
-                "Maximum workers ({}) reached in pool '{}'",
-                self.max_workers, self.name
+                "Maximum workers ({}) reached in pool '{}' ({} slots in use, {} requested)",
+                self.max_workers, self.name, used, weight
             )));
         }
         
         // プロセスを起動
-        let guard = if args.is_empty() {
-            ProcessGuard::new(command)
-                .map_err(|e| ProcessError::Io(e))?
-        } else {
-            ProcessGuard::new_with_args(command, args)
-                .map_err(|e| ProcessError::Io(e))?
-        };
+        let spawned = spawn();
+        if let Some(breaker) = breaker.as_mut() {
+            match &spawned {
+                Ok(_) => breaker.record_success(),
+                Err(_) => {
+                    breaker.record_failure();
+                    if breaker.state() == CircuitState::Open {
+                        eprintln!("ProcessPool '{}': 起動失敗が続いたためサーキットを開きました", self.name);
+                    }
+                }
+            }
+        }
+        let guard = spawned?;
         
         let pid = guard.pid()
             .ok_or_else(|| ProcessError::InvalidInput("Failed to get PID".into()))?;
@@ -96,18 +841,26 @@ impl ProcessPool {
         let info = WorkerInfo {
             pid,
             state: WorkerState::Running,
-            command: if args.is_empty() {
-                command.to_string()
-            } else {
-                format!("{} {}", command, args.join(" "))
-            },
+            command,
             started_at: std::time::Instant::now(),
+            shutdown_priority,
+            label,
+            weight,
+            adopted: false,
+            restarts: 0,
         };
         
-        println!("ProcessPool '{}': ワーカー起動 - PID: {}, Command: {}", 
-                 self.name, pid, info.command);
+        println!("ProcessPool '{}': ワーカー起動 - {}, Command: {}", 
+                 self.name, info, info.command);
         
-        workers.insert(pid, (guard, info));
+        let restartable = respawn.is_some();
+        workers.insert(pid, (Worker::Owned(guard, respawn), info));
+        self.persist(&workers);
+        drop(workers);
+        drop(breaker);
+        if restartable {
+            self.ensure_restart_monitor();
+        }
         Ok(pid)
     }
     
@@ -115,12 +868,19 @@ impl ProcessPool {
     pub fn terminate_worker(&self, pid: Pid) -> ProcessResult<()> {
         let mut workers = self.workers.lock().unwrap();
         
-        if let Some((mut guard, _info)) = workers.remove(&pid) {
-            println!("ProcessPool '{}': ワーカー終了 - PID: {}", self.name, pid);
+        if let Some((worker, info)) = workers.remove(&pid) {
+            println!("ProcessPool '{}': ワーカー終了 - {}", self.name, info);
+            self.persist(&workers);
             
-            // wait()を呼んで確実に終了を待つ
-            guard.wait()
-                .map_err(|e| ProcessError::Io(e))?;
+            match worker {
+                // wait()を呼んで確実に終了を待つ
+                Worker::Owned(mut guard, _) => {
+                    guard.wait()
+                        .map_err(|e| ProcessError::Io(e))?;
+                }
+                // 自分の子ではないのでwait()できない。DropでSIGTERM→SIGKILLする
+                Worker::Adopted(process) => drop(process),
+            }
             
             println!("ProcessPool '{}': ワーカー {} が正常に終了しました", self.name, pid);
             Ok(())
@@ -140,31 +900,92 @@ impl ProcessPool {
         println!("ProcessPool '{}': 全{}ワーカーを終了します", self.name, pids.len());
         
         for pid in pids {
-            if let Some((guard, _info)) = workers.remove(&pid) {
+            if let Some((worker, _info)) = workers.remove(&pid) {
                 // ProcessGuardのDropが自動的にクリーンアップを行う
-                drop(guard);
+                drop(worker);
             }
         }
+        self.persist(&workers);
         
         println!("ProcessPool '{}': 全ワーカーが終了しました", self.name);
         Ok(())
     }
     
+    /// 優先度順に全ワーカーを段階的に終了
+    /// 
+    /// 優先度の高いグループから順にSIGTERMを送信し、そのグループが
+    /// 全て終了するのを待ってから次のグループに進む。`grace`以内に
+    /// 終了しなかったワーカーはProcessGuardのDropで強制終了される。
+    /// 
+    /// # Returns
+    /// 
+    /// シグナルを送信した順のPIDリスト
+    pub fn shutdown_gracefully(&self, grace: Duration) -> ProcessResult<Vec<Pid>> {
+        // 待機中にロックを保持しないよう、先に全ワーカーを取り出す
+        let mut tiers: BTreeMap<i32, Vec<(Worker, WorkerInfo)>> = BTreeMap::new();
+        {
+            let mut workers = self.workers.lock().unwrap();
+            for (_pid, (worker, info)) in workers.drain() {
+                tiers.entry(info.shutdown_priority).or_default().push((worker, info));
+            }
+            self.persist(&workers);
+        }
+        
+        let mut order = Vec::new();
+        for (priority, mut tier) in tiers.into_iter().rev() {
+            println!("ProcessPool '{}': 優先度{}のワーカー{}個を終了します",
+                     self.name, priority, tier.len());
+            
+            for (_worker, info) in &tier {
+                if let Err(e) = kill(info.pid, Signal::SIGTERM) {
+                    eprintln!("ProcessPool '{}': SIGTERM送信失敗 - PID: {}: {}", self.name, info.pid, e);
+                }
+                order.push(info.pid);
+            }
+            
+            // このグループが全て終了するまで次に進まない
+            for (worker, info) in tier.iter_mut() {
+                if !worker.wait_timeout(grace)? {
+                    eprintln!("ProcessPool '{}': ワーカー {} が猶予時間内に終了しませんでした",
+                              self.name, info.pid);
+                }
+            }
+            // 残っているプロセスはDropでSIGKILLされる
+            drop(tier);
+        }
+        
+        println!("ProcessPool '{}': 全ワーカーが優先度順に終了しました", self.name);
+        Ok(order)
+    }
+    
     /// アクティブなワーカー数を取得
+    /// 
+    /// 再起動ポリシーの対象になるワーカーは、終了していても再起動の監視に任せて
+    /// プールに残す（数には含めない）
     pub fn active_workers(&self) -> usize {
         let mut workers = self.workers.lock().unwrap();
         
         // 終了したワーカーを削除
-        workers.retain(|_pid, (guard, info)| {
-            if guard.is_running() {
-                true
-            } else {
-                println!("ProcessPool '{}': ワーカー {} が終了を検出", self.name, info.pid);
-                false
+        let before = workers.len();
+        let mut running = 0;
+        workers.retain(|_pid, (worker, info)| {
+            if worker.is_running() {
+                running += 1;
+                return true;
+            }
+            let supervised = self.restart_policy != RestartPolicy::Never
+                && matches!(worker, Worker::Owned(_, Some(_)))
+                && info.state != WorkerState::Terminated;
+            if !supervised {
+                println!("ProcessPool '{}': ワーカー ({}) が終了を検出", self.name, info);
             }
+            supervised
         });
+        if workers.len() != before {
+            self.persist(&workers);
+        }
         
-        workers.len()
+        running
     }
     
     /// ワーカーの情報を取得
@@ -179,16 +1000,51 @@ impl ProcessPool {
         workers.values().map(|(_, info)| info.clone()).collect()
     }
     
+    /// 指定したラベルを持つワーカーの情報を取得（PID順）
+    pub fn handles_by_label(&self, label: &str) -> Vec<WorkerInfo> {
+        let workers = self.workers.lock().unwrap();
+        let mut matched: Vec<WorkerInfo> = workers
+            .values()
+            .filter(|(_, info)| info.label.as_deref() == Some(label))
+            .map(|(_, info)| info.clone())
+            .collect();
+        matched.sort_by_key(|info| info.pid);
+        matched
+    }
+    
+    /// 指定したラベルを持つワーカーだけにシグナルを送信
+    /// 
+    /// 他のラベルやラベルのないワーカーには送信しない。送信に失敗した
+    /// ワーカー（既に終了していた場合など）はログに出して読み飛ばす。
+    /// 
+    /// # Returns
+    /// 
+    /// シグナルを送信できたPIDリスト（PID順）
+    pub fn signal_by_label(&self, label: &str, signal: SignalType) -> Vec<Pid> {
+        let mut signaled = Vec::new();
+        for info in self.handles_by_label(label) {
+            match send_signal(info.pid.as_raw() as u32, signal) {
+                Ok(()) => signaled.push(info.pid),
+                Err(e) => eprintln!("ProcessPool '{}': {:?}の送信失敗 - {}: {}",
+                                    self.name, signal, info, e),
+            }
+        }
+        println!("ProcessPool '{}': ラベル'{}'のワーカー{}個に{:?}を送信しました",
+                 self.name, label, signaled.len(), signal);
+        signaled
+    }
+    
     /// プールのステータスを表示
     pub fn status(&self) {
         let workers = self.workers.lock().unwrap();
         println!("\n=== ProcessPool '{}' Status ===", self.name);
-        println!("Active workers: {}/{}", workers.len(), self.max_workers);
+        let used: usize = workers.values().map(|(_, info)| info.weight).sum();
+        println!("Active workers: {} (slots: {}/{})", workers.len(), used, self.max_workers);
         
         for (_, info) in workers.values() {
             let runtime = info.started_at.elapsed();
-            println!("  - PID: {}, Command: '{}', Runtime: {:?}", 
-                     info.pid, info.command, runtime);
+            println!("  - {}, Command: '{}', Runtime: {:?}", 
+                     info, info.command, runtime);
         }
         println!("================================\n");
     }
@@ -198,10 +1054,346 @@ impl Drop for ProcessPool {
     /// プールが破棄される際に全ワーカーを自動的に終了
     fn drop(&mut self) {
         println!("ProcessPool '{}': Dropping, terminating all workers", self.name);
+        // 終了させるワーカーを起動し直さないよう、先に再起動の監視を止める
+        *self.restart_monitor.lock().unwrap() = None;
+        self.descendant_monitor = None;
+        self.close_jobs();
         let _ = self.terminate_all();
     }
 }
 
+/// 空いたスロットで待機中のジョブを投入順に起動する（プールの破棄まで続く）
+/// 
+/// ワーカーの起動と同時に空きを判定しないよう、ワーカー→ジョブの順にロックする。
+fn dispatch_jobs(jobs: &Arc<JobQueue>, workers: &Workers, max_workers: usize) {
+    loop {
+        let workers_guard = workers.lock().unwrap();
+        let mut state = jobs.state.lock().unwrap();
+        if state.closed {
+            return;
+        }
+        let used: usize = workers_guard.values().map(|(_, info)| info.weight).sum();
+        // 先頭のジョブが入らなければ、後ろのジョブも追い越さずに待つ
+        while let Some(weight) = state.next_job().map(|job| job.weight) {
+            if used + state.running_weight() + weight > max_workers {
+                break;
+            }
+            let Some(job) = state.take_next_job() else {
+                break;
+            };
+            state.running.insert(job.id, RunningJob { weight, pid: None });
+            let id = job.id;
+            let queue = Arc::clone(jobs);
+            match thread::Builder::new()
+                .name(format!("job-{}", id))
+                .spawn(move || run_job(&queue, job))
+            {
+                Ok(handle) => state.runners.push(handle),
+                Err(e) => {
+                    // ジョブはスレッドと一緒に破棄され、ハンドルには取り消しが届く
+                    eprintln!("ProcessPool: ジョブ {} のスレッドを起動できません: {}", id, e);
+                    state.running.remove(&id);
+                }
+            }
+        }
+        state.runners.retain(|handle| !handle.is_finished());
+        drop(workers_guard);
+        
+        // ジョブの投入・終了で起こされるが、ワーカーの終了は定期的に確認する
+        let _ = jobs.changed.wait_timeout(state, JOB_DISPATCH_INTERVAL).unwrap();
+    }
+}
+
+/// ジョブを実行して結果を送り、スロットを空ける
+fn run_job(jobs: &JobQueue, job: Job) {
+    let started = Instant::now();
+    let result = job.builder.capture_output().spawn_child().and_then(|child| {
+        let pid = Pid::from_raw(child.id() as i32);
+        {
+            let mut state = jobs.state.lock().unwrap();
+            // 起動中に止められた場合は、kill_runningのSIGKILLに間に合っていない
+            if state.aborting.contains(&job.id) {
+                let _ = kill(pid, Signal::SIGKILL);
+            }
+            if let Some(running) = state.running.get_mut(&job.id) {
+                running.pid = Some(pid);
+            }
+        }
+        child.wait_with_output().map_err(ProcessError::Io)
+    });
+    
+    {
+        let mut state = jobs.state.lock().unwrap();
+        state.running.remove(&job.id);
+        if state.aborting.remove(&job.id) {
+            state.killed += 1;
+        } else {
+            state.finished += 1;
+        }
+        if matches!(&result, Ok(output) if output.status.success()) {
+            state.succeeded += 1;
+        } else {
+            state.failed += 1;
+        }
+        state.task_time += started.elapsed();
+    }
+    jobs.changed.notify_all();
+    // ハンドルが破棄されていれば結果は捨てる
+    let _ = job.result.send(result);
+}
+
+/// 状態ファイルをワーカー一覧で書き換える（失敗はログのみ）
+fn persist_workers(
+    name: &str,
+    state_file: Option<&Path>,
+    workers: &HashMap<Pid, (Worker, WorkerInfo)>,
+) {
+    let Some(path) = state_file else {
+        return;
+    };
+    let entries: Vec<PersistedWorker> = workers
+        .iter()
+        .filter_map(|(pid, (worker, info))| {
+            Some(PersistedWorker {
+                pid: *pid,
+                start_time: worker.start_time(*pid)?,
+                weight: info.weight,
+                shutdown_priority: info.shutdown_priority,
+                label: info.label.clone(),
+                command: info.command.clone(),
+            })
+        })
+        .collect();
+    if let Err(e) = pool_state::save(path, &entries) {
+        eprintln!("ProcessPool '{}': 状態ファイルの保存に失敗: {}", name, e);
+    }
+}
+
+/// 待ち時間の後に起動し直すワーカー
+struct ScheduledRestart {
+    due: Instant,
+    info: WorkerInfo,
+    respawn: Respawn,
+}
+
+/// 終了したワーカーを再起動ポリシーに従って起動し直す監視スレッドの状態
+struct RestartSupervisor {
+    name: String,
+    workers: Workers,
+    jobs: Arc<JobQueue>,
+    max_workers: usize,
+    policy: RestartPolicy,
+    stats: Arc<Mutex<RestartStats>>,
+    state_file: Option<PathBuf>,
+}
+
+impl RestartSupervisor {
+    fn run(self, stop: &AtomicBool) {
+        let mut scheduled = Vec::new();
+        while !stop.load(Ordering::Relaxed) {
+            thread::sleep(RESTART_CHECK_INTERVAL);
+            self.collect_exited(&mut scheduled);
+            
+            let now = Instant::now();
+            let (due, waiting): (Vec<_>, Vec<_>) =
+                scheduled.into_iter().partition(|restart: &ScheduledRestart| restart.due <= now);
+            scheduled = waiting;
+            for restart in due {
+                self.restart(restart, &mut scheduled);
+            }
+        }
+    }
+    
+    /// 終了したワーカーを回収してプールから外し、再起動するものを予約する
+    fn collect_exited(&self, scheduled: &mut Vec<ScheduledRestart>) {
+        let mut workers = self.workers.lock().unwrap();
+        let exited: Vec<(Pid, ExitStatus)> = workers
+            .iter_mut()
+            .filter_map(|(pid, (worker, info))| {
+                let Worker::Owned(guard, Some(_)) = worker else {
+                    return None;
+                };
+                // 子孫数の上限で終了させたワーカーは起動し直さない
+                if info.state == WorkerState::Terminated {
+                    return None;
+                }
+                match guard.wait_timeout(Duration::ZERO) {
+                    Ok(Some(status)) => Some((*pid, status)),
+                    _ => None,
+                }
+            })
+            .collect();
+        if exited.is_empty() {
+            return;
+        }
+        
+        for (pid, status) in exited {
+            let Some((Worker::Owned(_, Some(respawn)), info)) = workers.remove(&pid) else {
+                continue;
+            };
+            if !self.policy.applies_to(status) {
+                println!("ProcessPool '{}': ワーカー ({}) が終了しました: {}", self.name, info, status);
+                continue;
+            }
+            eprintln!("ProcessPool '{}': ワーカー ({}) が予期せず終了しました: {}", self.name, info, status);
+            self.schedule(info, respawn, scheduled);
+        }
+        persist_workers(&self.name, self.state_file.as_deref(), &workers);
+    }
+    
+    /// 次の待ち時間の後に起動し直す（上限に達していればプールから外したままにする）
+    fn schedule(&self, info: WorkerInfo, respawn: Respawn, scheduled: &mut Vec<ScheduledRestart>) {
+        match self.policy.next_backoff(info.restarts) {
+            Some(backoff) => {
+                println!("ProcessPool '{}': {:?}後にワーカーを起動し直します - Command: {}",
+                         self.name, backoff, info.command);
+                scheduled.push(ScheduledRestart {
+                    due: Instant::now() + backoff,
+                    info,
+                    respawn,
+                });
+            }
+            None => {
+                eprintln!("ProcessPool '{}': 再起動回数が上限に達したため、ワーカーをプールから外します - Command: {}",
+                          self.name, info.command);
+                self.stats.lock().unwrap().exhausted += 1;
+            }
+        }
+    }
+    
+    fn restart(&self, mut restart: ScheduledRestart, scheduled: &mut Vec<ScheduledRestart>) {
+        let mut workers = self.workers.lock().unwrap();
+        
+        // 待っている間に他のワーカーやジョブがスロットを使い切った場合は後で再試行する
+        let jobs = self.jobs.state.lock().unwrap().running_weight();
+        let used = workers.values().map(|(_, info)| info.weight).sum::<usize>() + jobs;
+        if used + restart.info.weight > self.max_workers {
+            restart.due = Instant::now() + RESTART_CHECK_INTERVAL;
+            scheduled.push(restart);
+            return;
+        }
+        
+        let ScheduledRestart { mut info, respawn, .. } = restart;
+        info.restarts += 1;
+        let spawned = respawn().and_then(|guard| {
+            let pid = guard.pid()
+                .ok_or_else(|| ProcessError::InvalidInput("Failed to get PID".into()))?;
+            Ok((Pid::from_raw(pid as i32), guard))
+        });
+        match spawned {
+            Ok((pid, guard)) => {
+                info.pid = pid;
+                info.state = WorkerState::Running;
+                info.started_at = Instant::now();
+                println!("ProcessPool '{}': ワーカーを起動し直しました（{}回目） - {}, Command: {}",
+                         self.name, info.restarts, info, info.command);
+                workers.insert(pid, (Worker::Owned(guard, Some(respawn)), info));
+                self.stats.lock().unwrap().restarts += 1;
+                persist_workers(&self.name, self.state_file.as_deref(), &workers);
+            }
+            Err(e) => {
+                eprintln!("ProcessPool '{}': ワーカーの再起動に失敗 - Command: {}: {}",
+                          self.name, info.command, e);
+                self.stats.lock().unwrap().failed_restarts += 1;
+                self.schedule(info, respawn, scheduled);
+            }
+        }
+    }
+}
+
+/// 子孫の数が上限を超えたワーカーをツリーごと終了させる
+fn enforce_descendant_limit(name: &str, workers: &Workers, max_descendants: usize) {
+    // /procの走査中はロックを保持しない
+    let pids: Vec<Pid> = workers
+        .lock()
+        .unwrap()
+        .values()
+        .filter(|(_, info)| info.state != WorkerState::Terminated)
+        .map(|(_, info)| info.pid)
+        .collect();
+    if pids.is_empty() {
+        return;
+    }
+    
+    let children = process_children();
+    for pid in pids {
+        let count = descendants(&children, pid).len();
+        if count <= max_descendants {
+            continue;
+        }
+        eprintln!("ProcessPool '{}': ワーカー PID: {} の子孫プロセスが{}個で上限{}を超えたため、ツリーごと終了します",
+                  name, pid, count, max_descendants);
+        kill_tree(pid);
+        
+        let mut workers = workers.lock().unwrap();
+        if let Some((worker, info)) = workers.get_mut(&pid) {
+            info.state = WorkerState::Terminated;
+            // 自分の子であればここで回収し、ゾンビを残さない
+            if !worker.wait_timeout(Duration::from_secs(1)).unwrap_or(false) {
+                eprintln!("ProcessPool '{}': ワーカー PID: {} の終了を確認できませんでした", name, pid);
+            }
+        }
+    }
+}
+
+/// `/proc`を走査して親PIDから子PIDへの対応を作る
+fn process_children() -> HashMap<Pid, Vec<Pid>> {
+    let mut children: HashMap<Pid, Vec<Pid>> = HashMap::new();
+    let Ok(entries) = std::fs::read_dir("/proc") else {
+        return children;
+    };
+    for entry in entries.flatten() {
+        let Some(pid) = entry.file_name().to_str().and_then(|name| name.parse().ok()) else {
+            continue;
+        };
+        let pid = Pid::from_raw(pid);
+        // 走査中に終了したプロセスは読み飛ばす
+        if let Some(ppid) = pool_state::proc_ppid(pid) {
+            children.entry(ppid).or_default().push(pid);
+        }
+    }
+    children
+}
+
+/// `root`の子孫（`root`自身は含まない）
+fn descendants(children: &HashMap<Pid, Vec<Pid>>, root: Pid) -> Vec<Pid> {
+    let mut found = Vec::new();
+    let mut queue = vec![root];
+    while let Some(pid) = queue.pop() {
+        for &child in children.get(&pid).into_iter().flatten() {
+            found.push(child);
+            queue.push(child);
+        }
+    }
+    found
+}
+
+/// プロセスツリー全体を止めてからSIGKILLする
+/// 
+/// 先に終了させるとフォークを続ける子孫がinitに引き取られてツリーから外れるため、
+/// 新しい子孫が見つからなくなるまでSIGSTOPで凍結してから一斉に終了させる。
+fn kill_tree(root: Pid) {
+    let mut frozen = HashSet::from([root]);
+    let _ = kill(root, Signal::SIGSTOP);
+    loop {
+        let children = process_children();
+        let new: Vec<Pid> = descendants(&children, root)
+            .into_iter()
+            .filter(|pid| !frozen.contains(pid))
+            .collect();
+        if new.is_empty() {
+            break;
+        }
+        for pid in new {
+            let _ = kill(pid, Signal::SIGSTOP);
+            frozen.insert(pid);
+        }
+    }
+    for pid in frozen {
+        let _ = kill(pid, Signal::SIGKILL);
+    }
+}
+
 /// ワーカー情報のClone実装
 impl Clone for WorkerInfo {
     fn clone(&self) -> Self {
@@ -210,6 +1402,11 @@ impl Clone for WorkerInfo {
             state: self.state.clone(),
             command: self.command.clone(),
             started_at: self.started_at,
+            shutdown_priority: self.shutdown_priority,
+            label: self.label.clone(),
+            weight: self.weight,
+            adopted: self.adopted,
+            restarts: self.restarts,
         }
     }
 }
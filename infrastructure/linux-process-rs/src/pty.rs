@@ -0,0 +1,128 @@
+//! Pseudo-terminal support for supervising interactive programs
+
+use crate::errors::{ProcessError, ProcessResult};
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::os::fd::{AsRawFd, OwnedFd, RawFd};
+use std::process::{Command, Stdio};
+
+/// Terminal window size in character cells
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WindowSize {
+    pub rows: u16,
+    pub cols: u16,
+}
+
+impl Default for WindowSize {
+    fn default() -> Self {
+        Self { rows: 24, cols: 80 }
+    }
+}
+
+impl WindowSize {
+    fn to_winsize(self) -> libc::winsize {
+        libc::winsize {
+            ws_row: self.rows,
+            ws_col: self.cols,
+            ws_xpixel: 0,
+            ws_ypixel: 0,
+        }
+    }
+}
+
+/// Master side of a PTY; reads the child's terminal output and writes its input
+#[derive(Debug)]
+pub struct Pty {
+    master: File,
+}
+
+impl Pty {
+    /// Allocate a PTY and attach its slave side to the command's stdio
+    ///
+    /// The child becomes a session leader (setsid) and takes the slave as its
+    /// controlling terminal (TIOCSCTTY) before exec.
+    pub(crate) fn attach(cmd: &mut Command, size: WindowSize) -> ProcessResult<Self> {
+        use std::os::unix::process::CommandExt;
+
+        let winsize = size.to_winsize();
+        let pty = nix::pty::openpty(Some(&winsize), None)?;
+
+        cmd.stdin(Stdio::from(clone_fd(&pty.slave)?));
+        cmd.stdout(Stdio::from(clone_fd(&pty.slave)?));
+        cmd.stderr(Stdio::from(pty.slave));
+
+        // stdioの設定後、exec前に子プロセス側で実行される
+        unsafe {
+            cmd.pre_exec(|| {
+                if libc::setsid() == -1 {
+                    return Err(io::Error::last_os_error());
+                }
+                if libc::ioctl(0, libc::TIOCSCTTY as _, 0) == -1 {
+                    return Err(io::Error::last_os_error());
+                }
+                Ok(())
+            });
+        }
+
+        Ok(Self {
+            master: File::from(pty.master),
+        })
+    }
+
+    /// Resize the terminal (TIOCSWINSZ); the child receives SIGWINCH
+    pub fn set_window_size(&self, size: WindowSize) -> ProcessResult<()> {
+        let winsize = size.to_winsize();
+        let ret = unsafe { libc::ioctl(self.master.as_raw_fd(), libc::TIOCSWINSZ as _, &winsize) };
+        if ret == -1 {
+            return Err(ProcessError::Io(io::Error::last_os_error()));
+        }
+        Ok(())
+    }
+
+    /// Current terminal size (TIOCGWINSZ)
+    pub fn window_size(&self) -> ProcessResult<WindowSize> {
+        let mut winsize = WindowSize::default().to_winsize();
+        let ret =
+            unsafe { libc::ioctl(self.master.as_raw_fd(), libc::TIOCGWINSZ as _, &mut winsize) };
+        if ret == -1 {
+            return Err(ProcessError::Io(io::Error::last_os_error()));
+        }
+        Ok(WindowSize {
+            rows: winsize.ws_row,
+            cols: winsize.ws_col,
+        })
+    }
+
+    /// Duplicate the master so reading and writing can happen on different threads
+    pub fn try_clone(&self) -> ProcessResult<Self> {
+        Ok(Self {
+            master: self.master.try_clone()?,
+        })
+    }
+}
+
+impl AsRawFd for Pty {
+    fn as_raw_fd(&self) -> RawFd {
+        self.master.as_raw_fd()
+    }
+}
+
+impl Read for Pty {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.master.read(buf)
+    }
+}
+
+impl Write for Pty {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.master.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.master.flush()
+    }
+}
+
+fn clone_fd(fd: &OwnedFd) -> ProcessResult<OwnedFd> {
+    Ok(fd.try_clone()?)
+}
@@ -0,0 +1,131 @@
+/// 再起動ポリシー - 予期せず終了したワーカーを起動し直す条件
+///
+/// プールの監視スレッドがワーカーの終了を検出すると、終了ステータスと
+/// それまでの再起動回数からポリシーが再起動するかと待ち時間を決める。
+/// 待ち時間は再起動のたびに倍になり（`MAX_RESTART_BACKOFF`まで）、
+/// 起動直後に終了し続けるワーカーで起動を繰り返さないようにする。
+use std::process::ExitStatus;
+use std::time::Duration;
+
+/// 再起動までの待ち時間の上限
+pub const MAX_RESTART_BACKOFF: Duration = Duration::from_secs(30);
+
+/// ワーカーが終了したときに起動し直すかどうか
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RestartPolicy {
+    /// 起動し直さない
+    Never,
+    /// 終了ステータスにかかわらず起動し直す
+    Always {
+        /// ワーカーごとの再起動回数の上限（超えたらプールから外す）
+        max_restarts: u32,
+        /// 最初の再起動までの待ち時間（再起動のたびに倍になる）
+        backoff: Duration,
+    },
+    /// 0以外のステータスかシグナルで終了した場合だけ起動し直す
+    OnFailure {
+        /// ワーカーごとの再起動回数の上限（超えたらプールから外す）
+        max_restarts: u32,
+        /// 最初の再起動までの待ち時間（再起動のたびに倍になる）
+        backoff: Duration,
+    },
+}
+
+impl RestartPolicy {
+    /// `status`で終了したワーカーを起動し直すべきか（回数の上限は見ない）
+    pub fn applies_to(&self, status: ExitStatus) -> bool {
+        match self {
+            RestartPolicy::Never => false,
+            RestartPolicy::Always { .. } => true,
+            RestartPolicy::OnFailure { .. } => !status.success(),
+        }
+    }
+
+    /// `restarts`回再起動済みのワーカーの次の再起動までの待ち時間
+    ///
+    /// 上限に達していれば`None`
+    pub fn next_backoff(&self, restarts: u32) -> Option<Duration> {
+        let (max_restarts, backoff) = match *self {
+            RestartPolicy::Never => return None,
+            RestartPolicy::Always {
+                max_restarts,
+                backoff,
+            }
+            | RestartPolicy::OnFailure {
+                max_restarts,
+                backoff,
+            } => (max_restarts, backoff),
+        };
+        if restarts >= max_restarts {
+            return None;
+        }
+        let factor = 1u32 << restarts.min(16);
+        Some(backoff.saturating_mul(factor).min(MAX_RESTART_BACKOFF))
+    }
+}
+
+/// プールが行った再起動の集計
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RestartStats {
+    /// 起動し直したワーカーの数
+    pub restarts: u64,
+    /// 起動し直そうとして起動に失敗した回数（次の待ち時間の後に再試行する）
+    pub failed_restarts: u64,
+    /// 再起動回数の上限に達してプールから外したワーカーの数
+    pub exhausted: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::process::ExitStatusExt;
+
+    fn exited(code: i32) -> ExitStatus {
+        ExitStatus::from_raw(code << 8)
+    }
+
+    #[test]
+    fn test_policy_applies_by_exit_status() {
+        let always = RestartPolicy::Always {
+            max_restarts: 3,
+            backoff: Duration::from_millis(100),
+        };
+        let on_failure = RestartPolicy::OnFailure {
+            max_restarts: 3,
+            backoff: Duration::from_millis(100),
+        };
+        let killed = ExitStatus::from_raw(libc::SIGKILL);
+
+        assert!(!RestartPolicy::Never.applies_to(exited(1)));
+        assert!(always.applies_to(exited(0)));
+        assert!(always.applies_to(exited(1)));
+        assert!(!on_failure.applies_to(exited(0)));
+        assert!(on_failure.applies_to(exited(1)));
+        assert!(on_failure.applies_to(killed));
+    }
+
+    #[test]
+    fn test_backoff_doubles_until_limit() {
+        let policy = RestartPolicy::OnFailure {
+            max_restarts: 3,
+            backoff: Duration::from_millis(100),
+        };
+
+        assert_eq!(policy.next_backoff(0), Some(Duration::from_millis(100)));
+        assert_eq!(policy.next_backoff(1), Some(Duration::from_millis(200)));
+        assert_eq!(policy.next_backoff(2), Some(Duration::from_millis(400)));
+        assert_eq!(policy.next_backoff(3), None);
+        assert_eq!(RestartPolicy::Never.next_backoff(0), None);
+    }
+
+    #[test]
+    fn test_backoff_is_capped() {
+        let policy = RestartPolicy::Always {
+            max_restarts: u32::MAX,
+            backoff: Duration::from_secs(1),
+        };
+
+        assert_eq!(policy.next_backoff(10), Some(MAX_RESTART_BACKOFF));
+        assert_eq!(policy.next_backoff(1000), Some(MAX_RESTART_BACKOFF));
+    }
+}
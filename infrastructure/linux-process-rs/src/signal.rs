@@ -1,11 +1,215 @@
 //! Signal handling module with safe abstractions
 
 use crate::errors::{ProcessError, ProcessResult};
-use signal_hook::{consts::signal::*, iterator::Signals};
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
+use signal_hook::{consts::signal::*, iterator::Signals, SigId};
+use std::os::unix::io::RawFd;
+use std::sync::atomic::{AtomicBool, AtomicPtr, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
 use std::thread;
 
+/// Environment variable through which `SignalHandler::reexec` passes the kept descriptors
+pub const REEXEC_FDS_ENV: &str = "LINUX_PROCESS_RS_REEXEC_FDS";
+
+/// Dispositions saved by `SignalHandler::ignore`, restored by `SignalHandler::restore`
+static IGNORED: Mutex<Vec<(i32, libc::sigaction)>> = Mutex::new(Vec::new());
+
+/// A callback installed by `SignalHandler::chain`
+///
+/// Links are leaked on installation and never freed, so the signal handler
+/// can walk them without synchronisation.
+struct ChainLink {
+    callback: Box<dyn Fn() + Send + Sync>,
+    /// Disposition that was in place before this link, if it was not our own trampoline
+    previous: Option<libc::sigaction>,
+    /// Link installed before this one on the same signal
+    next: *const ChainLink,
+}
+
+#[allow(clippy::declare_interior_mutable_const)]
+const NO_CHAIN: AtomicPtr<ChainLink> = AtomicPtr::new(std::ptr::null_mut());
+#[allow(clippy::declare_interior_mutable_const)]
+const CHAIN_IDLE: AtomicBool = AtomicBool::new(false);
+
+/// Real-time signals with a slot in the per-signal tables (Linux provides 30 or 31)
+const REAL_TIME_SLOTS: usize = 32;
+
+/// Size of the per-signal tables: `SignalType::ALL`, then one slot per real-time signal
+const SIGNAL_SLOTS: usize = SignalType::ALL.len() + REAL_TIME_SLOTS;
+
+/// Most recently installed chain link per signal, indexed by `SignalType::index`
+static CHAINS: [AtomicPtr<ChainLink>; SIGNAL_SLOTS] = [NO_CHAIN; SIGNAL_SLOTS];
+
+/// Set while the chain of a signal is running, to stop foreign handlers that
+/// chain back into our trampoline from looping forever
+static CHAIN_RUNNING: [AtomicBool; SIGNAL_SLOTS] = [CHAIN_IDLE; SIGNAL_SLOTS];
+
+/// Set for signals whose handler should not use `SA_RESTART`, indexed by `SignalType::index`
+static NO_RESTART: [AtomicBool; SIGNAL_SLOTS] = [CHAIN_IDLE; SIGNAL_SLOTS];
+
+/// Serialises `SignalHandler::chain` installations, `set_restart` updates and `restore_default`
+static CHAIN_LOCK: Mutex<()> = Mutex::new(());
+
+/// Signals whose disposition or mask this module changed, one bit per `SignalType::index`
+static INSTALLED: AtomicU64 = AtomicU64::new(0);
+const _: () = assert!(SIGNAL_SLOTS <= 64);
+
+/// Handlers taken out by `SignalHandler::restore_default`, put back when the signal is handled again
+///
+/// signal-hook installs its handler only on the first registration of a
+/// signal, so after a reset to `SIG_DFL` later registrations would otherwise
+/// leave the default action in place.
+static PARKED: Mutex<Vec<(i32, libc::sigaction)>> = Mutex::new(Vec::new());
+
+/// Identifies a handler registered with `SignalHandler::on`, for `SignalHandler::remove`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct HandlerId(u64);
+
+/// A closure registered with `SignalHandler::on`
+struct Registered {
+    id: HandlerId,
+    signal: SignalType,
+    handler: Arc<dyn Fn() + Send + Sync>,
+}
+
+/// Closures registered with `SignalHandler::on`, in registration order
+static HANDLERS: Mutex<Vec<Registered>> = Mutex::new(Vec::new());
+
+static NEXT_HANDLER_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Handle of the dispatch thread's `Signals`, started by the first `SignalHandler::on`
+static DISPATCHER: Mutex<Option<signal_hook::iterator::Handle>> = Mutex::new(None);
+
+/// Set once `SignalHandler::auto_reap_with` has started the reaper thread
+static REAPING: AtomicBool = AtomicBool::new(false);
+
+/// Collect every child that has terminated, without blocking
+///
+/// One SIGCHLD may stand for several exits, so this loops until `waitpid`
+/// reports no more zombies (or no children at all).
+fn reap_children(callback: &dyn Fn(u32, std::process::ExitStatus)) {
+    use std::os::unix::process::ExitStatusExt;
+
+    loop {
+        let mut status = 0;
+        let pid = unsafe { libc::waitpid(-1, &mut status, libc::WNOHANG) };
+        if pid > 0 {
+            callback(pid as u32, std::process::ExitStatus::from_raw(status));
+        } else if pid == 0 || std::io::Error::last_os_error().raw_os_error() != Some(libc::EINTR) {
+            return;
+        }
+    }
+}
+
+/// Record that `signals` were handled, ignored or blocked, for `SignalHandler::restore_all_defaults`
+fn mark_installed(signals: &[SignalType]) {
+    for signal in signals {
+        INSTALLED.fetch_or(1 << signal.index(), Ordering::SeqCst);
+    }
+}
+
+/// Take the handler `SignalHandler::restore_default` parked for `sig`, if any
+fn take_parked(sig: i32) -> Option<libc::sigaction> {
+    let mut parked = PARKED.lock().unwrap();
+    let index = parked.iter().position(|(s, _)| *s == sig)?;
+    Some(parked.remove(index).1)
+}
+
+/// Put back the parked handler of `sig` if a registration left it at `SIG_DFL`
+fn rearm(sig: i32) -> ProcessResult<()> {
+    let Some(parked) = take_parked(sig) else {
+        return Ok(());
+    };
+    let mut current: libc::sigaction = unsafe { std::mem::zeroed() };
+    if unsafe { libc::sigaction(sig, std::ptr::null(), &mut current) } != 0 {
+        return Err(ProcessError::SignalError(
+            std::io::Error::last_os_error().to_string(),
+        ));
+    }
+    if current.sa_sigaction == libc::SIG_DFL
+        && unsafe { libc::sigaction(sig, &parked, std::ptr::null_mut()) } != 0
+    {
+        return Err(ProcessError::SignalError(
+            std::io::Error::last_os_error().to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Body of the dispatch thread: run the registered closures for each delivery
+fn dispatch(mut signals: Signals) {
+    for sig in signals.forever() {
+        let Some(signal) = SignalType::from_signal(sig) else {
+            continue;
+        };
+        // 呼び出し中にハンドラが登録・削除されてもよいよう、ロックを外してから実行する
+        let handlers: Vec<_> = HANDLERS
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|registered| registered.signal == signal)
+            .map(|registered| registered.handler.clone())
+            .collect();
+        for handler in handlers {
+            handler();
+        }
+    }
+}
+
+type SigInfoHandler = extern "C" fn(libc::c_int, *mut libc::siginfo_t, *mut libc::c_void);
+
+extern "C" fn chain_trampoline(
+    sig: libc::c_int,
+    info: *mut libc::siginfo_t,
+    ctx: *mut libc::c_void,
+) {
+    let Some(signal) = SignalType::from_signal(sig) else {
+        return;
+    };
+    let index = signal.index();
+    if CHAIN_RUNNING[index].swap(true, Ordering::SeqCst) {
+        return;
+    }
+
+    let errno = unsafe { *libc::__errno_location() };
+    let mut link = CHAINS[index].load(Ordering::Acquire) as *const ChainLink;
+    while let Some(current) = unsafe { link.as_ref() } {
+        (current.callback)();
+        if let Some(previous) = &current.previous {
+            unsafe { invoke_previous(sig, previous, info, ctx) };
+        }
+        link = current.next;
+    }
+    unsafe { *libc::__errno_location() = errno };
+
+    CHAIN_RUNNING[index].store(false, Ordering::SeqCst);
+}
+
+/// Run a previously installed disposition from inside our handler
+///
+/// Only function handlers are called. `SIG_IGN` has nothing to run, and
+/// `SIG_DFL` is skipped as well: its action for the signals we manage is to
+/// terminate (or stop) the process, which is exactly what installing a
+/// handler is meant to replace.
+unsafe fn invoke_previous(
+    sig: libc::c_int,
+    previous: &libc::sigaction,
+    info: *mut libc::siginfo_t,
+    ctx: *mut libc::c_void,
+) {
+    let handler = previous.sa_sigaction;
+    if handler == libc::SIG_DFL || handler == libc::SIG_IGN {
+        return;
+    }
+
+    if previous.sa_flags & libc::SA_SIGINFO != 0 {
+        let f: SigInfoHandler = std::mem::transmute(handler);
+        f(sig, info, ctx);
+    } else {
+        let f: extern "C" fn(libc::c_int) = std::mem::transmute(handler);
+        f(sig);
+    }
+}
+
 /// Signal types supported by the handler
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum SignalType {
@@ -21,11 +225,21 @@ pub enum SignalType {
     User1,
     /// User-defined signal 2
     User2,
+    /// Write to a pipe or socket with no reader
+    Pipe,
+    /// Kill signal; cannot be caught, blocked or ignored, so only useful for sending
+    Kill,
+    /// Real-time signal `SIGRTMIN + n`
+    ///
+    /// `n` must be below `SignalType::real_time_count()`; APIs given a larger
+    /// offset fail with `ProcessError::InvalidInput`. Unlike standard signals,
+    /// real-time signals are queued, so every delivery is kept.
+    RealTime(u8),
 }
 
 impl SignalType {
     /// Convert to signal constant
-    fn to_signal(self) -> i32 {
+    pub(crate) fn to_signal(self) -> i32 {
         match self {
             Self::Interrupt => SIGINT,
             Self::Terminate => SIGTERM,
@@ -33,6 +247,71 @@ impl SignalType {
             Self::Quit => SIGQUIT,
             Self::User1 => SIGUSR1,
             Self::User2 => SIGUSR2,
+            Self::Pipe => SIGPIPE,
+            Self::Kill => SIGKILL,
+            Self::RealTime(n) => libc::SIGRTMIN() + n as i32,
+        }
+    }
+
+    /// Real-time signal `SIGRTMIN + offset`, checked against the platform's range
+    pub fn real_time(offset: u8) -> ProcessResult<Self> {
+        Self::RealTime(offset).checked()
+    }
+
+    /// Number of real-time signals available (`SIGRTMAX - SIGRTMIN + 1`)
+    pub fn real_time_count() -> u8 {
+        let count = (libc::SIGRTMAX() - libc::SIGRTMIN() + 1).max(0) as usize;
+        count.min(REAL_TIME_SLOTS) as u8
+    }
+
+    /// Reject a real-time offset outside `SIGRTMIN..=SIGRTMAX`
+    pub(crate) fn checked(self) -> ProcessResult<Self> {
+        match self {
+            Self::RealTime(n) if n >= Self::real_time_count() => {
+                Err(ProcessError::InvalidInput(format!(
+                    "Real-time signal offset {} is out of range (0..{})",
+                    n,
+                    Self::real_time_count()
+                )))
+            }
+            _ => Ok(self),
+        }
+    }
+
+    /// All standard signal types (real-time signals are not listed)
+    pub const ALL: [SignalType; 8] = [
+        Self::Interrupt,
+        Self::Terminate,
+        Self::Hangup,
+        Self::Quit,
+        Self::User1,
+        Self::User2,
+        Self::Pipe,
+        Self::Kill,
+    ];
+
+    /// Position in `ALL`, or after it for real-time signals; indexes per-signal tables
+    ///
+    /// Only call on checked values, real-time offsets past the tables panic.
+    fn index(self) -> usize {
+        match self {
+            Self::Interrupt => 0,
+            Self::Terminate => 1,
+            Self::Hangup => 2,
+            Self::Quit => 3,
+            Self::User1 => 4,
+            Self::User2 => 5,
+            Self::Pipe => 6,
+            Self::Kill => 7,
+            Self::RealTime(n) => Self::ALL.len() + n as usize,
+        }
+    }
+
+    /// Inverse of `index`
+    fn from_index(index: usize) -> Self {
+        match Self::ALL.get(index) {
+            Some(&signal) => signal,
+            None => Self::RealTime((index - Self::ALL.len()) as u8),
         }
     }
 
@@ -45,18 +324,51 @@ impl SignalType {
             SIGQUIT => Some(Self::Quit),
             SIGUSR1 => Some(Self::User1),
             SIGUSR2 => Some(Self::User2),
+            SIGPIPE => Some(Self::Pipe),
+            SIGKILL => Some(Self::Kill),
+            sig if sig >= libc::SIGRTMIN() && sig <= libc::SIGRTMAX() => {
+                Self::RealTime((sig - libc::SIGRTMIN()) as u8)
+                    .checked()
+                    .ok()
+            }
             _ => None,
         }
     }
 }
 
+/// Per-signal delivery counters
+///
+/// Incremented from the signal handler itself, so only lock-free atomics are used.
+#[derive(Debug)]
+struct SignalCounters([AtomicU64; SIGNAL_SLOTS]);
+
+impl Default for SignalCounters {
+    fn default() -> Self {
+        Self(std::array::from_fn(|_| AtomicU64::new(0)))
+    }
+}
+
+impl SignalCounters {
+    fn increment(&self, signal: SignalType) {
+        self.0[signal.index()].fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn get(&self, signal: SignalType) -> u64 {
+        self.0[signal.index()].load(Ordering::Relaxed)
+    }
+}
+
 /// Signal handler with graceful shutdown support
 pub struct SignalHandler {
-    #[allow(dead_code)]
     signals: Vec<SignalType>,
     shutdown: Arc<AtomicBool>,
     stop_flag: Arc<AtomicBool>,
     handle: Option<thread::JoinHandle<()>>,
+    /// Signal used to wake the dedicated thread out of `sigwaitinfo` on drop
+    wake_signal: Option<i32>,
+    counters: Arc<SignalCounters>,
+    /// Low-level counting actions registered by `new`, removed on drop
+    counter_ids: Vec<SigId>,
 }
 
 impl SignalHandler {
@@ -78,6 +390,9 @@ impl SignalHandler {
     /// }
     /// ```
     pub fn new(signals: &[SignalType]) -> ProcessResult<Self> {
+        for signal in signals {
+            signal.checked()?;
+        }
         let shutdown = Arc::new(AtomicBool::new(false));
         let shutdown_clone = shutdown.clone();
         let stop_flag = Arc::new(AtomicBool::new(false));
@@ -87,6 +402,25 @@ impl SignalHandler {
         let mut sig_handler =
             Signals::new(&signal_nums).map_err(|e| ProcessError::SignalError(e.to_string()))?;
 
+        // Signalsのイテレータは同じシグナルの連続した配送をまとめてしまうため、
+        // 回数はシグナルハンドラ内でアトミックに数える
+        let counters = Arc::new(SignalCounters::default());
+        let mut counter_ids = Vec::with_capacity(signals.len());
+        for &signal in signals {
+            let counters = counters.clone();
+            let id = unsafe {
+                signal_hook::low_level::register(signal.to_signal(), move || {
+                    counters.increment(signal)
+                })
+            }
+            .map_err(|e| ProcessError::SignalError(e.to_string()))?;
+            counter_ids.push(id);
+        }
+        for &sig in &signal_nums {
+            rearm(sig)?;
+        }
+        mark_installed(signals);
+
         let signals_vec = signals.to_vec();
         let handle = thread::spawn(move || {
             loop {
@@ -124,9 +458,694 @@ impl SignalHandler {
             shutdown,
             stop_flag,
             handle: Some(handle),
+            wake_signal: None,
+            counters,
+            counter_ids,
         })
     }
 
+    /// Create a handler that receives signals synchronously on a dedicated thread
+    ///
+    /// The managed signals are blocked with `pthread_sigmask` on the calling
+    /// thread, and a `signal-handler` thread waits for them with `sigwaitinfo`
+    /// and runs `callback` for each one. No signal handler ever interrupts
+    /// other threads, so the callback may do anything a normal thread can.
+    ///
+    /// # Init ordering
+    ///
+    /// The signal mask is inherited by threads spawned *after* this call only.
+    /// Call this from the main thread before spawning workers (or a runtime),
+    /// otherwise earlier threads keep the signals unblocked and the kernel may
+    /// deliver them there with their default action.
+    /// Dedicated threads block every signal themselves, so several handlers
+    /// (e.g. `on_reload` and `on_upgrade`) can be created one after another.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use linux_process_rs::signal::{SignalHandler, SignalType};
+    ///
+    /// // Before any other thread is started
+    /// let handler = SignalHandler::with_dedicated_thread(
+    ///     &[SignalType::Terminate, SignalType::Hangup],
+    ///     |signal| println!("got {:?}", signal),
+    /// ).expect("Failed to create signal handler");
+    ///
+    /// let worker = std::thread::spawn(|| { /* signals stay blocked here */ });
+    /// # worker.join().unwrap();
+    /// # drop(handler);
+    /// ```
+    pub fn with_dedicated_thread<F>(signals: &[SignalType], callback: F) -> ProcessResult<Self>
+    where
+        F: Fn(SignalType) + Send + 'static,
+    {
+        Self::dedicated_thread(signals, true, callback)
+    }
+
+    /// Run `reload` on a dedicated thread for every SIGHUP
+    ///
+    /// The conventional place to reopen log files after rotation and to
+    /// re-read configuration without restarting. Unlike `with_dedicated_thread`,
+    /// a reload does not set the shutdown flag. The same init-ordering rules
+    /// apply: call it from the main thread before spawning other threads.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use linux_process_rs::signal::SignalHandler;
+    ///
+    /// let reloader = SignalHandler::on_reload(|| {
+    ///     // ログファイルを開き直し、設定を読み直す
+    /// })
+    /// .expect("Failed to install reload handler");
+    /// # drop(reloader);
+    /// ```
+    pub fn on_reload<F>(reload: F) -> ProcessResult<Self>
+    where
+        F: Fn() + Send + 'static,
+    {
+        Self::dedicated_thread(&[SignalType::Hangup], false, move |_| reload())
+    }
+
+    /// Re-execute the current binary with `reexec` when SIGUSR2 arrives
+    ///
+    /// The classic graceful upgrade: install the new binary over the old one,
+    /// then send SIGUSR2. The process keeps its PID and the descriptors in
+    /// `keep_fds` (typically listening sockets), so no connection is refused
+    /// while the new code starts; it reclaims them with `inherited_fds`. If the
+    /// exec fails the error is logged and the process keeps running. Call it
+    /// from the main thread before spawning other threads.
+    pub fn on_upgrade(keep_fds: Vec<RawFd>) -> ProcessResult<Self> {
+        Self::dedicated_thread(&[SignalType::User2], false, move |_| {
+            if let Some(e) = Self::reexec(&keep_fds).err() {
+                eprintln!("Upgrade failed: {}", e);
+            }
+        })
+    }
+
+    /// Replace the process image with the current binary, keeping `keep_fds` open
+    ///
+    /// argv and the environment are passed on unchanged; `FD_CLOEXEC` is
+    /// cleared on `keep_fds` and their numbers are listed in `REEXEC_FDS_ENV`.
+    /// The binary is looked up by path, so an upgraded file installed at the
+    /// same location is the one that runs. Only returns on failure, in which
+    /// case the descriptors are marked close-on-exec again.
+    pub fn reexec(keep_fds: &[RawFd]) -> ProcessResult<std::convert::Infallible> {
+        use std::os::unix::ffi::OsStrExt;
+        use std::os::unix::process::CommandExt;
+
+        // 置き換えられたバイナリの/proc/self/exeには" (deleted)"が付く
+        let exe = std::env::current_exe()?;
+        let exe = match exe.as_os_str().as_bytes().strip_suffix(b" (deleted)") {
+            Some(path) => std::path::PathBuf::from(std::ffi::OsStr::from_bytes(path)),
+            None => exe,
+        };
+        let mut args = std::env::args_os();
+        let arg0 = args.next().unwrap_or_else(|| exe.clone().into_os_string());
+
+        for &fd in keep_fds {
+            set_cloexec(fd, false)?;
+        }
+        let fds: Vec<String> = keep_fds.iter().map(|fd| fd.to_string()).collect();
+        // 成功すれば戻らない（シグナルマスクはexec前に空に戻される）
+        let error = std::process::Command::new(&exe)
+            .arg0(arg0)
+            .args(args)
+            .env(REEXEC_FDS_ENV, fds.join(","))
+            .exec();
+
+        for &fd in keep_fds {
+            let _ = set_cloexec(fd, true);
+        }
+        Err(ProcessError::SpawnError {
+            reason: format!("re-exec of {} failed: {}", exe.display(), error),
+        })
+    }
+
+    /// Shared setup of `with_dedicated_thread`, `on_reload` and `on_upgrade`
+    fn dedicated_thread<F>(
+        signals: &[SignalType],
+        marks_shutdown: bool,
+        callback: F,
+    ) -> ProcessResult<Self>
+    where
+        F: Fn(SignalType) + Send + 'static,
+    {
+        use nix::sys::signal::{pthread_sigmask, SigSet, SigmaskHow};
+
+        let wake_signal = signals
+            .first()
+            .map(|s| s.to_signal())
+            .ok_or_else(|| ProcessError::InvalidInput("No signals to handle".into()))?;
+
+        let mask = signal_set(signals)?;
+        // 以降に生成されるスレッドはこのマスクを継承する
+        pthread_sigmask(SigmaskHow::SIG_BLOCK, Some(&mask), None)
+            .map_err(|e| ProcessError::SignalError(e.to_string()))?;
+        mark_installed(signals);
+
+        let shutdown = Arc::new(AtomicBool::new(false));
+        let shutdown_clone = shutdown.clone();
+        let stop_flag = Arc::new(AtomicBool::new(false));
+        let stop_flag_clone = stop_flag.clone();
+        let counters = Arc::new(SignalCounters::default());
+        let counters_clone = counters.clone();
+
+        // 専用スレッドは全シグナルをブロックした状態で生成する。後から別の専用スレッドを
+        // 作ったとき、その対象のシグナルがこのスレッドに配送されないようにするため
+        let mut caller_mask = SigSet::empty();
+        pthread_sigmask(
+            SigmaskHow::SIG_BLOCK,
+            Some(&SigSet::all()),
+            Some(&mut caller_mask),
+        )
+        .map_err(|e| ProcessError::SignalError(e.to_string()))?;
+        let spawned = thread::Builder::new()
+            .name("signal-handler".into())
+            .spawn(move || loop {
+                let mut info: libc::siginfo_t = unsafe { std::mem::zeroed() };
+                let sig = unsafe { libc::sigwaitinfo(mask.as_ref(), &mut info) };
+
+                // Dropからの起床シグナルは配送しない
+                if stop_flag_clone.load(Ordering::SeqCst) {
+                    break;
+                }
+                if sig == -1 {
+                    // EINTR: 他のシグナルで中断された場合は待機を再開
+                    continue;
+                }
+
+                if let Some(signal_type) = SignalType::from_signal(sig) {
+                    counters_clone.increment(signal_type);
+                    if marks_shutdown {
+                        shutdown_clone.store(true, Ordering::SeqCst);
+                    }
+                    callback(signal_type);
+                }
+            });
+        pthread_sigmask(SigmaskHow::SIG_SETMASK, Some(&caller_mask), None)
+            .map_err(|e| ProcessError::SignalError(e.to_string()))?;
+        let handle = spawned.map_err(|e| ProcessError::SignalError(e.to_string()))?;
+
+        Ok(Self {
+            signals: signals.to_vec(),
+            shutdown,
+            stop_flag,
+            handle: Some(handle),
+            wake_signal: Some(wake_signal),
+            counters,
+            counter_ids: Vec::new(),
+        })
+    }
+
+    /// Ignore a signal by installing `SIG_IGN`
+    ///
+    /// The previous disposition is remembered so `restore` can reinstate it.
+    /// Ignoring an already ignored signal keeps the original disposition.
+    /// A common use is ignoring SIGPIPE, so writes to a closed pipe fail with
+    /// `EPIPE` instead of killing the process.
+    pub fn ignore(signal: SignalType) -> ProcessResult<()> {
+        let sig = signal.checked()?.to_signal();
+        let mut ignore: libc::sigaction = unsafe { std::mem::zeroed() };
+        ignore.sa_sigaction = libc::SIG_IGN;
+        unsafe { libc::sigemptyset(&mut ignore.sa_mask) };
+
+        let mut ignored = IGNORED.lock().unwrap();
+        let mut previous: libc::sigaction = unsafe { std::mem::zeroed() };
+        if unsafe { libc::sigaction(sig, &ignore, &mut previous) } != 0 {
+            return Err(ProcessError::SignalError(
+                std::io::Error::last_os_error().to_string(),
+            ));
+        }
+        if !ignored.iter().any(|(s, _)| *s == sig) {
+            ignored.push((sig, previous));
+        }
+        mark_installed(&[signal]);
+        Ok(())
+    }
+
+    /// Reinstate the disposition a signal had before `ignore`
+    ///
+    /// Does nothing if the signal was not ignored through `ignore`.
+    pub fn restore(signal: SignalType) -> ProcessResult<()> {
+        let sig = signal.checked()?.to_signal();
+        let mut ignored = IGNORED.lock().unwrap();
+        let Some(index) = ignored.iter().position(|(s, _)| *s == sig) else {
+            return Ok(());
+        };
+
+        if unsafe { libc::sigaction(sig, &ignored[index].1, std::ptr::null_mut()) } != 0 {
+            return Err(ProcessError::SignalError(
+                std::io::Error::last_os_error().to_string(),
+            ));
+        }
+        ignored.remove(index);
+        Ok(())
+    }
+
+    /// Run `callback` on `signal` while keeping the previously installed handler
+    ///
+    /// The current `sigaction` is saved and replaced by a handler that runs
+    /// `callback` and then the saved disposition, so a handler installed by
+    /// another library keeps working. Chaining the same signal again runs the
+    /// callbacks newest first, followed by the disposition that was in place
+    /// before the first one. A previous `SIG_IGN` or `SIG_DFL` is not run.
+    ///
+    /// `callback` runs inside the signal handler and must be async-signal-safe
+    /// (e.g. only touch atomics). Chained callbacks stay installed for the
+    /// lifetime of the process.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use linux_process_rs::signal::{SignalHandler, SignalType};
+    /// use std::sync::atomic::{AtomicBool, Ordering};
+    ///
+    /// static TERMINATED: AtomicBool = AtomicBool::new(false);
+    ///
+    /// SignalHandler::chain(SignalType::Terminate, || {
+    ///     TERMINATED.store(true, Ordering::SeqCst)
+    /// })
+    /// .expect("Failed to chain SIGTERM handler");
+    /// ```
+    pub fn chain<F>(signal: SignalType, callback: F) -> ProcessResult<()>
+    where
+        F: Fn() + Send + Sync + 'static,
+    {
+        let sig = signal.checked()?.to_signal();
+        let head = &CHAINS[signal.index()];
+        let _lock = CHAIN_LOCK.lock().unwrap();
+
+        let mut current: libc::sigaction = unsafe { std::mem::zeroed() };
+        if unsafe { libc::sigaction(sig, std::ptr::null(), &mut current) } != 0 {
+            return Err(ProcessError::SignalError(
+                std::io::Error::last_os_error().to_string(),
+            ));
+        }
+        // restore_defaultで退避したハンドラがあれば、それを元の処置として繋ぐ
+        if current.sa_sigaction == libc::SIG_DFL {
+            if let Some(parked) = take_parked(sig) {
+                current = parked;
+            }
+        }
+        // 既に自分のトランポリンが入っていれば、リストに繋ぐだけでよい
+        let trampoline = chain_trampoline as SigInfoHandler as libc::sighandler_t;
+        let installed = current.sa_sigaction == trampoline;
+
+        let link: &'static ChainLink = Box::leak(Box::new(ChainLink {
+            callback: Box::new(callback),
+            previous: (!installed).then_some(current),
+            next: head.load(Ordering::Acquire),
+        }));
+        // トランポリンを入れる前に公開しないと、直後のシグナルで空のリストを辿ってしまう
+        head.store(
+            link as *const ChainLink as *mut ChainLink,
+            Ordering::Release,
+        );
+
+        if !installed {
+            let mut action: libc::sigaction = unsafe { std::mem::zeroed() };
+            action.sa_sigaction = trampoline;
+            action.sa_flags = libc::SA_SIGINFO;
+            if !NO_RESTART[signal.index()].load(Ordering::SeqCst) {
+                action.sa_flags |= libc::SA_RESTART;
+            }
+            unsafe { libc::sigemptyset(&mut action.sa_mask) };
+            if unsafe { libc::sigaction(sig, &action, std::ptr::null_mut()) } != 0 {
+                head.store(link.next as *mut ChainLink, Ordering::Release);
+                return Err(ProcessError::SignalError(
+                    std::io::Error::last_os_error().to_string(),
+                ));
+            }
+        }
+        mark_installed(&[signal]);
+        Ok(())
+    }
+
+    /// Run `handler` every time `signal` arrives, until it is removed
+    ///
+    /// The signal handler itself only writes to a self-pipe; the closures run
+    /// on a shared `signal-dispatch` thread, started by the first call, so
+    /// unlike `chain` they need not be async-signal-safe. Handlers of the same
+    /// signal run in registration order. Deliveries that arrive while the
+    /// thread is busy may be merged into one call.
+    ///
+    /// The disposition stays installed after the last handler of a signal is
+    /// removed, so later deliveries are swallowed rather than taking the
+    /// default action; `restore_default` restores it. `SignalType::Kill` cannot be
+    /// handled and is rejected.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use linux_process_rs::signal::{SignalHandler, SignalType};
+    ///
+    /// let id = SignalHandler::on(SignalType::User1, || {
+    ///     println!("dumping statistics");
+    /// })
+    /// .expect("Failed to register SIGUSR1 handler");
+    ///
+    /// SignalHandler::remove(id);
+    /// ```
+    pub fn on<F>(signal: SignalType, handler: F) -> ProcessResult<HandlerId>
+    where
+        F: Fn() + Send + Sync + 'static,
+    {
+        if signal.checked()? == SignalType::Kill {
+            return Err(ProcessError::InvalidInput(
+                "SIGKILL cannot be handled".to_string(),
+            ));
+        }
+
+        let mut dispatcher = DISPATCHER.lock().unwrap();
+        if dispatcher.is_none() {
+            let signals = Signals::new(std::iter::empty::<i32>())
+                .map_err(|e| ProcessError::SignalError(e.to_string()))?;
+            let handle = signals.handle();
+            thread::Builder::new()
+                .name("signal-dispatch".to_string())
+                .spawn(move || dispatch(signals))?;
+            *dispatcher = Some(handle);
+        }
+        // 配送を有効にする前に登録し、その直後に届いたシグナルも取りこぼさない
+        let id = HandlerId(NEXT_HANDLER_ID.fetch_add(1, Ordering::Relaxed));
+        HANDLERS.lock().unwrap().push(Registered {
+            id,
+            signal,
+            handler: Arc::new(handler),
+        });
+
+        // 登録済みのシグナルを再度追加しても何も起きない
+        let enabled = dispatcher
+            .as_ref()
+            .unwrap()
+            .add_signal(signal.to_signal())
+            .map_err(|e| ProcessError::SignalError(e.to_string()))
+            .and_then(|()| rearm(signal.to_signal()));
+        if let Err(e) = enabled {
+            Self::remove(id);
+            return Err(e);
+        }
+        mark_installed(&[signal]);
+        Ok(id)
+    }
+
+    /// Unregister a handler added with `on`
+    ///
+    /// Returns `false` if `id` was already removed. A call that is already
+    /// running on the dispatch thread is not interrupted.
+    pub fn remove(id: HandlerId) -> bool {
+        let mut handlers = HANDLERS.lock().unwrap();
+        let before = handlers.len();
+        handlers.retain(|registered| registered.id != id);
+        handlers.len() != before
+    }
+
+    /// Reap terminated children automatically so none is left as a zombie
+    ///
+    /// Same as `auto_reap_with` without a callback.
+    pub fn auto_reap() -> ProcessResult<()> {
+        Self::auto_reap_with(|_, _| {})
+    }
+
+    /// Reap terminated children on a `signal-reaper` thread, calling `callback` for each
+    ///
+    /// Every SIGCHLD wakes the thread, which collects all terminated children
+    /// with `waitpid(-1, WNOHANG)` in a loop, so exits that the kernel merged
+    /// into a single SIGCHLD are reaped as well. Children that were already
+    /// zombies when this is called are reaped immediately. `callback` receives
+    /// the pid and exit status of each child and runs on the reaper thread.
+    ///
+    /// Since any child may be reaped, `Child::wait` or `ProcessGuard` on a
+    /// child of the same process can then fail with `ECHILD`; use this in
+    /// programs that do not wait for their children themselves. The reaper
+    /// runs for the rest of the process and can only be started once.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use linux_process_rs::signal::SignalHandler;
+    ///
+    /// SignalHandler::auto_reap_with(|pid, status| {
+    ///     println!("child {} exited with {}", pid, status);
+    /// })
+    /// .expect("Failed to start reaper");
+    /// ```
+    pub fn auto_reap_with<F>(callback: F) -> ProcessResult<()>
+    where
+        F: Fn(u32, std::process::ExitStatus) + Send + 'static,
+    {
+        if REAPING.swap(true, Ordering::SeqCst) {
+            return Err(ProcessError::InvalidInput(
+                "Automatic reaping is already enabled".to_string(),
+            ));
+        }
+
+        // 回収する前に登録し、その間に終了した子のSIGCHLDも取りこぼさない
+        let mut sigchld = match Signals::new([SIGCHLD]) {
+            Ok(signals) => signals,
+            Err(e) => {
+                REAPING.store(false, Ordering::SeqCst);
+                return Err(ProcessError::SignalError(e.to_string()));
+            }
+        };
+        let spawned = thread::Builder::new()
+            .name("signal-reaper".to_string())
+            .spawn(move || loop {
+                reap_children(&callback);
+                sigchld.forever().next();
+            });
+        if let Err(e) = spawned {
+            REAPING.store(false, Ordering::SeqCst);
+            return Err(e.into());
+        }
+        Ok(())
+    }
+
+    /// Choose whether slow syscalls interrupted by `signal` are restarted (`SA_RESTART`)
+    ///
+    /// Handlers installed by `new` and `chain` restart by default: a blocking
+    /// `read` or `accept` resumes transparently once the handler has run. Pass
+    /// `false` to make such calls fail with `EINTR` instead, e.g. for a signal
+    /// meant to break a thread out of a blocking read. The flag is part of the
+    /// signal's disposition, so it applies to every handler of `signal`
+    /// process-wide and is kept if `chain` installs its handler later.
+    ///
+    /// A handler must already be installed; a signal with its default or
+    /// ignored disposition is rejected with `ProcessError::InvalidInput`.
+    pub fn set_restart(signal: SignalType, restart: bool) -> ProcessResult<()> {
+        let sig = signal.checked()?.to_signal();
+        let _lock = CHAIN_LOCK.lock().unwrap();
+
+        let mut action: libc::sigaction = unsafe { std::mem::zeroed() };
+        if unsafe { libc::sigaction(sig, std::ptr::null(), &mut action) } != 0 {
+            return Err(ProcessError::SignalError(
+                std::io::Error::last_os_error().to_string(),
+            ));
+        }
+        if action.sa_sigaction == libc::SIG_DFL || action.sa_sigaction == libc::SIG_IGN {
+            return Err(ProcessError::InvalidInput(format!(
+                "No handler installed for {:?}",
+                signal
+            )));
+        }
+
+        if restart {
+            action.sa_flags |= libc::SA_RESTART;
+        } else {
+            action.sa_flags &= !libc::SA_RESTART;
+        }
+        if unsafe { libc::sigaction(sig, &action, std::ptr::null_mut()) } != 0 {
+            return Err(ProcessError::SignalError(
+                std::io::Error::last_os_error().to_string(),
+            ));
+        }
+        NO_RESTART[signal.index()].store(!restart, Ordering::SeqCst);
+        Ok(())
+    }
+
+    /// Stream of deliveries of `signal` (requires the `tokio` feature)
+    ///
+    /// Built on `tokio::signal::unix::signal`, so it must be called from within
+    /// a tokio runtime. Every stream receives every delivery: several tasks can
+    /// subscribe to the same signal independently. Deliveries that arrive while
+    /// a stream is not being polled are merged into a single item. Once tokio
+    /// has registered a signal, its handler stays installed until `restore_default`, so
+    /// the default action (e.g. terminating) no longer applies.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # async fn run() -> linux_process_rs::ProcessResult<()> {
+    /// use futures_util::StreamExt;
+    /// use linux_process_rs::signal::{SignalHandler, SignalType};
+    ///
+    /// let mut reloads = SignalHandler::stream(SignalType::Hangup)?;
+    /// while let Some(signal) = reloads.next().await {
+    ///     println!("Received {:?}", signal);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "tokio")]
+    pub fn stream(
+        signal: SignalType,
+    ) -> ProcessResult<impl futures_core::Stream<Item = SignalType> + Send + Unpin> {
+        use tokio::signal::unix::{signal as tokio_signal, SignalKind};
+
+        let receiver = tokio_signal(SignalKind::from_raw(signal.checked()?.to_signal()))
+            .map_err(|e| ProcessError::SignalError(format!("{:?}: {}", signal, e)))?;
+        rearm(signal.to_signal())?;
+        mark_installed(&[signal]);
+        Ok(SignalStream { signal, receiver })
+    }
+
+    /// Receive `mask` through a file descriptor instead of a handler (Linux `signalfd`)
+    ///
+    /// The signals are blocked with `pthread_sigmask` on the calling thread, so
+    /// instead of interrupting it they stay pending until read from the
+    /// returned `SignalFd`. Its descriptor becomes readable whenever one is
+    /// pending, which lets it sit in an `epoll`/`poll` loop next to sockets.
+    /// As with `with_dedicated_thread`, only threads spawned after this call
+    /// inherit the mask; call it before spawning others, or a process-directed
+    /// signal may be delivered to a thread that still has it unblocked. The
+    /// signals stay blocked after the `SignalFd` is dropped.
+    /// `SignalType::Kill` cannot be blocked and is rejected.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use linux_process_rs::signal::{SignalHandler, SignalType};
+    ///
+    /// let mut fd = SignalHandler::signalfd(&[SignalType::Terminate, SignalType::Hangup])?;
+    /// // 通常はepollで読み込み可能になるのを待ってから読む
+    /// match fd.read()? {
+    ///     SignalType::Hangup => println!("reload"),
+    ///     signal => println!("stopping on {:?}", signal),
+    /// }
+    /// # Ok::<(), linux_process_rs::ProcessError>(())
+    /// ```
+    #[cfg(target_os = "linux")]
+    pub fn signalfd(mask: &[SignalType]) -> ProcessResult<SignalFd> {
+        use nix::sys::signal::{pthread_sigmask, SigmaskHow};
+        use nix::sys::signalfd::{SfdFlags, SignalFd as NixSignalFd};
+
+        if mask.is_empty() {
+            return Err(ProcessError::InvalidInput("No signals to handle".into()));
+        }
+        let set = blockable_set(mask)?;
+        pthread_sigmask(SigmaskHow::SIG_BLOCK, Some(&set), None)
+            .map_err(|e| ProcessError::SignalError(e.to_string()))?;
+        mark_installed(mask);
+        let inner = NixSignalFd::with_flags(&set, SfdFlags::SFD_CLOEXEC)
+            .map_err(|e| ProcessError::SignalError(e.to_string()))?;
+        Ok(SignalFd { inner })
+    }
+
+    /// Put `signal` back to its default action (`SIG_DFL`) and unblock it
+    ///
+    /// Drops what this module set up for the signal: the installed handler,
+    /// `chain` callbacks, closures registered with `on`, a saved `ignore`
+    /// disposition and the `set_restart` flag. The signal is unblocked on the
+    /// calling thread only, like the blocking done by `with_dedicated_thread`,
+    /// `signalfd` and `block`; other threads keep their masks.
+    ///
+    /// Handling the signal again afterwards works as before, and a
+    /// `SignalHandler` created before the call that is still alive then
+    /// receives it again. `SignalType::Kill` always has its default action and
+    /// is rejected.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use linux_process_rs::signal::{SignalHandler, SignalType};
+    ///
+    /// SignalHandler::on(SignalType::User1, || println!("dumping statistics"))?;
+    /// // ライブラリの終了処理: SIGUSR1は再びプロセスを終了させる
+    /// SignalHandler::restore_default(SignalType::User1)?;
+    /// # Ok::<(), linux_process_rs::ProcessError>(())
+    /// ```
+    pub fn restore_default(signal: SignalType) -> ProcessResult<()> {
+        if signal.checked()? == SignalType::Kill {
+            return Err(ProcessError::InvalidInput(
+                "SIGKILL cannot be reset".to_string(),
+            ));
+        }
+        let sig = signal.to_signal();
+        let index = signal.index();
+        let _lock = CHAIN_LOCK.lock().unwrap();
+
+        let mut current: libc::sigaction = unsafe { std::mem::zeroed() };
+        if unsafe { libc::sigaction(sig, std::ptr::null(), &mut current) } != 0 {
+            return Err(ProcessError::SignalError(
+                std::io::Error::last_os_error().to_string(),
+            ));
+        }
+        // トランポリンが入っていれば、最初のchainの前の処置を退避する
+        let trampoline = chain_trampoline as SigInfoHandler as libc::sighandler_t;
+        let replaced = if current.sa_sigaction == trampoline {
+            let mut link = CHAINS[index].load(Ordering::Acquire) as *const ChainLink;
+            let mut previous = None;
+            while let Some(current) = unsafe { link.as_ref() } {
+                previous = current.previous.or(previous);
+                link = current.next;
+            }
+            previous
+        } else {
+            Some(current)
+        };
+
+        let mut default: libc::sigaction = unsafe { std::mem::zeroed() };
+        default.sa_sigaction = libc::SIG_DFL;
+        unsafe { libc::sigemptyset(&mut default.sa_mask) };
+        if unsafe { libc::sigaction(sig, &default, std::ptr::null_mut()) } != 0 {
+            return Err(ProcessError::SignalError(
+                std::io::Error::last_os_error().to_string(),
+            ));
+        }
+        // 外したリンクは解放しない（実行中のトランポリンが辿っているかもしれない）
+        CHAINS[index].store(std::ptr::null_mut(), Ordering::Release);
+        NO_RESTART[index].store(false, Ordering::SeqCst);
+
+        if let Some(handler) = replaced.filter(|action| {
+            action.sa_sigaction != libc::SIG_DFL && action.sa_sigaction != libc::SIG_IGN
+        }) {
+            let mut parked = PARKED.lock().unwrap();
+            parked.retain(|(s, _)| *s != sig);
+            parked.push((sig, handler));
+        }
+        IGNORED.lock().unwrap().retain(|(s, _)| *s != sig);
+        HANDLERS
+            .lock()
+            .unwrap()
+            .retain(|registered| registered.signal != signal);
+
+        set_thread_mask(nix::sys::signal::SigmaskHow::SIG_UNBLOCK, &[signal])?;
+        INSTALLED.fetch_and(!(1 << index), Ordering::SeqCst);
+        Ok(())
+    }
+
+    /// `restore_default` every signal this module has handled, ignored or blocked
+    ///
+    /// Signals the process set up by other means are left alone, e.g. the
+    /// `SIGPIPE` the Rust runtime ignores at startup (unless it went through
+    /// `ignore`).
+    pub fn restore_all_defaults() -> ProcessResult<()> {
+        let installed = INSTALLED.load(Ordering::SeqCst);
+        for index in 0..SIGNAL_SLOTS {
+            if installed & (1 << index) != 0 {
+                Self::restore_default(SignalType::from_index(index))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Ignore a signal until the returned guard is dropped
+    pub fn ignore_scoped(signal: SignalType) -> ProcessResult<IgnoredSignal> {
+        Self::ignore(signal)?;
+        Ok(IgnoredSignal { signal })
+    }
+
     /// Check if shutdown has been requested
     pub fn should_shutdown(&self) -> bool {
         self.shutdown.load(Ordering::SeqCst)
@@ -148,6 +1167,83 @@ impl SignalHandler {
     pub fn shutdown_flag(&self) -> Arc<AtomicBool> {
         self.shutdown.clone()
     }
+
+    /// Number of times `signal` was delivered to this handler
+    ///
+    /// Signals that are not registered with this handler always report 0.
+    /// Standard signals do not queue: deliveries that arrive while the same
+    /// signal is still pending are merged by the kernel and counted once.
+    pub fn count(&self, signal: SignalType) -> u64 {
+        self.counters.get(signal)
+    }
+
+    /// Snapshot of the delivery counts of every registered signal
+    ///
+    /// Useful for spotting signal storms, e.g. a child repeatedly sending SIGHUP.
+    pub fn counts(&self) -> Vec<(SignalType, u64)> {
+        self.signals
+            .iter()
+            .map(|&signal| (signal, self.counters.get(signal)))
+            .collect()
+    }
+}
+
+/// Stream returned by `SignalHandler::stream`
+#[cfg(feature = "tokio")]
+struct SignalStream {
+    signal: SignalType,
+    receiver: tokio::signal::unix::Signal,
+}
+
+#[cfg(feature = "tokio")]
+impl futures_core::Stream for SignalStream {
+    type Item = SignalType;
+
+    fn poll_next(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Option<SignalType>> {
+        let signal = self.signal;
+        self.receiver
+            .poll_recv(cx)
+            .map(|received| received.map(|()| signal))
+    }
+}
+
+/// Descriptor returned by `SignalHandler::signalfd`
+#[cfg(target_os = "linux")]
+#[derive(Debug)]
+pub struct SignalFd {
+    inner: nix::sys::signalfd::SignalFd,
+}
+
+#[cfg(target_os = "linux")]
+impl SignalFd {
+    /// Block until one of the signals is pending and return it
+    pub fn read(&mut self) -> ProcessResult<SignalType> {
+        loop {
+            match self.inner.read_signal() {
+                Ok(Some(info)) => {
+                    return SignalType::from_signal(info.ssi_signo as i32).ok_or_else(|| {
+                        ProcessError::SignalError(format!(
+                            "Unexpected signal {} from signalfd",
+                            info.ssi_signo
+                        ))
+                    });
+                }
+                // 読み込みはブロックするので、EAGAINで返るのは割り込まれた場合だけ
+                Ok(None) | Err(nix::errno::Errno::EINTR) => continue,
+                Err(e) => return Err(ProcessError::Io(e.into())),
+            }
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl std::os::unix::io::AsRawFd for SignalFd {
+    fn as_raw_fd(&self) -> RawFd {
+        std::os::unix::io::AsRawFd::as_raw_fd(&self.inner)
+    }
 }
 
 impl Drop for SignalHandler {
@@ -155,29 +1251,181 @@ impl Drop for SignalHandler {
         // Signal the handler thread to stop
         self.stop_flag.store(true, Ordering::SeqCst);
 
+        for id in self.counter_ids.drain(..) {
+            signal_hook::low_level::unregister(id);
+        }
+
         // Wait for the handler thread to finish
         if let Some(handle) = self.handle.take() {
+            // 専用スレッドはsigwaitinfoでブロックしているので直接起こす
+            if let Some(sig) = self.wake_signal {
+                use std::os::unix::thread::JoinHandleExt;
+                unsafe {
+                    libc::pthread_kill(handle.as_pthread_t(), sig);
+                }
+            }
             let _ = handle.join();
         }
     }
 }
 
+/// Guard returned by `SignalHandler::ignore_scoped`; restores the signal on drop
+#[derive(Debug)]
+pub struct IgnoredSignal {
+    signal: SignalType,
+}
+
+impl Drop for IgnoredSignal {
+    fn drop(&mut self) {
+        let _ = SignalHandler::restore(self.signal);
+    }
+}
+
+/// Block `signals` on the calling thread (`pthread_sigmask(SIG_BLOCK)`)
+///
+/// A blocked signal sent to the thread stays pending until it is unblocked;
+/// one sent to the process goes to another thread that has it unblocked.
+/// Threads spawned afterwards inherit the mask, so blocking on the main thread
+/// before starting workers leaves the signals to a dedicated handling thread.
+/// `SignalType::Kill` cannot be blocked and is rejected.
+///
+/// # Examples
+///
+/// ```no_run
+/// use linux_process_rs::signal::{self, SignalType};
+///
+/// let worker = std::thread::spawn(|| {
+///     signal::block(&[SignalType::Interrupt, SignalType::Terminate]).unwrap();
+///     // このスレッドはSIGINT・SIGTERMで中断されない
+/// });
+/// # worker.join().unwrap();
+/// ```
+pub fn block(signals: &[SignalType]) -> ProcessResult<()> {
+    set_thread_mask(nix::sys::signal::SigmaskHow::SIG_BLOCK, signals)?;
+    mark_installed(signals);
+    Ok(())
+}
+
+/// Unblock `signals` on the calling thread (`pthread_sigmask(SIG_UNBLOCK)`)
+///
+/// Signals that became pending while blocked are delivered before this returns.
+pub fn unblock(signals: &[SignalType]) -> ProcessResult<()> {
+    set_thread_mask(nix::sys::signal::SigmaskHow::SIG_UNBLOCK, signals)
+}
+
+/// Run `f` with `signals` blocked on the calling thread, then restore the previous mask
+///
+/// The mask is restored even if `f` panics. Signals that arrived meanwhile
+/// are delivered when it is restored, unless they were already blocked before.
+///
+/// # Examples
+///
+/// ```no_run
+/// use linux_process_rs::signal::{self, SignalType};
+///
+/// // 書き込みの途中でSIGTERMのハンドラが走らないようにする
+/// signal::with_blocked(&[SignalType::Terminate], || {
+///     std::fs::write("state.json", b"{}")
+/// })??;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub fn with_blocked<F, R>(signals: &[SignalType], f: F) -> ProcessResult<R>
+where
+    F: FnOnce() -> R,
+{
+    use nix::sys::signal::{pthread_sigmask, SigSet, SigmaskHow};
+
+    /// Restores the saved mask on drop, so a panic in `f` does not leave it changed
+    struct RestoreMask(SigSet);
+
+    impl Drop for RestoreMask {
+        fn drop(&mut self) {
+            let _ = pthread_sigmask(SigmaskHow::SIG_SETMASK, Some(&self.0), None);
+        }
+    }
+
+    let set = blockable_set(signals)?;
+    let mut previous = SigSet::empty();
+    pthread_sigmask(SigmaskHow::SIG_BLOCK, Some(&set), Some(&mut previous))
+        .map_err(|e| ProcessError::SignalError(e.to_string()))?;
+    let _restore = RestoreMask(previous);
+    Ok(f())
+}
+
+fn set_thread_mask(how: nix::sys::signal::SigmaskHow, signals: &[SignalType]) -> ProcessResult<()> {
+    let set = blockable_set(signals)?;
+    nix::sys::signal::pthread_sigmask(how, Some(&set), None)
+        .map_err(|e| ProcessError::SignalError(e.to_string()))
+}
+
+/// Build a signal set for `pthread_sigmask`, rejecting `SignalType::Kill`
+fn blockable_set(signals: &[SignalType]) -> ProcessResult<nix::sys::signal::SigSet> {
+    if signals.contains(&SignalType::Kill) {
+        return Err(ProcessError::InvalidInput(
+            "SIGKILL cannot be blocked".to_string(),
+        ));
+    }
+    signal_set(signals)
+}
+
+/// Build a signal set from `signals`, real-time signals included
+///
+/// nix's `Signal` has no real-time signals, so the set is filled with `sigaddset`.
+fn signal_set(signals: &[SignalType]) -> ProcessResult<nix::sys::signal::SigSet> {
+    let mut raw: libc::sigset_t = unsafe { std::mem::zeroed() };
+    unsafe { libc::sigemptyset(&mut raw) };
+    for signal in signals {
+        if unsafe { libc::sigaddset(&mut raw, signal.checked()?.to_signal()) } != 0 {
+            return Err(ProcessError::SignalError(
+                std::io::Error::last_os_error().to_string(),
+            ));
+        }
+    }
+    Ok(unsafe { nix::sys::signal::SigSet::from_sigset_t_unchecked(raw) })
+}
+
+/// Take the descriptors kept open by `SignalHandler::reexec` in the previous image
+///
+/// Returns the still-open descriptors listed in `REEXEC_FDS_ENV`, marks them
+/// close-on-exec again and removes the variable so child processes do not see
+/// it. Returns an empty list when the process was not started by `reexec`.
+pub fn inherited_fds() -> Vec<RawFd> {
+    let Some(fds) = std::env::var_os(REEXEC_FDS_ENV) else {
+        return Vec::new();
+    };
+    std::env::remove_var(REEXEC_FDS_ENV);
+    fds.to_string_lossy()
+        .split(',')
+        .filter_map(|fd| fd.trim().parse().ok())
+        .filter(|&fd| set_cloexec(fd, true).is_ok())
+        .collect()
+}
+
+/// Set or clear `FD_CLOEXEC` on `fd`
+fn set_cloexec(fd: RawFd, cloexec: bool) -> ProcessResult<()> {
+    let flags = unsafe { libc::fcntl(fd, libc::F_GETFD) };
+    if flags == -1 {
+        return Err(std::io::Error::last_os_error().into());
+    }
+    let flags = if cloexec {
+        flags | libc::FD_CLOEXEC
+    } else {
+        flags & !libc::FD_CLOEXEC
+    };
+    if unsafe { libc::fcntl(fd, libc::F_SETFD, flags) } == -1 {
+        return Err(std::io::Error::last_os_error().into());
+    }
+    Ok(())
+}
+
 /// Send a signal to a process (Unix only)
 #[cfg(unix)]
 pub fn send_signal(pid: u32, signal: SignalType) -> ProcessResult<()> {
-    use nix::sys::signal::{kill, Signal};
-    use nix::unistd::Pid;
-
-    let nix_signal = match signal {
-        SignalType::Interrupt => Signal::SIGINT,
-        SignalType::Terminate => Signal::SIGTERM,
-        SignalType::Hangup => Signal::SIGHUP,
-        SignalType::Quit => Signal::SIGQUIT,
-        SignalType::User1 => Signal::SIGUSR1,
-        SignalType::User2 => Signal::SIGUSR2,
-    };
+    use nix::errno::Errno;
 
-    kill(Pid::from_raw(pid as i32), nix_signal)
+    // nixのSignalは実時間シグナルを表せないため、killを直接呼ぶ
+    let sig = signal.checked()?.to_signal();
+    Errno::result(unsafe { libc::kill(pid as i32, sig) })
         .map_err(|e| ProcessError::SignalError(e.to_string()))?;
 
     Ok(())
@@ -186,19 +1434,10 @@ pub fn send_signal(pid: u32, signal: SignalType) -> ProcessResult<()> {
 /// Send a signal to a process group (Unix only)
 #[cfg(unix)]
 pub fn send_signal_to_group(pgid: u32, signal: SignalType) -> ProcessResult<()> {
-    use nix::sys::signal::{killpg, Signal};
-    use nix::unistd::Pid;
-
-    let nix_signal = match signal {
-        SignalType::Interrupt => Signal::SIGINT,
-        SignalType::Terminate => Signal::SIGTERM,
-        SignalType::Hangup => Signal::SIGHUP,
-        SignalType::Quit => Signal::SIGQUIT,
-        SignalType::User1 => Signal::SIGUSR1,
-        SignalType::User2 => Signal::SIGUSR2,
-    };
+    use nix::errno::Errno;
 
-    killpg(Pid::from_raw(pgid as i32), nix_signal)
+    let sig = signal.checked()?.to_signal();
+    Errno::result(unsafe { libc::killpg(pgid as i32, sig) })
         .map_err(|e| ProcessError::SignalError(e.to_string()))?;
 
     Ok(())
@@ -213,6 +1452,11 @@ mod tests {
         assert_eq!(SignalType::Interrupt.to_signal(), SIGINT);
         assert_eq!(SignalType::from_signal(SIGINT), Some(SignalType::Interrupt));
         assert_eq!(SignalType::from_signal(999), None);
+
+        let rt = SignalType::RealTime(1);
+        assert_eq!(rt.to_signal(), libc::SIGRTMIN() + 1);
+        assert_eq!(SignalType::from_signal(libc::SIGRTMIN() + 1), Some(rt));
+        assert_eq!(rt.index(), SignalType::ALL.len() + 1);
     }
 
     #[test]
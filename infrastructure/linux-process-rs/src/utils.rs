@@ -1,6 +1,14 @@
 //! Utility functions for process management
 
-use crate::errors::ProcessResult;
+use crate::errors::{ProcessError, ProcessResult};
+use crate::process::ProcessBuilder;
+use std::collections::{HashMap, VecDeque};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::ops::ControlFlow;
+use std::path::Path;
+use std::process::{ExitStatus, Stdio};
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 
 /// Retry configuration for operations
@@ -109,6 +117,739 @@ impl RateLimiter {
     }
 }
 
+/// Run a command and tee its stdout/stderr to both our own streams and a log file
+///
+/// The log file is opened (truncated) before spawning, so a bad path is reported
+/// as `ProcessError::LogFileError` and the child is never started.
+pub fn run_tee<P: AsRef<Path>>(builder: ProcessBuilder, log_path: P) -> ProcessResult<ExitStatus> {
+    let log_path = log_path.as_ref();
+    let log_error = |source: io::Error| ProcessError::LogFileError {
+        path: log_path.display().to_string(),
+        source,
+    };
+
+    let log = Arc::new(Mutex::new(File::create(log_path).map_err(log_error)?));
+
+    let mut child = builder
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn_child()?;
+
+    let stdout = child.stdout.take();
+    let stderr = child.stderr.take();
+
+    let out_handle = stdout.map(|out| {
+        let log = Arc::clone(&log);
+        std::thread::spawn(move || tee_lines(out, io::stdout(), &log))
+    });
+    let err_handle = stderr.map(|err| {
+        let log = Arc::clone(&log);
+        std::thread::spawn(move || tee_lines(err, io::stderr(), &log))
+    });
+
+    // 両方のストリームを読み切ってから終了を待つ（パイプ詰まりを防ぐ）
+    for handle in [out_handle, err_handle].into_iter().flatten() {
+        handle
+            .join()
+            .map_err(|_| ProcessError::InvalidInput("Tee thread panicked".into()))?
+            .map_err(log_error)?;
+    }
+
+    let status = child.wait()?;
+    log.lock().unwrap().flush().map_err(log_error)?;
+    Ok(status)
+}
+
+/// Copy lines from `reader` to both `console` and the shared log file
+fn tee_lines<R: Read, W: Write>(reader: R, mut console: W, log: &Mutex<File>) -> io::Result<()> {
+    let mut reader = BufReader::new(reader);
+    let mut line = Vec::new();
+
+    loop {
+        line.clear();
+        if reader.read_until(b'\n', &mut line)? == 0 {
+            return Ok(());
+        }
+
+        // コンソールへの書き込み失敗（閉じたstdoutなど）はログを止めない
+        let _ = console.write_all(&line).and_then(|_| console.flush());
+
+        let mut file = log.lock().unwrap();
+        file.write_all(&line)?;
+        file.flush()?;
+    }
+}
+
+/// Run a command and keep only the last `max_lines` lines of its output
+///
+/// stdout and stderr share one pipe (like `2>&1`), so lines keep the order the
+/// child wrote them, and only the newest `max_lines` are held in a ring buffer,
+/// so memory stays bounded however chatty the child is. The tail is returned
+/// only if the child exits abnormally (non-zero status or killed by a signal);
+/// on success it is discarded and an empty `Vec` is returned.
+#[cfg(unix)]
+pub fn run_with_output_tail(
+    builder: ProcessBuilder,
+    max_lines: usize,
+) -> ProcessResult<(ExitStatus, Vec<String>)> {
+    let (reader, writer) = output_pipe()?;
+
+    // 書き込み側はbuilderと一緒に破棄されるので、子の終了で読み込み側がEOFになる
+    let mut child = builder
+        .stdout(Stdio::from(writer.try_clone()?))
+        .stderr(Stdio::from(writer))
+        .spawn_child()?;
+
+    let mut tail = VecDeque::with_capacity(max_lines);
+    // 読み切ってから終了を待つ（パイプ詰まりを防ぐ）
+    keep_last_lines(reader, &mut tail, max_lines)?;
+
+    let status = child.wait()?;
+    if status.success() {
+        return Ok((status, Vec::new()));
+    }
+    Ok((status, tail.into()))
+}
+
+/// Create a close-on-exec pipe whose write end can serve as both stdout and stderr
+///
+/// Returns the read end as a `File` and the write end as an `OwnedFd`.
+#[cfg(unix)]
+pub(crate) fn output_pipe() -> ProcessResult<(File, std::os::fd::OwnedFd)> {
+    use std::os::fd::{FromRawFd, OwnedFd};
+
+    let mut fds = [0; 2];
+    if unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC) } == -1 {
+        return Err(ProcessError::Io(io::Error::last_os_error()));
+    }
+    Ok(unsafe { (File::from_raw_fd(fds[0]), OwnedFd::from_raw_fd(fds[1])) })
+}
+
+/// Push lines from `reader` into `tail`, dropping the oldest beyond `max_lines`
+fn keep_last_lines<R: Read>(
+    reader: R,
+    tail: &mut VecDeque<String>,
+    max_lines: usize,
+) -> io::Result<()> {
+    let mut reader = BufReader::new(reader);
+    let mut line = Vec::new();
+
+    loop {
+        line.clear();
+        if reader.read_until(b'\n', &mut line)? == 0 {
+            return Ok(());
+        }
+        if max_lines == 0 {
+            continue;
+        }
+
+        let text = String::from_utf8_lossy(&line);
+        let text = text.trim_end_matches(['\n', '\r']).to_string();
+        if tail.len() == max_lines {
+            tail.pop_front();
+        }
+        tail.push_back(text);
+    }
+}
+
+/// Atomically replace `path` with `bytes`
+///
+/// Readers see either the previous content or the new content, never a
+/// partial file, even if the process crashes mid-write. Use this for PID files
+/// and persisted state.
+pub fn write_atomic<P: AsRef<Path>>(path: P, bytes: &[u8]) -> ProcessResult<()> {
+    write_atomic_with(path, |file| file.write_all(bytes))
+}
+
+/// Atomically replace `path` with whatever `write` writes to the temp file
+///
+/// The data goes to a temp file in the same directory, which is fsynced and then
+/// renamed over the target. If `write` fails the target is left untouched.
+pub fn write_atomic_with<P, F>(path: P, write: F) -> ProcessResult<()>
+where
+    P: AsRef<Path>,
+    F: FnOnce(&mut File) -> io::Result<()>,
+{
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    let path = path.as_ref();
+    let atomic_error = |reason: String| ProcessError::AtomicWriteError {
+        path: path.display().to_string(),
+        reason,
+    };
+
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| atomic_error("path has no file name".into()))?;
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+
+    // renameをアトミックにするため一時ファイルは同じディレクトリに作る
+    let tmp_path = dir.join(format!(
+        ".{}.tmp.{}.{}",
+        file_name.to_string_lossy(),
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+
+    let result = (|| {
+        let mut file = std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&tmp_path)
+            .map_err(|e| atomic_error(format!("failed to create temp file: {}", e)))?;
+        write(&mut file).map_err(|e| atomic_error(format!("failed to write temp file: {}", e)))?;
+        file.sync_all()
+            .map_err(|e| atomic_error(format!("failed to fsync temp file: {}", e)))?;
+
+        std::fs::rename(&tmp_path, path).map_err(|e| {
+            if e.raw_os_error() == Some(libc::EXDEV) {
+                atomic_error(format!(
+                    "cannot rename across filesystems ({} is a mount point or bind mount); \
+                     atomic replacement is not possible",
+                    path.display()
+                ))
+            } else {
+                atomic_error(format!("failed to rename temp file: {}", e))
+            }
+        })
+    })();
+
+    if result.is_err() {
+        let _ = std::fs::remove_file(&tmp_path);
+        return result;
+    }
+
+    // renameそのものを永続化するためディレクトリもfsyncする
+    File::open(dir)
+        .and_then(|d| d.sync_all())
+        .map_err(|e| atomic_error(format!("failed to fsync directory: {}", e)))
+}
+
+/// How often `tail_follow` checks the file for new data and rotation
+const TAIL_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Follow a log file like `tail -F`, calling `on_line` for every appended line
+///
+/// Following starts at the current end of the file, so only lines written
+/// after the call are reported, without their line ending. A trailing line
+/// without a newline is held back until it is completed. The file is polled
+/// every 100ms, and rotation is handled:
+///
+/// - when `path` is replaced by another file (different inode), the rest of
+///   the old file is read first and the new file is then followed from its start
+/// - when the file shrinks (`copytruncate`), reading restarts at its beginning
+/// - while `path` does not exist, the old file keeps being followed
+///
+/// Blocks until `on_line` returns `ControlFlow::Break`.
+#[cfg(unix)]
+pub fn tail_follow<P, F>(path: P, mut on_line: F) -> ProcessResult<()>
+where
+    P: AsRef<Path>,
+    F: FnMut(&str) -> ControlFlow<()>,
+{
+    use std::os::unix::fs::MetadataExt;
+
+    let path = path.as_ref();
+    let identity = |meta: &std::fs::Metadata| (meta.dev(), meta.ino());
+
+    let mut file = File::open(path)?;
+    let mut current = identity(&file.metadata()?);
+    let mut position = file.seek(SeekFrom::End(0))?;
+    let mut pending = Vec::new();
+
+    loop {
+        if read_new_lines(&mut file, &mut position, &mut pending, &mut on_line)?.is_break() {
+            return Ok(());
+        }
+
+        match std::fs::metadata(path) {
+            Ok(meta) if identity(&meta) != current => {
+                // 差し替えられた。直前に旧ファイルへ書かれた分も読んでから切り替える
+                let flow = read_new_lines(&mut file, &mut position, &mut pending, &mut on_line)?;
+                if flow.is_break() {
+                    return Ok(());
+                }
+                // 切り替えの間に再び消えた場合は次の確認でやり直す
+                if let Ok(next) = File::open(path) {
+                    current = identity(&next.metadata()?);
+                    file = next;
+                    position = 0;
+                    // 改行で終わらなかった旧ファイルの最後の行もここで渡す
+                    if !pending.is_empty() {
+                        let line = String::from_utf8_lossy(&pending).into_owned();
+                        pending.clear();
+                        if on_line(&line).is_break() {
+                            return Ok(());
+                        }
+                    }
+                    continue;
+                }
+            }
+            Ok(meta) if meta.len() < position => {
+                // copytruncateで切り詰められた
+                position = file.seek(SeekFrom::Start(0))?;
+                pending.clear();
+                continue;
+            }
+            // 消えている間は旧ファイルを追い続ける
+            _ => {}
+        }
+
+        std::thread::sleep(TAIL_POLL_INTERVAL);
+    }
+}
+
+/// Read what was appended to `file` and pass every completed line to `on_line`
+#[cfg(unix)]
+fn read_new_lines<F>(
+    file: &mut File,
+    position: &mut u64,
+    pending: &mut Vec<u8>,
+    on_line: &mut F,
+) -> io::Result<ControlFlow<()>>
+where
+    F: FnMut(&str) -> ControlFlow<()>,
+{
+    let read = file.read_to_end(pending)?;
+    *position += read as u64;
+
+    let mut start = 0;
+    while let Some(end) = pending[start..].iter().position(|&b| b == b'\n') {
+        let line = String::from_utf8_lossy(&pending[start..start + end]);
+        start += end + 1;
+        if on_line(line.trim_end_matches('\r')).is_break() {
+            pending.drain(..start);
+            return Ok(ControlFlow::Break(()));
+        }
+    }
+    pending.drain(..start);
+    Ok(ControlFlow::Continue(()))
+}
+
+/// Resource usage of a finished child process (from `wait4`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Rusage {
+    /// Peak resident set size in kilobytes
+    pub max_rss_kb: u64,
+    /// CPU time spent in user mode
+    pub user_time: Duration,
+    /// CPU time spent in the kernel
+    pub system_time: Duration,
+    /// Context switches because the process waited (I/O, sleep, ...)
+    pub voluntary_context_switches: u64,
+    /// Context switches forced by the scheduler (time slice expired)
+    pub involuntary_context_switches: u64,
+}
+
+impl Rusage {
+    fn from_raw(raw: &libc::rusage) -> Self {
+        let duration = |tv: libc::timeval| {
+            Duration::from_secs(tv.tv_sec as u64) + Duration::from_micros(tv.tv_usec as u64)
+        };
+
+        Self {
+            // Linuxではru_maxrssはキロバイト単位
+            max_rss_kb: raw.ru_maxrss as u64,
+            user_time: duration(raw.ru_utime),
+            system_time: duration(raw.ru_stime),
+            voluntary_context_switches: raw.ru_nvcsw as u64,
+            involuntary_context_switches: raw.ru_nivcsw as u64,
+        }
+    }
+}
+
+/// Run a command to completion and collect its resource usage
+///
+/// The child is reaped with `wait4`, which reports the rusage of that child
+/// alone (unlike `getrusage(RUSAGE_CHILDREN)`, which sums every reaped child).
+#[cfg(unix)]
+pub fn run_with_rusage(builder: ProcessBuilder) -> ProcessResult<(ExitStatus, Rusage)> {
+    use std::os::unix::process::ExitStatusExt;
+
+    let child = builder.spawn_child()?;
+    let pid = child.id() as libc::pid_t;
+
+    let mut status = 0;
+    let mut raw: libc::rusage = unsafe { std::mem::zeroed() };
+    loop {
+        let ret = unsafe { libc::wait4(pid, &mut status, 0, &mut raw) };
+        if ret == pid {
+            break;
+        }
+
+        let err = io::Error::last_os_error();
+        if err.kind() != io::ErrorKind::Interrupted {
+            return Err(ProcessError::Io(err));
+        }
+    }
+
+    Ok((ExitStatus::from_raw(status), Rusage::from_raw(&raw)))
+}
+
+/// Block until any of `pids` terminates and return which one did
+///
+/// Each pid is polled with a targeted `waitpid(pid, WNOHANG)`, so the exit
+/// status of children not in `pids` is never consumed. Between polls the call
+/// sleeps until the next SIGCHLD. A pid that has already exited (a zombie) is
+/// returned immediately.
+///
+/// Installs a SIGCHLD handler (via `signal-hook`) for the duration of the call;
+/// SIGCHLD is ignored by default, so this does not change other behavior.
+#[cfg(unix)]
+pub fn wait_any(
+    pids: &[nix::unistd::Pid],
+) -> ProcessResult<(nix::unistd::Pid, nix::sys::wait::WaitStatus)> {
+    use nix::errno::Errno;
+    use nix::sys::wait::{waitpid, WaitPidFlag, WaitStatus};
+    use signal_hook::{consts::SIGCHLD, iterator::Signals};
+
+    if pids.is_empty() {
+        return Err(ProcessError::InvalidInput("No pids to wait for".into()));
+    }
+
+    // ポーリングより先に登録し、その間に届いたSIGCHLDも取りこぼさない
+    let mut sigchld =
+        Signals::new([SIGCHLD]).map_err(|e| ProcessError::SignalError(e.to_string()))?;
+
+    loop {
+        for &pid in pids {
+            match waitpid(pid, Some(WaitPidFlag::WNOHANG)) {
+                Ok(WaitStatus::StillAlive) | Err(Errno::EINTR) => {}
+                Ok(status) => return Ok((pid, status)),
+                Err(e) => return Err(ProcessError::Io(e.into())),
+            }
+        }
+
+        // 次のSIGCHLDまで待つ（無関係な子の終了でも起きるので再度確認する）
+        sigchld.forever().next();
+    }
+}
+
+/// A running process found by `find_by_name`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProcessInfo {
+    pub pid: u32,
+    /// Executable name from `/proc/<pid>/comm` (truncated by the kernel to 15 bytes)
+    pub comm: String,
+    /// Arguments from `/proc/<pid>/cmdline`; empty for kernel threads, zombies
+    /// and processes still in the middle of `execve`
+    pub cmdline: Vec<String>,
+}
+
+/// How `find_by_name_with` matches a process against the name
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NameMatch {
+    /// `comm` equals the name (like `pgrep -x`)
+    Comm,
+    /// The command line, arguments joined with spaces, contains the name (like `pgrep -f`)
+    CmdlineContains,
+}
+
+/// Find running processes whose executable name (`comm`) is exactly `name`
+///
+/// Same as `find_by_name_with(name, NameMatch::Comm)`.
+#[cfg(target_os = "linux")]
+pub fn find_by_name(name: &str) -> ProcessResult<Vec<ProcessInfo>> {
+    find_by_name_with(name, NameMatch::Comm)
+}
+
+/// Find running processes by name, scanning `/proc`, sorted by pid
+///
+/// The kernel keeps only the first 15 bytes of `comm`, so with
+/// `NameMatch::Comm` a longer name is compared by that prefix. The calling
+/// process is never reported, as with `pgrep`. Processes that exit while
+/// `/proc` is being scanned are skipped.
+#[cfg(target_os = "linux")]
+pub fn find_by_name_with(name: &str, mode: NameMatch) -> ProcessResult<Vec<ProcessInfo>> {
+    const COMM_LEN: usize = 15;
+
+    if name.is_empty() {
+        return Err(ProcessError::InvalidInput("Process name is empty".into()));
+    }
+    let comm_name = &name.as_bytes()[..name.len().min(COMM_LEN)];
+    let own_pid = std::process::id();
+
+    let mut found = Vec::new();
+    for (pid, dir) in proc_entries()? {
+        if pid == own_pid {
+            continue;
+        }
+        // 一覧を取った後に終了したプロセスは読めないので飛ばす
+        let Some(info) = read_process_info(&dir, pid) else {
+            continue;
+        };
+        let matched = match mode {
+            NameMatch::Comm => info.comm.as_bytes() == comm_name,
+            NameMatch::CmdlineContains => info.cmdline.join(" ").contains(name),
+        };
+        if matched {
+            found.push(info);
+        }
+    }
+    found.sort_by_key(|info| info.pid);
+    Ok(found)
+}
+
+/// The `/proc/<pid>` directory of every process, in no particular order
+#[cfg(target_os = "linux")]
+fn proc_entries() -> ProcessResult<Vec<(u32, std::path::PathBuf)>> {
+    let mut entries = Vec::new();
+    for entry in std::fs::read_dir("/proc")? {
+        let entry = entry?;
+        if let Some(pid) = entry
+            .file_name()
+            .to_str()
+            .and_then(|s| s.parse::<u32>().ok())
+        {
+            entries.push((pid, entry.path()));
+        }
+    }
+    Ok(entries)
+}
+
+/// Read `comm` and `cmdline` of one `/proc/<pid>` directory
+#[cfg(target_os = "linux")]
+fn read_process_info(dir: &Path, pid: u32) -> Option<ProcessInfo> {
+    let comm = std::fs::read(dir.join("comm")).ok()?;
+    let cmdline = std::fs::read(dir.join("cmdline")).ok()?;
+
+    // 引数はNUL区切りで、最後の引数の後にもNULが付く
+    let args = cmdline.strip_suffix(&[0]).unwrap_or(&cmdline);
+    let cmdline = if args.is_empty() {
+        Vec::new()
+    } else {
+        args.split(|&b| b == 0)
+            .map(|arg| String::from_utf8_lossy(arg).into_owned())
+            .collect()
+    };
+    Some(ProcessInfo {
+        pid,
+        comm: String::from_utf8_lossy(comm.strip_suffix(b"\n").unwrap_or(&comm)).into_owned(),
+        cmdline,
+    })
+}
+
+/// Parent/child relationships of the running processes, built by `process_tree`
+///
+/// A snapshot: processes started or reaped after the scan are not reflected.
+/// Processes with no parent in the PID namespace hang off pid 0: PID 1 itself,
+/// `kthreadd` (pid 2, the parent of kernel threads) and processes entered from
+/// outside a container, so `children(0)` lists the roots.
+#[derive(Debug, Clone, Default)]
+pub struct ProcessTree {
+    parents: HashMap<u32, u32>,
+    children: HashMap<u32, Vec<u32>>,
+}
+
+impl ProcessTree {
+    /// Whether `pid` was running when the tree was built
+    pub fn contains(&self, pid: u32) -> bool {
+        self.parents.contains_key(&pid)
+    }
+
+    /// Parent of `pid`, or `None` for roots and unknown pids
+    pub fn parent(&self, pid: u32) -> Option<u32> {
+        self.parents.get(&pid).copied().filter(|&ppid| ppid != 0)
+    }
+
+    /// Direct children of `pid`, sorted by pid
+    pub fn children(&self, pid: u32) -> &[u32] {
+        self.children.get(&pid).map_or(&[], Vec::as_slice)
+    }
+
+    /// Every process below `pid`, depth-first with each parent before its children
+    pub fn descendants(&self, pid: u32) -> Descendants<'_> {
+        Descendants {
+            tree: self,
+            stack: self.children(pid).iter().rev().copied().collect(),
+        }
+    }
+}
+
+/// Iterator returned by `ProcessTree::descendants`
+#[derive(Debug, Clone)]
+pub struct Descendants<'a> {
+    tree: &'a ProcessTree,
+    stack: Vec<u32>,
+}
+
+impl Iterator for Descendants<'_> {
+    type Item = u32;
+
+    fn next(&mut self) -> Option<u32> {
+        let pid = self.stack.pop()?;
+        self.stack
+            .extend(self.tree.children(pid).iter().rev().copied());
+        Some(pid)
+    }
+}
+
+/// Build the process tree from the `PPid` line of every `/proc/<pid>/status`
+///
+/// Processes that exit during the scan are left out. Their children may then
+/// point at a parent that is not in the tree, or already show up under the
+/// process they were reparented to.
+#[cfg(target_os = "linux")]
+pub fn process_tree() -> ProcessResult<ProcessTree> {
+    let mut tree = ProcessTree::default();
+    for (pid, dir) in proc_entries()? {
+        let Ok(status) = std::fs::read_to_string(dir.join("status")) else {
+            continue;
+        };
+        let Some(ppid) = status
+            .lines()
+            .find_map(|line| line.strip_prefix("PPid:"))
+            .and_then(|ppid| ppid.trim().parse::<u32>().ok())
+        else {
+            continue;
+        };
+        tree.parents.insert(pid, ppid);
+        tree.children.entry(ppid).or_default().push(pid);
+    }
+    for children in tree.children.values_mut() {
+        children.sort_unstable();
+    }
+    Ok(tree)
+}
+
+/// A zombie found by `zombies`: a process that exited but was not yet waited for
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ZombieInfo {
+    pub pid: u32,
+    /// The process that is expected to reap it
+    pub ppid: u32,
+    /// Executable name (at most 15 bytes)
+    pub comm: String,
+}
+
+/// Find zombie processes (state `Z` in `/proc/<pid>/stat`), sorted by pid
+///
+/// Zombies that keep piling up under one parent point at a missing `wait`
+/// there. Processes that disappear during the scan, e.g. because their parent
+/// reaped them, are skipped.
+#[cfg(target_os = "linux")]
+pub fn zombies() -> ProcessResult<Vec<ZombieInfo>> {
+    let mut found = Vec::new();
+    for (pid, dir) in proc_entries()? {
+        let Ok(stat) = std::fs::read(dir.join("stat")) else {
+            continue;
+        };
+        if let Some(zombie) = parse_zombie(pid, &stat) {
+            found.push(zombie);
+        }
+    }
+    found.sort_by_key(|zombie| zombie.pid);
+    Ok(found)
+}
+
+/// Parse `pid (comm) state ppid ...`, returning the process only if it is a zombie
+#[cfg(target_os = "linux")]
+fn parse_zombie(pid: u32, stat: &[u8]) -> Option<ZombieInfo> {
+    // commには空白や括弧も入りうるので、最後の')'で区切る
+    let open = stat.iter().position(|&b| b == b'(')?;
+    let close = stat.iter().rposition(|&b| b == b')')?;
+    let comm = String::from_utf8_lossy(stat.get(open + 1..close)?).into_owned();
+    let rest = std::str::from_utf8(stat.get(close + 1..)?).ok()?;
+
+    let mut fields = rest.split_whitespace();
+    if fields.next()? != "Z" {
+        return None;
+    }
+    let ppid = fields.next()?.parse().ok()?;
+    Some(ZombieInfo { pid, ppid, comm })
+}
+
+/// Counter shared between a process and every child it forks
+///
+/// The value lives in an anonymous `MAP_SHARED` mapping, so children created
+/// with `fork` after the counter update the same page as the parent instead
+/// of a copy-on-write duplicate. Cheap enough for progress counting across a
+/// process pool without setting up an IPC channel.
+#[cfg(unix)]
+pub struct SharedCounter {
+    value: std::ptr::NonNull<std::sync::atomic::AtomicU64>,
+}
+
+// 共有ページ上のAtomicU64を指すだけなので、スレッド間で共有してよい
+#[cfg(unix)]
+unsafe impl Send for SharedCounter {}
+#[cfg(unix)]
+unsafe impl Sync for SharedCounter {}
+
+#[cfg(unix)]
+impl SharedCounter {
+    /// Map a new shared page holding a counter that starts at zero
+    pub fn new() -> ProcessResult<Self> {
+        use std::sync::atomic::AtomicU64;
+
+        let ptr = unsafe {
+            libc::mmap(
+                std::ptr::null_mut(),
+                std::mem::size_of::<AtomicU64>(),
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_SHARED | libc::MAP_ANONYMOUS,
+                -1,
+                0,
+            )
+        };
+        if ptr == libc::MAP_FAILED {
+            return Err(ProcessError::Io(io::Error::last_os_error()));
+        }
+
+        // 匿名マッピングはゼロで初期化され、ページ境界に揃っている
+        let value = std::ptr::NonNull::new(ptr.cast::<AtomicU64>())
+            .ok_or_else(|| ProcessError::Io(io::Error::from(io::ErrorKind::OutOfMemory)))?;
+        Ok(Self { value })
+    }
+
+    /// Add one and return the new value
+    pub fn inc(&self) -> u64 {
+        self.add(1)
+    }
+
+    /// Add `n` and return the new value
+    pub fn add(&self, n: u64) -> u64 {
+        self.atomic()
+            .fetch_add(n, std::sync::atomic::Ordering::SeqCst)
+            .wrapping_add(n)
+    }
+
+    /// Current value, including increments made by other processes
+    pub fn get(&self) -> u64 {
+        self.atomic().load(std::sync::atomic::Ordering::SeqCst)
+    }
+
+    fn atomic(&self) -> &std::sync::atomic::AtomicU64 {
+        unsafe { self.value.as_ref() }
+    }
+}
+
+#[cfg(unix)]
+impl std::fmt::Debug for SharedCounter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SharedCounter")
+            .field("value", &self.get())
+            .finish()
+    }
+}
+
+#[cfg(unix)]
+impl Drop for SharedCounter {
+    fn drop(&mut self) {
+        // 子プロセス側のマッピングはそれぞれの終了時に解放される
+        unsafe {
+            libc::munmap(
+                self.value.as_ptr().cast(),
+                std::mem::size_of::<std::sync::atomic::AtomicU64>(),
+            );
+        }
+    }
+}
+
 /// Convert a string to a C-compatible string
 #[cfg(unix)]
 pub fn to_cstring(s: &str) -> ProcessResult<std::ffi::CString> {
@@ -183,6 +924,22 @@ pub mod unix {
 mod tests {
     use super::*;
 
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_parse_zombie_stat_line() {
+        let zombie = parse_zombie(42, b"42 (my (odd) name) Z 7 42 42 0 -1 4227084").unwrap();
+        assert_eq!(
+            zombie,
+            ZombieInfo {
+                pid: 42,
+                ppid: 7,
+                comm: "my (odd) name".to_string(),
+            }
+        );
+        assert_eq!(parse_zombie(43, b"43 (sleep) S 7 43 43 0 -1 4194304"), None);
+        assert_eq!(parse_zombie(44, b"garbage"), None);
+    }
+
     #[test]
     fn test_retry_config_default() {
         let config = RetryConfig::default();
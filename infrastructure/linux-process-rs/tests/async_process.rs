@@ -0,0 +1,84 @@
+//! Tests for `ProcessBuilder::spawn_async` on the tokio runtime
+
+use linux_process_rs::errors::ProcessError;
+use linux_process_rs::ProcessBuilder;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::AsyncReadExt;
+
+fn is_reaped(pid: u32) -> bool {
+    !std::path::Path::new(&format!("/proc/{}", pid)).exists()
+}
+
+#[tokio::test]
+async fn test_spawn_async_echo_output() {
+    let mut process = ProcessBuilder::new("echo")
+        .arg("hello")
+        .arg("async")
+        .stdout(Stdio::piped())
+        .label("greeter")
+        .spawn_async()
+        .expect("Failed to spawn echo");
+    assert_eq!(process.label(), Some("greeter"));
+
+    let mut stdout = String::new();
+    process
+        .take_stdout()
+        .unwrap()
+        .read_to_string(&mut stdout)
+        .await
+        .unwrap();
+    let output = process.wait().await.unwrap();
+
+    assert!(output.success);
+    assert_eq!(output.status, Some(0));
+    assert_eq!(stdout, "hello async\n");
+    assert_eq!(process.pid(), None);
+}
+
+#[tokio::test]
+async fn test_async_kill_reaps_child() {
+    let mut process = ProcessBuilder::new("sleep")
+        .arg("10")
+        .spawn_async()
+        .expect("Failed to spawn sleep");
+    let pid = process.pid().unwrap();
+
+    process.kill().await.unwrap();
+    assert!(is_reaped(pid));
+}
+
+#[tokio::test]
+async fn test_async_timeout_kills_child() {
+    let mut process = ProcessBuilder::new("sleep")
+        .arg("10")
+        .timeout(Duration::from_millis(200))
+        .spawn_async()
+        .expect("Failed to spawn sleep");
+    let pid = process.pid().unwrap();
+
+    match process.wait().await {
+        Err(ProcessError::Timeout { elapsed }) => assert!(elapsed >= Duration::from_millis(200)),
+        other => panic!("Expected timeout error, got {:?}", other),
+    }
+    assert!(is_reaped(pid));
+}
+
+#[tokio::test]
+async fn test_dropped_async_process_is_killed_and_reaped() {
+    let process = ProcessBuilder::new("sleep")
+        .arg("10")
+        .spawn_async()
+        .expect("Failed to spawn sleep");
+    let pid = process.pid().unwrap();
+    drop(process);
+
+    // tokioがバックグラウンドで回収するまで待つ
+    for _ in 0..100 {
+        if is_reaped(pid) {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    panic!("PID {} was not reaped", pid);
+}
@@ -0,0 +1,78 @@
+//! Round-trip tests for the `serde` representation of `ProcessError`
+
+use linux_process_rs::errors::ProcessError;
+use std::io;
+use std::time::Duration;
+
+fn all_variants() -> Vec<ProcessError> {
+    vec![
+        ProcessError::Io(io::Error::from_raw_os_error(libc::ENOENT)),
+        ProcessError::Io(io::Error::new(io::ErrorKind::Other, "pipe closed")),
+        ProcessError::SpawnError {
+            reason: "sleep: not found".into(),
+        },
+        ProcessError::InvalidInput("bad arg".into()),
+        ProcessError::SignalError("sigaction failed".into()),
+        ProcessError::Timeout {
+            elapsed: Duration::from_secs(5),
+        },
+        ProcessError::PermissionDenied {
+            context: "setuid".into(),
+        },
+        ProcessError::ForkError(nix::errno::Errno::EAGAIN),
+        ProcessError::ResourceLimitError {
+            message: "RLIMIT_NPROC".into(),
+        },
+        ProcessError::TerminatedBySignal { signal: 9 },
+        ProcessError::LogFileError {
+            path: "/var/log/app.log".into(),
+            source: io::Error::from_raw_os_error(libc::EACCES),
+        },
+        ProcessError::AtomicWriteError {
+            path: "/run/app.pid".into(),
+            reason: "failed to fsync temp file".into(),
+        },
+        ProcessError::CgroupError {
+            path: "/sys/fs/cgroup/app.slice".into(),
+            reason: "cgroup.procs is not writable".into(),
+        },
+        ProcessError::NamespaceError {
+            namespaces: "UTS | PID".into(),
+            reason: "Operation not permitted (os error 1)".into(),
+        },
+        ProcessError::CircuitOpen {
+            pool: "workers".into(),
+            retry_after: Duration::from_millis(1500),
+        },
+        ProcessError::ProcessTerminated { pid: 42 },
+    ]
+}
+
+#[test]
+fn test_process_error_round_trip() {
+    for error in all_variants() {
+        let json = serde_json::to_value(&error).unwrap();
+        let back: ProcessError = serde_json::from_value(json.clone()).unwrap();
+
+        assert_eq!(serde_json::to_value(&back).unwrap(), json);
+        assert_eq!(back.to_string(), error.to_string());
+    }
+}
+
+#[test]
+fn test_process_error_tagged_representation() {
+    let json = serde_json::to_value(ProcessError::Io(io::Error::from_raw_os_error(
+        libc::ENOENT,
+    )))
+    .unwrap();
+    assert_eq!(json["kind"], "io");
+    assert_eq!(json["errno"], libc::ENOENT);
+
+    let json = serde_json::to_value(ProcessError::ForkError(nix::errno::Errno::EAGAIN)).unwrap();
+    assert_eq!(json["kind"], "fork");
+    assert_eq!(json["name"], "EAGAIN");
+
+    let back: ProcessError =
+        serde_json::from_str(r#"{"kind":"terminated_by_signal","signal":15}"#).unwrap();
+    assert!(matches!(back, ProcessError::TerminatedBySignal { signal: 15 }));
+}
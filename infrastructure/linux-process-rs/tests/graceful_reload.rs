@@ -0,0 +1,92 @@
+//! Graceful reload and re-exec test
+//!
+//! Runs without the libtest harness: the reload and upgrade handlers block
+//! their signals on the main thread before any other thread exists, and the
+//! child re-executes this binary in place.
+
+use linux_process_rs::signal::{inherited_fds, send_signal, SignalHandler, SignalType};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::os::unix::io::{AsRawFd, FromRawFd};
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+/// 子プロセスとして起動されたことを示す環境変数
+const CHILD_ENV: &str = "GRACEFUL_RELOAD_CHILD";
+
+fn main() {
+    if std::env::var_os(CHILD_ENV).is_some() {
+        child();
+        return;
+    }
+
+    let mut child = Command::new(std::env::current_exe().unwrap())
+        .env(CHILD_ENV, "1")
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("Failed to spawn child");
+    let mut lines = BufReader::new(child.stdout.take().unwrap()).lines();
+    let mut next_line = || lines.next().expect("child closed stdout").unwrap();
+
+    let ready = next_line();
+    let (pid, port) = parse_report(&ready, "READY");
+    assert_eq!(pid, child.id());
+
+    // SIGHUPでは再読み込みだけが行われ、プロセスは動き続ける
+    send_signal(child.id(), SignalType::Hangup).unwrap();
+    assert_eq!(next_line(), "RELOADED 1");
+
+    // SIGUSR2で同じPIDのまま新しいイメージに置き換わり、待ち受けソケットを引き継ぐ
+    send_signal(child.id(), SignalType::User2).unwrap();
+    let upgraded = next_line();
+    assert_eq!(parse_report(&upgraded, "UPGRADED"), (pid, port));
+
+    let mut stream = TcpStream::connect(("127.0.0.1", port)).expect("listener was not kept");
+    stream
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+    let mut reply = String::new();
+    stream.read_to_string(&mut reply).unwrap();
+    assert_eq!(reply, "served by the new image\n");
+
+    assert!(child.wait().unwrap().success());
+    println!("test graceful_reload ... ok");
+}
+
+/// "<tag> <pid> <port>" を読む
+fn parse_report(line: &str, tag: &str) -> (u32, u16) {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    assert_eq!(fields.len(), 3, "unexpected report: {:?}", line);
+    assert_eq!(fields[0], tag, "unexpected report: {:?}", line);
+    (fields[1].parse().unwrap(), fields[2].parse().unwrap())
+}
+
+fn child() {
+    // exec後の新しいイメージ: 引き継いだソケットで1回だけ応答して終了する
+    if let [fd] = inherited_fds()[..] {
+        let listener = unsafe { TcpListener::from_raw_fd(fd) };
+        let port = listener.local_addr().unwrap().port();
+        println!("UPGRADED {} {}", std::process::id(), port);
+        let (mut stream, _) = listener.accept().unwrap();
+        stream.write_all(b"served by the new image\n").unwrap();
+        return;
+    }
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    let reloads = std::sync::atomic::AtomicU32::new(0);
+    let _reloader = SignalHandler::on_reload(move || {
+        let count = reloads.fetch_add(1, std::sync::atomic::Ordering::SeqCst) + 1;
+        println!("RELOADED {}", count);
+    })
+    .expect("Failed to install reload handler");
+    let _upgrader = SignalHandler::on_upgrade(vec![listener.as_raw_fd()])
+        .expect("Failed to install upgrade handler");
+
+    println!("READY {} {}", std::process::id(), port);
+    // execで置き換えられるまで待ち受けソケットを保持する
+    loop {
+        std::thread::sleep(Duration::from_secs(1));
+    }
+}
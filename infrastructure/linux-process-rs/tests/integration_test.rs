@@ -1,8 +1,18 @@
 //! Integration tests for the process management library
 
 use linux_process_rs::errors::ProcessError;
-use linux_process_rs::process::{validate_input, ProcessBuilder};
-use linux_process_rs::signal::{SignalHandler, SignalType};
+use linux_process_rs::process::{
+    validate_input, ProcessBuilder, ProcessGuard, Rlimit, SchedPolicy,
+};
+use linux_process_rs::signal::{send_signal, SignalHandler, SignalType};
+use linux_process_rs::utils::{
+    run_tee, run_with_output_tail, run_with_rusage, tail_follow, wait_any, write_atomic,
+    write_atomic_with, SharedCounter,
+};
+use linux_process_rs::{
+    NamespaceFlags, Pipeline, PoolMetrics, ProcessPool, ProcessScope, RestartPolicy, RestartStats,
+    ShutdownMode, ShutdownReport,
+};
 use std::time::Duration;
 
 #[test]
@@ -32,6 +42,83 @@ fn test_process_with_environment() {
     assert!(stdout.contains("test_value"));
 }
 
+fn env_lines(builder: ProcessBuilder) -> Vec<String> {
+    let output = builder.output().expect("Failed to execute env");
+    assert!(output.status.success());
+    let mut lines: Vec<String> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(str::to_string)
+        .collect();
+    lines.sort();
+    lines
+}
+
+#[test]
+fn test_env_clear_keeps_only_explicit_variables() {
+    let lines = env_lines(
+        ProcessBuilder::new("env")
+            .env("DROPPED_BY_CLEAR", "1")
+            .env_clear()
+            .env("APP_MODE", "test")
+            .envs([("LANG", "C"), ("TZ", "UTC")]),
+    );
+    assert_eq!(lines, ["APP_MODE=test", "LANG=C", "TZ=UTC"]);
+}
+
+#[test]
+fn test_inherit_env_toggle() {
+    // PATHはテストを実行する環境に必ずある
+    let inherited = env_lines(ProcessBuilder::new("env").env("APP_MODE", "test"));
+    assert!(inherited.iter().any(|line| line.starts_with("PATH=")));
+    assert!(inherited.contains(&"APP_MODE=test".to_string()));
+
+    let isolated = env_lines(
+        ProcessBuilder::new("env")
+            .env("APP_MODE", "test")
+            .inherit_env(false),
+    );
+    assert_eq!(isolated, ["APP_MODE=test"]);
+}
+
+#[test]
+fn test_env_inherit_filter_passes_only_matching_variables() {
+    // 親の環境を変えずに済むよう、変数を設定した自分自身の中でenvを起動する
+    if std::env::var_os("ENV_FILTER_CHILD").is_some() {
+        for line in env_lines(
+            ProcessBuilder::new("/usr/bin/env")
+                .env_inherit_filter("MYAPP_*")
+                .env("EXTRA", "explicit"),
+        ) {
+            println!("child-env: {}", line);
+        }
+        return;
+    }
+
+    let exe = std::env::current_exe().unwrap();
+    let output = ProcessBuilder::new(exe.to_str().unwrap())
+        .arg("--exact")
+        .arg("test_env_inherit_filter_passes_only_matching_variables")
+        .arg("--nocapture")
+        .env("ENV_FILTER_CHILD", "1")
+        .env("MYAPP_TOKEN", "abc")
+        .env("MYAPP_MODE", "test")
+        .env("OTHER_SECRET", "leak")
+        .output_string()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+
+    let lines: Vec<&str> = output
+        .stdout
+        .lines()
+        // libtestの"test ... "の後ろに続けて出力されることがある
+        .filter_map(|line| line.split_once("child-env: ").map(|(_, var)| var))
+        .collect();
+    assert_eq!(
+        lines,
+        ["EXTRA=explicit", "MYAPP_MODE=test", "MYAPP_TOKEN=abc"]
+    );
+}
+
 #[test]
 fn test_process_timeout() {
     let builder = ProcessBuilder::new("sleep")
@@ -41,7 +128,7 @@ fn test_process_timeout() {
     let result = builder.spawn().and_then(|mut g| g.wait());
 
     match result {
-        Err(ProcessError::TimeoutError { .. }) => {
+        Err(ProcessError::Timeout { .. }) => {
             // Expected timeout error
         }
         _ => panic!("Expected timeout error"),
@@ -97,3 +184,2664 @@ fn test_process_with_working_directory() {
     let stdout = String::from_utf8_lossy(&output.stdout);
     assert!(stdout.contains("/tmp"));
 }
+
+#[cfg(unix)]
+#[test]
+fn test_process_starts_in_custom_working_directory() {
+    let dir = tempfile::tempdir().unwrap();
+    let output = ProcessBuilder::new("pwd")
+        .current_dir(dir.path())
+        .output()
+        .expect("Failed to execute process");
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(
+        std::path::Path::new(stdout.trim()),
+        dir.path().canonicalize().unwrap()
+    );
+}
+
+#[test]
+fn test_missing_working_directory_names_path() {
+    let dir = tempfile::tempdir().unwrap();
+    let missing = dir.path().join("missing");
+    let err = ProcessBuilder::new("pwd")
+        .current_dir(&missing)
+        .spawn()
+        .err()
+        .expect("missing working directory should be rejected");
+
+    assert!(matches!(err, ProcessError::InvalidInput(_)), "{:?}", err);
+    assert!(
+        err.to_string().contains(&missing.display().to_string()),
+        "{}",
+        err
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn test_signal_treated_as_success() {
+    let mut guard = ProcessBuilder::new("sleep")
+        .arg("10")
+        .treat_signals_as_success(&[SignalType::Terminate])
+        .spawn()
+        .expect("Failed to spawn process");
+
+    send_signal(guard.pid().unwrap(), SignalType::Terminate).expect("Failed to send signal");
+
+    let output = guard.wait().expect("SIGTERM should be treated as success");
+    assert!(output.success);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_signal_not_in_set_is_error() {
+    let mut guard = ProcessBuilder::new("sleep")
+        .arg("10")
+        .treat_signals_as_success(&[SignalType::Interrupt])
+        .spawn()
+        .expect("Failed to spawn process");
+
+    send_signal(guard.pid().unwrap(), SignalType::Terminate).expect("Failed to send signal");
+
+    match guard.wait() {
+        Err(ProcessError::TerminatedBySignal { signal }) => {
+            assert_eq!(signal, libc::SIGTERM);
+        }
+        other => panic!(
+            "Expected TerminatedBySignal error, got {:?}",
+            other.map(|o| o.success)
+        ),
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn test_pool_shutdown_in_priority_order() {
+    let pool = ProcessPool::new("ShutdownPool", 3);
+
+    let db_proxy = pool
+        .spawn_worker_with_priority("sleep", &["10"], 0)
+        .expect("Failed to spawn worker");
+    let app = pool
+        .spawn_worker_with_priority("sleep", &["10"], 20)
+        .expect("Failed to spawn worker");
+    let cache = pool
+        .spawn_worker_with_priority("sleep", &["10"], 10)
+        .expect("Failed to spawn worker");
+
+    let order = pool
+        .shutdown_gracefully(Duration::from_secs(2))
+        .expect("Failed to shut down pool");
+
+    assert_eq!(order, vec![app, cache, db_proxy]);
+    assert_eq!(pool.active_workers(), 0);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_run_tee_writes_log_file() {
+    let dir = tempfile::tempdir().expect("Failed to create temp dir");
+    let log_path = dir.path().join("tee.log");
+
+    let status = run_tee(ProcessBuilder::new("echo").arg("tee_output"), &log_path)
+        .expect("Failed to run command");
+
+    assert!(status.success());
+    let log = std::fs::read_to_string(&log_path).expect("Failed to read log file");
+    assert!(log.contains("tee_output"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_run_tee_log_file_error() {
+    let result = run_tee(
+        ProcessBuilder::new("echo").arg("never_runs"),
+        "/nonexistent-dir/tee.log",
+    );
+
+    assert!(matches!(result, Err(ProcessError::LogFileError { .. })));
+}
+
+#[test]
+fn test_process_with_pty() {
+    use std::io::{Read, Write};
+    use std::sync::mpsc;
+
+    let mut guard = ProcessBuilder::new("sh")
+        .pty_size(40, 120)
+        .spawn()
+        .expect("Failed to spawn on pty");
+    let mut pty = guard.take_pty().expect("pty should be allocated");
+
+    let size = pty.window_size().unwrap();
+    assert_eq!((size.rows, size.cols), (40, 120));
+
+    let mut reader = pty.try_clone().unwrap();
+    let (tx, rx) = mpsc::channel();
+    std::thread::spawn(move || {
+        let mut buf = [0u8; 1024];
+        while let Ok(n) = reader.read(&mut buf) {
+            if n == 0 || tx.send(buf[..n].to_vec()).is_err() {
+                break;
+            }
+        }
+    });
+
+    pty.write_all(b"echo pty_$((40 + 2))\n").unwrap();
+
+    let mut output = String::new();
+    while !output.contains("pty_42") {
+        let chunk = rx
+            .recv_timeout(Duration::from_secs(5))
+            .expect("timed out waiting for pty output");
+        output.push_str(&String::from_utf8_lossy(&chunk));
+    }
+
+    pty.write_all(b"exit\n").unwrap();
+    assert!(guard.wait().unwrap().success);
+}
+
+#[test]
+fn test_allocate_pty_makes_stdio_a_terminal() {
+    // `test -t`はファイルディスクリプタが端末なら0で終了する
+    let is_tty = |builder: ProcessBuilder| {
+        let mut guard = builder
+            .arg("-t")
+            .arg("0")
+            .arg("-a")
+            .arg("-t")
+            .arg("1")
+            .spawn()
+            .expect("Failed to spawn test");
+        // 子の終了までmasterを開いたままにする
+        let _pty = guard.take_pty();
+        guard.wait().unwrap().success
+    };
+
+    assert!(is_tty(ProcessBuilder::new("test").allocate_pty(true)));
+    assert!(!is_tty(
+        ProcessBuilder::new("test")
+            .pty_size(40, 120)
+            .allocate_pty(false)
+            .stdin(std::process::Stdio::null())
+            .stdout(std::process::Stdio::null())
+    ));
+}
+
+#[test]
+fn test_pool_circuit_breaker_trips_and_recovers() {
+    use linux_process_rs::{CircuitBreakerConfig, CircuitState};
+
+    let pool = ProcessPool::new("BreakerPool", 4).with_circuit_breaker(CircuitBreakerConfig {
+        failure_threshold: 3,
+        window: Duration::from_secs(10),
+        cooldown: Duration::from_millis(200),
+    });
+    assert_eq!(pool.circuit_state(), CircuitState::Closed);
+
+    for _ in 0..3 {
+        assert!(matches!(
+            pool.spawn_worker("/nonexistent/worker"),
+            Err(ProcessError::Io(_))
+        ));
+    }
+    assert_eq!(pool.circuit_state(), CircuitState::Open);
+
+    // 開いている間は正常なコマンドも拒否される
+    assert!(matches!(
+        pool.spawn_worker_with_args("sleep", &["0.1"]),
+        Err(ProcessError::CircuitOpen { .. })
+    ));
+    assert_eq!(pool.active_workers(), 0);
+
+    std::thread::sleep(Duration::from_millis(250));
+    assert_eq!(pool.circuit_state(), CircuitState::HalfOpen);
+
+    pool.spawn_worker_with_args("sleep", &["0.1"]).unwrap();
+    assert_eq!(pool.circuit_state(), CircuitState::Closed);
+}
+
+#[test]
+fn test_write_atomic_failed_write_keeps_old_content() {
+    use std::io::Write;
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("state.json");
+    write_atomic(&path, b"{\"version\":1}").unwrap();
+
+    // 書き込みの途中で失敗した場合をシミュレート
+    let result = write_atomic_with(&path, |file| {
+        file.write_all(b"{\"vers")?;
+        Err(std::io::Error::new(std::io::ErrorKind::Other, "crash"))
+    });
+    assert!(matches!(result, Err(ProcessError::AtomicWriteError { .. })));
+
+    assert_eq!(std::fs::read(&path).unwrap(), b"{\"version\":1}");
+    // 一時ファイルは残らない
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+
+    write_atomic(&path, b"{\"version\":2}").unwrap();
+    assert_eq!(std::fs::read(&path).unwrap(), b"{\"version\":2}");
+}
+
+#[test]
+fn test_write_atomic_readers_never_see_partial_file() {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("app.pid");
+    let old = vec![b'a'; 64 * 1024];
+    let new = vec![b'b'; 64 * 1024];
+    write_atomic(&path, &old).unwrap();
+
+    let done = Arc::new(AtomicBool::new(false));
+    let reader = {
+        let (path, done) = (path.clone(), done.clone());
+        std::thread::spawn(move || {
+            while !done.load(Ordering::SeqCst) {
+                let content = std::fs::read(&path).unwrap();
+                assert_eq!(content.len(), 64 * 1024);
+                assert!(content.iter().all(|&b| b == content[0]));
+            }
+        })
+    };
+
+    for i in 0..50 {
+        write_atomic(&path, if i % 2 == 0 { &new } else { &old }).unwrap();
+    }
+    done.store(true, Ordering::SeqCst);
+    reader.join().unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn test_pool_worker_label_round_trips() {
+    let pool = ProcessPool::new("LabelPool", 2);
+
+    let labeled = pool
+        .spawn_process(
+            ProcessBuilder::new("sleep")
+                .arg("10")
+                .label("image-resizer"),
+        )
+        .expect("Failed to spawn labeled worker");
+    let unlabeled = pool
+        .spawn_worker_with_args("sleep", &["10"])
+        .expect("Failed to spawn worker");
+
+    let info = pool.get_worker_info(labeled).expect("worker not found");
+    assert_eq!(info.label.as_deref(), Some("image-resizer"));
+    assert_eq!(info.command, "sleep 10");
+    assert!(info.to_string().contains("worker: image-resizer"));
+
+    let listed: Vec<_> = pool
+        .list_workers()
+        .into_iter()
+        .filter(|w| w.label.is_some())
+        .map(|w| w.pid)
+        .collect();
+    assert_eq!(listed, vec![labeled]);
+    assert_eq!(pool.get_worker_info(unlabeled).unwrap().label, None);
+
+    pool.terminate_all().expect("Failed to terminate workers");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_pool_weighted_slots() {
+    let pool = ProcessPool::new("WeightedPool", 3);
+    let sleep = || ProcessBuilder::new("sleep").arg("0.3");
+
+    let heavy = pool.submit_weighted(sleep().label("heavy"), 3);
+    let start = std::time::Instant::now();
+    while pool.used_slots() < 3 {
+        assert!(
+            start.elapsed() < Duration::from_secs(5),
+            "heavy job never started"
+        );
+        std::thread::sleep(Duration::from_millis(5));
+    }
+
+    // 容量3を重み3のジョブが使い切っているので、重み1のジョブは待機する
+    let light = pool.submit_weighted(sleep(), 1);
+    std::thread::sleep(Duration::from_millis(50));
+    assert_eq!(pool.queued_jobs(), 1);
+    assert_eq!(pool.running_jobs(), 1);
+    assert_eq!(pool.used_slots(), 3);
+
+    heavy.recv().expect("heavy job failed");
+    let started_light = std::time::Instant::now();
+    light.recv().expect("light job failed");
+    // 重いジョブが終わってから起動したので、軽いジョブの実行時間がまるごと残っている
+    assert!(started_light.elapsed() >= Duration::from_millis(200));
+
+    // 容量を超える重みは容量に丸められ、空いたプールで単独で実行される
+    let oversized = pool.submit_weighted(sleep(), 10);
+    let start = std::time::Instant::now();
+    while pool.used_slots() < 3 {
+        assert!(
+            start.elapsed() < Duration::from_secs(5),
+            "oversized job never started"
+        );
+        std::thread::sleep(Duration::from_millis(5));
+    }
+    assert_eq!(pool.used_slots(), 3);
+    oversized.recv().expect("oversized job failed");
+    assert_eq!(pool.used_slots(), 0);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_pool_submit_queues_jobs_beyond_pool_size() {
+    if let Some(dir) = std::env::var_os("POOL_JOB_DIR") {
+        // 実行中のジョブが1つずつファイルを置き、起動時に見えた数を報告する
+        let dir = std::path::PathBuf::from(dir);
+        let marker = dir.join(std::process::id().to_string());
+        std::fs::write(&marker, "").unwrap();
+        let running = std::fs::read_dir(&dir).unwrap().count();
+        println!(
+            "JOB={} RUNNING={}",
+            std::env::var("POOL_JOB_ID").unwrap(),
+            running
+        );
+        std::thread::sleep(Duration::from_millis(100));
+        std::fs::remove_file(&marker).unwrap();
+        return;
+    }
+
+    let dir = tempfile::tempdir().unwrap();
+    let exe = std::env::current_exe().unwrap();
+    let pool = ProcessPool::new("JobPool", 3);
+    let handles: Vec<_> = (0..10)
+        .map(|i| {
+            pool.submit(
+                ProcessBuilder::new(exe.to_str().unwrap())
+                    .arg("--exact")
+                    .arg("test_pool_submit_queues_jobs_beyond_pool_size")
+                    .arg("--nocapture")
+                    .env("POOL_JOB_DIR", dir.path().to_str().unwrap())
+                    .env("POOL_JOB_ID", i.to_string()),
+            )
+        })
+        .collect();
+    assert!(pool.running_jobs() <= 3);
+    assert!(pool.queued_jobs() > 0, "excess jobs should wait for a slot");
+
+    let mut peak = 0;
+    for (i, handle) in handles.into_iter().enumerate() {
+        let output = handle.recv().expect("job failed");
+        assert!(output.status.success());
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        // テストハーネスの出力と同じ行に出るため、行の途中から読む
+        let report = stdout
+            .lines()
+            .find_map(|line| line.find("JOB=").map(|at| &line[at..]))
+            .unwrap_or_else(|| panic!("job {} printed no report: {}", i, stdout));
+        let (job, running) = report.split_once(' ').unwrap();
+        assert_eq!(job, format!("JOB={}", i));
+        peak = peak.max(running["RUNNING=".len()..].parse::<usize>().unwrap());
+    }
+    assert!(peak <= 3, "{} jobs ran at once in a pool of 3", peak);
+    assert!(peak > 1, "jobs never ran concurrently");
+    assert_eq!(pool.queued_jobs(), 0);
+    assert_eq!(pool.used_slots(), 0);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_pool_jobs_share_slots_with_workers() {
+    let pool = ProcessPool::new("SharedJobPool", 2);
+    pool.spawn_worker_with_args("sleep", &["10"])
+        .expect("Failed to spawn worker");
+
+    let first = pool.submit(ProcessBuilder::new("sleep").arg("0.3"));
+    let second = pool.submit(ProcessBuilder::new("echo").arg("done"));
+    // ワーカーが1スロット使っているので、ジョブは1つずつしか動かない
+    assert!(first.recv_timeout(Duration::from_secs(5)).is_some());
+    let output = second.recv().expect("job failed");
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "done");
+
+    // ジョブの実行中はワーカー用のスロットも埋まる
+    let _busy = pool.submit(ProcessBuilder::new("sleep").arg("10"));
+    let start = std::time::Instant::now();
+    while pool.running_jobs() == 0 {
+        assert!(
+            start.elapsed() < Duration::from_secs(5),
+            "job never started"
+        );
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    assert!(matches!(
+        pool.spawn_worker("sleep"),
+        Err(ProcessError::InvalidInput(_))
+    ));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_pool_restarts_crashed_workers() {
+    let pool = ProcessPool::new("RestartPool", 3).with_restart_policy(RestartPolicy::OnFailure {
+        max_restarts: 100,
+        backoff: Duration::from_millis(10),
+    });
+    for _ in 0..3 {
+        pool.spawn_worker_with_args("sh", &["-c", "sleep 0.5; exit 3"])
+            .expect("Failed to spawn worker");
+    }
+    let original: Vec<_> = pool.list_workers().iter().map(|info| info.pid).collect();
+
+    // 全ワーカーが2回以上落ちるまで待つ間も、プールのサイズは保たれる
+    let start = std::time::Instant::now();
+    while pool.restart_stats().restarts < 6 {
+        assert!(
+            start.elapsed() < Duration::from_secs(10),
+            "{:?}",
+            pool.restart_stats()
+        );
+        std::thread::sleep(Duration::from_millis(50));
+    }
+    std::thread::sleep(Duration::from_millis(50));
+    assert_eq!(pool.active_workers(), 3);
+
+    let workers = pool.list_workers();
+    assert!(workers.iter().all(|info| !original.contains(&info.pid)));
+    assert!(
+        workers.iter().all(|info| info.restarts >= 1),
+        "{:?}",
+        workers
+    );
+    let stats = pool.restart_stats();
+    assert_eq!(stats.failed_restarts, 0);
+    assert_eq!(stats.exhausted, 0);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_polling_active_workers_keeps_crash_restarts() {
+    let pool =
+        ProcessPool::new("PolledRestartPool", 3).with_restart_policy(RestartPolicy::OnFailure {
+            max_restarts: 100,
+            backoff: Duration::from_millis(10),
+        });
+    for _ in 0..3 {
+        pool.spawn_worker_with_args("sh", &["-c", "sleep 0.2; exit 3"])
+            .expect("Failed to spawn worker");
+    }
+
+    // 落ちたワーカーを再起動の監視より先にactive_workersが片付けてはいけない
+    let start = std::time::Instant::now();
+    while pool.restart_stats().restarts < 6 {
+        assert!(
+            start.elapsed() < Duration::from_secs(10),
+            "{:?}",
+            pool.restart_stats()
+        );
+        assert!(pool.active_workers() <= 3);
+        std::thread::sleep(Duration::from_millis(1));
+    }
+    assert_eq!(pool.list_workers().len(), 3);
+    assert_eq!(pool.restart_stats().failed_restarts, 0);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_pool_gives_up_after_max_restarts() {
+    let pool = ProcessPool::new("GiveUpPool", 2).with_restart_policy(RestartPolicy::OnFailure {
+        max_restarts: 2,
+        backoff: Duration::from_millis(10),
+    });
+    pool.spawn_worker("false").expect("Failed to spawn worker");
+    // 正常終了したワーカーはOnFailureでは起動し直さない
+    pool.spawn_worker("true").expect("Failed to spawn worker");
+
+    let start = std::time::Instant::now();
+    while pool.restart_stats().exhausted < 1 {
+        assert!(
+            start.elapsed() < Duration::from_secs(10),
+            "{:?}",
+            pool.restart_stats()
+        );
+        std::thread::sleep(Duration::from_millis(20));
+    }
+    assert_eq!(
+        pool.restart_stats(),
+        RestartStats {
+            restarts: 2,
+            failed_restarts: 0,
+            exhausted: 1,
+        }
+    );
+    assert_eq!(pool.active_workers(), 0);
+}
+
+/// ワーカー1つとジョブ2つでスロット3つを埋め、残り4つのジョブを待機させる
+fn busy_pool(name: &str) -> (ProcessPool, Vec<linux_process_rs::JobHandle>) {
+    let pool = ProcessPool::new(name, 3);
+    pool.spawn_worker_with_args("sleep", &["30"])
+        .expect("Failed to spawn worker");
+    let handles = (0..6)
+        .map(|_| pool.submit(ProcessBuilder::new("sleep").arg("0.3")))
+        .collect();
+
+    let start = std::time::Instant::now();
+    while pool.running_jobs() < 2 {
+        assert!(
+            start.elapsed() < Duration::from_secs(5),
+            "jobs never started"
+        );
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    assert_eq!(pool.queued_jobs(), 4);
+    (pool, handles)
+}
+
+#[cfg(unix)]
+#[test]
+fn test_pool_metrics_track_job_states() {
+    let pool = ProcessPool::new("MetricsPool", 3);
+    assert_eq!(
+        pool.metrics(),
+        PoolMetrics {
+            idle_workers: 3,
+            ..Default::default()
+        }
+    );
+
+    pool.spawn_worker_with_args("sleep", &["30"])
+        .expect("Failed to spawn worker");
+    let slow: Vec<_> = (0..3)
+        .map(|_| pool.submit(ProcessBuilder::new("sleep").arg("0.3")))
+        .collect();
+    let start = std::time::Instant::now();
+    while pool.running_jobs() < 2 {
+        assert!(
+            start.elapsed() < Duration::from_secs(5),
+            "jobs never started"
+        );
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    let busy = pool.metrics();
+    assert_eq!(busy.active_workers, 3);
+    assert_eq!(busy.idle_workers, 0);
+    assert_eq!(busy.queued_tasks, 1);
+    assert_eq!((busy.completed_tasks, busy.failed_tasks), (0, 0));
+    assert_eq!(busy.average_task_duration, Duration::ZERO);
+
+    // 0以外のステータス、シグナル、起動の失敗はいずれも失敗として数える
+    let failing = vec![
+        pool.submit(ProcessBuilder::new("false")),
+        pool.submit(ProcessBuilder::new("sh").arg("-c").arg("kill -9 $$")),
+        pool.submit(ProcessBuilder::new("/nonexistent/command")),
+    ];
+    for handle in slow.into_iter().chain(failing) {
+        let _ = handle.recv();
+    }
+
+    let done = pool.metrics();
+    assert_eq!(done.active_workers, 1);
+    assert_eq!(done.idle_workers, 2);
+    assert_eq!(done.queued_tasks, 0);
+    assert_eq!(done.completed_tasks, 3);
+    assert_eq!(done.failed_tasks, 3);
+    // 0.3秒のジョブ3つと、すぐに終わるジョブ3つの平均
+    assert!(
+        done.average_task_duration >= Duration::from_millis(150),
+        "average {:?}",
+        done.average_task_duration
+    );
+    assert!(
+        done.average_task_duration < Duration::from_secs(1),
+        "average {:?}",
+        done.average_task_duration
+    );
+}
+
+/// 正常に終了したジョブと、取り消されたジョブの数
+fn job_outcomes(handles: Vec<linux_process_rs::JobHandle>) -> (usize, usize) {
+    let results: Vec<_> = handles.into_iter().map(|handle| handle.recv()).collect();
+    let succeeded = results
+        .iter()
+        .filter(|result| matches!(result, Ok(output) if output.status.success()))
+        .count();
+    let cancelled = results
+        .iter()
+        .filter(|result| matches!(result, Err(ProcessError::InvalidInput(_))))
+        .count();
+    (succeeded, cancelled)
+}
+
+#[cfg(unix)]
+#[test]
+fn test_pool_shutdown_drain_runs_queued_jobs() {
+    let (pool, handles) = busy_pool("DrainPool");
+
+    let report = pool.shutdown(ShutdownMode::Drain).expect("shutdown failed");
+    assert_eq!(
+        report,
+        ShutdownReport {
+            completed_jobs: 6,
+            cancelled_jobs: 0,
+            killed_jobs: 0,
+            terminated_workers: 1,
+        }
+    );
+    assert_eq!(job_outcomes(handles), (6, 0));
+    assert_eq!(pool.active_workers(), 0);
+    assert_eq!(pool.used_slots(), 0);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_pool_shutdown_graceful_drops_queued_jobs() {
+    let (pool, handles) = busy_pool("GracefulPool");
+
+    let report = pool
+        .shutdown(ShutdownMode::Graceful)
+        .expect("shutdown failed");
+    assert_eq!(
+        report,
+        ShutdownReport {
+            completed_jobs: 2,
+            cancelled_jobs: 4,
+            killed_jobs: 0,
+            terminated_workers: 1,
+        }
+    );
+    assert_eq!(job_outcomes(handles), (2, 4));
+    assert_eq!(pool.active_workers(), 0);
+
+    // 停止後に投入したジョブは実行されない
+    let late = pool.submit(ProcessBuilder::new("echo").arg("late"));
+    assert!(matches!(late.recv(), Err(ProcessError::InvalidInput(_))));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_pool_shutdown_immediate_kills_everything() {
+    use std::os::unix::process::ExitStatusExt;
+
+    let (pool, handles) = busy_pool("ImmediatePool");
+
+    let start = std::time::Instant::now();
+    let report = pool
+        .shutdown(ShutdownMode::Immediate)
+        .expect("shutdown failed");
+    assert!(
+        start.elapsed() < Duration::from_millis(300),
+        "{:?}",
+        start.elapsed()
+    );
+    assert_eq!(
+        report,
+        ShutdownReport {
+            completed_jobs: 0,
+            cancelled_jobs: 4,
+            killed_jobs: 2,
+            terminated_workers: 1,
+        }
+    );
+
+    let results: Vec<_> = handles.into_iter().map(|handle| handle.recv()).collect();
+    let killed = results
+        .iter()
+        .filter(
+            |result| matches!(result, Ok(output) if output.status.signal() == Some(libc::SIGKILL)),
+        )
+        .count();
+    assert_eq!(killed, 2);
+    assert_eq!(pool.active_workers(), 0);
+}
+
+/// `bulk`が大量のジョブを投入した後に`interactive`が1つ投入する
+fn flood_pool(pool: &ProcessPool) -> linux_process_rs::JobHandle {
+    for _ in 0..10 {
+        pool.submit_as("bulk", ProcessBuilder::new("sleep").arg("0.2"));
+    }
+    pool.submit_as("interactive", ProcessBuilder::new("echo").arg("hello"))
+}
+
+#[cfg(unix)]
+#[test]
+fn test_pool_fair_queuing_prevents_starvation() {
+    let pool = ProcessPool::new("FairPool", 1).with_fair_queuing();
+    let interactive = flood_pool(&pool);
+
+    let pending = pool.pending_by_submitter();
+    assert!((9..=10).contains(&pending["bulk"]), "{:?}", pending);
+    assert_eq!(pending["interactive"], 1);
+
+    // 投入順なら2秒待たされるが、公平モードでは実行中のbulkのジョブの次に回ってくる
+    let output = interactive
+        .recv_timeout(Duration::from_secs(1))
+        .expect("interactive job was starved by the bulk submitter")
+        .expect("job failed");
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "hello");
+    assert!(!pool.pending_by_submitter().contains_key("interactive"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_pool_fifo_queuing_runs_jobs_in_submission_order() {
+    let pool = ProcessPool::new("FifoPool", 1);
+    let interactive = flood_pool(&pool);
+
+    // 投入順では先に投入されたbulkのジョブが全て終わるまで待たされる
+    std::thread::sleep(Duration::from_millis(600));
+    assert!(interactive.try_recv().is_none());
+    assert_eq!(pool.pending_by_submitter()["interactive"], 1);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_pool_drop_cancels_pending_jobs() {
+    use std::os::unix::process::ExitStatusExt;
+
+    let pool = ProcessPool::new("DroppedJobPool", 1);
+    let running = pool.submit(ProcessBuilder::new("sleep").arg("30"));
+    let queued = pool.submit(ProcessBuilder::new("echo").arg("never"));
+    let start = std::time::Instant::now();
+    while pool.running_jobs() == 0 {
+        assert!(
+            start.elapsed() < Duration::from_secs(5),
+            "job never started"
+        );
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    // スロットは1つだけなので、2つ目のジョブは待機したまま
+    std::thread::sleep(Duration::from_millis(100));
+    assert!(queued.try_recv().is_none());
+
+    let start = std::time::Instant::now();
+    drop(pool);
+    assert!(start.elapsed() < Duration::from_secs(5));
+
+    // 実行中のジョブはSIGKILLされ、待機中のジョブは取り消される
+    let output = running
+        .recv()
+        .expect("running job should report its status");
+    assert_eq!(output.status.signal(), Some(libc::SIGKILL));
+    assert!(matches!(queued.recv(), Err(ProcessError::InvalidInput(_))));
+}
+
+/// 子プロセス側: SIGPIPEをデフォルトに戻してから無視・復元を試す
+fn sigpipe_child() {
+    unsafe {
+        libc::signal(libc::SIGPIPE, libc::SIG_DFL);
+    }
+
+    let write_to_closed_pipe = || {
+        let mut fds = [0; 2];
+        assert_eq!(unsafe { libc::pipe(fds.as_mut_ptr()) }, 0);
+        unsafe { libc::close(fds[0]) };
+        let ret = unsafe { libc::write(fds[1], b"x".as_ptr() as *const libc::c_void, 1) };
+        let err = std::io::Error::last_os_error();
+        unsafe { libc::close(fds[1]) };
+        (ret, err)
+    };
+
+    SignalHandler::ignore(SignalType::Pipe).expect("Failed to ignore SIGPIPE");
+    let (ret, err) = write_to_closed_pipe();
+    assert_eq!(ret, -1);
+    assert_eq!(err.raw_os_error(), Some(libc::EPIPE));
+    println!("EPIPE_OK");
+
+    // 復元後はデフォルト動作（プロセス終了）に戻る
+    SignalHandler::restore(SignalType::Pipe).expect("Failed to restore SIGPIPE");
+    write_to_closed_pipe();
+    println!("STILL_ALIVE");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_ignore_sigpipe_returns_epipe() {
+    use std::os::unix::process::ExitStatusExt;
+
+    // シグナルの処分はプロセス全体に効くため、テストバイナリを子プロセスとして再実行する
+    if std::env::var_os("SIGPIPE_CHILD").is_some() {
+        sigpipe_child();
+        return;
+    }
+
+    let output = std::process::Command::new(std::env::current_exe().unwrap())
+        .args([
+            "--exact",
+            "test_ignore_sigpipe_returns_epipe",
+            "--nocapture",
+        ])
+        .env("SIGPIPE_CHILD", "1")
+        .output()
+        .expect("Failed to run child test");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(stdout.contains("EPIPE_OK"), "stdout: {}", stdout);
+    assert!(!stdout.contains("STILL_ALIVE"), "stdout: {}", stdout);
+    assert_eq!(output.status.signal(), Some(libc::SIGPIPE));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_run_with_rusage_reports_peak_memory() {
+    // ddは32MiBのバッファを確保して書き込むので、ピークRSSはそれ以上になる
+    let builder = ProcessBuilder::new("dd")
+        .arg("if=/dev/zero")
+        .arg("of=/dev/null")
+        .arg("bs=32M")
+        .arg("count=1")
+        .stderr(std::process::Stdio::null());
+
+    let (status, usage) = run_with_rusage(builder).expect("Failed to run dd");
+
+    assert!(status.success());
+    assert!(
+        usage.max_rss_kb >= 32 * 1024,
+        "max RSS: {} KiB",
+        usage.max_rss_kb
+    );
+    assert!(
+        usage.max_rss_kb < 1024 * 1024,
+        "max RSS: {} KiB",
+        usage.max_rss_kb
+    );
+}
+
+/// 子テストプロセスとして実行される側：デタッチして起動し、PIDを出力してすぐ終了する
+#[cfg(unix)]
+fn detached_spawner_child() {
+    let pid = ProcessBuilder::new("sleep")
+        .arg("30")
+        .spawn_detached()
+        .expect("Failed to spawn detached process");
+    println!("DETACHED_PID={}", pid);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_detached_process_survives_parent_exit() {
+    if std::env::var_os("DETACHED_CHILD").is_some() {
+        detached_spawner_child();
+        return;
+    }
+
+    // 起動した側のプロセスが終了した後も、デタッチされた子が生き残ることを確認する
+    let output = std::process::Command::new(std::env::current_exe().unwrap())
+        .args([
+            "--exact",
+            "test_detached_process_survives_parent_exit",
+            "--nocapture",
+        ])
+        .env("DETACHED_CHILD", "1")
+        .output()
+        .expect("Failed to run child test");
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let pid: libc::pid_t = stdout
+        .lines()
+        .find_map(|line| line.split_once("DETACHED_PID="))
+        .and_then(|(_, pid)| pid.trim().parse().ok())
+        .unwrap_or_else(|| panic!("no PID in child output: {}", stdout));
+
+    let alive = unsafe { libc::kill(pid, 0) } == 0;
+    // 新しいセッションのリーダーになっている
+    let sid = unsafe { libc::getsid(pid) };
+    let our_sid = unsafe { libc::getsid(0) };
+    unsafe { libc::kill(pid, libc::SIGKILL) };
+
+    assert!(alive, "detached process {} died with its parent", pid);
+    assert_eq!(sid, pid);
+    assert_ne!(sid, our_sid);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_pool_state_file_readopts_survivors_after_restart() {
+    let dir = tempfile::tempdir().unwrap();
+    let state_file = dir.path().join("pool.state");
+
+    let pool = ProcessPool::new("PersistentPool", 4)
+        .with_state_file(&state_file)
+        .expect("Failed to open state file");
+    let survivor = pool
+        .spawn_process(ProcessBuilder::new("sleep").arg("30").label("survivor"))
+        .expect("Failed to spawn survivor");
+    let short_lived = pool
+        .spawn_process(ProcessBuilder::new("sleep").arg("0.1").label("short-lived"))
+        .expect("Failed to spawn short-lived worker");
+
+    // クラッシュを模擬：Dropを走らせずにプールを失う
+    std::mem::forget(pool);
+    std::thread::sleep(Duration::from_millis(300));
+
+    let restarted = ProcessPool::new("PersistentPool", 4)
+        .with_state_file(&state_file)
+        .expect("Failed to reload state file");
+
+    let workers = restarted.list_workers();
+    assert_eq!(workers.len(), 1);
+    assert_eq!(workers[0].pid, survivor);
+    assert_eq!(workers[0].label.as_deref(), Some("survivor"));
+    assert_eq!(workers[0].command, "sleep 30");
+    assert!(workers[0].adopted);
+    assert_eq!(restarted.used_slots(), 1);
+
+    // 終了していたワーカーは回収され、記録からも外れている
+    let reaped =
+        unsafe { libc::waitpid(short_lived.as_raw(), std::ptr::null_mut(), libc::WNOHANG) };
+    assert_eq!(reaped, -1);
+    let state = std::fs::read_to_string(&state_file).unwrap();
+    assert!(state.contains(&survivor.to_string()));
+    assert!(!state.contains("short-lived"));
+
+    // 引き継いだワーカーもプールの破棄時に終了される
+    drop(restarted);
+    assert!(unsafe { libc::kill(survivor.as_raw(), 0) } != 0);
+    assert!(std::fs::read_to_string(&state_file).unwrap().is_empty());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_signal_handler_counts_deliveries() {
+    let handler = SignalHandler::new(&[SignalType::User1, SignalType::User2])
+        .expect("Failed to create signal handler");
+
+    for expected in 1..=5 {
+        send_signal(std::process::id(), SignalType::User1).expect("Failed to send SIGUSR1");
+        // 保留中の同じシグナルはまとめられるため、1回ずつ配送を待つ
+        let start = std::time::Instant::now();
+        while handler.count(SignalType::User1) < expected {
+            assert!(
+                start.elapsed() < Duration::from_secs(5),
+                "SIGUSR1 #{} was not counted",
+                expected
+            );
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    assert_eq!(handler.count(SignalType::User1), 5);
+    assert_eq!(handler.count(SignalType::Terminate), 0);
+    assert_eq!(
+        handler.counts(),
+        vec![(SignalType::User1, 5), (SignalType::User2, 0)]
+    );
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_real_time_signal_sent_to_self_is_counted() {
+    let signal = SignalType::real_time(2).expect("SIGRTMIN+2 should exist");
+    let handler = SignalHandler::new(&[signal]).expect("Failed to create signal handler");
+
+    // 実時間シグナルはキューに積まれるので、続けて送ってもまとめられない
+    for _ in 0..3 {
+        send_signal(std::process::id(), signal).expect("Failed to send SIGRTMIN+2");
+    }
+    let start = std::time::Instant::now();
+    while handler.count(signal) < 3 {
+        assert!(
+            start.elapsed() < Duration::from_secs(5),
+            "SIGRTMIN+2 was not counted"
+        );
+        std::thread::sleep(Duration::from_millis(1));
+    }
+    assert_eq!(handler.counts(), vec![(signal, 3)]);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_real_time_signal_offset_out_of_range_is_error() {
+    let count = SignalType::real_time_count();
+    assert!(count > 0);
+    assert!(SignalType::real_time(count - 1).is_ok());
+
+    let out_of_range = SignalType::RealTime(count);
+    assert!(matches!(
+        SignalType::real_time(count),
+        Err(ProcessError::InvalidInput(_))
+    ));
+    assert!(matches!(
+        send_signal(std::process::id(), out_of_range),
+        Err(ProcessError::InvalidInput(_))
+    ));
+    assert!(matches!(
+        SignalHandler::new(&[out_of_range]),
+        Err(ProcessError::InvalidInput(_))
+    ));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_wait_any_reports_first_child_to_exit() {
+    use nix::sys::wait::WaitStatus;
+    use nix::unistd::Pid;
+    use std::process::Command;
+
+    let spawn = |secs: &str| Command::new("sleep").arg(secs).spawn().unwrap();
+    // 対象外の子が先に終了しても、そのステータスは消費されない
+    let mut unrelated = spawn("0");
+    let mut children = [spawn("0.6"), spawn("0.1"), spawn("0.3")];
+    let pids: Vec<Pid> = children
+        .iter()
+        .map(|c| Pid::from_raw(c.id() as i32))
+        .collect();
+
+    let (first, status) = wait_any(&pids).expect("wait_any failed");
+    assert_eq!(first, pids[1]);
+    assert_eq!(status, WaitStatus::Exited(pids[1], 0));
+    assert!(unrelated
+        .wait()
+        .expect("unrelated status was consumed")
+        .success());
+
+    let (second, _) = wait_any(&[pids[0], pids[2]]).expect("wait_any failed");
+    assert_eq!(second, pids[2]);
+
+    // 既に終了している（ゾンビの）子は即座に返る
+    // wait_anyが回収するのでChildは保持しない
+    let exited_pid = Pid::from_raw(Command::new("true").spawn().unwrap().id() as i32);
+    std::thread::sleep(Duration::from_millis(100));
+    let start = std::time::Instant::now();
+    let (pid, status) = wait_any(&[pids[0], exited_pid]).expect("wait_any failed");
+    assert_eq!(pid, exited_pid);
+    assert_eq!(status, WaitStatus::Exited(exited_pid, 0));
+    assert!(start.elapsed() < Duration::from_millis(300));
+
+    children[0].kill().unwrap();
+    assert!(children[0].wait().is_ok());
+}
+
+#[test]
+fn test_shared_counter_survives_fork() {
+    use nix::sys::wait::{waitpid, WaitStatus};
+    use nix::unistd::{fork, ForkResult};
+
+    const CHILDREN: u64 = 8;
+    const INCREMENTS: u64 = 1000;
+
+    let counter = SharedCounter::new().expect("Failed to map shared counter");
+    assert_eq!(counter.add(5), 5);
+
+    let mut pids = Vec::new();
+    for _ in 0..CHILDREN {
+        // 子ではアトミック操作と_exitだけを行う（fork後に安全な操作のみ）
+        match unsafe { fork() }.expect("fork failed") {
+            ForkResult::Child => {
+                for _ in 0..INCREMENTS {
+                    counter.inc();
+                }
+                unsafe { libc::_exit(0) };
+            }
+            ForkResult::Parent { child } => pids.push(child),
+        }
+    }
+
+    for pid in pids {
+        assert_eq!(waitpid(pid, None).unwrap(), WaitStatus::Exited(pid, 0));
+    }
+    assert_eq!(counter.get(), 5 + CHILDREN * INCREMENTS);
+}
+
+#[test]
+fn test_oom_score_adj_applies_to_child() {
+    let output = ProcessBuilder::new("cat")
+        .arg("/proc/self/oom_score_adj")
+        .oom_score_adj(500)
+        .output()
+        .expect("Failed to execute process");
+
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "500");
+
+    // 親プロセスの値は変わらない
+    let own = std::fs::read_to_string("/proc/self/oom_score_adj").unwrap();
+    assert_ne!(own.trim(), "500");
+}
+
+#[test]
+fn test_oom_score_adj_out_of_range_is_rejected() {
+    for score in [-1001, 1001] {
+        let err = ProcessBuilder::new("true")
+            .oom_score_adj(score)
+            .spawn()
+            .err()
+            .expect("out-of-range oom_score_adj should be rejected");
+        assert!(matches!(err, ProcessError::InvalidInput(_)), "{:?}", err);
+    }
+}
+
+#[test]
+fn test_rlimit_as_caps_child_allocation() {
+    if std::env::var_os("RLIMIT_AS_CHILD").is_some() {
+        // 制限下では1GiBのアドレス空間を確保できない
+        let mut buf: Vec<u8> = Vec::new();
+        match buf.try_reserve_exact(1024 * 1024 * 1024) {
+            Ok(()) => println!("ALLOCATION=succeeded"),
+            Err(_) => println!("ALLOCATION=failed"),
+        }
+        return;
+    }
+
+    let limit = 512 * 1024 * 1024;
+    let exe = std::env::current_exe().unwrap();
+    let output = ProcessBuilder::new(exe.to_str().unwrap())
+        .arg("--exact")
+        .arg("test_rlimit_as_caps_child_allocation")
+        .arg("--nocapture")
+        .env("RLIMIT_AS_CHILD", "1")
+        .rlimit(Rlimit::AddressSpace, limit, limit)
+        .output()
+        .expect("Failed to run child test");
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("ALLOCATION=failed"),
+        "child output: {}",
+        stdout
+    );
+
+    // 親プロセスの制限は変わらない
+    let (soft, _) = nix::sys::resource::getrlimit(nix::sys::resource::Resource::RLIMIT_AS).unwrap();
+    assert_ne!(soft, limit);
+}
+
+#[test]
+fn test_rlimit_soft_above_hard_is_rejected() {
+    let err = ProcessBuilder::new("true")
+        .rlimit(Rlimit::NoFile, 64, 32)
+        .spawn()
+        .err()
+        .expect("soft limit above hard limit should be rejected");
+    assert!(
+        matches!(err, ProcessError::ResourceLimitError { .. }),
+        "{:?}",
+        err
+    );
+}
+
+/// Field of `/proc/<pid>/stat`, numbered as in proc(5) (1 is the PID)
+fn proc_stat_field(pid: u32, field: usize) -> Option<i64> {
+    let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+    // 2番目のフィールド(comm)は空白を含みうるので、閉じ括弧の後から数える
+    stat[stat.rfind(')')? + 1..]
+        .split_whitespace()
+        .nth(field - 3)?
+        .parse()
+        .ok()
+}
+
+#[test]
+fn test_nice_applies_to_child() {
+    let guard = ProcessBuilder::new("sleep")
+        .arg("5")
+        .nice(10)
+        .spawn()
+        .expect("Failed to spawn niced process");
+
+    // proc(5): 19番目がnice値
+    assert_eq!(proc_stat_field(guard.pid().unwrap(), 19), Some(10));
+}
+
+#[test]
+fn test_sched_policy_applies_to_child() {
+    let guard = ProcessBuilder::new("sleep")
+        .arg("5")
+        .sched_policy(SchedPolicy::Batch)
+        .nice(5)
+        .spawn()
+        .expect("Failed to spawn batch process");
+
+    // proc(5): 41番目がスケジューリングポリシー
+    let pid = guard.pid().unwrap();
+    assert_eq!(proc_stat_field(pid, 41), Some(libc::SCHED_BATCH as i64));
+    assert_eq!(proc_stat_field(pid, 19), Some(5));
+}
+
+#[test]
+fn test_invalid_scheduling_values_are_rejected() {
+    for builder in [
+        ProcessBuilder::new("true").nice(-21),
+        ProcessBuilder::new("true").nice(20),
+        ProcessBuilder::new("true").sched_policy(SchedPolicy::Fifo(0)),
+        ProcessBuilder::new("true").sched_policy(SchedPolicy::RoundRobin(100)),
+    ] {
+        let err = builder
+            .spawn()
+            .err()
+            .expect("invalid scheduling values should be rejected");
+        assert!(matches!(err, ProcessError::InvalidInput(_)), "{:?}", err);
+    }
+}
+
+#[test]
+fn test_raising_priority_requires_privilege() {
+    // CAP_SYS_NICEがあれば成功し、なければ権限エラーになる
+    match ProcessBuilder::new("sleep").arg("5").nice(-5).spawn() {
+        Ok(guard) => assert_eq!(proc_stat_field(guard.pid().unwrap(), 19), Some(-5)),
+        Err(err) => assert!(
+            matches!(err, ProcessError::PermissionDenied { ref context } if context.contains("CAP_SYS_NICE")),
+            "{:?}",
+            err
+        ),
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn test_pool_signal_by_label_reaches_only_matching_workers() {
+    let pool = ProcessPool::new("LabelSignalPool", 4);
+    let spawn = |label: &str| {
+        pool.spawn_process(ProcessBuilder::new("sleep").arg("10").label(label))
+            .expect("Failed to spawn worker")
+    };
+    let mut web = vec![spawn("web"), spawn("web")];
+    web.sort();
+    let worker = spawn("worker");
+    let unlabeled = pool
+        .spawn_worker_with_args("sleep", &["10"])
+        .expect("Failed to spawn worker");
+
+    let handles: Vec<_> = pool.handles_by_label("web").iter().map(|w| w.pid).collect();
+    assert_eq!(handles, web);
+
+    assert_eq!(pool.signal_by_label("web", SignalType::Terminate), web);
+    assert!(pool
+        .signal_by_label("missing", SignalType::Terminate)
+        .is_empty());
+
+    // webワーカーだけが終了し（親が回収するまではゾンビ）、他のワーカーは動き続ける
+    let exited =
+        |pid: nix::unistd::Pid| match std::fs::read_to_string(format!("/proc/{}/stat", pid)) {
+            Ok(stat) => {
+                stat.rsplit(')')
+                    .next()
+                    .and_then(|rest| rest.split_whitespace().next())
+                    == Some("Z")
+            }
+            Err(_) => true,
+        };
+    let deadline = std::time::Instant::now() + Duration::from_secs(5);
+    while !web.iter().all(|&pid| exited(pid)) && std::time::Instant::now() < deadline {
+        std::thread::sleep(Duration::from_millis(20));
+    }
+    assert!(web.iter().all(|&pid| exited(pid)));
+    assert!(!exited(worker));
+    assert!(!exited(unlabeled));
+
+    pool.terminate_all().expect("Failed to terminate workers");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_chained_signal_handlers_all_run() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static FOREIGN: AtomicUsize = AtomicUsize::new(0);
+    static FIRST: AtomicUsize = AtomicUsize::new(0);
+    static SECOND: AtomicUsize = AtomicUsize::new(0);
+    static IGNORED: AtomicUsize = AtomicUsize::new(0);
+
+    extern "C" fn foreign_handler(_: libc::c_int) {
+        FOREIGN.fetch_add(1, Ordering::SeqCst);
+    }
+
+    if std::env::var_os("CHAIN_SIGNAL_CHILD").is_some() {
+        // 別のライブラリが先にハンドラを入れていた状況を再現する
+        unsafe {
+            libc::signal(
+                libc::SIGUSR2,
+                foreign_handler as extern "C" fn(libc::c_int) as libc::sighandler_t,
+            )
+        };
+        SignalHandler::chain(SignalType::User2, || {
+            FIRST.fetch_add(1, Ordering::SeqCst);
+        })
+        .unwrap();
+        SignalHandler::chain(SignalType::User2, || {
+            SECOND.fetch_add(1, Ordering::SeqCst);
+        })
+        .unwrap();
+
+        // 以前の処置がSIG_IGN・SIG_DFLでも、コールバックだけが実行されプロセスは終了しない
+        unsafe { libc::signal(libc::SIGHUP, libc::SIG_IGN) };
+        SignalHandler::chain(SignalType::Hangup, || {
+            IGNORED.fetch_add(1, Ordering::SeqCst);
+        })
+        .unwrap();
+        SignalHandler::chain(SignalType::User1, || {}).unwrap();
+
+        unsafe {
+            libc::raise(libc::SIGUSR2);
+            libc::raise(libc::SIGHUP);
+            libc::raise(libc::SIGUSR1);
+        }
+        println!(
+            "FOREIGN={} FIRST={} SECOND={} IGNORED={}",
+            FOREIGN.load(Ordering::SeqCst),
+            FIRST.load(Ordering::SeqCst),
+            SECOND.load(Ordering::SeqCst),
+            IGNORED.load(Ordering::SeqCst)
+        );
+        return;
+    }
+
+    // 登録したハンドラはプロセスが終わるまで残るため、子プロセスで試す
+    let exe = std::env::current_exe().unwrap();
+    let output = ProcessBuilder::new(exe.to_str().unwrap())
+        .arg("--exact")
+        .arg("test_chained_signal_handlers_all_run")
+        .arg("--nocapture")
+        .env("CHAIN_SIGNAL_CHILD", "1")
+        .output()
+        .expect("Failed to run child test");
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "child output: {}", stdout);
+    assert!(
+        stdout.contains("FOREIGN=1 FIRST=1 SECOND=1 IGNORED=1"),
+        "child output: {}",
+        stdout
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn test_closure_handlers_run_until_removed() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    if std::env::var_os("CLOSURE_SIGNAL_CHILD").is_some() {
+        let counter = Arc::new(AtomicUsize::new(0));
+        let counted = counter.clone();
+        let id = SignalHandler::on(SignalType::User2, move || {
+            counted.fetch_add(1, Ordering::SeqCst);
+        })
+        .unwrap();
+        assert!(SignalHandler::on(SignalType::Kill, || {}).is_err());
+
+        send_signal(std::process::id(), SignalType::User2).unwrap();
+        let start = std::time::Instant::now();
+        while counter.load(Ordering::SeqCst) < 1 {
+            assert!(
+                start.elapsed() < Duration::from_secs(5),
+                "handler did not run"
+            );
+            std::thread::sleep(Duration::from_millis(1));
+        }
+
+        // 削除後のシグナルは何もせず捨てられ、プロセスも終了しない
+        assert!(SignalHandler::remove(id));
+        assert!(!SignalHandler::remove(id));
+        send_signal(std::process::id(), SignalType::User2).unwrap();
+        std::thread::sleep(Duration::from_millis(200));
+        println!("COUNT={}", counter.load(Ordering::SeqCst));
+        return;
+    }
+
+    // 登録したシグナルの処置はプロセスが終わるまで残るため、子プロセスで試す
+    let exe = std::env::current_exe().unwrap();
+    let output = ProcessBuilder::new(exe.to_str().unwrap())
+        .arg("--exact")
+        .arg("test_closure_handlers_run_until_removed")
+        .arg("--nocapture")
+        .env("CLOSURE_SIGNAL_CHILD", "1")
+        .output()
+        .expect("Failed to run child test");
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "child output: {}", stdout);
+    assert!(stdout.contains("COUNT=1"), "child output: {}", stdout);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_restore_default_action() {
+    use std::os::unix::process::ExitStatusExt;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    if std::env::var_os("RESET_SIGNAL_CHILD").is_some() {
+        // リセット後に登録し直したハンドラも動く
+        let handler = SignalHandler::new(&[SignalType::User2]).unwrap();
+        SignalHandler::restore_default(SignalType::User2).unwrap();
+        let counter = Arc::new(AtomicUsize::new(0));
+        let counted = counter.clone();
+        SignalHandler::on(SignalType::User2, move || {
+            counted.fetch_add(1, Ordering::SeqCst);
+        })
+        .unwrap();
+        send_signal(std::process::id(), SignalType::User2).unwrap();
+        let start = std::time::Instant::now();
+        while counter.load(Ordering::SeqCst) < 1 {
+            assert!(
+                start.elapsed() < Duration::from_secs(5),
+                "handler registered after reset did not run"
+            );
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        drop(handler);
+        println!("REARMED");
+
+        SignalHandler::on(SignalType::User1, || {}).unwrap();
+        SignalHandler::chain(SignalType::User1, || {}).unwrap();
+        linux_process_rs::signal::block(&[SignalType::User1]).unwrap();
+        assert!(SignalHandler::restore_default(SignalType::Kill).is_err());
+        SignalHandler::restore_all_defaults().unwrap();
+
+        // ブロックも外れているので、既定の動作でプロセスが終了する
+        println!("RESET");
+        send_signal(std::process::id(), SignalType::User1).unwrap();
+        std::thread::sleep(Duration::from_secs(5));
+        println!("SURVIVED");
+        return;
+    }
+
+    let exe = std::env::current_exe().unwrap();
+    let output = ProcessBuilder::new(exe.to_str().unwrap())
+        .arg("--exact")
+        .arg("test_restore_default_action")
+        .arg("--nocapture")
+        .env("RESET_SIGNAL_CHILD", "1")
+        .output()
+        .expect("Failed to run child test");
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("REARMED"), "child output: {}", stdout);
+    assert!(stdout.contains("RESET"), "child output: {}", stdout);
+    assert!(!stdout.contains("SURVIVED"), "child output: {}", stdout);
+    assert_eq!(output.status.signal(), Some(libc::SIGUSR1));
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_find_by_name_until_killed() {
+    use linux_process_rs::utils::{find_by_name, find_by_name_with, NameMatch};
+
+    // sleepへのシンボリックリンクで起動すると、commがリンク名になる
+    let dir = tempfile::tempdir().unwrap();
+    let name = format!("lpfind{}", std::process::id());
+    let link = dir.path().join(&name);
+    std::os::unix::fs::symlink("/bin/sleep", &link).unwrap();
+    let mut child = std::process::Command::new(&link)
+        .arg("30")
+        .spawn()
+        .expect("Failed to spawn sleep");
+
+    // spawnはexecの途中（commの設定後、引数の配置前）で戻ることがあるので、
+    // cmdlineが読めるようになるまで待つ
+    let start = std::time::Instant::now();
+    let found = loop {
+        let found = find_by_name(&name).unwrap();
+        if found.iter().all(|info| !info.cmdline.is_empty()) {
+            break found;
+        }
+        assert!(
+            start.elapsed() < Duration::from_secs(5),
+            "found: {:?}",
+            found
+        );
+        std::thread::sleep(Duration::from_millis(1));
+    };
+    assert_eq!(found.len(), 1, "found: {:?}", found);
+    assert_eq!(found[0].pid, child.id());
+    assert_eq!(found[0].comm, name);
+    assert_eq!(
+        found[0].cmdline,
+        vec![link.to_str().unwrap().to_string(), "30".to_string()]
+    );
+    let by_cmdline =
+        find_by_name_with(&format!("{} 30", name), NameMatch::CmdlineContains).unwrap();
+    assert_eq!(by_cmdline, found);
+
+    child.kill().unwrap();
+    child.wait().unwrap();
+    assert!(find_by_name(&name).unwrap().is_empty());
+    assert!(find_by_name_with(&name, NameMatch::CmdlineContains)
+        .unwrap()
+        .is_empty());
+    assert!(matches!(
+        find_by_name(""),
+        Err(ProcessError::InvalidInput(_))
+    ));
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_process_tree_captures_grandchild() {
+    use linux_process_rs::utils::process_tree;
+    use std::io::BufRead;
+
+    // sh -> sleep の親子を作り、孫のPIDを標準出力で受け取る
+    let mut child = std::process::Command::new("sh")
+        .arg("-c")
+        .arg("sleep 30 & echo $!; wait")
+        .stdout(std::process::Stdio::piped())
+        .spawn()
+        .expect("Failed to spawn sh");
+    let mut line = String::new();
+    std::io::BufReader::new(child.stdout.take().unwrap())
+        .read_line(&mut line)
+        .unwrap();
+    let grandchild: u32 = line.trim().parse().unwrap();
+    let me = std::process::id();
+
+    let tree = process_tree().unwrap();
+    assert!(tree.contains(me));
+    assert_eq!(tree.parent(child.id()), Some(me));
+    assert_eq!(tree.parent(grandchild), Some(child.id()));
+    assert_eq!(tree.children(child.id()), &[grandchild]);
+    let descendants: Vec<u32> = tree.descendants(me).collect();
+    let position = |pid| descendants.iter().position(|&p| p == pid);
+    assert!(position(child.id()).unwrap() < position(grandchild).unwrap());
+    assert!(tree.children(0).contains(&1));
+
+    // 親を辿ると孫・子・テスト自身の順に並び、根（親のないプロセス）に着く
+    let mut chain = vec![grandchild];
+    while let Some(parent) = tree.parent(*chain.last().unwrap()) {
+        chain.push(parent);
+    }
+    assert_eq!(chain[..3], [grandchild, child.id(), me]);
+    assert!(tree.children(0).contains(chain.last().unwrap()));
+
+    unsafe { libc::kill(grandchild as i32, libc::SIGKILL) };
+    child.wait().unwrap();
+    assert!(!process_tree().unwrap().contains(grandchild));
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_zombies_reports_unwaited_child() {
+    use linux_process_rs::utils::zombies;
+
+    // waitしないまま子を終了させ、ゾンビにする
+    let mut child = std::process::Command::new("true")
+        .spawn()
+        .expect("Failed to spawn true");
+    let pid = child.id();
+    let me = std::process::id();
+
+    let start = std::time::Instant::now();
+    let zombie = loop {
+        if let Some(zombie) = zombies().unwrap().into_iter().find(|z| z.pid == pid) {
+            break zombie;
+        }
+        assert!(
+            start.elapsed() < Duration::from_secs(5),
+            "child did not become a zombie"
+        );
+        std::thread::sleep(Duration::from_millis(1));
+    };
+    assert_eq!(zombie.ppid, me);
+    assert_eq!(zombie.comm, "true");
+
+    assert!(child.wait().unwrap().success());
+    assert!(!zombies().unwrap().iter().any(|z| z.pid == pid));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_auto_reap_leaves_no_zombies() {
+    use std::collections::BTreeMap;
+    use std::sync::{Arc, Mutex};
+
+    if std::env::var_os("AUTO_REAP_CHILD").is_some() {
+        let reaped = Arc::new(Mutex::new(BTreeMap::new()));
+        let recorder = reaped.clone();
+        SignalHandler::auto_reap_with(move |pid, status| {
+            recorder.lock().unwrap().insert(pid, status.code());
+        })
+        .unwrap();
+        assert!(SignalHandler::auto_reap().is_err());
+
+        // ほぼ同時に終了させ、まとめて届いたSIGCHLDでも全員を回収できることを確かめる
+        let mut expected = BTreeMap::new();
+        for code in 0..8 {
+            // 回収は自動で行われるので、ここではwaitしない
+            #[allow(clippy::zombie_processes)]
+            let child = std::process::Command::new("sh")
+                .arg("-c")
+                .arg(format!("sleep 0.2; exit {}", code))
+                .spawn()
+                .unwrap();
+            expected.insert(child.id(), Some(code));
+        }
+
+        let start = std::time::Instant::now();
+        while reaped.lock().unwrap().len() < expected.len() {
+            assert!(
+                start.elapsed() < Duration::from_secs(5),
+                "reaped only {:?}",
+                reaped.lock().unwrap()
+            );
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(*reaped.lock().unwrap(), expected);
+
+        let zombies = expected
+            .keys()
+            .filter(|pid| {
+                std::fs::read_to_string(format!("/proc/{}/stat", pid)).is_ok_and(|stat| {
+                    stat.rsplit(')')
+                        .next()
+                        .unwrap()
+                        .trim_start()
+                        .starts_with('Z')
+                })
+            })
+            .count();
+        println!("ZOMBIES={}", zombies);
+        return;
+    }
+
+    // 回収スレッドは他のテストの子まで回収してしまうため、子プロセスで試す
+    let exe = std::env::current_exe().unwrap();
+    let output = ProcessBuilder::new(exe.to_str().unwrap())
+        .arg("--exact")
+        .arg("test_auto_reap_leaves_no_zombies")
+        .arg("--nocapture")
+        .env("AUTO_REAP_CHILD", "1")
+        .output()
+        .expect("Failed to run child test");
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        output.status.success(),
+        "child output: {}{}",
+        stdout,
+        stderr
+    );
+    assert!(stdout.contains("ZOMBIES=0"), "child output: {}", stdout);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_signalfd_reads_raised_signal() {
+    use std::os::unix::io::AsRawFd;
+
+    // テストスレッドだけでブロックし、raiseでこのスレッド宛てに送る
+    let mut fd = SignalHandler::signalfd(&[SignalType::User1, SignalType::Hangup])
+        .expect("Failed to create signalfd");
+    assert!(fd.as_raw_fd() >= 0);
+    assert!(SignalHandler::signalfd(&[SignalType::Kill]).is_err());
+
+    let mut poll = libc::pollfd {
+        fd: fd.as_raw_fd(),
+        events: libc::POLLIN,
+        revents: 0,
+    };
+    assert_eq!(unsafe { libc::poll(&mut poll, 1, 0) }, 0);
+
+    unsafe { libc::raise(libc::SIGUSR1) };
+    assert_eq!(unsafe { libc::poll(&mut poll, 1, 5000) }, 1);
+    assert_eq!(fd.read().unwrap(), SignalType::User1);
+
+    unsafe { libc::raise(libc::SIGHUP) };
+    assert_eq!(fd.read().unwrap(), SignalType::Hangup);
+}
+
+#[test]
+fn test_cgroup_missing_directory_is_rejected() {
+    let err = ProcessBuilder::new("true")
+        .cgroup("/sys/fs/cgroup/linux-process-rs-does-not-exist")
+        .spawn()
+        .err()
+        .expect("missing cgroup should be rejected");
+    assert!(matches!(err, ProcessError::CgroupError { .. }), "{:?}", err);
+}
+
+#[test]
+fn test_cgroup_places_child_in_cgroup() {
+    // cgroup v2のマウント先（ハイブリッド構成では/sys/fs/cgroup/unifiedなど）
+    let mounts = std::fs::read_to_string("/proc/self/mounts").unwrap();
+    let Some(root) = mounts
+        .lines()
+        .map(|line| line.split_whitespace().collect::<Vec<_>>())
+        .find(|fields| fields.len() > 2 && fields[2] == "cgroup2")
+        .map(|fields| std::path::PathBuf::from(fields[1]))
+    else {
+        eprintln!("cgroup v2 is not mounted, skipping");
+        return;
+    };
+
+    let cgroup = root.join(format!("linux-process-rs-test-{}", std::process::id()));
+    if let Err(e) = std::fs::create_dir(&cgroup) {
+        eprintln!("cannot create {} ({}), skipping", cgroup.display(), e);
+        return;
+    }
+
+    let mut guard = ProcessBuilder::new("sleep")
+        .arg("10")
+        .cgroup(&cgroup)
+        .spawn()
+        .expect("Failed to spawn into cgroup");
+    let pid = guard.pid().unwrap();
+    let procs = std::fs::read_to_string(cgroup.join("cgroup.procs")).unwrap();
+
+    send_signal(pid, SignalType::Terminate).unwrap();
+    let _ = guard.wait();
+    let _ = std::fs::remove_dir(&cgroup);
+
+    assert!(
+        procs.lines().any(|line| line == pid.to_string()),
+        "PID {} not in cgroup.procs: {:?}",
+        pid,
+        procs
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn test_output_tail_keeps_last_lines_of_failed_child() {
+    if std::env::var_os("OUTPUT_TAIL_CHILD").is_some() {
+        for i in 1..=1000 {
+            println!("line {}", i);
+        }
+        eprintln!("fatal: giving up");
+        std::process::exit(3);
+    }
+
+    let exe = std::env::current_exe().unwrap();
+    let builder = ProcessBuilder::new(exe.to_str().unwrap())
+        .arg("--exact")
+        .arg("test_output_tail_keeps_last_lines_of_failed_child")
+        .arg("--nocapture")
+        .arg("--test-threads=1")
+        .env("OUTPUT_TAIL_CHILD", "1");
+    let (status, tail) = run_with_output_tail(builder, 5).expect("Failed to run child");
+
+    assert_eq!(status.code(), Some(3));
+    // stdoutとstderrは書かれた順に並ぶ
+    assert_eq!(
+        tail,
+        [
+            "line 997",
+            "line 998",
+            "line 999",
+            "line 1000",
+            "fatal: giving up"
+        ]
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn test_output_tail_is_empty_on_success() {
+    let (status, tail) = run_with_output_tail(ProcessBuilder::new("seq").arg("1").arg("1000"), 5)
+        .expect("Failed to run seq");
+
+    assert!(status.success());
+    assert!(tail.is_empty());
+}
+
+fn both_streams_child(merge: bool) -> linux_process_rs::CapturedOutput {
+    let exe = std::env::current_exe().unwrap();
+    ProcessBuilder::new(exe.to_str().unwrap())
+        .arg("--exact")
+        .arg("test_output_string_captures_both_streams")
+        .arg("--nocapture")
+        .arg("--quiet")
+        .env("BOTH_STREAMS_CHILD", "1")
+        .merge_stderr(merge)
+        .output_string()
+        .expect("Failed to run child")
+}
+
+#[test]
+fn test_output_string_captures_both_streams() {
+    if std::env::var_os("BOTH_STREAMS_CHILD").is_some() {
+        use std::io::Write;
+
+        println!("out 1");
+        eprintln!("err 1");
+        std::io::stdout().write_all(b"bad \xff byte\n").unwrap();
+        eprintln!("err 2");
+        std::process::exit(4);
+    }
+
+    let separate = both_streams_child(false);
+    assert_eq!(separate.status.code(), Some(4));
+    assert!(
+        separate.stdout.contains("out 1\nbad \u{FFFD} byte\n"),
+        "{:?}",
+        separate.stdout
+    );
+    assert!(
+        separate.stderr.contains("err 1\nerr 2\n"),
+        "{:?}",
+        separate.stderr
+    );
+
+    let merged = both_streams_child(true);
+    assert_eq!(merged.status.code(), Some(4));
+    assert!(
+        merged
+            .stdout
+            .contains("out 1\nerr 1\nbad \u{FFFD} byte\nerr 2\n"),
+        "{:?}",
+        merged.stdout
+    );
+    assert!(merged.stderr.is_empty());
+}
+
+#[test]
+fn test_timeout_kills_and_reaps_child() {
+    let start = std::time::Instant::now();
+    let result = ProcessBuilder::new("sleep")
+        .arg("10")
+        .timeout(Duration::from_millis(200))
+        .spawn_and_wait();
+
+    match result {
+        Err(ProcessError::Timeout { elapsed }) => {
+            assert!(elapsed >= Duration::from_millis(200), "{:?}", elapsed);
+        }
+        other => panic!("Expected timeout error, got {:?}", other),
+    }
+    // sleepはSIGTERMで終了するので猶予期間を待たない
+    assert!(start.elapsed() < Duration::from_secs(2));
+
+    // 同じ経路でPIDを取り、待機後にゾンビが残っていないことを確かめる
+    let mut guard = ProcessBuilder::new("sleep")
+        .arg("10")
+        .timeout(Duration::from_millis(200))
+        .spawn()
+        .expect("Failed to spawn sleep");
+    let pid = guard.pid().unwrap();
+    assert!(matches!(guard.wait(), Err(ProcessError::Timeout { .. })));
+    assert!(!std::path::Path::new(&format!("/proc/{}", pid)).exists());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_timeout_escalates_to_sigkill_after_grace() {
+    if std::env::var_os("IGNORE_SIGTERM_CHILD").is_some() {
+        unsafe { libc::signal(libc::SIGTERM, libc::SIG_IGN) };
+        std::thread::sleep(Duration::from_secs(10));
+        return;
+    }
+
+    let exe = std::env::current_exe().unwrap();
+    let start = std::time::Instant::now();
+    let result = ProcessBuilder::new(exe.to_str().unwrap())
+        .arg("--exact")
+        .arg("test_timeout_escalates_to_sigkill_after_grace")
+        .arg("--quiet")
+        .env("IGNORE_SIGTERM_CHILD", "1")
+        .timeout(Duration::from_millis(300))
+        .kill_grace(Duration::from_millis(200))
+        .spawn_and_wait();
+
+    assert!(matches!(result, Err(ProcessError::Timeout { .. })));
+    let waited = start.elapsed();
+    assert!(waited >= Duration::from_millis(500), "{:?}", waited);
+    assert!(waited < Duration::from_secs(5), "{:?}", waited);
+}
+
+fn id_of(builder: ProcessBuilder) -> String {
+    let output = builder.output().expect("Failed to run id");
+    assert!(output.status.success(), "{:?}", output);
+    String::from_utf8_lossy(&output.stdout).trim().to_string()
+}
+
+#[cfg(unix)]
+#[test]
+fn test_child_drops_to_unprivileged_user() {
+    if !nix::unistd::Uid::effective().is_root() {
+        eprintln!("skipping: requires root");
+        return;
+    }
+
+    let nobody = |flag: &str| {
+        ProcessBuilder::new("id")
+            .arg(flag)
+            .uid(65534)
+            .gid(65534)
+            .supplementary_groups(&[65533])
+    };
+    assert_eq!(id_of(nobody("-u")), "65534");
+    assert_eq!(id_of(nobody("-g")), "65534");
+    let mut groups: Vec<String> = id_of(nobody("-G"))
+        .split_whitespace()
+        .map(String::from)
+        .collect();
+    groups.sort();
+    assert_eq!(groups, ["65533", "65534"]);
+
+    // ユーザーだけ指定しても root の補助グループは引き継がない
+    let groups = id_of(ProcessBuilder::new("id").arg("-G").uid(65534).gid(65534));
+    assert_eq!(groups, "65534");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_failed_privilege_drop_aborts_exec() {
+    let dir = tempfile::tempdir().unwrap();
+    let marker = dir.path().join("ran");
+
+    // rootならグループ数の上限超過、それ以外ならsetgroupsの権限不足で失敗させる
+    let builder = ProcessBuilder::new("touch").arg(marker.to_str().unwrap());
+    let builder = if nix::unistd::Uid::effective().is_root() {
+        let groups: Vec<u32> = (1..=70_000).collect();
+        builder.uid(65534).supplementary_groups(&groups)
+    } else {
+        builder.uid(nix::unistd::Uid::current().as_raw())
+    };
+
+    assert!(builder.spawn_and_wait().is_err());
+    assert!(!marker.exists());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_new_uts_namespace_allows_setting_hostname() {
+    let hostname = || std::fs::read_to_string("/proc/sys/kernel/hostname").unwrap();
+    if std::env::var_os("UTS_NAMESPACE_CHILD").is_some() {
+        let name = b"isolated-child";
+        let ret = unsafe { libc::sethostname(name.as_ptr() as *const libc::c_char, name.len()) };
+        assert_eq!(ret, 0, "{}", std::io::Error::last_os_error());
+        println!("hostname={}", hostname().trim());
+        return;
+    }
+    if !nix::unistd::Uid::effective().is_root() {
+        eprintln!("skipping: requires root");
+        return;
+    }
+
+    let before = hostname();
+    let exe = std::env::current_exe().unwrap();
+    let output = ProcessBuilder::new(exe.to_str().unwrap())
+        .arg("--exact")
+        .arg("test_new_uts_namespace_allows_setting_hostname")
+        .arg("--nocapture")
+        .env("UTS_NAMESPACE_CHILD", "1")
+        .unshare(NamespaceFlags::UTS)
+        .output_string()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    assert!(
+        output.stdout.contains("hostname=isolated-child\n"),
+        "{}",
+        output.stdout
+    );
+
+    // 子の名前空間で変更しても親のホスト名は変わらない
+    assert_eq!(hostname(), before);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_child_is_pid_1_in_new_pid_namespace() {
+    if std::env::var_os("PID_NAMESPACE_CHILD").is_some() {
+        println!("pid={}", std::process::id());
+        return;
+    }
+    if !nix::unistd::Uid::effective().is_root() {
+        eprintln!("skipping: requires root");
+        return;
+    }
+
+    let exe = std::env::current_exe().unwrap();
+    let output = ProcessBuilder::new(exe.to_str().unwrap())
+        .arg("--exact")
+        .arg("test_child_is_pid_1_in_new_pid_namespace")
+        .arg("--nocapture")
+        .env("PID_NAMESPACE_CHILD", "1")
+        .unshare(NamespaceFlags::PID | NamespaceFlags::UTS)
+        .output_string()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    assert!(output.stdout.contains("pid=1\n"), "{}", output.stdout);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_pid_namespace_reports_how_the_command_exited() {
+    if !nix::unistd::Uid::effective().is_root() {
+        eprintln!("skipping: requires root");
+        return;
+    }
+
+    let status = ProcessBuilder::new("false")
+        .unshare(NamespaceFlags::PID)
+        .output()
+        .unwrap()
+        .status;
+    assert_eq!(status.code(), Some(1));
+
+    // PID 1はハンドラのないSIGTERMを無視するので、猶予後にSIGKILLで終わる
+    let start = std::time::Instant::now();
+    let result = ProcessBuilder::new("sleep")
+        .arg("10")
+        .unshare(NamespaceFlags::PID)
+        .timeout(Duration::from_millis(200))
+        .kill_grace(Duration::from_millis(200))
+        .spawn_and_wait();
+    assert!(matches!(result, Err(ProcessError::Timeout { .. })));
+    assert!(start.elapsed() < Duration::from_secs(5));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_unshare_without_privileges_is_a_namespace_error() {
+    if nix::unistd::Uid::effective().is_root() {
+        eprintln!("skipping: requires an unprivileged user");
+        return;
+    }
+
+    let result = ProcessBuilder::new("true")
+        .unshare(NamespaceFlags::NETWORK)
+        .spawn_and_wait();
+    assert!(
+        matches!(result, Err(ProcessError::NamespaceError { .. })),
+        "{:?}",
+        result
+    );
+}
+
+#[test]
+fn test_pipeline_passes_stdout_to_next_stage() {
+    let output = ProcessBuilder::new("echo")
+        .arg("hello")
+        .pipe(ProcessBuilder::new("tr").arg("a-z").arg("A-Z"))
+        .output()
+        .unwrap();
+
+    assert_eq!(output.stdout, b"HELLO\n");
+    assert_eq!(output.statuses.len(), 2);
+    assert!(output.success());
+}
+
+#[test]
+fn test_pipeline_streams_large_output_without_deadlock() {
+    // パイプのバッファ（64KiB）を大きく超える量を3段で流す
+    let output = Pipeline::new(ProcessBuilder::new("seq").arg("1").arg("200000"))
+        .pipe(ProcessBuilder::new("grep").arg("7"))
+        .pipe(ProcessBuilder::new("wc").arg("-l"))
+        .output()
+        .unwrap();
+
+    assert!(output.success());
+    let count: usize = String::from_utf8_lossy(&output.stdout)
+        .trim()
+        .parse()
+        .unwrap();
+    let expected = (1..=200_000)
+        .filter(|n: &u32| n.to_string().contains('7'))
+        .count();
+    assert_eq!(count, expected);
+}
+
+#[test]
+fn test_pipeline_reports_every_stage_status() {
+    let output = ProcessBuilder::new("false")
+        .pipe(ProcessBuilder::new("cat"))
+        .output()
+        .unwrap();
+
+    assert_eq!(output.statuses[0].code(), Some(1));
+    assert!(output.statuses[1].success());
+    assert!(!output.success());
+
+    let result = ProcessBuilder::new("echo")
+        .pipe(ProcessBuilder::new("/nonexistent/command"))
+        .output();
+    assert!(result.is_err());
+}
+
+/// `/proc/<pid>/stat`の状態文字（プロセスがなければ`None`）
+fn proc_state(pid: i32) -> Option<char> {
+    let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+    stat[stat.rfind(')')? + 1..].trim_start().chars().next()
+}
+
+#[cfg(unix)]
+#[test]
+fn test_pool_kills_tree_exceeding_descendant_limit() {
+    if let Some(path) = std::env::var_os("FORK_BOMB_CHILD") {
+        // 子を次々に増やし、起動した子のPIDを記録する
+        let mut children = Vec::new();
+        for _ in 0..50 {
+            let child = std::process::Command::new("sleep")
+                .arg("30")
+                .spawn()
+                .unwrap();
+            let mut file = std::fs::OpenOptions::new()
+                .append(true)
+                .create(true)
+                .open(&path)
+                .unwrap();
+            std::io::Write::write_all(&mut file, format!("{}\n", child.id()).as_bytes()).unwrap();
+            children.push(child);
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        std::thread::sleep(Duration::from_secs(30));
+        return;
+    }
+
+    let dir = tempfile::tempdir().unwrap();
+    let pids_file = dir.path().join("grandchildren");
+    let exe = std::env::current_exe().unwrap();
+    let pool = ProcessPool::new("ForkBombPool", 2).with_descendant_limit(5);
+    let worker = pool
+        .spawn_process(
+            ProcessBuilder::new(exe.to_str().unwrap())
+                .arg("--exact")
+                .arg("test_pool_kills_tree_exceeding_descendant_limit")
+                .env("FORK_BOMB_CHILD", pids_file.to_str().unwrap()),
+        )
+        .unwrap();
+
+    let start = std::time::Instant::now();
+    while pool.active_workers() > 0 {
+        assert!(
+            start.elapsed() < Duration::from_secs(5),
+            "worker with too many descendants was not killed"
+        );
+        std::thread::sleep(Duration::from_millis(50));
+    }
+
+    // 上限を少し超えたところで止められ、孫も残っていない（回収待ちのゾンビは可）
+    let grandchildren: Vec<i32> = std::fs::read_to_string(&pids_file)
+        .unwrap()
+        .lines()
+        .map(|line| line.parse().unwrap())
+        .collect();
+    assert!(grandchildren.len() > 5, "{:?}", grandchildren);
+    assert!(grandchildren.len() < 50, "{:?}", grandchildren);
+    std::thread::sleep(Duration::from_millis(100));
+    for pid in grandchildren.into_iter().chain([worker.as_raw()]) {
+        let state = proc_state(pid);
+        assert!(matches!(state, None | Some('Z')), "{} is {:?}", pid, state);
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn test_pool_descendant_limit_spares_workers_within_limit() {
+    let pool = ProcessPool::new("CalmPool", 2).with_descendant_limit(5);
+    pool.spawn_process(ProcessBuilder::new("sleep").arg("1"))
+        .unwrap();
+
+    std::thread::sleep(Duration::from_millis(300));
+    assert_eq!(pool.active_workers(), 1);
+}
+
+#[test]
+fn test_stdin_bytes_larger_than_pipe_buffer() {
+    let input: Vec<u8> = (0..1024 * 1024).map(|i| (i % 251) as u8).collect();
+    let output = ProcessBuilder::new("cat")
+        .stdin_bytes(input.clone())
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    assert_eq!(output.stdout.len(), input.len());
+    assert_eq!(output.stdout, input);
+}
+
+#[test]
+fn test_stdin_bytes_tolerates_child_that_stops_reading() {
+    let output = ProcessBuilder::new("head")
+        .arg("-c")
+        .arg("5")
+        .stdin_bytes(vec![b'x'; 1024 * 1024])
+        .output_string()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(output.stdout, "xxxxx");
+
+    let conflict = ProcessBuilder::new("cat")
+        .stdin(std::process::Stdio::null())
+        .stdin_bytes(b"data".to_vec())
+        .output();
+    assert!(matches!(conflict, Err(ProcessError::InvalidInput(_))));
+}
+
+/// `signal`をスレッドに送り、パイプからのブロッキングreadの結果を返す
+#[cfg(unix)]
+fn read_interrupted_by(signal: libc::c_int) -> isize {
+    use std::os::unix::thread::JoinHandleExt;
+
+    let mut fds = [0; 2];
+    assert_eq!(unsafe { libc::pipe(fds.as_mut_ptr()) }, 0);
+    let [read_fd, write_fd] = fds;
+
+    let reader = std::thread::spawn(move || {
+        let mut buf = [0u8; 1];
+        let n = unsafe { libc::read(read_fd, buf.as_mut_ptr() as *mut libc::c_void, 1) };
+        if n == -1 {
+            assert_eq!(
+                std::io::Error::last_os_error().kind(),
+                std::io::ErrorKind::Interrupted
+            );
+        }
+        n
+    });
+
+    std::thread::sleep(Duration::from_millis(100));
+    unsafe { libc::pthread_kill(reader.as_pthread_t(), signal) };
+    // 再開されたreadはこの書き込みで返る
+    std::thread::sleep(Duration::from_millis(100));
+    unsafe { libc::write(write_fd, b"x".as_ptr() as *const libc::c_void, 1) };
+
+    let n = reader.join().unwrap();
+    unsafe {
+        libc::close(read_fd);
+        libc::close(write_fd);
+    }
+    n
+}
+
+#[cfg(unix)]
+#[test]
+fn test_set_restart_controls_eintr() {
+    // SIGQUITは他のテストで使わないので、プロセス全体の設定を変えても影響しない
+    assert!(matches!(
+        SignalHandler::set_restart(SignalType::Quit, false),
+        Err(ProcessError::InvalidInput(_))
+    ));
+    SignalHandler::chain(SignalType::Quit, || {}).expect("Failed to chain SIGQUIT handler");
+
+    SignalHandler::set_restart(SignalType::Quit, false).unwrap();
+    assert_eq!(read_interrupted_by(libc::SIGQUIT), -1);
+
+    SignalHandler::set_restart(SignalType::Quit, true).unwrap();
+    assert_eq!(read_interrupted_by(libc::SIGQUIT), 1);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_tail_follow_survives_rotation_and_truncation() {
+    use std::io::Write;
+    use std::ops::ControlFlow;
+    use std::sync::mpsc;
+
+    let dir = tempfile::tempdir().unwrap();
+    let log = dir.path().join("service.log");
+    let append = |path: &std::path::Path, text: &str| {
+        std::fs::OpenOptions::new()
+            .append(true)
+            .create(true)
+            .open(path)
+            .unwrap()
+            .write_all(text.as_bytes())
+            .unwrap();
+    };
+    append(&log, "before follow\n");
+
+    let (tx, rx) = mpsc::channel();
+    let follower = {
+        let log = log.clone();
+        std::thread::spawn(move || {
+            tail_follow(&log, |line| {
+                tx.send(line.to_string()).unwrap();
+                if line == "stop" {
+                    ControlFlow::Break(())
+                } else {
+                    ControlFlow::Continue(())
+                }
+            })
+        })
+    };
+    let next = || rx.recv_timeout(Duration::from_secs(5)).unwrap();
+    // 追跡を始める前に書かれた行は読まない
+    std::thread::sleep(Duration::from_millis(200));
+
+    append(&log, "first\nsecond\n");
+    assert_eq!(next(), "first");
+    assert_eq!(next(), "second");
+
+    // logrotate風に移動し、移動後の旧ファイルへの書き込みも取りこぼさない
+    let rotated = dir.path().join("service.log.1");
+    std::fs::rename(&log, &rotated).unwrap();
+    append(&rotated, "late write\n");
+    append(&log, "new file\n");
+    assert_eq!(next(), "late write");
+    assert_eq!(next(), "new file");
+
+    // copytruncate風に切り詰める
+    std::fs::OpenOptions::new()
+        .write(true)
+        .truncate(true)
+        .open(&log)
+        .unwrap();
+    std::thread::sleep(Duration::from_millis(300));
+    append(&log, "after truncate\nstop\n");
+    assert_eq!(next(), "after truncate");
+    assert_eq!(next(), "stop");
+
+    follower.join().unwrap().unwrap();
+}
+
+/// 自分自身を子として起動し、Dropで届いたシグナルの名前を子に書き出させる
+fn signal_delivered_on_drop(test_name: &str, signal: Option<SignalType>) -> Option<String> {
+    if let Some(marker) = std::env::var_os("GUARD_SIGNAL_CHILD") {
+        let marker = std::path::PathBuf::from(marker);
+        let handler = SignalHandler::new(&[SignalType::Terminate, SignalType::User1]).unwrap();
+        std::fs::write(marker.with_extension("ready"), "").unwrap();
+        // Dropの猶予（100ms）内に書き出せるよう細かく確認する
+        while !handler.should_shutdown() {
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        let name = if handler.count(SignalType::User1) > 0 {
+            "USR1"
+        } else {
+            "TERM"
+        };
+        std::fs::write(&marker, name).unwrap();
+        std::process::exit(0);
+    }
+
+    let dir = tempfile::tempdir().unwrap();
+    let marker = dir.path().join("signal");
+    let exe = std::env::current_exe().unwrap();
+    let mut guard = ProcessBuilder::new(exe.to_str().unwrap())
+        .arg("--exact")
+        .arg(test_name)
+        .arg("--nocapture")
+        .env("GUARD_SIGNAL_CHILD", marker.to_str().unwrap())
+        .stdout(std::process::Stdio::null())
+        .spawn()
+        .unwrap();
+    if let Some(signal) = signal {
+        guard = guard.with_signal(signal);
+    }
+
+    let start = std::time::Instant::now();
+    while !marker.with_extension("ready").exists() {
+        assert!(
+            start.elapsed() < Duration::from_secs(10),
+            "child never became ready"
+        );
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    drop(guard);
+    std::fs::read_to_string(&marker).ok()
+}
+
+#[test]
+fn test_guard_sends_sigterm_on_drop_by_default() {
+    let received = signal_delivered_on_drop("test_guard_sends_sigterm_on_drop_by_default", None);
+    assert_eq!(received.as_deref(), Some("TERM"));
+}
+
+#[test]
+fn test_guard_with_signal_delivers_configured_signal() {
+    let received = signal_delivered_on_drop(
+        "test_guard_with_signal_delivers_configured_signal",
+        Some(SignalType::User1),
+    );
+    assert_eq!(received.as_deref(), Some("USR1"));
+}
+
+#[test]
+fn test_guard_with_sigkill_gives_no_chance_to_clean_up() {
+    let received = signal_delivered_on_drop(
+        "test_guard_with_sigkill_gives_no_chance_to_clean_up",
+        Some(SignalType::Kill),
+    );
+    assert_eq!(received, None);
+}
+
+#[test]
+fn test_guard_without_kill_on_drop_only_reaps() {
+    let guard = ProcessBuilder::new("sleep")
+        .arg("0.3")
+        .spawn()
+        .unwrap()
+        .kill_on_drop(false);
+    let pid = guard.pid().unwrap() as i32;
+
+    let start = std::time::Instant::now();
+    drop(guard);
+    assert!(start.elapsed() < Duration::from_millis(100));
+    assert!(matches!(proc_state(pid), Some(state) if state != 'Z'));
+
+    // 自分で終了した後はバックグラウンドで回収され、ゾンビも残らない
+    std::thread::sleep(Duration::from_millis(800));
+    assert_eq!(proc_state(pid), None);
+}
+
+#[test]
+fn test_guard_force_kills_child_ignoring_sigterm_after_grace() {
+    if let Some(ready) = std::env::var_os("IGNORE_TERM_CHILD") {
+        SignalHandler::ignore(SignalType::Terminate).unwrap();
+        std::fs::write(ready, "").unwrap();
+        std::thread::sleep(Duration::from_secs(30));
+        return;
+    }
+
+    let dir = tempfile::tempdir().unwrap();
+    let ready = dir.path().join("ready");
+    let exe = std::env::current_exe().unwrap();
+    let mut guard = ProcessBuilder::new(exe.to_str().unwrap())
+        .arg("--exact")
+        .arg("test_guard_force_kills_child_ignoring_sigterm_after_grace")
+        .env("IGNORE_TERM_CHILD", ready.to_str().unwrap())
+        .stdout(std::process::Stdio::null())
+        .spawn()
+        .unwrap();
+    guard.set_grace(Duration::from_millis(300));
+    let pid = guard.pid().unwrap() as i32;
+
+    let start = std::time::Instant::now();
+    while !ready.exists() {
+        assert!(
+            start.elapsed() < Duration::from_secs(10),
+            "child never became ready"
+        );
+        std::thread::sleep(Duration::from_millis(10));
+    }
+
+    let start = std::time::Instant::now();
+    drop(guard);
+    let elapsed = start.elapsed();
+    assert!(elapsed >= Duration::from_millis(300), "{:?}", elapsed);
+    assert!(elapsed < Duration::from_secs(2), "{:?}", elapsed);
+    // SIGKILLの後に回収され、ゾンビも残らない
+    assert_eq!(proc_state(pid), None);
+}
+
+#[test]
+fn test_guard_drop_returns_as_soon_as_child_exits() {
+    let mut guard = ProcessBuilder::new("sleep").arg("30").spawn().unwrap();
+    guard.set_grace(Duration::from_secs(5));
+
+    let start = std::time::Instant::now();
+    drop(guard);
+    assert!(start.elapsed() < Duration::from_secs(1));
+}
+
+#[test]
+fn test_wait_status_reports_exit_code() {
+    let guard = ProcessBuilder::new("sh")
+        .arg("-c")
+        .arg("exit 3")
+        .spawn()
+        .unwrap();
+    let status = guard.wait_status().unwrap();
+    assert_eq!(status.code(), Some(3));
+    assert!(!status.success());
+
+    let status = ProcessBuilder::new("true")
+        .spawn()
+        .unwrap()
+        .wait_status()
+        .unwrap();
+    assert!(status.success());
+}
+
+#[test]
+fn test_wait_status_reports_terminating_signal() {
+    use std::os::unix::process::ExitStatusExt;
+
+    // treat_signals_as_successはwaitの成否の判定だけに使われ、生の状態には影響しない
+    let guard = ProcessBuilder::new("sleep")
+        .arg("30")
+        .treat_signals_as_success(&[SignalType::Kill])
+        .spawn()
+        .unwrap();
+    let pid = guard.pid().unwrap();
+    send_signal(pid, SignalType::Kill).unwrap();
+
+    let status = guard.wait_status().unwrap();
+    assert_eq!(status.signal(), Some(libc::SIGKILL));
+    assert_eq!(status.code(), None);
+    // 回収済みなので、Dropが同じPIDを再び待ったりシグナルを送ったりしない
+    assert_eq!(proc_state(pid as i32), None);
+}
+
+#[test]
+fn test_scope_terminates_all_children_on_drop() {
+    let mut scope = ProcessScope::new();
+    for _ in 0..5 {
+        scope.spawn(ProcessBuilder::new("sleep").arg("30")).unwrap();
+    }
+    let pids = scope.pids();
+    assert_eq!(pids.len(), 5);
+    for &pid in &pids {
+        assert!(proc_state(pid as i32).is_some());
+    }
+
+    let start = std::time::Instant::now();
+    drop(scope);
+    assert!(start.elapsed() < Duration::from_secs(1));
+    // 全員SIGTERMで終了し、回収されている
+    for pid in pids {
+        assert_eq!(proc_state(pid as i32), None);
+    }
+}
+
+#[test]
+fn test_scope_waits_for_grace_periods_concurrently() {
+    let dir = tempfile::tempdir().unwrap();
+    let exe = std::env::current_exe().unwrap();
+    let mut scope = ProcessScope::new();
+    let mut ready_files = Vec::new();
+
+    // SIGTERMを無視する子を3つ（1つずつ待つと猶予の合計0.9秒以上かかる）
+    for i in 0..3 {
+        let ready = dir.path().join(format!("ready-{}", i));
+        let guard = scope
+            .spawn(
+                ProcessBuilder::new(exe.to_str().unwrap())
+                    .arg("--exact")
+                    .arg("test_guard_force_kills_child_ignoring_sigterm_after_grace")
+                    .env("IGNORE_TERM_CHILD", ready.to_str().unwrap())
+                    .stdout(std::process::Stdio::null()),
+            )
+            .unwrap();
+        guard.set_grace(Duration::from_millis(300));
+        ready_files.push(ready);
+    }
+    // 猶予なしでSIGKILLされるガードも混ぜられる
+    scope.add(
+        ProcessBuilder::new("sleep")
+            .arg("30")
+            .spawn()
+            .unwrap()
+            .with_signal(SignalType::Kill),
+    );
+    let pids = scope.pids();
+    assert_eq!(scope.len(), 4);
+
+    let start = std::time::Instant::now();
+    for ready in &ready_files {
+        while !ready.exists() {
+            assert!(
+                start.elapsed() < Duration::from_secs(10),
+                "child never became ready"
+            );
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    let start = std::time::Instant::now();
+    drop(scope);
+    let elapsed = start.elapsed();
+    assert!(elapsed >= Duration::from_millis(300), "{:?}", elapsed);
+    assert!(elapsed < Duration::from_millis(900), "{:?}", elapsed);
+    for pid in pids {
+        assert_eq!(proc_state(pid as i32), None);
+    }
+}
+
+/// 子プロセスに孫の`sleep`を起動させ、孫のPIDを返す
+fn spawn_with_grandchild(dir: &std::path::Path, new_group: bool) -> (ProcessGuard, i32) {
+    let pid_file = dir.join(format!("grandchild-{}", new_group));
+    let exe = std::env::current_exe().unwrap();
+    let guard = ProcessBuilder::new(exe.to_str().unwrap())
+        .arg("--exact")
+        .arg("test_group_guard_terminates_grandchildren")
+        .env("GRANDCHILD_PID_FILE", pid_file.to_str().unwrap())
+        .stdout(std::process::Stdio::null())
+        .new_process_group(new_group)
+        .spawn()
+        .unwrap();
+
+    let start = std::time::Instant::now();
+    let grandchild = loop {
+        if let Ok(pid) = std::fs::read_to_string(&pid_file) {
+            if let Ok(pid) = pid.trim().parse() {
+                break pid;
+            }
+        }
+        assert!(
+            start.elapsed() < Duration::from_secs(10),
+            "child never started the grandchild"
+        );
+        std::thread::sleep(Duration::from_millis(10));
+    };
+    (guard, grandchild)
+}
+
+#[test]
+fn test_group_guard_terminates_grandchildren() {
+    if let Some(path) = std::env::var_os("GRANDCHILD_PID_FILE") {
+        let mut grandchild = std::process::Command::new("sleep")
+            .arg("30")
+            .spawn()
+            .unwrap();
+        // 書き込み途中を読まれないよう、書き終えてから名前を変える
+        let tmp = format!("{}.tmp", path.to_str().unwrap());
+        std::fs::write(&tmp, grandchild.id().to_string()).unwrap();
+        std::fs::rename(&tmp, &path).unwrap();
+        // 孫の`sleep 30`が終わるまで待つ
+        let _ = grandchild.wait();
+        return;
+    }
+
+    let dir = tempfile::tempdir().unwrap();
+    let (guard, grandchild) = spawn_with_grandchild(dir.path(), true);
+    let pid = guard.pid().unwrap() as i32;
+    assert_eq!(guard.pgid(), Some(pid as u32));
+    assert_eq!(unsafe { libc::getpgid(grandchild) }, pid);
+
+    drop(guard);
+    // 子は回収済み、孫も終了している（initの回収待ちのゾンビは可）
+    assert_eq!(proc_state(pid), None);
+    std::thread::sleep(Duration::from_millis(100));
+    let state = proc_state(grandchild);
+    assert!(
+        matches!(state, None | Some('Z')),
+        "grandchild is {:?}",
+        state
+    );
+
+    // グループを分けなければ孫は残る
+    let (guard, grandchild) = spawn_with_grandchild(dir.path(), false);
+    assert_eq!(guard.pgid(), None);
+    drop(guard);
+    std::thread::sleep(Duration::from_millis(100));
+    let state = proc_state(grandchild);
+    assert!(
+        matches!(state, Some('S' | 'R')),
+        "grandchild is {:?}",
+        state
+    );
+    unsafe { libc::kill(grandchild, libc::SIGKILL) };
+}
@@ -0,0 +1,63 @@
+//! Per-thread signal mask test
+//!
+//! Runs without the libtest harness: unblocking a pending signal delivers it
+//! to the test thread, and the SIGUSR1 handler installed here must not be
+//! seen by tests that count their own deliveries.
+
+use linux_process_rs::signal::{self, SignalHandler, SignalType};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static DELIVERED: AtomicUsize = AtomicUsize::new(0);
+
+/// このスレッドでSIGUSR1が保留中かどうか
+fn usr1_pending() -> bool {
+    let mut pending: libc::sigset_t = unsafe { std::mem::zeroed() };
+    unsafe { libc::sigpending(&mut pending) };
+    unsafe { libc::sigismember(&pending, libc::SIGUSR1) == 1 }
+}
+
+fn main() {
+    SignalHandler::chain(SignalType::User1, || {
+        DELIVERED.fetch_add(1, Ordering::SeqCst);
+    })
+    .expect("Failed to install SIGUSR1 handler");
+
+    std::thread::spawn(|| {
+        // ブロック中に自スレッド宛てに送ったシグナルは保留されたままになる
+        signal::block(&[SignalType::User1]).unwrap();
+        unsafe { libc::raise(libc::SIGUSR1) };
+        assert!(usr1_pending());
+        assert_eq!(DELIVERED.load(Ordering::SeqCst), 0);
+
+        // ブロックを解除すると、戻る前に配送される
+        signal::unblock(&[SignalType::User1]).unwrap();
+        assert!(!usr1_pending());
+        assert_eq!(DELIVERED.load(Ordering::SeqCst), 1);
+
+        // スコープを抜けると元のマスクに戻り、その間のシグナルが配送される
+        let pending_inside = signal::with_blocked(&[SignalType::User1], || {
+            unsafe { libc::raise(libc::SIGUSR1) };
+            usr1_pending()
+        })
+        .unwrap();
+        assert!(pending_inside);
+        assert_eq!(DELIVERED.load(Ordering::SeqCst), 2);
+
+        // 元からブロックしていたシグナルは、スコープを抜けてもブロックされたまま
+        signal::block(&[SignalType::User1]).unwrap();
+        signal::with_blocked(&[SignalType::User1, SignalType::User2], || {
+            unsafe { libc::raise(libc::SIGUSR1) };
+        })
+        .unwrap();
+        assert!(usr1_pending());
+        assert_eq!(DELIVERED.load(Ordering::SeqCst), 2);
+        signal::unblock(&[SignalType::User1]).unwrap();
+        assert_eq!(DELIVERED.load(Ordering::SeqCst), 3);
+
+        assert!(signal::block(&[SignalType::Kill]).is_err());
+    })
+    .join()
+    .unwrap();
+
+    println!("test signal_mask ... ok");
+}
@@ -0,0 +1,34 @@
+//! Tests for `SignalHandler::stream` on the tokio runtime
+
+use futures_util::StreamExt;
+use linux_process_rs::errors::ProcessError;
+use linux_process_rs::signal::{send_signal, SignalHandler, SignalType};
+use std::time::Duration;
+
+#[tokio::test]
+async fn test_stream_yields_raised_signal_to_every_subscriber() {
+    let mut first = SignalHandler::stream(SignalType::User1).expect("Failed to subscribe");
+    let mut second = SignalHandler::stream(SignalType::User1).expect("Failed to subscribe");
+
+    send_signal(std::process::id(), SignalType::User1).unwrap();
+
+    for stream in [&mut first, &mut second] {
+        let received = tokio::time::timeout(Duration::from_secs(5), stream.next())
+            .await
+            .expect("signal was not delivered to the stream");
+        assert_eq!(received, Some(SignalType::User1));
+    }
+
+    // 次のシグナルが届くまでは何も返さない
+    assert!(
+        tokio::time::timeout(Duration::from_millis(100), first.next())
+            .await
+            .is_err()
+    );
+}
+
+#[tokio::test]
+async fn test_stream_rejects_uncatchable_signal() {
+    let result = SignalHandler::stream(SignalType::Kill);
+    assert!(matches!(result, Err(ProcessError::SignalError(_))));
+}
@@ -0,0 +1,34 @@
+//! Dedicated signal thread test
+//!
+//! Runs without the libtest harness: the signal mask must be set on the main
+//! thread before any other thread exists, which libtest does not allow.
+
+use linux_process_rs::signal::{send_signal, SignalHandler, SignalType};
+use std::sync::mpsc;
+use std::time::Duration;
+
+fn main() {
+    let (tx, rx) = mpsc::channel();
+    let handler = SignalHandler::with_dedicated_thread(&[SignalType::User1], move |signal| {
+        let current = std::thread::current();
+        let _ = tx.send((signal, current.id(), current.name().map(String::from)));
+    })
+    .expect("Failed to create signal handler");
+
+    // マスクを継承したワーカースレッドからプロセス宛てにシグナルを送る
+    std::thread::spawn(|| send_signal(std::process::id(), SignalType::User1).unwrap())
+        .join()
+        .unwrap();
+
+    let (signal, thread_id, thread_name) = rx
+        .recv_timeout(Duration::from_secs(5))
+        .expect("callback was not invoked");
+    assert_eq!(signal, SignalType::User1);
+    assert_ne!(thread_id, std::thread::current().id());
+    assert_eq!(thread_name.as_deref(), Some("signal-handler"));
+    assert!(handler.should_shutdown());
+
+    // Dropで専用スレッドが終了すること
+    drop(handler);
+    println!("test dedicated_signal_thread ... ok");
+}
@@ -0,0 +1,648 @@
+//! IPCチャネルモジュール
+//!
+//! Unix Domain Socket上で長さプレフィックス付きフレームを送受信し、
+//! 遅い相手に対するバックプレッシャー制御を提供する
+
+use std::io::{Read, Write};
+use std::os::unix::io::AsRawFd;
+use std::os::unix::net::UnixStream;
+use std::time::Duration;
+
+use crate::errors::{IPCError, Result};
+use crate::ipc::IPCMessage;
+#[cfg(feature = "ipc-trace")]
+use crate::trace::TraceState;
+
+/// 送信ウィンドウが埋まったときの振る舞い
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BackpressureMode {
+    /// 相手が読み出すまで送信をブロックする
+    Block,
+    /// 即座に `IPCError::WouldBlock` を返す
+    Error,
+}
+
+/// チャネルの設定
+#[derive(Debug, Clone)]
+pub struct ChannelConfig {
+    /// 送信中（未読）データの上限バイト数。カーネルの送信バッファ（SO_SNDBUF）に設定される
+    pub send_window: Option<usize>,
+    /// ウィンドウが埋まったときの振る舞い
+    pub backpressure: BackpressureMode,
+    /// Blockモードでの最大待ち時間（Noneなら無期限）
+    pub block_timeout: Option<Duration>,
+    /// `ipc-trace` フィーチャー有効時、ペイロードを伏せ字ではなくhexdumpで記録する
+    pub trace_payload: bool,
+}
+
+impl Default for ChannelConfig {
+    fn default() -> Self {
+        Self {
+            send_window: None,
+            backpressure: BackpressureMode::Block,
+            block_timeout: None,
+            trace_payload: false,
+        }
+    }
+}
+
+/// 接続相手のプロセスの資格情報（SO_PEERCRED）
+///
+/// 相手が `connect`（または `socketpair`）した時点の値で、その後に相手が
+/// 権限を落としても変わらない
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PeerCredentials {
+    /// 相手のPID（別のPID名前空間にいて見えない場合は0）
+    pub pid: i32,
+    pub uid: u32,
+    pub gid: u32,
+}
+
+/// `wait_for_peer_death` が検出した相手の終了
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PeerDeath {
+    /// 相手側のソケットが閉じられた（EOF）
+    Closed,
+    /// ソケットは開いたままだが、相手のプロセスが /proc から消えた（またはゾンビになった）
+    ProcessGone,
+}
+
+/// 長さプレフィックス（4バイト LE）でフレーミングされたIPCチャネル
+pub struct IPCChannel {
+    stream: UnixStream,
+    config: ChannelConfig,
+    peer: PeerCredentials,
+    /// 部分的にしか書き込めなかったフレームの残り（次のフレームより先に書き出す）
+    pending: Vec<u8>,
+    #[cfg(feature = "ipc-trace")]
+    trace: TraceState,
+}
+
+impl IPCChannel {
+    /// 既存のストリームからチャネルを作成
+    pub fn new(stream: UnixStream, config: ChannelConfig) -> Result<Self> {
+        if let Some(window) = config.send_window {
+            set_send_buffer_size(&stream, window)?;
+        }
+        stream.set_write_timeout(config.block_timeout)?;
+        let peer = peer_credentials(&stream)?;
+
+        Ok(Self {
+            stream,
+            config,
+            peer,
+            pending: Vec::new(),
+            #[cfg(feature = "ipc-trace")]
+            trace: TraceState::default(),
+        })
+    }
+
+    /// ソケットパスに接続してチャネルを作成
+    pub fn connect<P: AsRef<std::path::Path>>(path: P, config: ChannelConfig) -> Result<Self> {
+        let stream = UnixStream::connect(path)?;
+        Self::new(stream, config)
+    }
+
+    /// 接続済みのチャネルのペアを作成（テスト・スレッド間通信用）
+    pub fn pair(config: ChannelConfig) -> Result<(Self, Self)> {
+        let (a, b) = UnixStream::pair()?;
+        Ok((Self::new(a, config.clone())?, Self::new(b, config)?))
+    }
+
+    /// 現在の設定
+    pub fn config(&self) -> &ChannelConfig {
+        &self.config
+    }
+
+    /// 内部のストリームへの参照
+    pub fn stream(&self) -> &UnixStream {
+        &self.stream
+    }
+
+    /// 接続相手の資格情報（チャネル作成時に取得したもの）
+    pub fn peer_credentials(&self) -> PeerCredentials {
+        self.peer
+    }
+
+    /// 相手が終了するまでブロックする
+    ///
+    /// 相手のソケットが閉じられる（EOF）と `PeerDeath::Closed` を返す。
+    /// 未読のメッセージは消費しないので、戻った後も `recv` で読み出せる。
+    /// 相手が子プロセスにソケットを引き継いでいるとEOFは届かないため、
+    /// `pid_check` を指定するとその間隔で相手のPIDを /proc で確認し、
+    /// 消えていれば（またはゾンビなら） `PeerDeath::ProcessGone` を返す。
+    /// PIDが見えない相手（PIDが0）は確認しない
+    pub fn wait_for_peer_death(&self, pid_check: Option<Duration>) -> Result<PeerDeath> {
+        let pid_check = pid_check.filter(|_| self.peer.pid > 0);
+        let timeout = match pid_check {
+            Some(interval) => interval.as_millis().clamp(1, libc::c_int::MAX as u128) as libc::c_int,
+            None => -1,
+        };
+
+        loop {
+            // POLLINは待たない（未読データがあっても相手の終了までは起きない）
+            let mut fd = libc::pollfd {
+                fd: self.stream.as_raw_fd(),
+                events: libc::POLLRDHUP,
+                revents: 0,
+            };
+            let ret = unsafe { libc::poll(&mut fd, 1, timeout) };
+            if ret == -1 {
+                let err = std::io::Error::last_os_error();
+                if err.kind() == std::io::ErrorKind::Interrupted {
+                    continue;
+                }
+                return Err(err.into());
+            }
+            if ret > 0 && fd.revents & (libc::POLLRDHUP | libc::POLLHUP | libc::POLLERR) != 0 {
+                return Ok(PeerDeath::Closed);
+            }
+            if pid_check.is_some() && !process_alive(self.peer.pid) {
+                return Ok(PeerDeath::ProcessGone);
+            }
+        }
+    }
+
+    /// メッセージを送信
+    ///
+    /// 送信ウィンドウが埋まっている場合、`BackpressureMode` に従って
+    /// ブロックするか `IPCError::WouldBlock` を返す
+    pub fn send(&mut self, message: &IPCMessage) -> Result<()> {
+        self.send_frame(&message.to_bytes()?)
+    }
+
+    /// メッセージを受信
+    pub fn recv(&mut self) -> Result<IPCMessage> {
+        IPCMessage::from_bytes(&self.recv_frame()?)
+    }
+
+    /// バイト列を1フレームとして送信（暗号化チャネルなど上位の層が使う）
+    pub(crate) fn send_frame(&mut self, bytes: &[u8]) -> Result<()> {
+        let mut frame = Vec::with_capacity(4 + bytes.len());
+        frame.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+        frame.extend_from_slice(bytes);
+
+        match self.config.backpressure {
+            BackpressureMode::Block => self.send_blocking(&frame)?,
+            BackpressureMode::Error => self.send_nonblocking(frame)?,
+        }
+
+        #[cfg(feature = "ipc-trace")]
+        self.trace.on_send(bytes, self.config.trace_payload);
+
+        Ok(())
+    }
+
+    /// 1フレーム分のバイト列を受信
+    pub(crate) fn recv_frame(&mut self) -> Result<Vec<u8>> {
+        let mut size_buf = [0u8; 4];
+        self.stream.read_exact(&mut size_buf)?;
+
+        let message_size = u32::from_le_bytes(size_buf) as usize;
+        if message_size > IPCMessage::MAX_PAYLOAD_SIZE {
+            return Err(IPCError::protocol(format!(
+                "frame too large: {} bytes",
+                message_size
+            )));
+        }
+
+        let mut message_buf = vec![0u8; message_size];
+        self.stream.read_exact(&mut message_buf)?;
+
+        #[cfg(feature = "ipc-trace")]
+        self.trace.on_recv(&message_buf, self.config.trace_payload);
+
+        Ok(message_buf)
+    }
+
+    /// 部分送信されたフレームの残りを書き出す
+    ///
+    /// Errorモードで残りが書き込めない場合は `IPCError::WouldBlock` を返す
+    pub fn flush(&mut self) -> Result<()> {
+        match self.config.backpressure {
+            BackpressureMode::Block => self.flush_pending_blocking(),
+            BackpressureMode::Error => self.flush_pending(),
+        }
+    }
+
+    /// 相手が読み出すまで待って書き込む
+    ///
+    /// `block_timeout` までにフレームが1バイトも書けなければメッセージは受理されない。
+    /// 途中まで書けた場合は受理して残りを保持し、次のフレームより先に書き出す
+    /// （残りを捨てると、相手は以降のフレームの長さプレフィックスを読み違える）
+    fn send_blocking(&mut self, frame: &[u8]) -> Result<()> {
+        self.flush_pending_blocking()?;
+
+        let written = self.write_blocking(frame)?;
+        if written == 0 {
+            return Err(IPCError::WouldBlock);
+        }
+        self.pending = frame[written..].to_vec();
+        Ok(())
+    }
+
+    fn flush_pending_blocking(&mut self) -> Result<()> {
+        let written = self.write_blocking(&self.pending)?;
+        self.pending.drain(..written);
+        if !self.pending.is_empty() {
+            return Err(IPCError::WouldBlock);
+        }
+        Ok(())
+    }
+
+    /// 書き込みタイムアウトまで書き込み、書けたバイト数を返す
+    fn write_blocking(&self, buf: &[u8]) -> Result<usize> {
+        let mut written = 0;
+        while written < buf.len() {
+            match (&self.stream).write(&buf[written..]) {
+                Ok(0) => return Err(std::io::Error::from(std::io::ErrorKind::WriteZero).into()),
+                Ok(n) => written += n,
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+                // 書き込みタイムアウトはWouldBlock/TimedOutとして報告される
+                Err(e)
+                    if matches!(
+                        e.kind(),
+                        std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut
+                    ) =>
+                {
+                    break
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Ok(written)
+    }
+
+    /// ブロックせずに書き込む
+    ///
+    /// フレームが1バイトも書けなければメッセージは受理されない。
+    /// 一部だけ書けた場合は残りを保持し、次回の送信前に書き出す
+    fn send_nonblocking(&mut self, frame: Vec<u8>) -> Result<()> {
+        self.flush_pending()?;
+
+        let written = self.try_write(&frame)?;
+        if written == 0 {
+            return Err(IPCError::WouldBlock);
+        }
+        if written < frame.len() {
+            self.pending = frame[written..].to_vec();
+        }
+        Ok(())
+    }
+
+    fn flush_pending(&mut self) -> Result<()> {
+        while !self.pending.is_empty() {
+            let written = self.try_write(&self.pending)?;
+            if written == 0 {
+                return Err(IPCError::WouldBlock);
+            }
+            self.pending.drain(..written);
+        }
+        Ok(())
+    }
+
+    /// MSG_DONTWAITで書き込み、書き込めたバイト数を返す（0はウィンドウ満杯）
+    fn try_write(&self, buf: &[u8]) -> Result<usize> {
+        loop {
+            let ret = unsafe {
+                libc::send(
+                    self.stream.as_raw_fd(),
+                    buf.as_ptr() as *const libc::c_void,
+                    buf.len(),
+                    libc::MSG_DONTWAIT | libc::MSG_NOSIGNAL,
+                )
+            };
+            if ret >= 0 {
+                return Ok(ret as usize);
+            }
+
+            let err = std::io::Error::last_os_error();
+            match err.kind() {
+                std::io::ErrorKind::Interrupted => continue,
+                std::io::ErrorKind::WouldBlock => return Ok(0),
+                _ => return Err(err.into()),
+            }
+        }
+    }
+}
+
+/// SO_PEERCREDで接続相手の資格情報を取得
+fn peer_credentials(stream: &UnixStream) -> Result<PeerCredentials> {
+    let mut cred = libc::ucred {
+        pid: 0,
+        uid: 0,
+        gid: 0,
+    };
+    let mut len = std::mem::size_of::<libc::ucred>() as libc::socklen_t;
+    let ret = unsafe {
+        libc::getsockopt(
+            stream.as_raw_fd(),
+            libc::SOL_SOCKET,
+            libc::SO_PEERCRED,
+            &mut cred as *mut libc::ucred as *mut libc::c_void,
+            &mut len,
+        )
+    };
+    if ret == -1 {
+        return Err(std::io::Error::last_os_error().into());
+    }
+    Ok(PeerCredentials {
+        pid: cred.pid,
+        uid: cred.uid,
+        gid: cred.gid,
+    })
+}
+
+/// /proc/<pid>/stat が読めて、ゾンビでなければ生きている
+fn process_alive(pid: i32) -> bool {
+    match std::fs::read_to_string(format!("/proc/{}/stat", pid)) {
+        // コマンド名に空白や括弧が含まれてもよいよう、最後の ')' の後の状態を見る
+        Ok(stat) => stat
+            .rsplit_once(')')
+            .and_then(|(_, rest)| rest.split_whitespace().next())
+            .is_some_and(|state| state != "Z" && state != "X"),
+        Err(_) => false,
+    }
+}
+
+/// ソケットの送信バッファサイズを設定
+fn set_send_buffer_size(stream: &UnixStream, size: usize) -> Result<()> {
+    let value = size as libc::c_int;
+    let ret = unsafe {
+        libc::setsockopt(
+            stream.as_raw_fd(),
+            libc::SOL_SOCKET,
+            libc::SO_SNDBUF,
+            &value as *const libc::c_int as *const libc::c_void,
+            std::mem::size_of::<libc::c_int>() as libc::socklen_t,
+        )
+    };
+    if ret == -1 {
+        return Err(std::io::Error::last_os_error().into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+
+    fn small_window(mode: BackpressureMode) -> ChannelConfig {
+        ChannelConfig {
+            send_window: Some(4096),
+            backpressure: mode,
+            block_timeout: None,
+            trace_payload: false,
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let (mut a, mut b) = IPCChannel::pair(ChannelConfig::default()).unwrap();
+        let msg = IPCMessage::request(b"ping".to_vec());
+        a.send(&msg).unwrap();
+
+        let received = b.recv().unwrap();
+        assert_eq!(received.id, msg.id);
+        assert_eq!(received.payload, b"ping");
+    }
+
+    #[test]
+    fn test_error_mode_returns_would_block_when_window_full() {
+        let (mut sender, mut receiver) =
+            IPCChannel::pair(small_window(BackpressureMode::Error)).unwrap();
+        let payload = vec![0u8; 1024];
+
+        // 相手が読まない限りウィンドウはいずれ埋まる
+        let mut sent = 0;
+        let err = loop {
+            match sender.send(&IPCMessage::request(payload.clone())) {
+                Ok(()) => sent += 1,
+                Err(e) => break e,
+            }
+            assert!(sent < 10_000, "window never filled");
+        };
+        assert!(matches!(err, IPCError::WouldBlock));
+        assert!(err.is_retryable());
+
+        // 相手が読み出せば再び送信できる（最後のフレームは部分送信の可能性がある）
+        for _ in 0..sent - 1 {
+            receiver.recv().unwrap();
+        }
+        sender.flush().unwrap();
+        receiver.recv().unwrap();
+        sender.send(&IPCMessage::request(payload)).unwrap();
+    }
+
+    #[test]
+    fn test_block_mode_waits_for_peer_to_drain() {
+        let (mut sender, mut receiver) =
+            IPCChannel::pair(small_window(BackpressureMode::Block)).unwrap();
+        let total = 200;
+        let sent = Arc::new(AtomicUsize::new(0));
+
+        let sent_clone = sent.clone();
+        let handle = thread::spawn(move || {
+            for _ in 0..total {
+                sender.send(&IPCMessage::request(vec![0u8; 4096])).unwrap();
+                sent_clone.fetch_add(1, Ordering::SeqCst);
+            }
+        });
+
+        // 読み出さない間は送信側がブロックしている
+        thread::sleep(Duration::from_millis(200));
+        assert!(sent.load(Ordering::SeqCst) < total);
+
+        for _ in 0..total {
+            receiver.recv().unwrap();
+        }
+        handle.join().unwrap();
+        assert_eq!(sent.load(Ordering::SeqCst), total);
+    }
+
+    #[test]
+    fn test_block_mode_timeout_reports_would_block() {
+        let config = ChannelConfig {
+            block_timeout: Some(Duration::from_millis(100)),
+            ..small_window(BackpressureMode::Block)
+        };
+        let (mut sender, _receiver) = IPCChannel::pair(config).unwrap();
+
+        let mut result = Ok(());
+        for _ in 0..10_000 {
+            result = sender.send(&IPCMessage::request(vec![0u8; 1024]));
+            if result.is_err() {
+                break;
+            }
+        }
+        assert!(matches!(result, Err(IPCError::WouldBlock)));
+    }
+
+    #[test]
+    fn test_block_mode_timeout_mid_frame_keeps_stream_in_sync() {
+        let config = ChannelConfig {
+            block_timeout: Some(Duration::from_millis(100)),
+            ..small_window(BackpressureMode::Block)
+        };
+        let (mut sender, mut receiver) = IPCChannel::pair(config).unwrap();
+
+        // 送信バッファより大きいフレームは途中でタイムアウトする
+        let large = IPCMessage::request(vec![7u8; 256 * 1024]);
+        sender.send(&large).unwrap();
+        assert!(!sender.pending.is_empty());
+
+        let reader = thread::spawn(move || {
+            let first = receiver.recv().unwrap();
+            let second = receiver.recv().unwrap();
+            (first, second)
+        });
+        // 残りが先に書き出されるので、次のフレームも正しく区切られる
+        let small = IPCMessage::request(b"after".to_vec());
+        let mut result = sender.send(&small);
+        while matches!(result, Err(IPCError::WouldBlock)) {
+            result = sender.send(&small);
+        }
+        result.unwrap();
+        sender.flush().unwrap();
+
+        let (first, second) = reader.join().unwrap();
+        assert_eq!(first.id, large.id);
+        assert_eq!(first.payload, large.payload);
+        assert_eq!(second.id, small.id);
+        assert_eq!(second.payload, b"after");
+    }
+
+    /// 接続してから止まるクライアントをforkで起動する
+    ///
+    /// `share_with_child` なら、接続後にソケットを引き継いだ子をもう1つforkする
+    /// （どちらも同じプロセスグループにいるので、グループごとに終了させられる）
+    fn spawn_client(path: &std::path::Path, share_with_child: bool) -> nix::unistd::Pid {
+        use nix::unistd::{fork, setpgid, ForkResult, Pid};
+
+        match unsafe { fork() }.unwrap() {
+            ForkResult::Child => {
+                // fork後の子ではシステムコールだけを使う
+                let _ = setpgid(Pid::from_raw(0), Pid::from_raw(0));
+                let _stream = UnixStream::connect(path);
+                if share_with_child {
+                    let _ = unsafe { fork() };
+                }
+                loop {
+                    unsafe { libc::pause() };
+                }
+            }
+            ForkResult::Parent { child } => child,
+        }
+    }
+
+    fn socket_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("rust-signal-ipc-{}-{}.sock", name, std::process::id()))
+    }
+
+    #[test]
+    fn test_wait_for_peer_death_on_client_kill() {
+        use nix::sys::signal::{kill, Signal};
+        use nix::sys::wait::waitpid;
+
+        let path = socket_path("peer-death");
+        let _ = std::fs::remove_file(&path);
+        let listener = std::os::unix::net::UnixListener::bind(&path).unwrap();
+        let client = spawn_client(&path, false);
+        let (stream, _) = listener.accept().unwrap();
+        let channel = IPCChannel::new(stream, ChannelConfig::default()).unwrap();
+
+        let peer = channel.peer_credentials();
+        assert_eq!(peer.pid, client.as_raw());
+        assert_eq!(peer.uid, unsafe { libc::getuid() });
+        assert_eq!(peer.gid, unsafe { libc::getgid() });
+
+        let killer = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(200));
+            kill(client, Signal::SIGKILL).unwrap();
+        });
+        assert_eq!(channel.wait_for_peer_death(None).unwrap(), PeerDeath::Closed);
+        killer.join().unwrap();
+
+        waitpid(client, None).unwrap();
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_wait_for_peer_death_checks_pid_when_socket_is_shared() {
+        use nix::sys::signal::{kill, killpg, Signal};
+        use nix::sys::wait::waitpid;
+
+        let path = socket_path("peer-gone");
+        let _ = std::fs::remove_file(&path);
+        let listener = std::os::unix::net::UnixListener::bind(&path).unwrap();
+        let client = spawn_client(&path, true);
+        let (stream, _) = listener.accept().unwrap();
+        let channel = IPCChannel::new(stream, ChannelConfig::default()).unwrap();
+        assert_eq!(channel.peer_credentials().pid, client.as_raw());
+
+        // 相手の子がソケットを持ち続けるので、EOFは届かずPIDの確認で検出する
+        thread::sleep(Duration::from_millis(200));
+        kill(client, Signal::SIGKILL).unwrap();
+        let death = channel
+            .wait_for_peer_death(Some(Duration::from_millis(20)))
+            .unwrap();
+        assert_eq!(death, PeerDeath::ProcessGone);
+
+        let _ = killpg(client, Signal::SIGKILL);
+        waitpid(client, None).unwrap();
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[cfg(feature = "ipc-trace")]
+    #[test]
+    fn test_send_emits_trace_record_with_size() {
+        use std::sync::Mutex;
+        use tracing_subscriber::fmt::MakeWriter;
+
+        #[derive(Clone, Default)]
+        struct Captured(Arc<Mutex<Vec<u8>>>);
+
+        impl std::io::Write for Captured {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                self.0.lock().unwrap().extend_from_slice(buf);
+                Ok(buf.len())
+            }
+
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        impl<'a> MakeWriter<'a> for Captured {
+            type Writer = Captured;
+
+            fn make_writer(&'a self) -> Self::Writer {
+                self.clone()
+            }
+        }
+
+        let captured = Captured::default();
+        let subscriber = tracing_subscriber::fmt()
+            .with_max_level(tracing::Level::DEBUG)
+            .with_ansi(false)
+            .with_writer(captured.clone())
+            .finish();
+
+        let msg = IPCMessage::request(b"secret".to_vec());
+        let expected_size = msg.to_bytes().unwrap().len();
+
+        tracing::subscriber::with_default(subscriber, || {
+            let (mut a, _b) = IPCChannel::pair(ChannelConfig::default()).unwrap();
+            a.send(&msg).unwrap();
+        });
+
+        let output = String::from_utf8(captured.0.lock().unwrap().clone()).unwrap();
+        assert!(output.contains("direction=\"send\""), "{}", output);
+        assert!(output.contains(&format!("size={}", expected_size)), "{}", output);
+        assert!(output.contains("frame=1"), "{}", output);
+        assert!(output.contains("<redacted>"), "{}", output);
+    }
+}
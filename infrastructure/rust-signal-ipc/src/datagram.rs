@@ -0,0 +1,502 @@
+//! データグラムチャネルモジュール
+//!
+//! SOCK_DGRAMのUnix Domain Socket上で、1回の送信を1データグラムとして扱う。
+//! メッセージ境界はカーネルが保つためフレーミングは不要で、
+//! 順序を必要としない小さな独立したメッセージを低レイテンシで送れる。
+//! フラグメンテーションを有効にすると、1データグラムに収まらないメッセージを
+//! 複数のフラグメントに分割して送り、受信側で組み立て直す
+
+use std::collections::HashMap;
+use std::os::unix::io::AsRawFd;
+use std::os::unix::net::UnixDatagram;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use uuid::Uuid;
+
+use crate::errors::{IPCError, Result};
+use crate::ipc::IPCMessage;
+
+/// データグラムの最大サイズのデフォルト（64KiB）
+pub const DEFAULT_MAX_DATAGRAM_SIZE: usize = 64 * 1024;
+
+/// フラグメントのヘッダ長（メッセージID 16 + 番号 4 + 総数 4 + オフセット 4 + 全体長 4）
+const FRAGMENT_HEADER_LEN: usize = 32;
+
+/// 組み立てるメッセージの最大サイズ（ペイロードの上限にヘッダ分の余裕を持たせる）
+const MAX_REASSEMBLED_SIZE: usize = 2 * IPCMessage::MAX_PAYLOAD_SIZE;
+
+/// 1メッセージ＝1データグラムのIPCチャネル
+pub struct DatagramChannel {
+    socket: UnixDatagram,
+    max_datagram_size: usize,
+    /// フラグメンテーションが有効なら、組み立ての待ち時間の上限
+    reassembly_timeout: Option<Duration>,
+    /// 組み立て途中のメッセージ（分割時に振ったIDごと）
+    partial: Mutex<HashMap<Uuid, PartialMessage>>,
+}
+
+/// 組み立て途中のメッセージ
+struct PartialMessage {
+    data: Vec<u8>,
+    received: Vec<bool>,
+    remaining: usize,
+    started: Instant,
+}
+
+/// フラグメント1つ分（1データグラム）
+struct Fragment<'a> {
+    message_id: Uuid,
+    index: u32,
+    count: u32,
+    offset: u32,
+    total_len: u32,
+    data: &'a [u8],
+}
+
+impl<'a> Fragment<'a> {
+    fn encode(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(FRAGMENT_HEADER_LEN + self.data.len());
+        bytes.extend_from_slice(self.message_id.as_bytes());
+        bytes.extend_from_slice(&self.index.to_le_bytes());
+        bytes.extend_from_slice(&self.count.to_le_bytes());
+        bytes.extend_from_slice(&self.offset.to_le_bytes());
+        bytes.extend_from_slice(&self.total_len.to_le_bytes());
+        bytes.extend_from_slice(self.data);
+        bytes
+    }
+
+    fn decode(bytes: &'a [u8]) -> Result<Self> {
+        if bytes.len() < FRAGMENT_HEADER_LEN {
+            return Err(IPCError::protocol(format!(
+                "fragment too short: {} bytes",
+                bytes.len()
+            )));
+        }
+        let field = |at: usize| u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap());
+        let fragment = Self {
+            message_id: Uuid::from_slice(&bytes[..16]).unwrap(),
+            index: field(16),
+            count: field(20),
+            offset: field(24),
+            total_len: field(28),
+            data: &bytes[FRAGMENT_HEADER_LEN..],
+        };
+
+        // 空でないフラグメントは1バイト以上運ぶので、総数は全体長を超えない
+        let end = fragment.offset as usize + fragment.data.len();
+        if fragment.index >= fragment.count
+            || fragment.count > fragment.total_len.max(1)
+            || end > fragment.total_len as usize
+        {
+            return Err(IPCError::protocol(format!(
+                "malformed fragment {}/{} (bytes {}..{} of {})",
+                fragment.index, fragment.count, fragment.offset, end, fragment.total_len
+            )));
+        }
+        Ok(fragment)
+    }
+}
+
+impl DatagramChannel {
+    /// 既存のソケットからチャネルを作成
+    pub fn new(socket: UnixDatagram) -> Self {
+        Self {
+            socket,
+            max_datagram_size: DEFAULT_MAX_DATAGRAM_SIZE,
+            reassembly_timeout: None,
+            partial: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// ソケットパスにバインドしてチャネルを作成
+    pub fn bind<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Ok(Self::new(UnixDatagram::bind(path)?))
+    }
+
+    /// 接続済みのチャネルのペアを作成（テスト・スレッド間通信用）
+    pub fn pair() -> Result<(Self, Self)> {
+        let (a, b) = UnixDatagram::pair()?;
+        Ok((Self::new(a), Self::new(b)))
+    }
+
+    /// 送受信できるデータグラムの最大サイズを設定
+    pub fn with_max_datagram_size(mut self, size: usize) -> Self {
+        self.max_datagram_size = size;
+        self
+    }
+
+    /// データグラムに収まらないメッセージの分割・組み立てを有効にする
+    ///
+    /// 送受信の両端で有効にする必要がある（すべてのデータグラムにフラグメントの
+    /// ヘッダが付く）。最初のフラグメントから`reassembly_timeout`以内に揃わない
+    /// メッセージは破棄され、`recv`が`IPCError::IncompleteMessage`を返す
+    pub fn with_fragmentation(mut self, reassembly_timeout: Duration) -> Self {
+        self.reassembly_timeout = Some(reassembly_timeout);
+        self
+    }
+
+    /// 送信先のソケットパスを固定する
+    pub fn connect<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        self.socket.connect(path)?;
+        Ok(())
+    }
+
+    /// 内部のソケットへの参照
+    pub fn socket(&self) -> &UnixDatagram {
+        &self.socket
+    }
+
+    /// メッセージを1データグラム（フラグメンテーション有効時は必要な数のデータグラム）として送信
+    pub fn send(&self, message: &IPCMessage) -> Result<()> {
+        let bytes = message.to_bytes()?;
+        self.send_datagrams(&bytes, |datagram| self.socket.send(datagram))
+    }
+
+    /// 指定したパスのソケットへメッセージを送信
+    pub fn send_to<P: AsRef<Path>>(&self, message: &IPCMessage, path: P) -> Result<()> {
+        let bytes = message.to_bytes()?;
+        let path = path.as_ref();
+        self.send_datagrams(&bytes, |datagram| self.socket.send_to(datagram, path))
+    }
+
+    /// データグラムを1つ受信してメッセージに復元
+    ///
+    /// 受信バッファに収まらないデータグラムは切り詰められるため、
+    /// `IPCError::MessageTooLarge` として報告する。
+    /// フラグメンテーション有効時はメッセージが揃うまで受信を続ける
+    pub fn recv(&self) -> Result<IPCMessage> {
+        let mut buf = vec![0u8; self.max_datagram_size];
+        let Some(timeout) = self.reassembly_timeout else {
+            let size = self.recv_datagram(&mut buf)?;
+            return IPCMessage::from_bytes(&buf[..size]);
+        };
+
+        loop {
+            // 期限切れのメッセージを先に報告し、次の期限までだけ受信を待つ
+            let deadline = self.expire_partial(timeout)?;
+            if let Some(wait) = deadline {
+                if !self.wait_readable(wait)? {
+                    continue;
+                }
+            }
+
+            let size = self.recv_datagram(&mut buf)?;
+            if let Some(bytes) = self.reassemble(&buf[..size])? {
+                return IPCMessage::from_bytes(&bytes);
+            }
+        }
+    }
+
+    fn send_datagrams(
+        &self,
+        bytes: &[u8],
+        send: impl Fn(&[u8]) -> std::io::Result<usize>,
+    ) -> Result<()> {
+        if self.reassembly_timeout.is_none() {
+            self.check_size(bytes.len())?;
+            return match send(bytes) {
+                Ok(_) => Ok(()),
+                Err(e) => Err(self.map_send_error(e, bytes.len())),
+            };
+        }
+
+        if bytes.len() > MAX_REASSEMBLED_SIZE {
+            return Err(IPCError::MessageTooLarge {
+                size: bytes.len(),
+                max: MAX_REASSEMBLED_SIZE,
+            });
+        }
+        let chunk_size = self.max_datagram_size.saturating_sub(FRAGMENT_HEADER_LEN);
+        if chunk_size == 0 {
+            return Err(IPCError::MessageTooLarge {
+                size: FRAGMENT_HEADER_LEN + 1,
+                max: self.max_datagram_size,
+            });
+        }
+
+        // メッセージ自身のIDではなく送信ごとに振る（同じメッセージの再送と混ざらない）
+        let message_id = Uuid::new_v4();
+        let count = bytes.len().div_ceil(chunk_size).max(1);
+        for index in 0..count {
+            let offset = index * chunk_size;
+            let end = (offset + chunk_size).min(bytes.len());
+            let datagram = Fragment {
+                message_id,
+                index: index as u32,
+                count: count as u32,
+                offset: offset as u32,
+                total_len: bytes.len() as u32,
+                data: &bytes[offset..end],
+            }
+            .encode();
+            if let Err(e) = send(&datagram) {
+                return Err(self.map_send_error(e, datagram.len()));
+            }
+        }
+        Ok(())
+    }
+
+    /// フラグメントを取り込み、メッセージが揃ったらその内容を返す
+    fn reassemble(&self, datagram: &[u8]) -> Result<Option<Vec<u8>>> {
+        let fragment = Fragment::decode(datagram)?;
+        let total_len = fragment.total_len as usize;
+        if total_len > MAX_REASSEMBLED_SIZE {
+            return Err(IPCError::MessageTooLarge {
+                size: total_len,
+                max: MAX_REASSEMBLED_SIZE,
+            });
+        }
+
+        let mut partial = self.partial.lock().unwrap();
+        let message = partial
+            .entry(fragment.message_id)
+            .or_insert_with(|| PartialMessage {
+                data: vec![0u8; total_len],
+                received: vec![false; fragment.count as usize],
+                remaining: fragment.count as usize,
+                started: Instant::now(),
+            });
+        if message.data.len() != total_len || message.received.len() != fragment.count as usize {
+            partial.remove(&fragment.message_id);
+            return Err(IPCError::protocol(
+                "fragments of one message disagree on its size",
+            ));
+        }
+
+        // 重複したフラグメントは無視する
+        let index = fragment.index as usize;
+        if !message.received[index] {
+            let offset = fragment.offset as usize;
+            message.data[offset..offset + fragment.data.len()].copy_from_slice(fragment.data);
+            message.received[index] = true;
+            message.remaining -= 1;
+        }
+
+        if message.remaining > 0 {
+            return Ok(None);
+        }
+        Ok(partial
+            .remove(&fragment.message_id)
+            .map(|message| message.data))
+    }
+
+    /// 期限切れの組み立て途中のメッセージを1つ破棄してエラーにする
+    ///
+    /// 期限切れがなければ、次に期限を迎えるまでの時間を返す
+    fn expire_partial(&self, timeout: Duration) -> Result<Option<Duration>> {
+        let mut partial = self.partial.lock().unwrap();
+        let expired = partial
+            .iter()
+            .find(|(_, message)| message.started.elapsed() >= timeout)
+            .map(|(id, _)| *id);
+        if let Some(message) = expired.and_then(|id| partial.remove(&id)) {
+            return Err(IPCError::IncompleteMessage {
+                received: message.received.len() - message.remaining,
+                expected: message.received.len(),
+            });
+        }
+
+        Ok(partial
+            .values()
+            .map(|message| timeout.saturating_sub(message.started.elapsed()))
+            .min())
+    }
+
+    /// ソケットが読み込み可能になるまで最大`wait`だけ待つ
+    fn wait_readable(&self, wait: Duration) -> Result<bool> {
+        let mut fd = libc::pollfd {
+            fd: self.socket.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        // 切り捨てると期限の直前で起きてしまうため、ミリ秒に切り上げる
+        let millis = wait
+            .as_micros()
+            .div_ceil(1000)
+            .min(libc::c_int::MAX as u128) as libc::c_int;
+        let ret = unsafe { libc::poll(&mut fd, 1, millis) };
+        if ret >= 0 {
+            return Ok(ret > 0);
+        }
+
+        let err = std::io::Error::last_os_error();
+        if err.kind() == std::io::ErrorKind::Interrupted {
+            return Ok(false);
+        }
+        Err(err.into())
+    }
+
+    /// データグラムを1つ受信し、その長さを返す
+    fn recv_datagram(&self, buf: &mut [u8]) -> Result<usize> {
+        let size = loop {
+            // MSG_TRUNCを付けると切り詰め前の実際の長さが返る
+            let ret = unsafe {
+                libc::recv(
+                    self.socket.as_raw_fd(),
+                    buf.as_mut_ptr() as *mut libc::c_void,
+                    buf.len(),
+                    libc::MSG_TRUNC,
+                )
+            };
+            if ret >= 0 {
+                break ret as usize;
+            }
+
+            let err = std::io::Error::last_os_error();
+            if err.kind() != std::io::ErrorKind::Interrupted {
+                return Err(err.into());
+            }
+        };
+
+        if size > buf.len() {
+            return Err(IPCError::MessageTooLarge {
+                size,
+                max: self.max_datagram_size,
+            });
+        }
+
+        Ok(size)
+    }
+
+    fn check_size(&self, size: usize) -> Result<()> {
+        if size > self.max_datagram_size {
+            return Err(IPCError::MessageTooLarge {
+                size,
+                max: self.max_datagram_size,
+            });
+        }
+        Ok(())
+    }
+
+    /// EMSGSIZE（ソケットの送信バッファを超える）をMessageTooLargeに変換
+    fn map_send_error(&self, err: std::io::Error, size: usize) -> IPCError {
+        if err.raw_os_error() == Some(libc::EMSGSIZE) {
+            IPCError::MessageTooLarge {
+                size,
+                max: self.max_datagram_size,
+            }
+        } else {
+            err.into()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_message_boundaries_are_preserved() {
+        let (a, b) = DatagramChannel::pair().unwrap();
+        let payloads: Vec<Vec<u8>> = vec![
+            b"first".to_vec(),
+            vec![],
+            vec![0xab; 1000],
+            b"last".to_vec(),
+        ];
+
+        for payload in &payloads {
+            a.send(&IPCMessage::notification(payload.clone())).unwrap();
+        }
+        for payload in &payloads {
+            let received = b.recv().unwrap();
+            assert_eq!(&received.payload, payload);
+        }
+    }
+
+    #[test]
+    fn test_send_rejects_oversized_message() {
+        let (a, _b) = DatagramChannel::pair().unwrap();
+        let a = a.with_max_datagram_size(256);
+
+        let err = a.send(&IPCMessage::request(vec![0u8; 512])).unwrap_err();
+        assert!(matches!(err, IPCError::MessageTooLarge { max: 256, .. }));
+        assert!(!err.is_retryable());
+    }
+
+    #[test]
+    fn test_recv_reports_truncated_datagram() {
+        let (a, b) = DatagramChannel::pair().unwrap();
+        let b = b.with_max_datagram_size(128);
+
+        a.send(&IPCMessage::request(vec![0u8; 512])).unwrap();
+        let err = b.recv().unwrap_err();
+        assert!(matches!(err, IPCError::MessageTooLarge { size, max: 128 } if size > 512));
+
+        // 後続のデータグラムには影響しない
+        a.send(&IPCMessage::request(b"ok".to_vec())).unwrap();
+        assert_eq!(b.recv().unwrap().payload, b"ok");
+    }
+
+    fn fragmented_pair(max_datagram_size: usize) -> (DatagramChannel, DatagramChannel) {
+        let (a, b) = DatagramChannel::pair().unwrap();
+        let timeout = Duration::from_millis(200);
+        (
+            a.with_max_datagram_size(max_datagram_size)
+                .with_fragmentation(timeout),
+            b.with_max_datagram_size(max_datagram_size)
+                .with_fragmentation(timeout),
+        )
+    }
+
+    #[test]
+    fn test_large_payload_is_fragmented_and_reassembled() {
+        let (a, b) = fragmented_pair(1024);
+        let large: Vec<u8> = (0..10 * 1024).map(|i| (i % 251) as u8).collect();
+        let payloads = vec![
+            large.clone(),
+            b"small".to_vec(),
+            large.iter().rev().copied().collect(),
+        ];
+
+        // ソケットのバッファが埋まっても詰まらないよう別スレッドから送る
+        let sent = payloads.clone();
+        let sender = std::thread::spawn(move || {
+            for payload in sent {
+                a.send(&IPCMessage::notification(payload)).unwrap();
+            }
+        });
+
+        for payload in &payloads {
+            assert_eq!(&b.recv().unwrap().payload, payload);
+        }
+        sender.join().unwrap();
+        assert!(b.partial.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_missing_fragment_is_reported_after_timeout() {
+        let (a, b) = fragmented_pair(1024);
+        let data = vec![7u8; 3000];
+        let first = Fragment {
+            message_id: Uuid::new_v4(),
+            index: 0,
+            count: 3,
+            offset: 0,
+            total_len: data.len() as u32,
+            data: &data[..900],
+        };
+        a.socket().send(&first.encode()).unwrap();
+
+        let start = Instant::now();
+        let err = b.recv().unwrap_err();
+        assert!(
+            matches!(
+                err,
+                IPCError::IncompleteMessage {
+                    received: 1,
+                    expected: 3
+                }
+            ),
+            "{:?}",
+            err
+        );
+        assert!(start.elapsed() >= Duration::from_millis(200));
+
+        // 破棄した後も後続のメッセージは受け取れる
+        a.send(&IPCMessage::request(b"next".to_vec())).unwrap();
+        assert_eq!(b.recv().unwrap().payload, b"next");
+    }
+}
@@ -0,0 +1,354 @@
+/// 事前共有鍵による暗号化チャネルモジュール
+///
+/// フレーミング済みのチャネルの上で、各メッセージをXChaCha20-Poly1305で
+/// 暗号化・認証する。鍵は両端で事前に（帯域外で）共有しておく。
+/// 192ビットのnonceは接続ごとのランダムなセッションID（128ビット）と
+/// 送信カウンタ（64ビット）から作るため、同じ鍵を多くの接続で使い回しても
+/// nonceが重複することはない。受信側はカウンタが1ずつ増えることを確かめ、
+/// 再送・並べ替え・削除されたフレームを拒否する。
+/// 送信側の役割（`Role`）を追加認証データ（AAD）に含めるため、
+/// 相手が送り返してきた自分のフレームは認証に失敗する
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+use rand::RngCore;
+
+use crate::channel::{ChannelConfig, IPCChannel};
+use crate::errors::{IPCError, Result};
+use crate::ipc::IPCMessage;
+
+/// 共有鍵の長さ（バイト）
+pub const KEY_LEN: usize = 32;
+
+const SESSION_LEN: usize = 16;
+const NONCE_LEN: usize = SESSION_LEN + 8;
+
+/// 接続の中でのこの端の役割
+///
+/// 両端が同じ鍵を使うため、送信方向を区別するのに使う。
+/// 片方が `Initiator`、もう片方が `Responder` でなければならない
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    /// 接続した側
+    Initiator,
+    /// 接続を受け付けた側
+    Responder,
+}
+
+impl Role {
+    /// 相手の役割
+    pub fn peer(self) -> Self {
+        match self {
+            Role::Initiator => Role::Responder,
+            Role::Responder => Role::Initiator,
+        }
+    }
+
+    /// AADとしてフレームに結び付けるバイト
+    fn aad(self) -> [u8; 1] {
+        match self {
+            Role::Initiator => [0],
+            Role::Responder => [1],
+        }
+    }
+}
+
+/// 事前共有鍵
+#[derive(Clone)]
+pub struct SharedKey([u8; KEY_LEN]);
+
+impl SharedKey {
+    /// 32バイトの鍵から作成
+    pub fn from_bytes(bytes: [u8; KEY_LEN]) -> Self {
+        Self(bytes)
+    }
+
+    /// ファイルなどから読んだバイト列から作成（長さが違えばエラー）
+    pub fn from_slice(bytes: &[u8]) -> Result<Self> {
+        let bytes: [u8; KEY_LEN] = bytes.try_into().map_err(|_| {
+            IPCError::Other(format!(
+                "shared key must be {} bytes, got {}",
+                KEY_LEN,
+                bytes.len()
+            ))
+        })?;
+        Ok(Self(bytes))
+    }
+
+    /// OSの乱数生成器で新しい鍵を作成
+    pub fn generate() -> Self {
+        let mut bytes = [0u8; KEY_LEN];
+        rand::rngs::OsRng.fill_bytes(&mut bytes);
+        Self(bytes)
+    }
+
+    /// 鍵のバイト列（相手と共有するため）
+    pub fn as_bytes(&self) -> &[u8; KEY_LEN] {
+        &self.0
+    }
+}
+
+impl std::fmt::Debug for SharedKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // 鍵がログに出ないよう伏せる
+        f.write_str("SharedKey(..)")
+    }
+}
+
+/// 暗号化・認証付きチャネル
+///
+/// 各フレームは `セッションID(16) || カウンタ(8, LE) || 暗号文+タグ` で、
+/// 先頭24バイトがそのままnonceになる
+pub struct EncryptedChannel {
+    channel: IPCChannel,
+    cipher: XChaCha20Poly1305,
+    /// この端の役割（送信フレームのAADになる）
+    role: Role,
+    /// この端が送信に使うセッションID
+    session: [u8; SESSION_LEN],
+    /// 次に送るフレームのカウンタ
+    send_counter: u64,
+    /// 相手のセッションIDと、次に受け取るべきカウンタ
+    peer: Option<([u8; SESSION_LEN], u64)>,
+}
+
+impl EncryptedChannel {
+    /// チャネルを共有鍵で暗号化する
+    ///
+    /// `role` は両端で異なっていなければならない
+    pub fn new(channel: IPCChannel, key: &SharedKey, role: Role) -> Self {
+        let mut session = [0u8; SESSION_LEN];
+        rand::rngs::OsRng.fill_bytes(&mut session);
+
+        Self {
+            channel,
+            cipher: XChaCha20Poly1305::new(Key::from_slice(&key.0)),
+            role,
+            session,
+            send_counter: 0,
+            peer: None,
+        }
+    }
+
+    /// 接続済みのチャネルのペアを作成（テスト・スレッド間通信用）
+    pub fn pair(config: ChannelConfig, key: &SharedKey) -> Result<(Self, Self)> {
+        let (a, b) = IPCChannel::pair(config)?;
+        Ok((
+            Self::new(a, key, Role::Initiator),
+            Self::new(b, key, Role::Responder),
+        ))
+    }
+
+    /// メッセージを暗号化して送信
+    pub fn send(&mut self, message: &IPCMessage) -> Result<()> {
+        let counter = self.send_counter;
+        // カウンタが一周するとnonceが重複するため、それ以上は送らない
+        let next = counter
+            .checked_add(1)
+            .ok_or_else(|| IPCError::protocol("nonce counter exhausted, reconnect"))?;
+
+        let mut nonce = [0u8; NONCE_LEN];
+        nonce[..SESSION_LEN].copy_from_slice(&self.session);
+        nonce[SESSION_LEN..].copy_from_slice(&counter.to_le_bytes());
+
+        let plaintext = message.to_bytes()?;
+        let payload = Payload {
+            msg: &plaintext,
+            aad: &self.role.aad(),
+        };
+        let ciphertext = self
+            .cipher
+            .encrypt(XNonce::from_slice(&nonce), payload)
+            .map_err(|_| IPCError::protocol("encryption failed"))?;
+
+        let mut frame = Vec::with_capacity(NONCE_LEN + ciphertext.len());
+        frame.extend_from_slice(&nonce);
+        frame.extend_from_slice(&ciphertext);
+        self.channel.send_frame(&frame)?;
+        // 送れなかった（WouldBlockなど）フレームのカウンタは次の送信で使い直す
+        self.send_counter = next;
+        Ok(())
+    }
+
+    /// メッセージを受信して復号
+    ///
+    /// 改ざんされたフレームや別の鍵で暗号化されたフレーム、再送・並べ替え
+    /// されたフレーム、この端自身が送ったフレームは `IPCError::Protocol` になる
+    pub fn recv(&mut self) -> Result<IPCMessage> {
+        let frame = self.channel.recv_frame()?;
+        if frame.len() < NONCE_LEN {
+            return Err(IPCError::protocol("encrypted frame too short"));
+        }
+        let (nonce, ciphertext) = frame.split_at(NONCE_LEN);
+
+        // 相手の役割をAADにして復号するので、反射されたフレームは認証に失敗する
+        let payload = Payload {
+            msg: ciphertext,
+            aad: &self.role.peer().aad(),
+        };
+        let plaintext = self
+            .cipher
+            .decrypt(XNonce::from_slice(nonce), payload)
+            .map_err(|_| IPCError::protocol("message authentication failed"))?;
+
+        // 認証に成功したフレームだけでセッションとカウンタを確認する
+        let session: [u8; SESSION_LEN] = nonce[..SESSION_LEN].try_into().unwrap();
+        let counter = u64::from_le_bytes(nonce[SESSION_LEN..].try_into().unwrap());
+        let expected = match self.peer {
+            Some((peer_session, next)) if peer_session == session => next,
+            Some(_) => return Err(IPCError::protocol("peer session changed")),
+            None => 0,
+        };
+        if counter != expected {
+            return Err(IPCError::protocol(format!(
+                "unexpected frame counter {} (expected {}): replayed or reordered",
+                counter, expected
+            )));
+        }
+        self.peer = Some((session, expected + 1));
+
+        IPCMessage::from_bytes(&plaintext)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key() -> SharedKey {
+        SharedKey::from_bytes([7u8; KEY_LEN])
+    }
+
+    /// 送信側の暗号化フレームを生のまま取り出す
+    fn captured_frames(count: usize) -> Vec<Vec<u8>> {
+        let (raw, peer) = IPCChannel::pair(ChannelConfig::default()).unwrap();
+        let mut sender = EncryptedChannel::new(raw, &key(), Role::Initiator);
+        let mut peer = peer;
+        (0..count)
+            .map(|i| {
+                sender
+                    .send(&IPCMessage::request(format!("secret {}", i).into_bytes()))
+                    .unwrap();
+                peer.recv_frame().unwrap()
+            })
+            .collect()
+    }
+
+    /// 生のフレームを暗号化チャネルの受信側に届ける
+    fn deliver(frames: &[Vec<u8>], key: &SharedKey) -> Vec<Result<IPCMessage>> {
+        deliver_as(frames, key, Role::Responder)
+    }
+
+    /// 指定した役割の受信側に生のフレームを届ける
+    fn deliver_as(frames: &[Vec<u8>], key: &SharedKey, role: Role) -> Vec<Result<IPCMessage>> {
+        let (mut raw, peer) = IPCChannel::pair(ChannelConfig::default()).unwrap();
+        let mut receiver = EncryptedChannel::new(peer, key, role);
+        frames
+            .iter()
+            .map(|frame| {
+                raw.send_frame(frame).unwrap();
+                receiver.recv()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_valid_message_round_trips() {
+        let (mut a, mut b) = EncryptedChannel::pair(ChannelConfig::default(), &key()).unwrap();
+
+        a.send(&IPCMessage::request(b"ping".to_vec())).unwrap();
+        let request = b.recv().unwrap();
+        assert_eq!(request.payload, b"ping");
+
+        b.send(&IPCMessage::response(b"pong".to_vec(), request.id))
+            .unwrap();
+        let response = a.recv().unwrap();
+        assert_eq!(response.payload, b"pong");
+        assert_eq!(response.correlation_id, Some(request.id));
+    }
+
+    #[test]
+    fn test_payload_is_not_sent_in_plaintext() {
+        let frames = captured_frames(1);
+        assert!(!frames[0].windows(6).any(|w| w == b"secret"));
+    }
+
+    #[test]
+    fn test_tampered_ciphertext_fails_authentication() {
+        let mut frames = captured_frames(1);
+        let last = frames[0].len() - 1;
+        frames[0][last] ^= 0x01;
+
+        let err = deliver(&frames, &key()).pop().unwrap().unwrap_err();
+        assert!(matches!(err, IPCError::Protocol(_)), "{:?}", err);
+        assert!(err.is_fatal());
+    }
+
+    #[test]
+    fn test_wrong_key_fails_authentication() {
+        let frames = captured_frames(1);
+        let results = deliver(&frames, &SharedKey::generate());
+        assert!(matches!(results[0], Err(IPCError::Protocol(_))));
+    }
+
+    #[test]
+    fn test_replayed_and_reordered_frames_are_rejected() {
+        let frames = captured_frames(2);
+
+        let replayed = deliver(&[frames[0].clone(), frames[0].clone()], &key());
+        assert!(replayed[0].is_ok());
+        assert!(matches!(replayed[1], Err(IPCError::Protocol(_))));
+
+        let reordered = deliver(&[frames[1].clone()], &key());
+        assert!(matches!(reordered[0], Err(IPCError::Protocol(_))));
+    }
+
+    #[test]
+    fn test_reflected_frame_is_rejected() {
+        // Initiatorが送ったフレームをそのままInitiatorに送り返す
+        let frames = captured_frames(1);
+        let reflected = deliver_as(&frames, &key(), Role::Initiator);
+        assert!(matches!(reflected[0], Err(IPCError::Protocol(_))));
+    }
+
+    #[test]
+    fn test_would_block_does_not_skip_a_counter() {
+        use crate::channel::BackpressureMode;
+
+        let config = ChannelConfig {
+            send_window: Some(4096),
+            backpressure: BackpressureMode::Error,
+            ..ChannelConfig::default()
+        };
+        let (mut sender, mut receiver) = EncryptedChannel::pair(config, &key()).unwrap();
+        let payload = vec![0u8; 1024];
+
+        let mut sent = 0;
+        let err = loop {
+            match sender.send(&IPCMessage::request(payload.clone())) {
+                Ok(()) => sent += 1,
+                Err(e) => break e,
+            }
+            assert!(sent < 10_000, "window never filled");
+        };
+        assert!(matches!(err, IPCError::WouldBlock));
+
+        // 最後に受理されたフレームは部分送信の可能性があるので、その前まで読む
+        for _ in 0..sent - 1 {
+            receiver.recv().unwrap();
+        }
+        // 次の送信で残りが書き出され、カウンタの続きで受理される
+        sender
+            .send(&IPCMessage::request(b"after".to_vec()))
+            .unwrap();
+        receiver.recv().unwrap();
+        assert_eq!(receiver.recv().unwrap().payload, b"after");
+    }
+
+    #[test]
+    fn test_key_length_is_checked() {
+        assert!(SharedKey::from_slice(&[0u8; 16]).is_err());
+        let key = SharedKey::from_slice(&[1u8; KEY_LEN]).unwrap();
+        assert_eq!(key.as_bytes(), &[1u8; KEY_LEN]);
+        assert_eq!(format!("{:?}", key), "SharedKey(..)");
+    }
+}
@@ -23,6 +23,22 @@ pub enum IPCError {
     #[error("connection error: {0}")]
     Connection(String),
     
+    /// 送信ウィンドウが埋まっており、相手がまだ読み出していない
+    #[error("operation would block: peer is not draining")]
+    WouldBlock,
+    
+    /// データグラムが最大サイズを超えている
+    #[error("message too large: {size} bytes (max {max} bytes)")]
+    MessageTooLarge { size: usize, max: usize },
+    
+    /// 分割されたメッセージのフラグメントが期限内に揃わなかった
+    #[error("incomplete message: received {received} of {expected} fragments before the reassembly timeout")]
+    IncompleteMessage { received: usize, expected: usize },
+    
+    /// 名前付きチャネルが登録されていない（サーバー未起動）
+    #[error("no channel registered under name '{name}'")]
+    NotRegistered { name: String },
+    
     /// その他のエラー
     #[error("{0}")]
     Other(String),
@@ -50,8 +66,16 @@ impl IPCError {
                 | std::io::ErrorKind::InvalidInput
             ),
             Self::Connection(_) => true,
+            // 相手が読み出せば送信できる
+            Self::WouldBlock => true,
             // プロトコルエラーとシリアライゼーションエラーは再試行不可
             Self::Protocol(_) | Self::Serialization(_) => false,
+            // 同じメッセージを送り直しても収まらない
+            Self::MessageTooLarge { .. } => false,
+            // 失われたフラグメントは再送されない
+            Self::IncompleteMessage { .. } => false,
+            // サーバーが後から起動すれば接続できる
+            Self::NotRegistered { .. } => true,
             Self::Other(_) => false,
         }
     }
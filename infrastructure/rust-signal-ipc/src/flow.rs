@@ -0,0 +1,262 @@
+/// クレジットベースのフロー制御モジュール
+///
+/// 受信側が送信側に「送ってよいメッセージ数（クレジット）」を付与し、
+/// 送信側はクレジットが尽きると付与を待つ。受信側はアプリケーションが
+/// メッセージを受け取るたびにクレジットを補充するため、相手の処理が
+/// 遅くても未処理のメッセージはウィンドウサイズを超えて溜まらない。
+/// クレジット待ちの間も送信側はソケットを読み続けるので、双方向に
+/// 送り合ってもカーネルバッファが埋まって互いに書き込みで止まることはない
+use std::collections::VecDeque;
+
+use crate::channel::{ChannelConfig, IPCChannel};
+use crate::errors::{IPCError, Result};
+use crate::ipc::{IPCMessage, MessageType};
+
+/// 初期ウィンドウサイズのデフォルト（メッセージ数）
+pub const DEFAULT_INITIAL_WINDOW: u32 = 64;
+
+/// フロー制御の設定
+#[derive(Debug, Clone)]
+pub struct FlowControlConfig {
+    /// 接続時に相手へ付与するクレジット数（未処理メッセージの上限）
+    pub initial_window: u32,
+}
+
+impl Default for FlowControlConfig {
+    fn default() -> Self {
+        Self {
+            initial_window: DEFAULT_INITIAL_WINDOW,
+        }
+    }
+}
+
+/// クレジットベースのフロー制御付きチャネル
+///
+/// 両端をこの型で包んで使う。どちらの端も送信側かつ受信側になれる
+pub struct FlowControlledChannel {
+    channel: IPCChannel,
+    initial_window: u32,
+    /// 相手から付与された、まだ使っていないクレジット
+    send_credits: u32,
+    /// 相手に付与済みで、まだ届いていないメッセージ数
+    granted: u32,
+    /// 受け取り済みだが、まだクレジットとして返していないメッセージ数
+    consumed: u32,
+    /// クレジット待ちの間に届いたメッセージ
+    inbox: VecDeque<IPCMessage>,
+}
+
+impl FlowControlledChannel {
+    /// チャネルをフロー制御付きにし、相手に初期クレジットを付与する
+    pub fn new(channel: IPCChannel, config: FlowControlConfig) -> Result<Self> {
+        if config.initial_window == 0 {
+            return Err(IPCError::Other(
+                "initial window must be at least 1 message".into(),
+            ));
+        }
+
+        let mut flow = Self {
+            channel,
+            initial_window: config.initial_window,
+            send_credits: 0,
+            granted: 0,
+            consumed: 0,
+            inbox: VecDeque::new(),
+        };
+        flow.grant(config.initial_window)?;
+        Ok(flow)
+    }
+
+    /// 接続済みのチャネルのペアを作成（テスト・スレッド間通信用）
+    pub fn pair(channel_config: ChannelConfig, config: FlowControlConfig) -> Result<(Self, Self)> {
+        let (a, b) = IPCChannel::pair(channel_config)?;
+        Ok((Self::new(a, config.clone())?, Self::new(b, config)?))
+    }
+
+    /// 相手に付与した初期ウィンドウサイズ
+    pub fn initial_window(&self) -> u32 {
+        self.initial_window
+    }
+
+    /// 現在送信に使えるクレジット数
+    pub fn credits(&self) -> u32 {
+        self.send_credits
+    }
+
+    /// メッセージを送信
+    ///
+    /// クレジットが尽きている場合は、相手が受け取ってクレジットを
+    /// 返すまでブロックする
+    pub fn send(&mut self, message: &IPCMessage) -> Result<()> {
+        if message.message_type == MessageType::Credit {
+            return Err(IPCError::protocol(
+                "credit messages are managed by the channel",
+            ));
+        }
+
+        while self.send_credits == 0 {
+            if let Some(message) = self.read_frame()? {
+                self.inbox.push_back(message);
+            }
+        }
+
+        self.channel.send(message)?;
+        self.send_credits -= 1;
+        Ok(())
+    }
+
+    /// メッセージを受信
+    ///
+    /// 受け取ったメッセージの分だけ、ウィンドウの半分ごとにまとめて
+    /// 相手にクレジットを返す
+    pub fn recv(&mut self) -> Result<IPCMessage> {
+        let message = match self.inbox.pop_front() {
+            Some(message) => message,
+            None => loop {
+                if let Some(message) = self.read_frame()? {
+                    break message;
+                }
+            },
+        };
+
+        self.consumed += 1;
+        if self.consumed >= (self.initial_window / 2).max(1) {
+            let credits = std::mem::take(&mut self.consumed);
+            match self.grant(credits) {
+                // 送り終えて切断した相手には返す必要がない（次のrecvがEOFを報告する）
+                Err(IPCError::Io(e))
+                    if matches!(
+                        e.kind(),
+                        std::io::ErrorKind::BrokenPipe | std::io::ErrorKind::ConnectionReset
+                    ) => {}
+                result => result?,
+            }
+        }
+        Ok(message)
+    }
+
+    /// 1フレーム読み、クレジットなら反映して`None`、通常のメッセージなら返す
+    fn read_frame(&mut self) -> Result<Option<IPCMessage>> {
+        let message = self.channel.recv()?;
+
+        if message.message_type == MessageType::Credit {
+            let bytes: [u8; 4] = message
+                .payload
+                .as_slice()
+                .try_into()
+                .map_err(|_| IPCError::protocol("malformed credit message"))?;
+            self.send_credits = self.send_credits.saturating_add(u32::from_le_bytes(bytes));
+            return Ok(None);
+        }
+
+        // 付与した以上に送ってくる相手はバッファを溢れさせうる
+        if self.granted == 0 {
+            return Err(IPCError::protocol("peer sent a message without credit"));
+        }
+        self.granted -= 1;
+        Ok(Some(message))
+    }
+
+    fn grant(&mut self, credits: u32) -> Result<()> {
+        self.channel.send(&IPCMessage::credit(credits))?;
+        self.granted += credits;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Duration;
+
+    fn window(initial_window: u32) -> FlowControlConfig {
+        FlowControlConfig { initial_window }
+    }
+
+    #[test]
+    fn test_slow_receiver_throttles_sender() {
+        let (mut sender, mut receiver) =
+            FlowControlledChannel::pair(ChannelConfig::default(), window(4)).unwrap();
+        let total = 20;
+        let sent = Arc::new(AtomicUsize::new(0));
+
+        let sent_clone = sent.clone();
+        let handle = thread::spawn(move || {
+            for i in 0..total {
+                sender
+                    .send(&IPCMessage::request(vec![i as u8; 1024]))
+                    .unwrap();
+                sent_clone.fetch_add(1, Ordering::SeqCst);
+            }
+        });
+
+        // 受信側が読まない間、送信側はクレジットを使い切って止まる
+        thread::sleep(Duration::from_millis(200));
+        assert_eq!(sent.load(Ordering::SeqCst), 4);
+
+        for i in 0..total {
+            let message = receiver.recv().unwrap();
+            assert_eq!(message.payload[0], i as u8);
+            // 未処理のメッセージは常にウィンドウ以内
+            thread::sleep(Duration::from_millis(10));
+            assert!(sent.load(Ordering::SeqCst) <= i + 1 + 4);
+        }
+        handle.join().unwrap();
+        assert_eq!(sent.load(Ordering::SeqCst), total);
+    }
+
+    #[test]
+    fn test_initial_window_is_granted_to_peer() {
+        let (mut a, mut b) =
+            FlowControlledChannel::pair(ChannelConfig::default(), window(8)).unwrap();
+        assert_eq!(a.initial_window(), 8);
+        assert_eq!(a.credits(), 0);
+
+        // 最初の送信で相手からの初期クレジットを受け取る
+        a.send(&IPCMessage::notification(b"hello".to_vec()))
+            .unwrap();
+        assert_eq!(a.credits(), 7);
+        assert_eq!(b.recv().unwrap().payload, b"hello");
+    }
+
+    #[test]
+    fn test_both_sides_can_send_without_deadlock() {
+        let (mut a, mut b) =
+            FlowControlledChannel::pair(ChannelConfig::default(), window(2)).unwrap();
+
+        // 双方がクレジットを使い切るまで送ってから受け取る
+        let handle = thread::spawn(move || {
+            for _ in 0..10 {
+                b.send(&IPCMessage::request(vec![0u8; 64 * 1024])).unwrap();
+                b.recv().unwrap();
+            }
+        });
+        for _ in 0..10 {
+            a.send(&IPCMessage::request(vec![0u8; 64 * 1024])).unwrap();
+            a.recv().unwrap();
+        }
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn test_message_without_credit_is_rejected() {
+        let (mut raw, peer) = IPCChannel::pair(ChannelConfig::default()).unwrap();
+        let mut flow = FlowControlledChannel::new(peer, window(2)).unwrap();
+
+        // フロー制御を無視してウィンドウを超えて送る
+        for payload in [b"1", b"2", b"3"] {
+            raw.send(&IPCMessage::request(payload.to_vec())).unwrap();
+        }
+        raw.send(&IPCMessage::credit(1)).unwrap();
+
+        // クレジット待ちの間に届いた3通目が付与数を超える
+        let err = flow
+            .send(&IPCMessage::request(b"reply".to_vec()))
+            .unwrap_err();
+        assert!(matches!(err, IPCError::Protocol(_)));
+        assert!(err.is_fatal());
+    }
+}
@@ -3,7 +3,11 @@
 /// UUIDとcorrelation_idを含む実践的なIPCプロトコル
 
 use serde::{Serialize, Deserialize};
+use std::os::unix::fs::{DirBuilderExt, FileTypeExt};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
 use uuid::Uuid;
+use crate::channel::{ChannelConfig, IPCChannel};
 use crate::errors::{IPCError, Result};
 
 /// IPCメッセージの種類
@@ -14,6 +18,8 @@ pub enum MessageType {
     Notification,
     Heartbeat,
     Error,
+    /// フロー制御：受信側から送信側へのクレジット付与
+    Credit,
 }
 
 /// IPCメッセージ（UUID付き）
@@ -68,6 +74,11 @@ impl IPCMessage {
         Self::new(MessageType::Heartbeat, vec![])
     }
     
+    /// クレジット付与メッセージを作成（ペイロードは付与数のu32 LE）
+    pub fn credit(credits: u32) -> Self {
+        Self::new(MessageType::Credit, credits.to_le_bytes().to_vec())
+    }
+    
     /// エラーメッセージを作成
     pub fn error(error_msg: String, correlation_id: Option<Uuid>) -> Self {
         let mut msg = Self::new(MessageType::Error, error_msg.into_bytes());
@@ -114,10 +125,213 @@ impl IPCMessage {
     }
 }
 
+/// 名前付きチャネルのレジストリ
+///
+/// `<runtime_dir>/<name>.sock` という規約でソケットパスを決め、
+/// サーバーは名前で登録、クライアントは名前で接続できるようにする
+#[derive(Debug, Clone)]
+pub struct ChannelRegistry {
+    runtime_dir: PathBuf,
+}
+
+impl Default for ChannelRegistry {
+    /// `$XDG_RUNTIME_DIR/rust-signal-ipc`（未設定なら一時ディレクトリ配下）を使う
+    fn default() -> Self {
+        let base = std::env::var_os("XDG_RUNTIME_DIR")
+            .map(PathBuf::from)
+            .unwrap_or_else(std::env::temp_dir);
+        Self::new(base.join("rust-signal-ipc"))
+    }
+}
+
+impl ChannelRegistry {
+    /// ソケットを置くディレクトリを指定してレジストリを作成
+    pub fn new<P: Into<PathBuf>>(runtime_dir: P) -> Self {
+        Self {
+            runtime_dir: runtime_dir.into(),
+        }
+    }
+
+    /// ソケットを置くディレクトリ
+    pub fn runtime_dir(&self) -> &Path {
+        &self.runtime_dir
+    }
+
+    /// 名前に対応するソケットパス
+    pub fn socket_path(&self, name: &str) -> Result<PathBuf> {
+        validate_name(name)?;
+        Ok(self.runtime_dir.join(format!("{}.sock", name)))
+    }
+
+    /// 名前でサーバーを登録してソケットをバインド
+    ///
+    /// 前回のプロセスが残した古いソケットファイルは削除してから再利用する。
+    /// 同じ名前で稼働中のサーバーがいる場合はエラー
+    pub fn register(&self, name: &str) -> Result<RegisteredServer> {
+        let path = self.socket_path(name)?;
+        std::fs::DirBuilder::new()
+            .recursive(true)
+            .mode(0o700)
+            .create(&self.runtime_dir)?;
+
+        if let Ok(metadata) = std::fs::symlink_metadata(&path) {
+            if !metadata.file_type().is_socket() {
+                return Err(IPCError::connection(format!(
+                    "{} exists and is not a socket",
+                    path.display()
+                )));
+            }
+            // 接続できれば稼働中、拒否されれば持ち主のいない古いソケット
+            if UnixStream::connect(&path).is_ok() {
+                return Err(IPCError::connection(format!(
+                    "channel '{}' is already registered",
+                    name
+                )));
+            }
+            std::fs::remove_file(&path)?;
+        }
+
+        let listener = UnixListener::bind(&path)?;
+        Ok(RegisteredServer { listener, path })
+    }
+
+    /// 名前で登録されたサーバーに接続
+    pub fn connect_by_name(&self, name: &str, config: ChannelConfig) -> Result<IPCChannel> {
+        let path = self.socket_path(name)?;
+        match UnixStream::connect(&path) {
+            Ok(stream) => IPCChannel::new(stream, config),
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    std::io::ErrorKind::NotFound | std::io::ErrorKind::ConnectionRefused
+                ) =>
+            {
+                Err(IPCError::NotRegistered {
+                    name: name.to_string(),
+                })
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+/// デフォルトのレジストリで名前を登録
+pub fn register(name: &str) -> Result<RegisteredServer> {
+    ChannelRegistry::default().register(name)
+}
+
+/// デフォルトのレジストリで名前を指定して接続
+pub fn connect_by_name(name: &str) -> Result<IPCChannel> {
+    ChannelRegistry::default().connect_by_name(name, ChannelConfig::default())
+}
+
+/// 登録済みのサーバーソケット。ドロップ時にソケットファイルを削除する
+#[derive(Debug)]
+pub struct RegisteredServer {
+    listener: UnixListener,
+    path: PathBuf,
+}
+
+impl RegisteredServer {
+    /// クライアントの接続を1つ受け付ける
+    pub fn accept(&self, config: ChannelConfig) -> Result<IPCChannel> {
+        let (stream, _) = self.listener.accept()?;
+        IPCChannel::new(stream, config)
+    }
+
+    /// 内部のリスナーへの参照
+    pub fn listener(&self) -> &UnixListener {
+        &self.listener
+    }
+
+    /// バインドしたソケットパス
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for RegisteredServer {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// パス区切りや隠しファイルにならない名前だけを受け付ける
+fn validate_name(name: &str) -> Result<()> {
+    let valid = !name.is_empty()
+        && !name.starts_with('.')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if !valid {
+        return Err(IPCError::protocol(format!("invalid channel name: {:?}", name)));
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     
+    fn test_registry() -> ChannelRegistry {
+        static COUNTER: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+        let n = COUNTER.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        ChannelRegistry::new(
+            std::env::temp_dir().join(format!("rust-signal-ipc-test-{}-{}", std::process::id(), n)),
+        )
+    }
+
+    #[test]
+    fn test_register_and_connect_by_name() {
+        let registry = test_registry();
+        let server = registry.register("echo").unwrap();
+        assert_eq!(server.path(), registry.socket_path("echo").unwrap());
+
+        let mut client = registry.connect_by_name("echo", ChannelConfig::default()).unwrap();
+        let mut accepted = server.accept(ChannelConfig::default()).unwrap();
+
+        client.send(&IPCMessage::request(b"ping".to_vec())).unwrap();
+        assert_eq!(accepted.recv().unwrap().payload, b"ping");
+
+        // 稼働中の名前は二重登録できない
+        assert!(registry.register("echo").is_err());
+
+        drop(server);
+        let _ = std::fs::remove_dir(registry.runtime_dir());
+    }
+
+    #[test]
+    fn test_connect_to_unregistered_name() {
+        let registry = test_registry();
+        let err = registry
+            .connect_by_name("missing", ChannelConfig::default())
+            .err()
+            .unwrap();
+        assert!(matches!(err, IPCError::NotRegistered { ref name } if name == "missing"));
+        assert!(registry.socket_path("../escape").is_err());
+    }
+
+    #[test]
+    fn test_register_replaces_stale_socket() {
+        let registry = test_registry();
+        let path = registry.socket_path("stale").unwrap();
+        std::fs::create_dir_all(registry.runtime_dir()).unwrap();
+        // リスナーを閉じてもソケットファイルは残る（クラッシュしたサーバー相当）
+        drop(UnixListener::bind(&path).unwrap());
+        assert!(path.exists());
+
+        assert!(matches!(
+            registry.connect_by_name("stale", ChannelConfig::default()),
+            Err(IPCError::NotRegistered { .. })
+        ));
+        let server = registry.register("stale").unwrap();
+        assert!(registry.connect_by_name("stale", ChannelConfig::default()).is_ok());
+
+        drop(server);
+        assert!(!path.exists());
+        let _ = std::fs::remove_dir(registry.runtime_dir());
+    }
+
     #[test]
     fn test_message_creation() {
         let msg = IPCMessage::request(b"test".to_vec());
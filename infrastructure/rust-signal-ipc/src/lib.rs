@@ -5,6 +5,13 @@
 
 pub mod errors;
 pub mod ipc;
+pub mod channel;
+pub mod datagram;
+pub mod flow;
+#[cfg(feature = "encryption")]
+pub mod encrypted;
+#[cfg(feature = "ipc-trace")]
+mod trace;
 pub mod examples;
 
 pub use errors::{IPCError, Result};
\ No newline at end of file
@@ -0,0 +1,87 @@
+//! IPCトラフィックのトレースモジュール
+//!
+//! `ipc-trace` フィーチャー有効時のみコンパイルされ、チャネルの送受信を
+//! debugレベルで記録する。ペイロードはデフォルトで伏せ字にし、
+//! `ChannelConfig::trace_payload` が有効なときだけhexdumpを出力する
+
+use std::fmt::Write;
+
+/// チャネルごとのフレームカウンタ
+#[derive(Debug, Default)]
+pub(crate) struct TraceState {
+    frames_sent: u64,
+    frames_received: u64,
+}
+
+impl TraceState {
+    /// 送信したフレームを記録
+    pub(crate) fn on_send(&mut self, bytes: &[u8], hexdump: bool) {
+        self.frames_sent += 1;
+        if hexdump {
+            tracing::debug!(
+                target: "rust_signal_ipc::channel",
+                direction = "send",
+                size = bytes.len(),
+                frame = self.frames_sent,
+                payload = %hex(bytes),
+                "ipc frame"
+            );
+        } else {
+            tracing::debug!(
+                target: "rust_signal_ipc::channel",
+                direction = "send",
+                size = bytes.len(),
+                frame = self.frames_sent,
+                payload = "<redacted>",
+                "ipc frame"
+            );
+        }
+    }
+
+    /// 受信したフレームを記録
+    pub(crate) fn on_recv(&mut self, bytes: &[u8], hexdump: bool) {
+        self.frames_received += 1;
+        if hexdump {
+            tracing::debug!(
+                target: "rust_signal_ipc::channel",
+                direction = "recv",
+                size = bytes.len(),
+                frame = self.frames_received,
+                payload = %hex(bytes),
+                "ipc frame"
+            );
+        } else {
+            tracing::debug!(
+                target: "rust_signal_ipc::channel",
+                direction = "recv",
+                size = bytes.len(),
+                frame = self.frames_received,
+                payload = "<redacted>",
+                "ipc frame"
+            );
+        }
+    }
+}
+
+/// バイト列を空白区切りの16進文字列に変換
+fn hex(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len() * 3);
+    for (i, b) in bytes.iter().enumerate() {
+        if i > 0 {
+            out.push(' ');
+        }
+        let _ = write!(out, "{:02x}", b);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hex() {
+        assert_eq!(hex(&[0x00, 0xab, 0x10]), "00 ab 10");
+        assert_eq!(hex(&[]), "");
+    }
+}
@@ -1,36 +1,129 @@
-use crate::test::TestResult;
+use anyhow::Result;
+
+use crate::clipboard;
+use crate::test::{normalize_tag, TestCase, TestResult};
+use crate::theme::Theme;
 
 pub struct App {
     pub test_results: Vec<TestResult>,
     pub selected_test: usize,
     pub tab_index: usize,
     pub show_help: bool,
+    pub theme: Theme,
+    pub status_message: Option<String>,
+    // 再実行用のテスト定義（test_resultsと同じ順序）
+    pub test_cases: Vec<TestCase>,
+    // 再実行中のテストのインデックス
+    pub running: Option<usize>,
+    // 一覧に表示するテストのタグ（Noneなら全件表示）
+    pub tag_filter: Option<String>,
+    // コピーした内容を保持するため、アプリの終了まで開いたままにする
+    clipboard: clipboard::SystemClipboard,
 }
 
 impl App {
-    pub fn new(test_results: Vec<TestResult>) -> Self {
+    pub fn new(test_results: Vec<TestResult>, theme: Theme) -> Self {
         App {
             test_results,
             selected_test: 0,
             tab_index: 0,
             show_help: false,
+            theme,
+            status_message: None,
+            test_cases: Vec::new(),
+            running: None,
+            tag_filter: None,
+            clipboard: clipboard::SystemClipboard::default(),
         }
     }
 
+    pub fn with_test_cases(mut self, test_cases: Vec<TestCase>) -> Self {
+        self.test_cases = test_cases;
+        self
+    }
+
+    // タグの絞り込みに一致する（一覧に表示される）テストかどうか
+    pub fn is_visible(&self, index: usize) -> bool {
+        match &self.tag_filter {
+            None => true,
+            Some(tag) => self.test_cases.get(index).is_some_and(|test| test.has_tag(tag)),
+        }
+    }
+
+    // 一覧に表示するテストのインデックス
+    pub fn visible_tests(&self) -> Vec<usize> {
+        (0..self.test_results.len()).filter(|&i| self.is_visible(i)).collect()
+    }
+
+    // 表示中のテストだけを順に移動する
     pub fn next(&mut self) {
-        if !self.test_results.is_empty() {
-            self.selected_test = (self.selected_test + 1) % self.test_results.len();
+        let visible = self.visible_tests();
+        if visible.is_empty() {
+            return;
         }
+        self.selected_test = match visible.iter().position(|&i| i == self.selected_test) {
+            Some(pos) => visible[(pos + 1) % visible.len()],
+            None => visible[0],
+        };
     }
 
     pub fn previous(&mut self) {
-        if !self.test_results.is_empty() {
-            self.selected_test = if self.selected_test > 0 {
-                self.selected_test - 1
-            } else {
-                self.test_results.len() - 1
-            };
+        let visible = self.visible_tests();
+        if visible.is_empty() {
+            return;
         }
+        self.selected_test = match visible.iter().position(|&i| i == self.selected_test) {
+            Some(0) | None => visible[visible.len() - 1],
+            Some(pos) => visible[pos - 1],
+        };
+    }
+
+    // テストに付いているタグの一覧（重複なし、"@"を除いた名前でソート済み）
+    pub fn available_tags(&self) -> Vec<String> {
+        let mut tags: Vec<String> = self
+            .test_cases
+            .iter()
+            .flat_map(|test| test.tags.iter().flatten())
+            .map(|tag| normalize_tag(tag).to_string())
+            .collect();
+        tags.sort();
+        tags.dedup();
+        tags
+    }
+
+    // 絞り込むタグを次に切り替える。最後のタグの次は全件表示に戻る
+    pub fn cycle_tag_filter(&mut self) {
+        let tags = self.available_tags();
+        if tags.is_empty() {
+            self.status_message = Some("No tags defined".to_string());
+            return;
+        }
+
+        let next = match &self.tag_filter {
+            None => Some(0),
+            Some(current) => tags
+                .iter()
+                .position(|tag| tag == current)
+                .map(|pos| pos + 1)
+                .filter(|&pos| pos < tags.len()),
+        };
+        self.tag_filter = next.map(|pos| tags[pos].clone());
+
+        // 選択中のテストが隠れたら、表示中の先頭を選び直す
+        if !self.is_visible(self.selected_test) {
+            if let Some(&first) = self.visible_tests().first() {
+                self.selected_test = first;
+            }
+        }
+
+        self.status_message = Some(match &self.tag_filter {
+            Some(tag) => format!(
+                "Showing {} tests tagged @{}",
+                self.visible_tests().len(),
+                tag
+            ),
+            None => "Showing all tests".to_string(),
+        });
     }
 
     pub fn next_tab(&mut self) {
@@ -49,6 +142,61 @@ impl App {
         self.show_help = !self.show_help;
     }
 
+    // 選択中のテストのコマンドとdiffをクリップボードにコピーする
+    pub fn copy_selected(&mut self) {
+        let Some(result) = self.test_results.get(self.selected_test) else {
+            self.status_message = Some("No test selected".to_string());
+            return;
+        };
+
+        let payload = clipboard::copy_payload(result);
+        self.status_message = Some(match self.clipboard.copy(&payload) {
+            Ok(()) => format!("Copied \"{}\" to clipboard", result.name),
+            Err(e) => format!("Clipboard unavailable: {:#}", e),
+        });
+    }
+
+    // 選択中のテストの再実行を開始する。実行すべきテストのインデックスを返す
+    pub fn begin_rerun(&mut self) -> Option<usize> {
+        let index = self.selected_test;
+        let Some(test) = self.test_cases.get(index) else {
+            self.status_message = Some("No test selected".to_string());
+            return None;
+        };
+
+        self.status_message = Some(format!("Running \"{}\"…", test.name));
+        self.running = Some(index);
+        Some(index)
+    }
+
+    // 再実行の結果をその位置の結果と置き換える。
+    // 同名のテストがあってもインデックスで対応付けるので、その1件だけが更新される
+    pub fn finish_rerun(&mut self, index: usize, outcome: Result<TestResult>) {
+        self.running = None;
+        let Some(slot) = self.test_results.get_mut(index) else {
+            return;
+        };
+
+        let result = outcome.unwrap_or_else(|e| TestResult {
+            name: slot.name.clone(),
+            command_line: slot.command_line.clone(),
+            success: false,
+            actual_output: String::new(),
+            diff: None,
+            forbidden_matches: Vec::new(),
+            error: Some(format!("{:#}", e)),
+            skipped: None,
+        });
+        let verdict = match (result.success, &result.error, &result.skipped) {
+            (true, _, _) => "passed",
+            (false, Some(_), _) => "errored",
+            (false, None, Some(_)) => "skipped",
+            (false, None, None) => "failed",
+        };
+        self.status_message = Some(format!("Re-ran \"{}\": {}", result.name, verdict));
+        *slot = result;
+    }
+
     pub fn get_stats(&self) -> (usize, usize, f64) {
         let total = self.test_results.len();
         let passed = self.test_results.iter().filter(|r| r.success).count();
@@ -60,4 +208,135 @@ impl App {
         
         (passed, total, pass_rate)
     }
-} 
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn case(name: &str, args: &[&str]) -> TestCase {
+        tagged(name, args, &[])
+    }
+
+    fn tagged(name: &str, args: &[&str], tags: &[&str]) -> TestCase {
+        TestCase {
+            name: name.to_string(),
+            command: "echo".to_string(),
+            args: Some(args.iter().map(|a| a.to_string()).collect()),
+            expected_output: Some("ok".to_string()),
+            tags: Some(tags.iter().map(|t| t.to_string()).collect()),
+            ..Default::default()
+        }
+    }
+
+    fn result(test: &TestCase, success: bool) -> TestResult {
+        TestResult {
+            name: test.name.clone(),
+            command_line: test.command_line(),
+            success,
+            actual_output: String::new(),
+            diff: None,
+            forbidden_matches: Vec::new(),
+            error: None,
+            skipped: None,
+        }
+    }
+
+    fn app() -> App {
+        // 同じ名前で引数だけ違うテスト（マトリクスの各インスタンス）
+        let cases = vec![case("echo", &["a"]), case("echo", &["b"]), case("other", &[])];
+        let results = cases.iter().map(|c| result(c, false)).collect();
+        App::new(results, Theme::default()).with_test_cases(cases)
+    }
+
+    #[test]
+    fn test_rerun_replaces_only_selected_instance() {
+        let mut app = app();
+        app.selected_test = 1;
+
+        let index = app.begin_rerun().unwrap();
+        assert_eq!(app.running, Some(1));
+        assert!(app.status_message.as_deref().unwrap().starts_with("Running"));
+
+        let rerun = result(&app.test_cases[index], true);
+        app.finish_rerun(index, Ok(rerun));
+
+        assert_eq!(app.running, None);
+        assert!(!app.test_results[0].success);
+        assert!(app.test_results[1].success);
+        assert_eq!(app.test_results[1].command_line, "echo b");
+        assert!(!app.test_results[2].success);
+        assert_eq!(app.selected_test, 1);
+        assert_eq!(app.status_message.as_deref(), Some("Re-ran \"echo\": passed"));
+    }
+
+    #[test]
+    fn test_rerun_error_is_recorded_in_place() {
+        let mut app = app();
+        app.selected_test = 2;
+        app.test_results[2].success = true;
+
+        let index = app.begin_rerun().unwrap();
+        app.finish_rerun(index, Err(anyhow::anyhow!("Command timed out: other")));
+
+        let updated = &app.test_results[2];
+        assert!(!updated.success);
+        assert_eq!(updated.name, "other");
+        assert_eq!(updated.error.as_deref(), Some("Command timed out: other"));
+        assert_eq!(app.status_message.as_deref(), Some("Re-ran \"other\": errored"));
+    }
+
+    #[test]
+    fn test_rerun_without_test_cases_is_a_no_op() {
+        let mut app = App::new(Vec::new(), Theme::default());
+        assert_eq!(app.begin_rerun(), None);
+        assert_eq!(app.running, None);
+    }
+
+    #[test]
+    fn test_tag_filter_cycles_and_limits_navigation() {
+        let cases = vec![
+            tagged("a", &[], &["@fast"]),
+            tagged("b", &[], &["slow"]),
+            tagged("c", &[], &["fast", "@slow"]),
+            case("d", &[]),
+        ];
+        let results = cases.iter().map(|c| result(c, true)).collect();
+        let mut app = App::new(results, Theme::default()).with_test_cases(cases);
+        app.selected_test = 1;
+
+        assert_eq!(app.available_tags(), ["fast", "slow"]);
+
+        // "fast" で絞り込むと選択中の "b" が隠れるので先頭の表示中テストを選ぶ
+        app.cycle_tag_filter();
+        assert_eq!(app.tag_filter.as_deref(), Some("fast"));
+        assert_eq!(app.visible_tests(), [0, 2]);
+        assert_eq!(app.selected_test, 0);
+        assert_eq!(app.status_message.as_deref(), Some("Showing 2 tests tagged @fast"));
+
+        app.next();
+        assert_eq!(app.selected_test, 2);
+        app.next();
+        assert_eq!(app.selected_test, 0);
+        app.previous();
+        assert_eq!(app.selected_test, 2);
+
+        app.cycle_tag_filter();
+        assert_eq!(app.tag_filter.as_deref(), Some("slow"));
+        assert_eq!(app.visible_tests(), [1, 2]);
+        assert_eq!(app.selected_test, 2);
+
+        app.cycle_tag_filter();
+        assert_eq!(app.tag_filter, None);
+        assert_eq!(app.visible_tests(), [0, 1, 2, 3]);
+        assert_eq!(app.status_message.as_deref(), Some("Showing all tests"));
+    }
+
+    #[test]
+    fn test_tag_filter_without_tags() {
+        let mut app = app();
+        app.cycle_tag_filter();
+        assert_eq!(app.tag_filter, None);
+        assert_eq!(app.status_message.as_deref(), Some("No tags defined"));
+    }
+}
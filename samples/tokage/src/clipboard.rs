@@ -0,0 +1,122 @@
+use crate::test::TestResult;
+use anyhow::{Context, Result};
+use similar::ChangeTag;
+
+// バグ報告に貼り付けるためのテキストを組み立てる（コマンド + diff）
+pub fn copy_payload(result: &TestResult) -> String {
+    let status = match (result.success, &result.error, &result.skipped) {
+        (true, _, _) => "PASS",
+        (false, Some(_), _) => "ERROR",
+        (false, None, Some(_)) => "SKIP",
+        (false, None, None) => "FAIL",
+    };
+    let mut payload = format!("Test: {} [{}]\nCommand: {}\n", result.name, status, result.command_line);
+
+    if let Some(error) = &result.error {
+        payload.push_str(&format!("Error: {}\n", error));
+    }
+
+    if let Some(reason) = &result.skipped {
+        payload.push_str(&format!("Skipped: {}\n", reason));
+    }
+
+    for text in &result.forbidden_matches {
+        payload.push_str(&format!("Forbidden output: {:?}\n", text));
+    }
+
+    match &result.diff {
+        Some(diff) => {
+            payload.push_str("--- expected\n+++ actual\n");
+            for line in diff {
+                let prefix = match line.tag {
+                    ChangeTag::Delete => "-",
+                    ChangeTag::Insert => "+",
+                    ChangeTag::Equal => " ",
+                };
+                payload.push_str(prefix);
+                payload.push_str(line.content.trim_end_matches('\n'));
+                payload.push('\n');
+            }
+        }
+        None => {
+            payload.push_str("Output:\n");
+            payload.push_str(&result.actual_output);
+            if !result.actual_output.ends_with('\n') {
+                payload.push('\n');
+            }
+        }
+    }
+
+    payload
+}
+
+// システムのクリップボード（SSH越しなどでは使えない）
+//
+// X11/Waylandではコピーした内容を所有者のプロセスが提供し続けるため、
+// 最初のコピーで開いたクリップボードをアプリの終了まで保持する
+#[derive(Default)]
+pub struct SystemClipboard {
+    clipboard: Option<arboard::Clipboard>,
+}
+
+impl SystemClipboard {
+    pub fn copy(&mut self, text: &str) -> Result<()> {
+        let clipboard = match &mut self.clipboard {
+            Some(clipboard) => clipboard,
+            None => self
+                .clipboard
+                .insert(arboard::Clipboard::new().context("clipboard is not available")?),
+        };
+        clipboard
+            .set_text(text.to_string())
+            .context("failed to write to clipboard")?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::DiffLine;
+
+    #[test]
+    fn payload_contains_command_and_unified_diff() {
+        let result = TestResult {
+            name: "Word Count Test".to_string(),
+            command_line: "wc -w".to_string(),
+            success: false,
+            actual_output: "7\n".to_string(),
+            diff: Some(vec![
+                DiffLine { tag: ChangeTag::Equal, content: "count:\n".to_string() },
+                DiffLine { tag: ChangeTag::Delete, content: "8\n".to_string() },
+                DiffLine { tag: ChangeTag::Insert, content: "7\n".to_string() },
+            ]),
+            forbidden_matches: Vec::new(),
+            error: None,
+            skipped: None,
+        };
+
+        assert_eq!(
+            copy_payload(&result),
+            "Test: Word Count Test [FAIL]\nCommand: wc -w\n--- expected\n+++ actual\n count:\n-8\n+7\n"
+        );
+    }
+
+    #[test]
+    fn payload_for_passing_test_includes_output() {
+        let result = TestResult {
+            name: "Echo Test".to_string(),
+            command_line: "echo \"Hello, World!\"".to_string(),
+            success: true,
+            actual_output: "Hello, World!".to_string(),
+            diff: None,
+            forbidden_matches: Vec::new(),
+            error: None,
+            skipped: None,
+        };
+
+        let payload = copy_payload(&result);
+        assert!(payload.starts_with("Test: Echo Test [PASS]\nCommand: echo \"Hello, World!\"\n"));
+        assert!(payload.ends_with("Output:\nHello, World!\n"));
+    }
+}
@@ -0,0 +1,138 @@
+use crate::test::TestCase;
+
+// コマンドラインで指定された実行対象の絞り込み条件。
+// include_tags のいずれかを持ち（未指定なら全テスト）、exclude_tags を1つも持たず、
+// 名前に skip のいずれも含まないテストだけを選ぶ
+#[derive(Debug, Clone, Default)]
+pub struct TestFilter {
+    pub include_tags: Vec<String>,
+    pub exclude_tags: Vec<String>,
+    pub skip: Vec<String>,
+}
+
+impl TestFilter {
+    pub fn is_empty(&self) -> bool {
+        self.include_tags.is_empty() && self.exclude_tags.is_empty() && self.skip.is_empty()
+    }
+
+    pub fn matches(&self, test: &TestCase) -> bool {
+        let included = self.include_tags.is_empty()
+            || self.include_tags.iter().any(|tag| test.has_tag(tag));
+        let excluded = self.exclude_tags.iter().any(|tag| test.has_tag(tag));
+        let skipped = self.skip.iter().any(|pattern| test.name.contains(pattern.as_str()));
+
+        included && !excluded && !skipped
+    }
+
+    // 条件に合うテストだけを元の順序のまま残す
+    pub fn apply(&self, tests: Vec<TestCase>) -> Vec<TestCase> {
+        tests.into_iter().filter(|test| self.matches(test)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn case(name: &str, tags: &[&str]) -> TestCase {
+        TestCase {
+            name: name.to_string(),
+            command: "echo".to_string(),
+            expected_output: Some("ok".to_string()),
+            tags: if tags.is_empty() {
+                None
+            } else {
+                Some(tags.iter().map(|t| t.to_string()).collect())
+            },
+            ..Default::default()
+        }
+    }
+
+    fn suite() -> Vec<TestCase> {
+        vec![
+            case("parse small", &["@fast", "parser"]),
+            case("parse huge", &["@slow", "parser"]),
+            case("render", &["@fast", "ui"]),
+            case("network", &["@slow", "@flaky"]),
+            case("untagged", &[]),
+        ]
+    }
+
+    fn selected(filter: &TestFilter) -> Vec<String> {
+        filter.apply(suite()).into_iter().map(|t| t.name).collect()
+    }
+
+    fn tags(tags: &[&str]) -> Vec<String> {
+        tags.iter().map(|t| t.to_string()).collect()
+    }
+
+    #[test]
+    fn empty_filter_selects_everything() {
+        let filter = TestFilter::default();
+        assert!(filter.is_empty());
+        assert_eq!(selected(&filter).len(), 5);
+    }
+
+    #[test]
+    fn include_selects_tests_with_any_tag() {
+        let filter = TestFilter {
+            include_tags: tags(&["@fast"]),
+            ..Default::default()
+        };
+        assert_eq!(selected(&filter), ["parse small", "render"]);
+
+        // 複数指定はいずれかに一致すればよい
+        let filter = TestFilter {
+            include_tags: tags(&["ui", "@flaky"]),
+            ..Default::default()
+        };
+        assert_eq!(selected(&filter), ["render", "network"]);
+    }
+
+    #[test]
+    fn exclude_keeps_untagged_tests() {
+        let filter = TestFilter {
+            exclude_tags: tags(&["@slow"]),
+            ..Default::default()
+        };
+        assert_eq!(selected(&filter), ["parse small", "render", "untagged"]);
+    }
+
+    #[test]
+    fn include_minus_exclude() {
+        let filter = TestFilter {
+            include_tags: tags(&["parser", "@slow"]),
+            exclude_tags: tags(&["@flaky"]),
+            ..Default::default()
+        };
+        assert_eq!(selected(&filter), ["parse small", "parse huge"]);
+
+        // 同じタグを両方に指定すると除外が優先される
+        let filter = TestFilter {
+            include_tags: tags(&["@fast"]),
+            exclude_tags: tags(&["@fast"]),
+            ..Default::default()
+        };
+        assert!(selected(&filter).is_empty());
+    }
+
+    #[test]
+    fn skip_removes_tests_by_name() {
+        let filter = TestFilter {
+            include_tags: tags(&["parser"]),
+            skip: vec!["huge".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(selected(&filter), ["parse small"]);
+    }
+
+    #[test]
+    fn at_prefix_is_optional() {
+        let filter = TestFilter {
+            include_tags: tags(&["fast"]),
+            exclude_tags: tags(&["@ui"]),
+            ..Default::default()
+        };
+        assert_eq!(selected(&filter), ["parse small"]);
+    }
+}
@@ -1,3 +1,8 @@
 pub mod app;
+pub mod clipboard;
+pub mod filter;
+pub mod report;
+pub mod schema;
 pub mod test;
+pub mod theme;
 pub mod ui; 
\ No newline at end of file
@@ -1,5 +1,10 @@
 mod app;
+mod clipboard;
+mod filter;
+mod report;
+mod schema;
 mod test;
+mod theme;
 mod ui;
 
 use anyhow::{Context, Result};
@@ -15,6 +20,7 @@ use ratatui::{
     Terminal,
 };
 use std::io;
+use theme::Theme;
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
@@ -22,6 +28,34 @@ struct Args {
     /// Path to the test configuration file (YAML or TOML)
     #[arg(short, long)]
     config: std::path::PathBuf,
+
+    /// Color theme for the TUI and reports (dark, light, mono)
+    #[arg(long, default_value = "dark")]
+    theme: String,
+
+    /// Disable colored output (also enabled by the NO_COLOR environment variable)
+    #[arg(long)]
+    no_color: bool,
+
+    /// Print a plain text report instead of launching the TUI (alias: --no-tui)
+    #[arg(long = "no-ui", alias = "no-tui")]
+    no_ui: bool,
+
+    /// Exit with status 0 even if tests fail (for report-only runs)
+    #[arg(long)]
+    exit_zero: bool,
+
+    /// Only run tests with this tag (repeatable; a test needs any one of them)
+    #[arg(long = "tag", value_name = "TAG")]
+    tags: Vec<String>,
+
+    /// Do not run tests with this tag (repeatable; applied after --tag)
+    #[arg(long = "exclude-tag", value_name = "TAG")]
+    exclude_tags: Vec<String>,
+
+    /// Do not run tests whose name contains this text (repeatable)
+    #[arg(long, value_name = "NAME")]
+    skip: Vec<String>,
 }
 
 fn main() -> Result<()> {
@@ -32,16 +66,51 @@ fn main() -> Result<()> {
     let config = test::load_config(&args.config)
         .with_context(|| format!("failed to load config from `{}`", args.config.display()))?;
     
-    // Run all tests
-    let test_results = test::run_tests(&config.tests)?;
+    // Resolve the color theme: --theme preset, then [theme] overrides from the config
+    let use_color = theme::color_enabled(args.no_color);
+    let theme = if use_color {
+        let base = Theme::from_name(&args.theme)?;
+        match &config.theme {
+            Some(overrides) => base.with_overrides(overrides)?,
+            None => base,
+        }
+    } else {
+        Theme::monochrome()
+    };
+    
+    // Select tests by tag and name: --tag minus --exclude-tag minus --skip
+    let filter = filter::TestFilter {
+        include_tags: args.tags,
+        exclude_tags: args.exclude_tags,
+        skip: args.skip,
+    };
+    let tests = filter.apply(config.tests);
+    if tests.is_empty() && !filter.is_empty() {
+        anyhow::bail!("no tests match the given --tag/--exclude-tag/--skip filters");
+    }
     
-    // Display results in TUI
-    start_ui(test_results)?;
+    // Run the selected tests
+    let mut test_results = test::run_tests(&tests)?;
     
-    Ok(())
+    if args.no_ui {
+        // レポートの最終行が1行サマリーになっている
+        print!("{}", report::render_report(&test_results, &theme, use_color));
+    } else {
+        // Display results in TUI（再実行した結果でサマリーと終了コードを決める）
+        test_results = start_ui(test_results, tests, theme)?;
+        println!("{}", report::summary_line(&test_results));
+    }
+    let exit_code = report::exit_code(&test_results, args.exit_zero);
+    
+    // CIで結果を判定できるよう、失敗やエラーがあれば非ゼロで終了する
+    std::process::exit(exit_code);
 }
 
-fn start_ui(test_results: Vec<test::TestResult>) -> Result<()> {
+fn start_ui(
+    test_results: Vec<test::TestResult>,
+    test_cases: Vec<test::TestCase>,
+    theme: Theme,
+) -> Result<Vec<test::TestResult>> {
     // Setup terminal
     enable_raw_mode()?;
     let mut stdout = io::stdout();
@@ -50,7 +119,7 @@ fn start_ui(test_results: Vec<test::TestResult>) -> Result<()> {
     let mut terminal = Terminal::new(backend)?;
     
     // Create app state
-    let mut app = App::new(test_results);
+    let mut app = App::new(test_results, theme).with_test_cases(test_cases);
     
     // Start the main loop
     loop {
@@ -60,6 +129,8 @@ fn start_ui(test_results: Vec<test::TestResult>) -> Result<()> {
         
         // Handle input
         if let Event::Key(key) = event::read()? {
+            // ステータスメッセージは次のキー入力まで表示する
+            app.status_message = None;
             match key.code {
                 KeyCode::Char('q') => break,
                 KeyCode::Char('?') => app.toggle_help(),
@@ -83,6 +154,20 @@ fn start_ui(test_results: Vec<test::TestResult>) -> Result<()> {
                         app.previous_tab()
                     }
                 },
+                // タグで一覧を絞り込む（タグを順に切り替え、最後に全件表示へ戻る）
+                KeyCode::Char('t') if !app.show_help => app.cycle_tag_filter(),
+                KeyCode::Char('y') if !app.show_help => app.copy_selected(),
+                // 結果タブで選択中のテストだけを再実行する
+                KeyCode::Enter | KeyCode::Char('x') if !app.show_help && app.tab_index == 0 => {
+                    if let Some(index) = app.begin_rerun() {
+                        // 実行中の表示を出してから同期的に実行する
+                        terminal.draw(|frame| {
+                            ui::render_ui::<CrosstermBackend<io::Stdout>>(frame, &app);
+                        })?;
+                        let outcome = test::run_test(&app.test_cases[index]);
+                        app.finish_rerun(index, outcome);
+                    }
+                },
                 KeyCode::Esc => {
                     if app.show_help {
                         app.toggle_help();
@@ -101,5 +186,5 @@ fn start_ui(test_results: Vec<test::TestResult>) -> Result<()> {
     )?;
     terminal.show_cursor()?;
     
-    Ok(())
+    Ok(app.test_results)
 } 
\ No newline at end of file
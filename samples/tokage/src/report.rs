@@ -0,0 +1,190 @@
+use crate::test::TestResult;
+use crate::theme::{paint, Theme};
+use similar::ChangeTag;
+
+// TUIを使わない場合のテキストレポートを生成する
+pub fn render_report(results: &[TestResult], theme: &Theme, use_color: bool) -> String {
+    let mut out = String::new();
+
+    out.push_str(&paint("Tokage Test Report", theme.header, use_color));
+    out.push('\n');
+
+    for result in results {
+        let status = if result.success {
+            paint("✓ PASS", theme.pass, use_color)
+        } else if result.error.is_some() {
+            paint("! ERROR", theme.fail, use_color)
+        } else if result.skipped.is_some() {
+            paint("- SKIP", theme.header, use_color)
+        } else {
+            paint("✗ FAIL", theme.fail, use_color)
+        };
+        out.push_str(&format!("{} {}\n", status, result.name));
+
+        if let Some(error) = &result.error {
+            out.push_str(&format!("    {}\n", error));
+        }
+
+        if let Some(reason) = &result.skipped {
+            out.push_str(&format!("    skipped: {}\n", reason));
+        }
+
+        for text in &result.forbidden_matches {
+            out.push_str(&format!("    output contains forbidden text {:?}\n", text));
+        }
+
+        if let Some(diff) = &result.diff {
+            for line in diff {
+                let content = line.content.trim_end_matches('\n');
+                let rendered = match line.tag {
+                    ChangeTag::Delete => paint(&format!("  - {}", content), theme.fail, use_color),
+                    ChangeTag::Insert => paint(&format!("  + {}", content), theme.pass, use_color),
+                    ChangeTag::Equal => format!("    {}", content),
+                };
+                out.push_str(&rendered);
+                out.push('\n');
+            }
+        }
+    }
+
+    let summary_color = if all_passed(results) { theme.pass } else { theme.fail };
+    out.push_str(&paint(&summary_line(results), summary_color, use_color));
+    out.push('\n');
+
+    out
+}
+
+// 全テストが成功したかどうか
+pub fn all_passed(results: &[TestResult]) -> bool {
+    results.iter().all(|r| r.success)
+}
+
+// CIのログ向けの1行サマリー（例: "1/3 tests passed, 1 failed, 1 errored"）。
+// 依存先の失敗で実行しなかったテストがあれば ", 1 skipped" を付け足す
+pub fn summary_line(results: &[TestResult]) -> String {
+    let total = results.len();
+    let passed = results.iter().filter(|r| r.success).count();
+    let errored = results.iter().filter(|r| !r.success && r.error.is_some()).count();
+    let skipped = results
+        .iter()
+        .filter(|r| !r.success && r.error.is_none() && r.skipped.is_some())
+        .count();
+    let failed = total - passed - errored - skipped;
+    let mut line = format!("{}/{} tests passed, {} failed, {} errored", passed, total, failed, errored);
+    if skipped > 0 {
+        line.push_str(&format!(", {} skipped", skipped));
+    }
+    line
+}
+
+// テスト結果に応じた終了コード（失敗・エラーがあれば1、allow_failuresなら常に0）
+pub fn exit_code(results: &[TestResult], allow_failures: bool) -> i32 {
+    if allow_failures || all_passed(results) {
+        0
+    } else {
+        1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::DiffLine;
+
+    // ANSIエスケープシーケンスを取り除く
+    fn strip_ansi(text: &str) -> String {
+        let mut result = String::with_capacity(text.len());
+        let mut chars = text.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            if c == '\x1b' && chars.peek() == Some(&'[') {
+                chars.next();
+                // 終端文字（@〜~）までを読み飛ばす
+                for c in chars.by_ref() {
+                    if ('@'..='~').contains(&c) {
+                        break;
+                    }
+                }
+            } else {
+                result.push(c);
+            }
+        }
+
+        result
+    }
+
+    fn sample_results() -> Vec<TestResult> {
+        vec![
+            TestResult {
+                name: "Echo Test".to_string(),
+                command_line: "echo Hello".to_string(),
+                success: true,
+                actual_output: "Hello".to_string(),
+                diff: None,
+                forbidden_matches: Vec::new(),
+                error: None,
+                skipped: None,
+            },
+            TestResult {
+                name: "Word Count Test".to_string(),
+                command_line: "wc -w".to_string(),
+                success: false,
+                actual_output: "7".to_string(),
+                diff: Some(vec![
+                    DiffLine { tag: ChangeTag::Delete, content: "8\n".to_string() },
+                    DiffLine { tag: ChangeTag::Insert, content: "7\n".to_string() },
+                ]),
+                forbidden_matches: Vec::new(),
+                error: None,
+                skipped: None,
+            },
+        ]
+    }
+
+    #[test]
+    fn no_color_report_has_no_ansi_escapes() {
+        let colored = render_report(&sample_results(), &Theme::dark(), true);
+        assert!(colored.contains('\x1b'));
+
+        let plain = render_report(&sample_results(), &Theme::dark(), false);
+        assert!(!plain.contains('\x1b'));
+        assert_eq!(plain, strip_ansi(&colored));
+        assert!(plain.contains("✗ FAIL Word Count Test"));
+        assert!(plain.contains("1/2 tests passed"));
+    }
+
+    #[test]
+    fn summary_and_exit_code_reflect_outcomes() {
+        let mut results = sample_results();
+        assert_eq!(summary_line(&results), "1/2 tests passed, 1 failed, 0 errored");
+        assert_eq!(exit_code(&results, false), 1);
+        assert_eq!(exit_code(&results, true), 0);
+
+        results[1].error = Some("reference command failed".to_string());
+        assert_eq!(summary_line(&results), "1/2 tests passed, 0 failed, 1 errored");
+        assert_eq!(exit_code(&results, false), 1);
+
+        results.truncate(1);
+        assert_eq!(summary_line(&results), "1/1 tests passed, 0 failed, 0 errored");
+        assert_eq!(exit_code(&results, false), 0);
+    }
+
+    #[test]
+    fn theme_overrides_from_config() {
+        use crate::theme::ThemeConfig;
+        use ratatui::style::Color;
+
+        let config = ThemeConfig {
+            pass: Some("blue".to_string()),
+            accent: Some("#102030".to_string()),
+            ..Default::default()
+        };
+        let theme = Theme::dark().with_overrides(&config).unwrap();
+        assert_eq!(theme.pass, Color::Blue);
+        assert_eq!(theme.accent, Color::Rgb(0x10, 0x20, 0x30));
+        assert_eq!(theme.fail, Color::Red);
+
+        let bad = ThemeConfig { fail: Some("not-a-color".to_string()), ..Default::default() };
+        assert!(Theme::dark().with_overrides(&bad).is_err());
+    }
+}
@@ -0,0 +1,248 @@
+use serde_yaml::Value;
+use std::fmt;
+use std::path::PathBuf;
+
+// 設定ファイル読み込み時のエラー
+#[derive(Debug)]
+pub enum ConfigError {
+    NotFound { path: PathBuf },
+    Io { path: PathBuf, source: std::io::Error },
+    UnsupportedFormat { extension: String },
+    Parse { format: &'static str, message: String },
+    Schema { location: String, message: String },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::NotFound { path } => {
+                write!(f, "config file not found: {}", path.display())
+            }
+            ConfigError::Io { path, source } => {
+                write!(f, "failed to read config file {}: {}", path.display(), source)
+            }
+            ConfigError::UnsupportedFormat { extension } => {
+                write!(f, "unsupported config file format: {:?} (expected yaml, yml or toml)", extension)
+            }
+            ConfigError::Parse { format, message } => {
+                write!(f, "failed to parse {} config: {}", format, message)
+            }
+            ConfigError::Schema { location, message } => {
+                write!(f, "invalid config at `{}`: {}", location, message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ConfigError::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum FieldType {
+    String,
+    StringList,
+    UnsignedInt,
+}
+
+impl FieldType {
+    fn describe(self) -> &'static str {
+        match self {
+            FieldType::String => "a string",
+            FieldType::StringList => "a list of strings",
+            FieldType::UnsignedInt => "a non-negative integer",
+        }
+    }
+
+    fn matches(self, value: &Value) -> bool {
+        match self {
+            FieldType::String => value.is_string(),
+            FieldType::StringList => value
+                .as_sequence()
+                .is_some_and(|items| items.iter().all(Value::is_string)),
+            FieldType::UnsignedInt => value.as_u64().is_some(),
+        }
+    }
+}
+
+// テストケースのスキーマ: (フィールド名, 型, 必須かどうか)
+const TEST_FIELDS: &[(&str, FieldType, bool)] = &[
+    ("name", FieldType::String, true),
+    ("command", FieldType::String, true),
+    ("args", FieldType::StringList, false),
+    ("input", FieldType::String, false),
+    ("expected_output", FieldType::String, false),
+    ("expected_command", FieldType::StringList, false),
+    ("must_not_contain", FieldType::StringList, false),
+    ("timeout_secs", FieldType::UnsignedInt, false),
+    ("tags", FieldType::StringList, false),
+    ("depends_on", FieldType::StringList, false),
+];
+
+const THEME_FIELDS: &[&str] = &["pass", "fail", "header", "accent"];
+
+// 値の種類を人が読める形で返す
+fn kind_of(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "a boolean",
+        Value::Number(n) if n.is_f64() => "a float",
+        Value::Number(n) if n.as_i64().is_some_and(|n| n < 0) => "a negative integer",
+        Value::Number(_) => "an integer",
+        Value::String(_) => "a string",
+        Value::Sequence(_) => "a list",
+        Value::Mapping(_) => "a table",
+        Value::Tagged(_) => "a tagged value",
+    }
+}
+
+fn schema_error(location: impl Into<String>, message: impl Into<String>) -> ConfigError {
+    ConfigError::Schema {
+        location: location.into(),
+        message: message.into(),
+    }
+}
+
+// パース済みの設定がスキーマに沿っているかを検証する
+pub fn validate(config: &Value) -> Result<(), ConfigError> {
+    let root = config
+        .as_mapping()
+        .ok_or_else(|| schema_error("<root>", format!("expected a table, found {}", kind_of(config))))?;
+
+    let tests = root
+        .get("tests")
+        .ok_or_else(|| schema_error("tests", "missing required field `tests`"))?;
+    let tests = tests
+        .as_sequence()
+        .ok_or_else(|| schema_error("tests", format!("expected a list of tests, found {}", kind_of(tests))))?;
+
+    for (index, test) in tests.iter().enumerate() {
+        validate_test(index, test)?;
+    }
+    validate_dependencies(tests)?;
+
+    if let Some(theme) = root.get("theme") {
+        validate_theme(theme)?;
+    }
+
+    Ok(())
+}
+
+fn validate_test(index: usize, test: &Value) -> Result<(), ConfigError> {
+    let location = format!("tests[{}]", index);
+    let fields = test
+        .as_mapping()
+        .ok_or_else(|| schema_error(&location, format!("expected a table, found {}", kind_of(test))))?;
+
+    // 名前が分かればエラーメッセージに含める
+    let label = match fields.get("name").and_then(Value::as_str) {
+        Some(name) => format!("{} ({:?})", location, name),
+        None => location.clone(),
+    };
+
+    for (field, expected, required) in TEST_FIELDS {
+        match fields.get(*field) {
+            None if *required => {
+                return Err(schema_error(
+                    &label,
+                    format!("missing required field `{}`", field),
+                ));
+            }
+            None => {}
+            Some(value) if !expected.matches(value) => {
+                return Err(schema_error(
+                    format!("{}.{}", label, field),
+                    format!("expected {}, found {}", expected.describe(), kind_of(value)),
+                ));
+            }
+            Some(_) => {}
+        }
+    }
+
+    // 期待値は固定文字列か参照コマンドのどちらか一方で指定する
+    let expected_command = fields.get("expected_command").and_then(Value::as_sequence);
+    match (fields.get("expected_output"), expected_command) {
+        (None, None) => {
+            return Err(schema_error(&label, "missing required field `expected_output`"));
+        }
+        (Some(_), Some(_)) => {
+            return Err(schema_error(
+                &label,
+                "`expected_output` and `expected_command` cannot both be set",
+            ));
+        }
+        (None, Some(command)) if command.is_empty() => {
+            return Err(schema_error(
+                format!("{}.expected_command", label),
+                "expected a non-empty list of strings",
+            ));
+        }
+        _ => {}
+    }
+
+    // 空文字列はどんな出力にも含まれてしまう
+    let must_not_contain = fields.get("must_not_contain").and_then(Value::as_sequence);
+    if must_not_contain.is_some_and(|items| items.iter().any(|item| item.as_str() == Some(""))) {
+        return Err(schema_error(
+            format!("{}.must_not_contain", label),
+            "expected non-empty strings",
+        ));
+    }
+
+    // "@" だけのタグはどの --tag とも一致しない
+    let tags = fields.get("tags").and_then(Value::as_sequence);
+    if tags.is_some_and(|items| items.iter().any(|item| matches!(item.as_str(), Some("" | "@")))) {
+        return Err(schema_error(format!("{}.tags", label), "expected non-empty tag names"));
+    }
+
+    Ok(())
+}
+
+// depends_on には設定内に存在するテストの名前だけを書ける
+fn validate_dependencies(tests: &[Value]) -> Result<(), ConfigError> {
+    let names: Vec<&str> = tests
+        .iter()
+        .filter_map(|test| test.get("name").and_then(Value::as_str))
+        .collect();
+
+    for (index, test) in tests.iter().enumerate() {
+        let depends_on = test.get("depends_on").and_then(Value::as_sequence);
+        let unknown = depends_on
+            .into_iter()
+            .flatten()
+            .filter_map(Value::as_str)
+            .find(|dep| !names.contains(dep));
+        if let Some(unknown) = unknown {
+            let name = test.get("name").and_then(Value::as_str).unwrap_or_default();
+            return Err(schema_error(
+                format!("tests[{}] ({:?}).depends_on", index, name),
+                format!("unknown test `{}`", unknown),
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+fn validate_theme(theme: &Value) -> Result<(), ConfigError> {
+    let fields = theme
+        .as_mapping()
+        .ok_or_else(|| schema_error("theme", format!("expected a table, found {}", kind_of(theme))))?;
+
+    for (key, value) in fields {
+        let key = key.as_str().unwrap_or_default();
+        if THEME_FIELDS.contains(&key) && !value.is_string() {
+            return Err(schema_error(
+                format!("theme.{}", key),
+                format!("expected a color string, found {}", kind_of(value)),
+            ));
+        }
+    }
+
+    Ok(())
+}
@@ -1,6 +1,8 @@
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 use similar::{ChangeTag, TextDiff};
+use crate::schema::{self, ConfigError};
+use crate::theme::ThemeConfig;
 use std::{
     fs::File,
     io::{BufReader, Read, Write},
@@ -12,24 +14,40 @@ use std::{
 #[derive(Debug, Serialize, Deserialize)]
 pub struct TestConfig {
     pub tests: Vec<TestCase>,
+    pub theme: Option<ThemeConfig>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct TestCase {
     pub name: String,
     pub command: String,
     pub args: Option<Vec<String>>,
     pub input: Option<String>,
-    pub expected_output: String,
+    pub expected_output: Option<String>,
+    // 参照実装のコマンド（argv形式）。その標準出力を期待値として使う
+    pub expected_command: Option<Vec<String>>,
+    // 標準出力・標準エラー出力のどちらにも含まれてはならない文字列
+    pub must_not_contain: Option<Vec<String>>,
     pub timeout_secs: Option<u64>,
+    // --tag / --exclude-tag で実行対象を選ぶためのタグ（"@fast" のように書いてもよい）
+    pub tags: Option<Vec<String>>,
+    // 先に成功している必要があるテストの名前（失敗していればこのテストは実行しない）
+    pub depends_on: Option<Vec<String>>,
 }
 
 #[derive(Debug)]
 pub struct TestResult {
     pub name: String,
+    pub command_line: String,
     pub success: bool,
     pub actual_output: String,
     pub diff: Option<Vec<DiffLine>>,
+    // 出力に現れた must_not_contain の文字列
+    pub forbidden_matches: Vec<String>,
+    // 期待値を得られずテストを実行できなかった場合の理由
+    pub error: Option<String>,
+    // 依存先が失敗したため実行しなかった場合の理由
+    pub skipped: Option<String>,
 }
 
 #[derive(Debug)]
@@ -38,10 +56,58 @@ pub struct DiffLine {
     pub content: String,
 }
 
+impl TestCase {
+    // 実行するコマンドを1行の文字列として表現する
+    pub fn command_line(&self) -> String {
+        let mut parts = vec![self.command.clone()];
+        if let Some(args) = &self.args {
+            parts.extend(args.iter().map(|arg| {
+                if arg.contains(char::is_whitespace) {
+                    format!("{:?}", arg)
+                } else {
+                    arg.clone()
+                }
+            }));
+        }
+        parts.join(" ")
+    }
+
+    // 先頭の "@" の有無は区別せずにタグを比較する
+    pub fn has_tag(&self, tag: &str) -> bool {
+        let tag = normalize_tag(tag);
+        self.tags
+            .iter()
+            .flatten()
+            .any(|own| normalize_tag(own) == tag)
+    }
+}
+
+impl TestResult {
+    // 依存先が失敗したか選ばれなかったため実行しなかったテストの結果
+    fn skipped(test: &TestCase, reason: String) -> Self {
+        TestResult {
+            name: test.name.clone(),
+            command_line: test.command_line(),
+            success: false,
+            actual_output: String::new(),
+            diff: None,
+            forbidden_matches: Vec::new(),
+            error: None,
+            skipped: Some(reason),
+        }
+    }
+}
+
+pub fn normalize_tag(tag: &str) -> &str {
+    tag.strip_prefix('@').unwrap_or(tag)
+}
+
 pub fn load_config(config_path: &PathBuf) -> Result<TestConfig> {
-    // ファイルを開く
-    let file = File::open(config_path)
-        .with_context(|| format!("Failed to open config file: {:?}", config_path))?;
+    // ファイルを開く（存在しない場合は専用のエラーにする）
+    let file = File::open(config_path).map_err(|e| match e.kind() {
+        std::io::ErrorKind::NotFound => ConfigError::NotFound { path: config_path.clone() },
+        _ => ConfigError::Io { path: config_path.clone(), source: e },
+    })?;
     let mut reader = BufReader::new(file);
     
     // ファイル拡張子を取得
@@ -52,104 +118,293 @@ pub fn load_config(config_path: &PathBuf) -> Result<TestConfig> {
     // ファイル内容を文字列として読み込む
     let mut content = String::new();
     reader.read_to_string(&mut content)
-        .with_context(|| format!("Failed to read config file: {:?}", config_path))?;
+        .map_err(|e| ConfigError::Io { path: config_path.clone(), source: e })?;
     
-    // 拡張子に応じて適切なパーサーを使用
-    match extension.to_lowercase().as_str() {
+    Ok(parse_config(&content, extension)?)
+}
+
+// 設定文字列をパースし、スキーマを検証してから TestConfig に変換する
+pub fn parse_config(content: &str, extension: &str) -> Result<TestConfig, ConfigError> {
+    // 拡張子に応じて適切なパーサーを使用し、まずは汎用的な値として読み込む
+    let (format, value) = match extension.to_lowercase().as_str() {
         "yaml" | "yml" => {
-            match serde_yaml::from_str::<TestConfig>(&content) {
-                Ok(config) => Ok(config),
-                Err(e) => Err(anyhow::anyhow!("Failed to parse YAML config: {}", e))
-            }
+            let value = serde_yaml::from_str::<serde_yaml::Value>(content)
+                .map_err(|e| ConfigError::Parse { format: "YAML", message: e.to_string() })?;
+            ("YAML", value)
         },
         "toml" => {
-            match toml::from_str::<TestConfig>(&content) {
-                Ok(config) => Ok(config),
-                Err(e) => Err(anyhow::anyhow!("Failed to parse TOML config: {}", e))
-            }
+            let value = toml::from_str::<serde_yaml::Value>(content)
+                .map_err(|e| ConfigError::Parse { format: "TOML", message: e.to_string() })?;
+            ("TOML", value)
         },
-        _ => Err(anyhow::anyhow!("Unsupported config file format: {}", extension))
-    }
+        _ => return Err(ConfigError::UnsupportedFormat { extension: extension.to_string() }),
+    };
+    
+    // フィールド単位で分かりやすいエラーを出すため、serde より先に検証する
+    schema::validate(&value)?;
+    
+    serde_yaml::from_value(value)
+        .map_err(|e| ConfigError::Parse { format, message: e.to_string() })
 }
 
+// 依存先から順に実行し、結果は設定ファイルと同じ順序で返す
 pub fn run_tests(tests: &[TestCase]) -> Result<Vec<TestResult>> {
-    let mut results = Vec::new();
+    let order = execution_order(tests)?;
+    let mut results: Vec<Option<TestResult>> = tests.iter().map(|_| None).collect();
     
-    for test in tests {
-        println!("Running test: {}", test.name);
+    for index in order {
+        let test = &tests[index];
+        // 絞り込みで選ばれなかった依存先は実行されないので、成功したとはみなさない
+        let unselected: Vec<&str> = test
+            .depends_on
+            .as_deref()
+            .unwrap_or_default()
+            .iter()
+            .filter(|name| !tests.iter().any(|t| &t.name == *name))
+            .map(String::as_str)
+            .collect();
+        // 依存先は実行済みなので、成功していないものがあれば実行しない
+        let mut failed: Vec<&str> = dependencies(tests, index)
+            .into_iter()
+            .filter(|&dep| !results[dep].as_ref().is_some_and(|r| r.success))
+            .map(|dep| tests[dep].name.as_str())
+            .collect();
+        failed.dedup();
         
-        let mut command = Command::new(&test.command);
-        
-        // Add arguments if provided
-        if let Some(args) = &test.args {
-            command.args(args);
-        }
-        
-        // Setup stdin if input is provided
-        let mut child = if let Some(_input) = &test.input {
-            command
-                .stdin(Stdio::piped())
-                .stdout(Stdio::piped())
-                .spawn()
-                .with_context(|| format!("Failed to spawn command: {}", test.command))?
+        let skip_reason = if !unselected.is_empty() {
+            Some(format!("dependency not selected: {}", unselected.join(", ")))
+        } else if !failed.is_empty() {
+            Some(format!("dependency failed: {}", failed.join(", ")))
         } else {
-            command
-                .stdout(Stdio::piped())
-                .spawn()
-                .with_context(|| format!("Failed to spawn command: {}", test.command))?
+            None
         };
-        
-        // Write to stdin if input is provided
-        if let Some(input) = &test.input {
-            if let Some(mut stdin) = child.stdin.take() {
-                stdin.write_all(input.as_bytes())
-                    .context("Failed to write to stdin")?;
-                // 標準入力をクローズして、コマンドが入力の終了を認識できるようにする
-                // drop(stdin)は自動的に行われる
+        let result = match skip_reason {
+            None => {
+                println!("Running test: {}", test.name);
+                run_test(test)?
             }
-        }
-        
-        // Get output with timeout
-        let timeout = Duration::from_secs(test.timeout_secs.unwrap_or(30));
-        let output_status = child.wait_timeout(timeout)
-            .context("Command execution failed")?;
-        
-        let output = if output_status.is_some() {
-            child.wait_with_output()?
-        } else {
-            child.kill()?;
-            return Err(anyhow::anyhow!("Command timed out: {}", test.name));
-        };
-        
-        let actual_output = String::from_utf8_lossy(&output.stdout).to_string();
-        let success = actual_output.trim() == test.expected_output.trim();
-        
-        // Generate diff if test failed
-        let diff = if !success {
-            let text_diff = TextDiff::from_lines(&test.expected_output, &actual_output);
-            
-            let mut diff_lines = Vec::new();
-            for change in text_diff.iter_all_changes() {
-                diff_lines.push(DiffLine {
-                    tag: change.tag(),
-                    content: change.value().to_string(),
-                });
+            Some(reason) => {
+                println!("Skipping test: {}", test.name);
+                TestResult::skipped(test, reason)
             }
-            
-            Some(diff_lines)
-        } else {
-            None
         };
+        results[index] = Some(result);
+    }
+    
+    Ok(results.into_iter().flatten().collect())
+}
+
+// depends_on に書かれた名前を持つテストのインデックス（同名のテストはすべて依存先になる）。
+// 絞り込みで選ばれなかったテストは含まれない
+fn dependencies(tests: &[TestCase], index: usize) -> Vec<usize> {
+    let names = tests[index].depends_on.as_deref().unwrap_or_default();
+    (0..tests.len())
+        .filter(|&dep| names.contains(&tests[dep].name))
+        .collect()
+}
+
+// 依存先が必ず先に来る実行順（トポロジカルソート）。
+// 依存関係のないテストは元の順序を保ち、循環があればエラーにする
+pub fn execution_order(tests: &[TestCase]) -> Result<Vec<usize>> {
+    let deps: Vec<Vec<usize>> = (0..tests.len()).map(|i| dependencies(tests, i)).collect();
+    let mut done = vec![false; tests.len()];
+    let mut order = Vec::with_capacity(tests.len());
+    
+    while order.len() < tests.len() {
+        // 依存先がすべて済んでいる最初のテストを選ぶ
+        let next = (0..tests.len())
+            .find(|&i| !done[i] && deps[i].iter().all(|&dep| done[dep]));
+        let Some(next) = next else {
+            let cycle: Vec<&str> = (0..tests.len())
+                .filter(|&i| !done[i])
+                .map(|i| tests[i].name.as_str())
+                .collect();
+            anyhow::bail!("dependency cycle, cannot order tests: {}", cycle.join(", "));
+        };
+        done[next] = true;
+        order.push(next);
+    }
+    
+    Ok(order)
+}
+
+// 1件のテストを実行して結果を返す（TUIからの個別再実行にも使う）
+pub fn run_test(test: &TestCase) -> Result<TestResult> {
+    let timeout = Duration::from_secs(test.timeout_secs.unwrap_or(30));
+    let args = test.args.as_deref().unwrap_or_default();
+    let output = run_command(&test.command, args, test.input.as_deref(), timeout)?
+        .ok_or_else(|| anyhow::anyhow!("Command timed out: {}", test.name))?;
+    let actual_output = String::from_utf8_lossy(&output.stdout).to_string();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let forbidden_matches = forbidden_matches(test, &actual_output, &stderr);
+    
+    // 期待値は固定文字列か、参照コマンドの出力
+    let expected_output = match expected_output(test, timeout) {
+        Ok(expected) => expected,
+        Err(e) => {
+            return Ok(TestResult {
+                name: test.name.clone(),
+                command_line: test.command_line(),
+                success: false,
+                actual_output,
+                diff: None,
+                forbidden_matches,
+                error: Some(format!("{:#}", e)),
+                skipped: None,
+            });
+        }
+    };
+    
+    let output_matches = actual_output.trim() == expected_output.trim();
+    // 期待値との一致と、禁止文字列が現れないことの両方を満たして成功
+    let success = output_matches && forbidden_matches.is_empty();
+    
+    // Generate diff if output differs
+    let diff = if !output_matches {
+        let text_diff = TextDiff::from_lines(&expected_output, &actual_output);
         
-        results.push(TestResult {
-            name: test.name.clone(),
-            success,
-            actual_output,
-            diff,
-        });
+        let mut diff_lines = Vec::new();
+        for change in text_diff.iter_all_changes() {
+            diff_lines.push(DiffLine {
+                tag: change.tag(),
+                content: change.value().to_string(),
+            });
+        }
+        
+        Some(diff_lines)
+    } else {
+        None
+    };
+    
+    Ok(TestResult {
+        name: test.name.clone(),
+        command_line: test.command_line(),
+        success,
+        actual_output,
+        diff,
+        forbidden_matches,
+        error: None,
+        skipped: None,
+    })
+}
+
+// must_not_contain のうち、標準出力か標準エラー出力に現れたものを返す
+fn forbidden_matches(test: &TestCase, stdout: &str, stderr: &str) -> Vec<String> {
+    test.must_not_contain
+        .iter()
+        .flatten()
+        .filter(|text| stdout.contains(text.as_str()) || stderr.contains(text.as_str()))
+        .cloned()
+        .collect()
+}
+
+// 参照コマンドがあれば同じ入力で実行し、その標準出力を期待値にする
+fn expected_output(test: &TestCase, timeout: Duration) -> Result<String> {
+    let Some(reference) = &test.expected_command else {
+        return Ok(test.expected_output.clone().unwrap_or_default());
+    };
+    let (program, args) = reference
+        .split_first()
+        .context("expected_command is empty")?;
+    
+    let output = run_command(program, args, test.input.as_deref(), timeout)
+        .with_context(|| format!("reference command `{}` failed", reference.join(" ")))?
+        .ok_or_else(|| anyhow::anyhow!("reference command `{}` timed out", reference.join(" ")))?;
+    
+    if !output.status.success() {
+        return Err(anyhow::anyhow!(
+            "reference command `{}` exited with {}",
+            reference.join(" "),
+            output.status
+        ));
+    }
+    
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+// コマンドを実行して出力を取得する（タイムアウト時は None）
+//
+// 待っている間に標準出力・標準エラー出力を別スレッドで読み続ける。
+// 読まずにいるとパイプのバッファ（約64KiB）が埋まり、子プロセスが書き込みで止まってしまう
+fn run_command(
+    program: &str,
+    args: &[String],
+    input: Option<&str>,
+    timeout: Duration,
+) -> Result<Option<std::process::Output>> {
+    let mut command = Command::new(program);
+    command.args(args).stdout(Stdio::piped()).stderr(Stdio::piped());
+    
+    // Setup stdin if input is provided
+    if input.is_some() {
+        command.stdin(Stdio::piped());
+    }
+    
+    let mut child = command
+        .spawn()
+        .with_context(|| format!("Failed to spawn command: {}", program))?;
+    
+    let stdout = read_in_background(child.stdout.take());
+    let stderr = read_in_background(child.stderr.take());
+    
+    // 標準入力も別スレッドで書き込み、入力を読まないコマンドでもタイムアウトが効くようにする
+    let stdin = match (input, child.stdin.take()) {
+        (Some(input), Some(mut stdin)) => {
+            let input = input.to_string();
+            // 書き終えたらstdinがドロップされ、コマンドが入力の終了を認識できる
+            Some(std::thread::spawn(move || stdin.write_all(input.as_bytes())))
+        }
+        _ => None,
+    };
+    
+    // Get output with timeout
+    let output_status = child.wait_timeout(timeout)
+        .context("Command execution failed")?;
+    
+    let Some(status) = output_status else {
+        child.kill()?;
+        child.wait()?;
+        return Ok(None);
+    };
+    
+    if let Some(stdin) = stdin {
+        match stdin.join() {
+            Ok(Ok(())) => {}
+            // 入力を読み切らずに終了したコマンドは失敗扱いにしない
+            Ok(Err(e)) if e.kind() == std::io::ErrorKind::BrokenPipe => {}
+            Ok(Err(e)) => return Err(e).context("Failed to write to stdin"),
+            Err(_) => anyhow::bail!("stdin writer thread panicked"),
+        }
     }
     
-    Ok(results)
+    Ok(Some(std::process::Output {
+        status,
+        stdout: join_reader(stdout).context("Failed to read stdout")?,
+        stderr: join_reader(stderr).context("Failed to read stderr")?,
+    }))
+}
+
+type Reader = Option<std::thread::JoinHandle<std::io::Result<Vec<u8>>>>;
+
+// パイプを最後まで読むスレッドを起動する
+fn read_in_background<R: Read + Send + 'static>(pipe: Option<R>) -> Reader {
+    pipe.map(|mut pipe| {
+        std::thread::spawn(move || {
+            let mut buf = Vec::new();
+            pipe.read_to_end(&mut buf)?;
+            Ok(buf)
+        })
+    })
+}
+
+fn join_reader(reader: Reader) -> Result<Vec<u8>> {
+    match reader {
+        Some(handle) => handle
+            .join()
+            .map_err(|_| anyhow::anyhow!("reader thread panicked"))?
+            .map_err(Into::into),
+        None => Ok(Vec::new()),
+    }
 }
 
 // Extension trait for Command to add wait_timeout functionality
@@ -182,4 +437,333 @@ impl CommandExt for std::process::Child {
         // タイムアウト
         Ok(None)
     }
-} 
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn schema_error(result: Result<TestConfig, ConfigError>) -> (String, String) {
+        match result {
+            Err(ConfigError::Schema { location, message }) => (location, message),
+            other => panic!("expected schema error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn missing_required_field_names_test_and_field() {
+        let yaml = r#"
+tests:
+  - name: "Echo Test"
+    command: "echo"
+    expected_output: "hi"
+  - name: "Word Count Test"
+    command: "wc"
+"#;
+        let (location, message) = schema_error(parse_config(yaml, "yaml"));
+        assert_eq!(location, r#"tests[1] ("Word Count Test")"#);
+        assert_eq!(message, "missing required field `expected_output`");
+
+        let err = parse_config(yaml, "yaml").unwrap_err().to_string();
+        assert_eq!(
+            err,
+            r#"invalid config at `tests[1] ("Word Count Test")`: missing required field `expected_output`"#
+        );
+    }
+
+    #[test]
+    fn wrong_type_names_test_index_and_field() {
+        let toml = r#"
+[[tests]]
+name = "Echo Test"
+command = "echo"
+expected_output = "hi"
+timeout_secs = "five"
+"#;
+        let (location, message) = schema_error(parse_config(toml, "toml"));
+        assert_eq!(location, r#"tests[0] ("Echo Test").timeout_secs"#);
+        assert_eq!(message, "expected a non-negative integer, found a string");
+    }
+
+    #[test]
+    fn expected_output_and_command_are_exclusive() {
+        let yaml = r#"
+tests:
+  - name: "Both"
+    command: "echo"
+    expected_output: "hi"
+    expected_command: ["echo", "hi"]
+"#;
+        let (location, message) = schema_error(parse_config(yaml, "yaml"));
+        assert_eq!(location, r#"tests[0] ("Both")"#);
+        assert!(message.contains("cannot both be set"));
+
+        let yaml = r#"
+tests:
+  - name: "Golden"
+    command: "echo"
+    expected_command: ["echo", "hi"]
+"#;
+        let config = parse_config(yaml, "yaml").unwrap();
+        assert_eq!(config.tests[0].expected_command.as_deref().map(|c| c.len()), Some(2));
+    }
+
+    #[test]
+    fn must_not_contain_rejects_empty_strings() {
+        let yaml = r#"
+tests:
+  - name: "Quiet"
+    command: "echo"
+    expected_output: "hi"
+    must_not_contain: ["warning", ""]
+"#;
+        let (location, message) = schema_error(parse_config(yaml, "yaml"));
+        assert_eq!(location, r#"tests[0] ("Quiet").must_not_contain"#);
+        assert_eq!(message, "expected non-empty strings");
+    }
+
+    #[test]
+    fn tags_are_parsed_and_validated() {
+        let yaml = r#"
+tests:
+  - name: "Quick"
+    command: "echo"
+    expected_output: "hi"
+    tags: ["@fast", "smoke"]
+"#;
+        let config = parse_config(yaml, "yaml").unwrap();
+        assert!(config.tests[0].has_tag("fast"));
+        assert!(config.tests[0].has_tag("@smoke"));
+        assert!(!config.tests[0].has_tag("slow"));
+
+        let yaml = r#"
+tests:
+  - name: "Quick"
+    command: "echo"
+    expected_output: "hi"
+    tags: ["@"]
+"#;
+        let (location, message) = schema_error(parse_config(yaml, "yaml"));
+        assert_eq!(location, r#"tests[0] ("Quick").tags"#);
+        assert_eq!(message, "expected non-empty tag names");
+    }
+
+    #[test]
+    fn distinguishes_not_found_parse_and_schema_errors() {
+        let missing = load_config(&PathBuf::from("does/not/exist.yaml")).unwrap_err();
+        assert!(matches!(
+            missing.downcast_ref::<ConfigError>(),
+            Some(ConfigError::NotFound { .. })
+        ));
+
+        let parse = parse_config("tests: [unclosed", "yaml").unwrap_err();
+        assert!(matches!(parse, ConfigError::Parse { format: "YAML", .. }));
+
+        let schema = parse_config("tests: 3", "yaml").unwrap_err();
+        assert!(matches!(schema, ConfigError::Schema { .. }));
+    }
+
+    fn golden_test(command: &[&str], expected_command: &[&str]) -> TestCase {
+        TestCase {
+            name: "Golden".to_string(),
+            command: command[0].to_string(),
+            args: Some(command[1..].iter().map(|s| s.to_string()).collect()),
+            input: Some("one two three\n".to_string()),
+            expected_command: Some(expected_command.iter().map(|s| s.to_string()).collect()),
+            timeout_secs: Some(5),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn expected_command_output_is_compared() {
+        // 同じ入力を両方のコマンドに渡して出力を比較する
+        let tests = vec![
+            golden_test(&["cat"], &["sh", "-c", "cat"]),
+            golden_test(&["wc", "-w"], &["echo", "4"]),
+        ];
+        let results = run_tests(&tests).unwrap();
+
+        assert!(results[0].success);
+        assert!(results[0].error.is_none());
+
+        assert!(!results[1].success);
+        assert!(results[1].error.is_none());
+        let diff = results[1].diff.as_ref().unwrap();
+        assert!(diff.iter().any(|line| line.tag == ChangeTag::Delete && line.content == "4\n"));
+    }
+
+    #[test]
+    fn large_stderr_does_not_block_until_timeout() {
+        // パイプのバッファより多く標準エラー出力に書いてから、入力を読まずに終了する
+        let args: Vec<String> = ["-c", "head -c 200000 /dev/zero >&2; echo done"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let start = std::time::Instant::now();
+        let output = run_command("sh", &args, Some("ignored\n"), Duration::from_secs(5))
+            .unwrap()
+            .expect("command timed out");
+
+        assert!(start.elapsed() < Duration::from_secs(5));
+        assert!(output.status.success());
+        assert_eq!(output.stdout, b"done\n");
+        assert_eq!(output.stderr.len(), 200000);
+    }
+
+    #[test]
+    fn failing_expected_command_marks_test_errored() {
+        let tests = vec![
+            golden_test(&["cat"], &["false"]),
+            golden_test(&["cat"], &["/nonexistent/reference"]),
+        ];
+        let results = run_tests(&tests).unwrap();
+
+        for result in &results {
+            assert!(!result.success);
+            assert!(result.diff.is_none());
+            assert!(result.error.as_deref().unwrap().contains("reference command"));
+        }
+    }
+
+    fn shell_test(script: &str, expected_output: &str, must_not_contain: &[&str]) -> TestCase {
+        TestCase {
+            name: "Forbidden".to_string(),
+            command: "sh".to_string(),
+            args: Some(vec!["-c".to_string(), script.to_string()]),
+            expected_output: Some(expected_output.to_string()),
+            must_not_contain: Some(must_not_contain.iter().map(|s| s.to_string()).collect()),
+            timeout_secs: Some(5),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn forbidden_output_fails_test() {
+        let tests = vec![
+            // 期待値とは一致するが、標準エラー出力に警告が出ている
+            shell_test("echo ok; echo 'warning: unused' >&2", "ok", &["warning:", "panic"]),
+            shell_test("echo 'ok (panic recovered)'", "ok", &["panic"]),
+            shell_test("echo ok", "ok", &["warning:"]),
+            // 期待値とも一致しない場合は差分も残る
+            shell_test("echo 'warning: nope'", "ok", &["warning:"]),
+        ];
+        let results = run_tests(&tests).unwrap();
+
+        assert!(!results[0].success);
+        assert_eq!(results[0].forbidden_matches, ["warning:"]);
+        assert!(results[0].diff.is_none());
+        assert!(results[0].error.is_none());
+
+        assert!(!results[1].success);
+        assert_eq!(results[1].forbidden_matches, ["panic"]);
+
+        assert!(results[2].success);
+        assert!(results[2].forbidden_matches.is_empty());
+
+        assert!(!results[3].success);
+        assert_eq!(results[3].forbidden_matches, ["warning:"]);
+        assert!(results[3].diff.is_some());
+    }
+
+    fn dependent_test(name: &str, expected_output: &str, depends_on: &[&str]) -> TestCase {
+        TestCase {
+            name: name.to_string(),
+            command: "echo".to_string(),
+            args: Some(vec!["ok".to_string()]),
+            expected_output: Some(expected_output.to_string()),
+            timeout_secs: Some(5),
+            depends_on: Some(depends_on.iter().map(|s| s.to_string()).collect()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn dependencies_run_first() {
+        let tests = vec![
+            dependent_test("deploy", "ok", &["build", "migrate"]),
+            dependent_test("lint", "ok", &[]),
+            dependent_test("migrate", "ok", &["build"]),
+            dependent_test("build", "ok", &[]),
+        ];
+        // 依存関係のないテストは元の順序のまま
+        assert_eq!(execution_order(&tests).unwrap(), [1, 3, 2, 0]);
+
+        // 結果は実行順ではなく設定ファイルの順序で返る
+        let results = run_tests(&tests).unwrap();
+        let names: Vec<&str> = results.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, ["deploy", "lint", "migrate", "build"]);
+        assert!(results.iter().all(|r| r.success && r.skipped.is_none()));
+    }
+
+    #[test]
+    fn failed_dependency_skips_dependents() {
+        let tests = vec![
+            dependent_test("build", "not ok", &[]),
+            dependent_test("unit", "ok", &["build"]),
+            // スキップされたテストも失敗扱いなので、その依存先もスキップされる
+            dependent_test("deploy", "ok", &["unit"]),
+            dependent_test("docs", "ok", &[]),
+            // 絞り込みで選ばれなかったテストに依存するテストも実行しない
+            dependent_test("report", "ok", &["docs", "not selected"]),
+        ];
+        let results = run_tests(&tests).unwrap();
+
+        assert!(!results[0].success);
+        assert!(results[0].skipped.is_none());
+        assert!(results[0].diff.is_some());
+
+        assert!(!results[1].success);
+        assert_eq!(results[1].skipped.as_deref(), Some("dependency failed: build"));
+        assert!(results[1].diff.is_none());
+        assert!(results[1].error.is_none());
+        assert_eq!(results[2].skipped.as_deref(), Some("dependency failed: unit"));
+
+        assert!(results[3].success);
+        assert!(!results[4].success);
+        assert_eq!(results[4].skipped.as_deref(), Some("dependency not selected: not selected"));
+    }
+
+    #[test]
+    fn dependency_cycles_are_rejected() {
+        let tests = vec![
+            dependent_test("independent", "ok", &[]),
+            dependent_test("a", "ok", &["c"]),
+            dependent_test("b", "ok", &["a"]),
+            dependent_test("c", "ok", &["b"]),
+        ];
+        let err = run_tests(&tests).unwrap_err().to_string();
+        assert_eq!(err, "dependency cycle, cannot order tests: a, b, c");
+
+        let itself = vec![dependent_test("self", "ok", &["self"])];
+        assert!(execution_order(&itself).is_err());
+    }
+
+    #[test]
+    fn unknown_dependency_is_a_schema_error() {
+        let yaml = r#"
+tests:
+  - name: "Build"
+    command: "echo"
+    expected_output: "ok"
+  - name: "Deploy"
+    command: "echo"
+    expected_output: "ok"
+    depends_on: ["Build", "Bulid"]
+"#;
+        let (location, message) = schema_error(parse_config(yaml, "yaml"));
+        assert_eq!(location, r#"tests[1] ("Deploy").depends_on"#);
+        assert_eq!(message, "unknown test `Bulid`");
+
+        let config = parse_config(&yaml.replace(r#", "Bulid""#, ""), "yaml").unwrap();
+        assert_eq!(config.tests[1].depends_on.as_deref(), Some(&["Build".to_string()][..]));
+    }
+
+    #[test]
+    fn valid_config_loads() {
+        let config = load_config(&PathBuf::from("tests.yaml")).unwrap();
+        assert!(!config.tests.is_empty());
+        let config = load_config(&PathBuf::from("tests.toml")).unwrap();
+        assert!(!config.tests.is_empty());
+    }
+}
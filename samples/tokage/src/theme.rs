@@ -0,0 +1,152 @@
+use anyhow::{Context, Result};
+use ratatui::style::Color;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+// 意味的な役割ごとの色設定
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Theme {
+    pub pass: Color,
+    pub fail: Color,
+    pub header: Color,
+    pub accent: Color,
+    // 通常の文字
+    pub text: Color,
+    // ヒントやタグなどの控えめな文字
+    pub muted: Color,
+    // 選択行とステータスバーの背景
+    pub selection: Color,
+}
+
+// 設定ファイルの [theme] セクション
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ThemeConfig {
+    pub pass: Option<String>,
+    pub fail: Option<String>,
+    pub header: Option<String>,
+    pub accent: Option<String>,
+    pub text: Option<String>,
+    pub muted: Option<String>,
+    pub selection: Option<String>,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::dark()
+    }
+}
+
+impl Theme {
+    pub fn dark() -> Self {
+        Theme {
+            pass: Color::Green,
+            fail: Color::Red,
+            header: Color::Yellow,
+            accent: Color::Cyan,
+            text: Color::White,
+            muted: Color::Gray,
+            selection: Color::DarkGray,
+        }
+    }
+
+    pub fn light() -> Self {
+        Theme {
+            pass: Color::Rgb(0, 128, 0),
+            fail: Color::Rgb(178, 34, 34),
+            header: Color::Rgb(0, 0, 139),
+            accent: Color::Rgb(128, 0, 128),
+            text: Color::Black,
+            muted: Color::Rgb(96, 96, 96),
+            selection: Color::Rgb(211, 211, 211),
+        }
+    }
+
+    // 端末のデフォルト色のみを使用
+    pub fn monochrome() -> Self {
+        Theme {
+            pass: Color::Reset,
+            fail: Color::Reset,
+            header: Color::Reset,
+            accent: Color::Reset,
+            text: Color::Reset,
+            muted: Color::Reset,
+            selection: Color::Reset,
+        }
+    }
+
+    pub fn from_name(name: &str) -> Result<Self> {
+        match name.to_lowercase().as_str() {
+            "dark" | "default" => Ok(Theme::dark()),
+            "light" => Ok(Theme::light()),
+            "mono" | "monochrome" => Ok(Theme::monochrome()),
+            _ => Err(anyhow::anyhow!(
+                "Unknown theme: {} (expected dark, light or mono)",
+                name
+            )),
+        }
+    }
+
+    // 設定ファイルで指定された色で上書きする
+    pub fn with_overrides(mut self, config: &ThemeConfig) -> Result<Self> {
+        let roles = [
+            ("pass", &config.pass, &mut self.pass),
+            ("fail", &config.fail, &mut self.fail),
+            ("header", &config.header, &mut self.header),
+            ("accent", &config.accent, &mut self.accent),
+            ("text", &config.text, &mut self.text),
+            ("muted", &config.muted, &mut self.muted),
+            ("selection", &config.selection, &mut self.selection),
+        ];
+
+        for (role, value, slot) in roles {
+            if let Some(value) = value {
+                *slot = Color::from_str(value)
+                    .map_err(|_| anyhow::anyhow!("invalid color `{}`", value))
+                    .with_context(|| format!("Failed to parse theme.{}", role))?;
+            }
+        }
+
+        Ok(self)
+    }
+}
+
+// --no-color フラグまたは NO_COLOR 環境変数（https://no-color.org）を考慮する
+pub fn color_enabled(no_color_flag: bool) -> bool {
+    if no_color_flag {
+        return false;
+    }
+    !std::env::var("NO_COLOR").is_ok_and(|v| !v.is_empty())
+}
+
+// 文字列をANSIエスケープで着色する（無効時はそのまま返す）
+pub fn paint(text: &str, color: Color, use_color: bool) -> String {
+    match ansi_code(color) {
+        Some(code) if use_color => format!("\x1b[{}m{}\x1b[0m", code, text),
+        _ => text.to_string(),
+    }
+}
+
+fn ansi_code(color: Color) -> Option<String> {
+    let code = match color {
+        Color::Reset => return None,
+        Color::Black => "30".to_string(),
+        Color::Red => "31".to_string(),
+        Color::Green => "32".to_string(),
+        Color::Yellow => "33".to_string(),
+        Color::Blue => "34".to_string(),
+        Color::Magenta => "35".to_string(),
+        Color::Cyan => "36".to_string(),
+        Color::Gray => "37".to_string(),
+        Color::DarkGray => "90".to_string(),
+        Color::LightRed => "91".to_string(),
+        Color::LightGreen => "92".to_string(),
+        Color::LightYellow => "93".to_string(),
+        Color::LightBlue => "94".to_string(),
+        Color::LightMagenta => "95".to_string(),
+        Color::LightCyan => "96".to_string(),
+        Color::White => "97".to_string(),
+        Color::Rgb(r, g, b) => format!("38;2;{};{};{}", r, g, b),
+        Color::Indexed(i) => format!("38;5;{}", i),
+    };
+    Some(code)
+}
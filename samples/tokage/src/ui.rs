@@ -1,8 +1,9 @@
 use crate::app::App;
+use crate::test::normalize_tag;
 use ratatui::{
     backend::Backend,
     layout::{Alignment, Constraint, Direction, Layout, Rect},
-    style::{Color, Modifier, Style},
+    style::{Modifier, Style},
     text::{Line as TextLine, Span},
     widgets::{
         Block, BorderType, Borders, Cell, List, ListItem, Paragraph, Row, Table, Tabs, Wrap,
@@ -17,7 +18,7 @@ pub fn render_ui<B: Backend>(frame: &mut Frame, app: &App) {
     
     if app.show_help {
         // Show help overlay
-        render_help::<B>(frame, size);
+        render_help::<B>(frame, size, app);
     } else {
         // Main UI
         let main_chunks = Layout::default()
@@ -34,7 +35,7 @@ pub fn render_ui<B: Backend>(frame: &mut Frame, app: &App) {
             .split(size);
         
         // Title
-        render_title::<B>(frame, main_chunks[0]);
+        render_title::<B>(frame, main_chunks[0], app);
         
         // Tabs
         render_tabs::<B>(frame, main_chunks[1], app);
@@ -48,23 +49,24 @@ pub fn render_ui<B: Backend>(frame: &mut Frame, app: &App) {
         }
         
         // Status bar
-        render_status_bar::<B>(frame, main_chunks[3]);
+        render_status_bar::<B>(frame, main_chunks[3], app);
     }
 }
 
-fn render_title<B: Backend>(frame: &mut Frame, area: Rect) {
+fn render_title<B: Backend>(frame: &mut Frame, area: Rect, app: &App) {
+    let theme = &app.theme;
     let title = Paragraph::new(vec![
         TextLine::from(vec![
-            Span::styled("TO", Style::default().fg(Color::Green).add_modifier(Modifier::BOLD)),
-            Span::styled("KA", Style::default().fg(Color::Blue).add_modifier(Modifier::BOLD)),
-            Span::styled("GE", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
-            Span::styled(" - ", Style::default().fg(Color::White)),
-            Span::styled("Test Observer & Runner Interface", Style::default().fg(Color::Cyan).add_modifier(Modifier::ITALIC)),
+            Span::styled("TO", Style::default().fg(theme.pass).add_modifier(Modifier::BOLD)),
+            Span::styled("KA", Style::default().fg(theme.accent).add_modifier(Modifier::BOLD)),
+            Span::styled("GE", Style::default().fg(theme.header).add_modifier(Modifier::BOLD)),
+            Span::styled(" - ", Style::default().fg(theme.text)),
+            Span::styled("Test Observer & Runner Interface", Style::default().fg(theme.accent).add_modifier(Modifier::ITALIC)),
         ]),
         TextLine::from(vec![
-            Span::styled("Press ", Style::default().fg(Color::Gray)),
-            Span::styled("?", Style::default().fg(Color::White).add_modifier(Modifier::BOLD)),
-            Span::styled(" for help", Style::default().fg(Color::Gray)),
+            Span::styled("Press ", Style::default().fg(theme.muted)),
+            Span::styled("?", Style::default().fg(theme.text).add_modifier(Modifier::BOLD)),
+            Span::styled(" for help", Style::default().fg(theme.muted)),
         ]),
     ])
     .alignment(Alignment::Center)
@@ -72,23 +74,25 @@ fn render_title<B: Backend>(frame: &mut Frame, area: Rect) {
         Block::default()
             .borders(Borders::ALL)
             .border_type(BorderType::Rounded)
-            .border_style(Style::default().fg(Color::Cyan))
+            .border_style(Style::default().fg(theme.accent))
     );
     
     frame.render_widget(title, area);
 }
 
 fn render_tabs<B: Backend>(frame: &mut Frame, area: Rect, app: &App) {
+    let theme = &app.theme;
     let titles = vec!["Test Results", "Statistics", "Diff View"];
     let tabs = Tabs::new(titles.iter().map(|t| TextLine::from(*t)).collect::<Vec<_>>())
         .block(Block::default().borders(Borders::ALL).border_type(BorderType::Rounded))
-        .highlight_style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))
+        .highlight_style(Style::default().fg(theme.header).add_modifier(Modifier::BOLD))
         .select(app.tab_index);
     
     frame.render_widget(tabs, area);
 }
 
 fn render_results_tab<B: Backend>(frame: &mut Frame, area: Rect, app: &App) {
+    let theme = &app.theme;
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints(
@@ -105,26 +109,55 @@ fn render_results_tab<B: Backend>(frame: &mut Frame, area: Rect, app: &App) {
         .test_results
         .iter()
         .enumerate()
+        .filter(|(i, _)| app.is_visible(*i))
         .map(|(i, t)| {
-            let status_symbol = if t.success { "✓" } else { "✗" };
-            let status_color = if t.success { Color::Green } else { Color::Red };
+            let running = app.running == Some(i);
+            let status_symbol = match (running, t.success, &t.error, &t.skipped) {
+                (true, _, _, _) => "…",
+                (false, true, _, _) => "✓",
+                (false, false, Some(_), _) => "!",
+                (false, false, None, Some(_)) => "-",
+                (false, false, None, None) => "✗",
+            };
+            let status_color = if running {
+                theme.accent
+            } else if t.success {
+                theme.pass
+            } else if t.skipped.is_some() {
+                theme.header
+            } else {
+                theme.fail
+            };
             
-            let content = TextLine::from(vec![
+            let mut spans = vec![
                 Span::styled(
                     format!(" {} ", status_symbol),
                     Style::default().fg(status_color).add_modifier(Modifier::BOLD),
                 ),
                 Span::styled(
                     format!("[Test {:02}] ", i + 1),
-                    Style::default().fg(Color::Blue),
+                    Style::default().fg(theme.accent),
                 ),
                 Span::raw(t.name.clone()),
-            ]);
+            ];
+            // タグは名前の後ろに "@fast" の形で並べる
+            if let Some(tags) = app.test_cases.get(i).and_then(|test| test.tags.as_ref()) {
+                for tag in tags {
+                    spans.push(Span::styled(
+                        format!(" @{}", normalize_tag(tag)),
+                        Style::default().fg(theme.muted),
+                    ));
+                }
+            }
+            if running {
+                spans.push(Span::styled(" running…", Style::default().fg(theme.accent)));
+            }
+            let content = TextLine::from(spans);
             
             if i == app.selected_test {
                 ListItem::new(content).style(
                     Style::default()
-                        .bg(Color::DarkGray)
+                        .bg(theme.selection)
                         .add_modifier(Modifier::BOLD),
                 )
             } else {
@@ -133,19 +166,23 @@ fn render_results_tab<B: Backend>(frame: &mut Frame, area: Rect, app: &App) {
         })
         .collect();
     
+    let list_title = match &app.tag_filter {
+        Some(tag) => format!(" Tests (@{}) ", tag),
+        None => " Tests ".to_string(),
+    };
     let tests_list = List::new(tests)
         .block(
             Block::default()
-                .title(" Tests ")
-                .title_style(Style::default().fg(Color::Yellow))
+                .title(list_title)
+                .title_style(Style::default().fg(theme.header))
                 .borders(Borders::ALL)
                 .border_type(BorderType::Rounded)
-                .border_style(Style::default().fg(Color::Blue))
+                .border_style(Style::default().fg(theme.accent))
         )
         .highlight_style(
             Style::default()
-                .bg(Color::DarkGray)
-                .fg(Color::White)
+                .bg(theme.selection)
+                .fg(theme.text)
                 .add_modifier(Modifier::BOLD),
         );
     
@@ -171,7 +208,7 @@ fn render_results_tab<B: Backend>(frame: &mut Frame, area: Rect, app: &App) {
                     .filter(|line| line.tag != ChangeTag::Insert)
                     .map(|line| {
                         let style = match line.tag {
-                            ChangeTag::Delete => Style::default().fg(Color::Red),
+                            ChangeTag::Delete => Style::default().fg(theme.fail),
                             _ => Style::default(),
                         };
                         TextLine::from(vec![Span::styled(&line.content, style)])
@@ -185,12 +222,12 @@ fn render_results_tab<B: Backend>(frame: &mut Frame, area: Rect, app: &App) {
         .block(
             Block::default()
                 .title(expected_title)
-                .title_style(Style::default().fg(Color::Yellow))
+                .title_style(Style::default().fg(theme.header))
                 .borders(Borders::ALL)
                 .border_type(BorderType::Rounded)
                 .border_style(
                     Style::default().fg(
-                        if test_result.success { Color::Green } else { Color::Red }
+                        if test_result.success { theme.pass } else { theme.fail }
                     )
                 )
         )
@@ -207,7 +244,7 @@ fn render_results_tab<B: Backend>(frame: &mut Frame, area: Rect, app: &App) {
                     .filter(|line| line.tag != ChangeTag::Delete)
                     .map(|line| {
                         let style = match line.tag {
-                            ChangeTag::Insert => Style::default().fg(Color::Green),
+                            ChangeTag::Insert => Style::default().fg(theme.pass),
                             _ => Style::default(),
                         };
                         TextLine::from(vec![Span::styled(&line.content, style)])
@@ -221,12 +258,12 @@ fn render_results_tab<B: Backend>(frame: &mut Frame, area: Rect, app: &App) {
         .block(
             Block::default()
                 .title(actual_title)
-                .title_style(Style::default().fg(Color::Yellow))
+                .title_style(Style::default().fg(theme.header))
                 .borders(Borders::ALL)
                 .border_type(BorderType::Rounded)
                 .border_style(
                     Style::default().fg(
-                        if test_result.success { Color::Green } else { Color::Yellow }
+                        if test_result.success { theme.pass } else { theme.header }
                     )
                 )
         )
@@ -237,7 +274,7 @@ fn render_results_tab<B: Backend>(frame: &mut Frame, area: Rect, app: &App) {
     } else {
         // No test selected or no tests available
         let no_tests = Paragraph::new("No test results available")
-            .style(Style::default().fg(Color::Gray))
+            .style(Style::default().fg(theme.muted))
             .alignment(Alignment::Center)
             .block(
                 Block::default()
@@ -251,6 +288,7 @@ fn render_results_tab<B: Backend>(frame: &mut Frame, area: Rect, app: &App) {
 }
 
 fn render_stats_tab<B: Backend>(frame: &mut Frame, area: Rect, app: &App) {
+    let theme = &app.theme;
     let (passed, total, pass_rate) = app.get_stats();
     
     let chunks = Layout::default()
@@ -267,9 +305,9 @@ fn render_stats_tab<B: Backend>(frame: &mut Frame, area: Rect, app: &App) {
     // Summary stats in a fancy table
     let header_cells = ["Metric", "Value"]
         .iter()
-        .map(|h| Cell::from(*h).style(Style::default().fg(Color::Yellow)));
+        .map(|h| Cell::from(*h).style(Style::default().fg(theme.header)));
     let header = Row::new(header_cells)
-        .style(Style::default().fg(Color::Yellow))
+        .style(Style::default().fg(theme.header))
         .height(1)
         .bottom_margin(1);
     
@@ -280,21 +318,21 @@ fn render_stats_tab<B: Backend>(frame: &mut Frame, area: Rect, app: &App) {
         ]),
         Row::new(vec![
             Cell::from("Passed Tests"),
-            Cell::from(passed.to_string()).style(Style::default().fg(Color::Green)),
+            Cell::from(passed.to_string()).style(Style::default().fg(theme.pass)),
         ]),
         Row::new(vec![
             Cell::from("Failed Tests"),
-            Cell::from((total - passed).to_string()).style(Style::default().fg(Color::Red)),
+            Cell::from((total - passed).to_string()).style(Style::default().fg(theme.fail)),
         ]),
         Row::new(vec![
             Cell::from("Pass Rate"),
             Cell::from(format!("{:.1}%", pass_rate)).style(
                 if pass_rate > 90.0 {
-                    Style::default().fg(Color::Green)
+                    Style::default().fg(theme.pass)
                 } else if pass_rate > 70.0 {
-                    Style::default().fg(Color::Yellow)
+                    Style::default().fg(theme.header)
                 } else {
-                    Style::default().fg(Color::Red)
+                    Style::default().fg(theme.fail)
                 }
             ),
         ]),
@@ -305,10 +343,10 @@ fn render_stats_tab<B: Backend>(frame: &mut Frame, area: Rect, app: &App) {
         .block(
             Block::default()
                 .title(" Test Statistics ")
-                .title_style(Style::default().fg(Color::Cyan))
+                .title_style(Style::default().fg(theme.accent))
                 .borders(Borders::ALL)
                 .border_type(BorderType::Rounded)
-                .border_style(Style::default().fg(Color::Blue))
+                .border_style(Style::default().fg(theme.accent))
         );
     
     frame.render_widget(table, chunks[0]);
@@ -321,10 +359,10 @@ fn render_stats_tab<B: Backend>(frame: &mut Frame, area: Rect, app: &App) {
         .block(
             Block::default()
                 .title(" Pass Rate ")
-                .title_style(Style::default().fg(Color::Cyan))
+                .title_style(Style::default().fg(theme.accent))
                 .borders(Borders::ALL)
                 .border_type(BorderType::Rounded)
-                .border_style(Style::default().fg(Color::Blue))
+                .border_style(Style::default().fg(theme.accent))
         )
         .paint(|ctx| {
             // background
@@ -333,7 +371,7 @@ fn render_stats_tab<B: Backend>(frame: &mut Frame, area: Rect, app: &App) {
                 y: 0.0,
                 width: 100.0,
                 height: 5.0,
-                color: Color::DarkGray,
+                color: theme.selection,
             });
             
             // Pass bar (green)
@@ -342,7 +380,7 @@ fn render_stats_tab<B: Backend>(frame: &mut Frame, area: Rect, app: &App) {
                 y: 0.0,
                 width: 100.0 * pass_percentage,
                 height: 5.0,
-                color: Color::Green,
+                color: theme.pass,
             });
             
             // Add a line at 100%
@@ -351,7 +389,7 @@ fn render_stats_tab<B: Backend>(frame: &mut Frame, area: Rect, app: &App) {
                 y1: 0.0,
                 x2: 100.0,
                 y2: 5.0,
-                color: Color::White,
+                color: theme.text,
             });
             
             // Markers at 25%, 50%, 75%
@@ -361,7 +399,7 @@ fn render_stats_tab<B: Backend>(frame: &mut Frame, area: Rect, app: &App) {
                     y1: 0.0,
                     x2: x,
                     y2: 5.0,
-                    color: Color::Gray,
+                    color: theme.muted,
                 });
             }
         })
@@ -372,6 +410,7 @@ fn render_stats_tab<B: Backend>(frame: &mut Frame, area: Rect, app: &App) {
 }
 
 fn render_diff_tab<B: Backend>(frame: &mut Frame, area: Rect, app: &App) {
+    let theme = &app.theme;
     if let Some(test_result) = app.test_results.get(app.selected_test) {
         if let Some(diff) = &test_result.diff {
             // Create a unified diff view
@@ -379,8 +418,8 @@ fn render_diff_tab<B: Backend>(frame: &mut Frame, area: Rect, app: &App) {
             
             // Header
             diff_spans.push(TextLine::from(vec![
-                Span::styled("Diff for test: ", Style::default().fg(Color::White)),
-                Span::styled(&test_result.name, Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+                Span::styled("Diff for test: ", Style::default().fg(theme.text)),
+                Span::styled(&test_result.name, Style::default().fg(theme.header).add_modifier(Modifier::BOLD)),
             ]));
             
             diff_spans.push(TextLine::from(vec![Span::raw("───────────────────────────────────────")]));
@@ -388,8 +427,8 @@ fn render_diff_tab<B: Backend>(frame: &mut Frame, area: Rect, app: &App) {
             // Add each diff line with appropriate styling
             for line in diff {
                 let (prefix, style) = match line.tag {
-                    ChangeTag::Delete => ("-", Style::default().fg(Color::Red)),
-                    ChangeTag::Insert => ("+", Style::default().fg(Color::Green)),
+                    ChangeTag::Delete => ("-", Style::default().fg(theme.fail)),
+                    ChangeTag::Insert => ("+", Style::default().fg(theme.pass)),
                     ChangeTag::Equal => (" ", Style::default()),
                 };
                 
@@ -405,10 +444,10 @@ fn render_diff_tab<B: Backend>(frame: &mut Frame, area: Rect, app: &App) {
                 .block(
                     Block::default()
                         .title(" Unified Diff View ")
-                        .title_style(Style::default().fg(Color::Magenta))
+                        .title_style(Style::default().fg(theme.accent))
                         .borders(Borders::ALL)
                         .border_type(BorderType::Rounded)
-                        .border_style(Style::default().fg(Color::Blue))
+                        .border_style(Style::default().fg(theme.accent))
                 )
                 .wrap(Wrap { trim: false });
             
@@ -416,17 +455,23 @@ fn render_diff_tab<B: Backend>(frame: &mut Frame, area: Rect, app: &App) {
         } else {
             // No diff available (test passed)
             let message = if test_result.success {
-                "✓ Test passed - no differences to display"
+                "✓ Test passed - no differences to display".to_string()
+            } else if let Some(error) = &test_result.error {
+                format!("! Test errored: {}", error)
+            } else if let Some(reason) = &test_result.skipped {
+                format!("- Test skipped: {}", reason)
+            } else if !test_result.forbidden_matches.is_empty() {
+                format!("✗ Output contains forbidden text: {:?}", test_result.forbidden_matches)
             } else {
-                "No diff information available"
+                "No diff information available".to_string()
             };
             
             let no_diff = Paragraph::new(message)
                 .style(
                     if test_result.success {
-                        Style::default().fg(Color::Green)
+                        Style::default().fg(theme.pass)
                     } else {
-                        Style::default().fg(Color::Yellow)
+                        Style::default().fg(theme.header)
                     }
                 )
                 .alignment(Alignment::Center)
@@ -442,7 +487,7 @@ fn render_diff_tab<B: Backend>(frame: &mut Frame, area: Rect, app: &App) {
     } else {
         // No test selected
         let no_test = Paragraph::new("No test selected")
-            .style(Style::default().fg(Color::Gray))
+            .style(Style::default().fg(theme.muted))
             .alignment(Alignment::Center)
             .block(
                 Block::default()
@@ -455,35 +500,48 @@ fn render_diff_tab<B: Backend>(frame: &mut Frame, area: Rect, app: &App) {
     }
 }
 
-fn render_status_bar<B: Backend>(frame: &mut Frame, area: Rect) {
-    let status_text = vec![
-        Span::styled("q", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
-        Span::raw(": quit | "),
-        Span::styled("↑/k", Style::default().fg(Color::Yellow)),
-        Span::raw(" "),
-        Span::styled("↓/j", Style::default().fg(Color::Yellow)),
-        Span::raw(": navigate | "),
-        Span::styled("←/h", Style::default().fg(Color::Yellow)),
-        Span::raw(" "),
-        Span::styled("→/l", Style::default().fg(Color::Yellow)),
-        Span::raw(": tabs | "),
-        Span::styled("?", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
-        Span::raw(": help"),
-    ];
+fn render_status_bar<B: Backend>(frame: &mut Frame, area: Rect, app: &App) {
+    let theme = &app.theme;
+    // 直前の操作結果があればそれを優先して表示する
+    let status_text = if let Some(message) = &app.status_message {
+        vec![Span::styled(message.clone(), Style::default().fg(theme.accent))]
+    } else {
+        vec![
+            Span::styled("q", Style::default().fg(theme.header).add_modifier(Modifier::BOLD)),
+            Span::raw(": quit | "),
+            Span::styled("↑/k", Style::default().fg(theme.header)),
+            Span::raw(" "),
+            Span::styled("↓/j", Style::default().fg(theme.header)),
+            Span::raw(": navigate | "),
+            Span::styled("←/h", Style::default().fg(theme.header)),
+            Span::raw(" "),
+            Span::styled("→/l", Style::default().fg(theme.header)),
+            Span::raw(": tabs | "),
+            Span::styled("y", Style::default().fg(theme.header)),
+            Span::raw(": copy diff | "),
+            Span::styled("Enter/x", Style::default().fg(theme.header)),
+            Span::raw(": re-run | "),
+            Span::styled("t", Style::default().fg(theme.header)),
+            Span::raw(": filter tag | "),
+            Span::styled("?", Style::default().fg(theme.header).add_modifier(Modifier::BOLD)),
+            Span::raw(": help"),
+        ]
+    };
     
     let status_bar = Paragraph::new(TextLine::from(status_text))
-        .style(Style::default().bg(Color::DarkGray))
+        .style(Style::default().bg(theme.selection))
         .alignment(Alignment::Center);
     
     frame.render_widget(status_bar, area);
 }
 
-fn render_help<B: Backend>(frame: &mut Frame, area: Rect) {
+fn render_help<B: Backend>(frame: &mut Frame, area: Rect, app: &App) {
+    let theme = &app.theme;
     let help_text = vec![
         TextLine::from(vec![
             Span::styled(
                 "Tokage Test Runner - Help",
-                Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
+                Style::default().fg(theme.header).add_modifier(Modifier::BOLD),
             ),
         ]),
         TextLine::from(vec![Span::raw("")]),
@@ -491,17 +549,31 @@ fn render_help<B: Backend>(frame: &mut Frame, area: Rect) {
             Span::styled("Navigation", Style::default().add_modifier(Modifier::UNDERLINED)),
         ]),
         TextLine::from(vec![
-            Span::styled("    j", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+            Span::styled("    j", Style::default().fg(theme.header).add_modifier(Modifier::BOLD)),
             Span::raw(" or "),
-            Span::styled("↓", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+            Span::styled("↓", Style::default().fg(theme.header).add_modifier(Modifier::BOLD)),
             Span::raw(": Move to next test"),
         ]),
         TextLine::from(vec![
-            Span::styled("    k", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+            Span::styled("    k", Style::default().fg(theme.header).add_modifier(Modifier::BOLD)),
             Span::raw(" or "),
-            Span::styled("↑", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+            Span::styled("↑", Style::default().fg(theme.header).add_modifier(Modifier::BOLD)),
             Span::raw(": Move to previous test"),
         ]),
+        TextLine::from(vec![
+            Span::styled("    y", Style::default().fg(theme.header).add_modifier(Modifier::BOLD)),
+            Span::raw(": Copy the selected test's command and diff to the clipboard"),
+        ]),
+        TextLine::from(vec![
+            Span::styled("    Enter", Style::default().fg(theme.header).add_modifier(Modifier::BOLD)),
+            Span::raw(" or "),
+            Span::styled("x", Style::default().fg(theme.header).add_modifier(Modifier::BOLD)),
+            Span::raw(": Re-run the selected test (Results tab)"),
+        ]),
+        TextLine::from(vec![
+            Span::styled("    t", Style::default().fg(theme.header).add_modifier(Modifier::BOLD)),
+            Span::raw(": Show only tests with the next tag (cycles back to all tests)"),
+        ]),
         // 他のヘルプテキストを追加
     ];
     
@@ -511,7 +583,7 @@ fn render_help<B: Backend>(frame: &mut Frame, area: Rect) {
                 .title(" Help ")
                 .borders(Borders::ALL)
                 .border_type(BorderType::Rounded)
-                .border_style(Style::default().fg(Color::Cyan))
+                .border_style(Style::default().fg(theme.accent))
         )
         .alignment(Alignment::Left)
         .wrap(Wrap { trim: true });
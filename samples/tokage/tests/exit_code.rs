@@ -0,0 +1,151 @@
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+// 一時ディレクトリにテスト設定を書き出す
+fn write_config(name: &str, content: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("tokage-{}-{}.yaml", name, std::process::id()));
+    std::fs::write(&path, content).unwrap();
+    path
+}
+
+fn run_tokage(config: &PathBuf, extra_args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_tokage"))
+        .arg("--config")
+        .arg(config)
+        .args(["--no-ui", "--no-color"])
+        .args(extra_args)
+        .output()
+        .unwrap()
+}
+
+const PASSING_SUITE: &str = r#"
+tests:
+  - name: "Echo Test"
+    command: "echo"
+    args: ["hello"]
+    expected_output: "hello"
+"#;
+
+const FAILING_SUITE: &str = r#"
+tests:
+  - name: "Echo Test"
+    command: "echo"
+    args: ["hello"]
+    expected_output: "hello"
+  - name: "Wrong Output Test"
+    command: "echo"
+    args: ["hello"]
+    expected_output: "goodbye"
+"#;
+
+#[test]
+fn failing_suite_exits_non_zero() {
+    let config = write_config("failing", FAILING_SUITE);
+    let output = run_tokage(&config, &[]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stdout.lines().last().unwrap().contains("1/2 tests passed, 1 failed, 0 errored"));
+
+    // レポートのみの実行では失敗があっても0で終了する
+    let output = run_tokage(&config, &["--exit-zero"]);
+    assert_eq!(output.status.code(), Some(0));
+
+    std::fs::remove_file(config).unwrap();
+}
+
+#[test]
+fn passing_suite_exits_zero() {
+    let config = write_config("passing", PASSING_SUITE);
+    let output = run_tokage(&config, &[]);
+
+    assert_eq!(output.status.code(), Some(0));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("1/1 tests passed"));
+
+    // 以前の --no-tui も使える
+    let output = Command::new(env!("CARGO_BIN_EXE_tokage"))
+        .arg("--config")
+        .arg(&config)
+        .args(["--no-tui", "--no-color"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(0));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("1/1 tests passed"));
+
+    std::fs::remove_file(config).unwrap();
+}
+
+const TAGGED_SUITE: &str = r#"
+tests:
+  - name: "Fast Test"
+    command: "echo"
+    args: ["hello"]
+    expected_output: "hello"
+    tags: ["@fast"]
+  - name: "Slow Failing Test"
+    command: "echo"
+    args: ["hello"]
+    expected_output: "goodbye"
+    tags: ["@slow"]
+  - name: "Fast Flaky Test"
+    command: "echo"
+    args: ["hello"]
+    expected_output: "goodbye"
+    tags: ["@fast", "@flaky"]
+"#;
+
+#[test]
+fn tag_filters_select_tests() {
+    let config = write_config("tagged", TAGGED_SUITE);
+
+    let output = run_tokage(&config, &["--tag", "@fast", "--exclude-tag", "flaky"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(output.status.code(), Some(0));
+    assert!(stdout.contains("Fast Test"));
+    assert!(stdout.contains("1/1 tests passed"));
+
+    let output = run_tokage(&config, &["--skip", "Slow"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("1/2 tests passed"));
+
+    // 1件も選ばれない場合は設定ミスとみなす
+    let output = run_tokage(&config, &["--tag", "missing"]);
+    assert_ne!(output.status.code(), Some(0));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("no tests match"));
+
+    std::fs::remove_file(config).unwrap();
+}
+
+const DEPENDENT_SUITE: &str = r#"
+tests:
+  - name: "Deploy Test"
+    command: "echo"
+    args: ["deployed"]
+    expected_output: "deployed"
+    depends_on: ["Build Test"]
+  - name: "Build Test"
+    command: "echo"
+    args: ["broken"]
+    expected_output: "built"
+"#;
+
+#[test]
+fn failed_dependency_is_reported_as_skipped() {
+    let config = write_config("dependent", DEPENDENT_SUITE);
+    let output = run_tokage(&config, &[]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stdout.contains("- SKIP Deploy Test"));
+    assert!(stdout.contains("skipped: dependency failed: Build Test"));
+    assert!(stdout.lines().last().unwrap().contains("0/2 tests passed, 1 failed, 0 errored, 1 skipped"));
+
+    // 絞り込みで外した依存先は成功したとはみなさない
+    let output = run_tokage(&config, &["--skip", "Build"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stdout.contains("skipped: dependency not selected: Build Test"));
+    assert!(stdout.lines().last().unwrap().contains("0/1 tests passed, 0 failed, 0 errored, 1 skipped"));
+
+    std::fs::remove_file(config).unwrap();
+}
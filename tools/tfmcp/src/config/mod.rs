@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs;
 use std::path::Path;
 use thiserror::Error;
@@ -26,11 +27,38 @@ pub struct TerraformConfig {
     pub executable_path: Option<String>,
     pub project_directory: Option<String>,
     pub auto_init: Option<bool>,
+    /// File that executed terraform commands are appended to (JSON lines)
+    pub audit_log_path: Option<String>,
+    /// Check for drift and pending changes when the MCP server starts (default: false)
+    pub startup_check: Option<bool>,
+    /// Only apply saved plans (`plan_file`), rejecting them if the configuration changed since (default: false)
+    pub require_plan_file: Option<bool>,
+    /// Named projects that can be selected with the `switch_project` tool
+    pub projects: Option<BTreeMap<String, ProjectConfig>>,
+    /// Monthly prices (USD) for `estimate_cost`, keyed by resource type or `type:size`;
+    /// they extend or override the built-in price table
+    pub prices: Option<BTreeMap<String, f64>>,
+}
+
+/// A Terraform project selectable by name
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ProjectConfig {
+    /// Project directory (relative paths are resolved against the current directory)
+    pub directory: String,
+    /// File passed to `terraform init` as `-backend-config`
+    pub backend_config: Option<String>,
+    /// Files passed to plan and apply as `-var-file`
+    #[serde(default)]
+    pub var_files: Vec<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct McpConfig {
     pub tools: Vec<String>,
+    /// Tool outputs larger than this many bytes are returned as resources
+    pub inline_result_limit: Option<usize>,
+    /// Number of stored result resources kept before the least recently used is dropped
+    pub stored_result_limit: Option<usize>,
 }
 
 pub fn init_default() -> anyhow::Result<Config> {
@@ -52,6 +80,11 @@ pub fn init_default() -> anyhow::Result<Config> {
             executable_path: None,
             project_directory: None,
             auto_init: Some(true),
+            audit_log_path: None,
+            startup_check: None,
+            require_plan_file: None,
+            projects: None,
+            prices: None,
         },
         mcp: McpConfig {
             tools: vec![
@@ -60,6 +93,8 @@ pub fn init_default() -> anyhow::Result<Config> {
                 "get_terraform_plan".to_string(),
                 "apply_terraform".to_string(),
             ],
+            inline_result_limit: None,
+            stored_result_limit: None,
         },
     })
 }
@@ -1,7 +1,9 @@
-use crate::config::{self, Config};
+use crate::config::{self, Config, ProjectConfig};
 use crate::mcp::handler::McpHandler;
 use crate::mcp::stdio::StdioTransport;
-use crate::terraform::service::TerraformService;
+use crate::terraform::cost::{estimate_cost, PricingProvider, StaticPriceTable};
+use crate::terraform::model::{ApplyPreview, AuditEntry, CostEstimate, ProjectInfo, StartupCheck};
+use crate::terraform::service::{ApplyOptions, ProjectOptions, TerraformService};
 use crate::shared::logging;
 use std::path::{Path, PathBuf};
 
@@ -16,6 +18,9 @@ pub enum TfMcpError {
     #[error("Error running Terraform command: {0}")]
     TerraformCommandError(String),
 
+    #[error("Unknown project '{name}' (configured: {available})")]
+    UnknownProject { name: String, available: String },
+
     #[error(transparent)]
     Other(#[from] anyhow::Error),
 }
@@ -36,6 +41,10 @@ pub enum JsonRpcErrorCode {
 pub struct TfMcp {
     config: Config,
     terraform_service: TerraformService,
+    /// Prices used by `estimate_cost`
+    pricing: Box<dyn PricingProvider>,
+    /// Project selected with `switch_project`; cleared when the directory is set directly
+    active_project: Option<String>,
 }
 
 impl TfMcp {
@@ -197,8 +206,18 @@ resource "local_file" "example" {
             std::fs::write(&main_tf_path, sample_tf_content)?;
         }
         
+        let audit_log_path = match &config.terraform.audit_log_path {
+            Some(path) => PathBuf::from(path),
+            None => dirs::home_dir()
+                .unwrap_or_else(|| PathBuf::from("."))
+                .join(".config/tfmcp/audit.log"),
+        };
+        logging::info(&format!("Recording terraform commands to audit log: {}", audit_log_path.display()));
+        
         let terraform_service = match TerraformService::new(terraform_path, project_directory) {
-            Ok(service) => service,
+            Ok(service) => service
+                .with_audit_log_path(audit_log_path)
+                .with_require_plan_file(config.terraform.require_plan_file.unwrap_or(false)),
             Err(e) => {
                 logging::error(&format!("Error creating TerraformService: {}", e));
                 // Instead of immediately returning error, create a dummy service
@@ -207,10 +226,15 @@ resource "local_file" "example" {
             }
         };
         
+        let pricing = StaticPriceTable::default()
+            .with_prices(config.terraform.prices.clone().unwrap_or_default());
+        
         logging::info("TfMcp initialized successfully");
         Ok(Self {
             config,
             terraform_service,
+            pricing: Box::new(pricing),
+            active_project: None,
         })
     }
     
@@ -265,6 +289,10 @@ resource "local_file" "example" {
         handler.launch_mcp(&transport).await
     }
     
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+    
     pub async fn analyze_terraform(&mut self) -> anyhow::Result<()> {
         let analysis = self.terraform_service.analyze_configurations().await?;
         println!("{}", serde_json::to_string_pretty(&analysis)?);
@@ -279,8 +307,24 @@ resource "local_file" "example" {
         self.terraform_service.get_plan().await
     }
     
-    pub async fn apply_terraform(&self, auto_approve: bool) -> anyhow::Result<String> {
-        self.terraform_service.apply(auto_approve).await
+    pub async fn get_terraform_plan_with(&self, options: &ApplyOptions) -> anyhow::Result<String> {
+        self.terraform_service.plan(options).await
+    }
+    
+    pub async fn apply_terraform(&self, options: &ApplyOptions) -> anyhow::Result<String> {
+        self.terraform_service.apply(options).await
+    }
+    
+    pub fn preview_apply(&self, options: &ApplyOptions) -> ApplyPreview {
+        self.terraform_service.preview_apply(options)
+    }
+    
+    pub async fn startup_check(&self) -> anyhow::Result<StartupCheck> {
+        self.terraform_service.startup_check().await
+    }
+    
+    pub fn command_history(&self, limit: usize) -> Vec<AuditEntry> {
+        self.terraform_service.command_history(limit)
     }
     
     pub async fn init_terraform(&self) -> anyhow::Result<String> {
@@ -295,10 +339,84 @@ resource "local_file" "example" {
         self.terraform_service.list_resources().await
     }
     
+    pub async fn terraform_console(&self, expression: &str) -> anyhow::Result<String> {
+        self.terraform_service.console(expression).await
+    }
+    
+    /// Advisory monthly cost delta of the changes in a saved plan
+    pub async fn estimate_cost(&self, plan_file: &Path) -> anyhow::Result<CostEstimate> {
+        let changes = self.terraform_service.resource_changes(plan_file).await?;
+        Ok(estimate_cost(&changes, self.pricing.as_ref()))
+    }
+    
     pub async fn validate_configuration(&self) -> anyhow::Result<String> {
         self.terraform_service.validate().await
     }
 
+    /// Named projects from the configuration, sorted by name
+    pub fn list_projects(&self) -> Vec<ProjectInfo> {
+        self.projects()
+            .map(|(name, project)| ProjectInfo {
+                name: name.clone(),
+                directory: project.directory.clone(),
+                backend_config: project.backend_config.clone(),
+                var_files: project.var_files.clone(),
+                active: self.active_project.as_ref() == Some(name),
+            })
+            .collect()
+    }
+
+    pub fn active_project(&self) -> Option<&str> {
+        self.active_project.as_deref()
+    }
+
+    /// Selects a configured project: its directory, backend config and var files
+    ///
+    /// Unlike `change_project_directory`, the directory must already contain a
+    /// Terraform configuration; no sample project is created.
+    pub fn switch_project(&mut self, name: &str) -> anyhow::Result<ProjectInfo> {
+        let project = match self.projects().find(|(project_name, _)| *project_name == name) {
+            Some((_, project)) => project.clone(),
+            None => {
+                let available: Vec<&str> = self.projects().map(|(name, _)| name.as_str()).collect();
+                return Err(TfMcpError::UnknownProject {
+                    name: name.to_string(),
+                    available: if available.is_empty() {
+                        "none".to_string()
+                    } else {
+                        available.join(", ")
+                    },
+                }
+                .into());
+            }
+        };
+
+        let dir_path = PathBuf::from(&project.directory);
+        let project_directory = if dir_path.is_absolute() {
+            dir_path
+        } else {
+            std::env::current_dir()?.join(dir_path)
+        };
+        self.terraform_service.change_project_directory(project_directory.clone())?;
+        self.terraform_service.set_project_options(ProjectOptions {
+            backend_config: project.backend_config.as_ref().map(PathBuf::from),
+            var_files: project.var_files.iter().map(PathBuf::from).collect(),
+        });
+        std::env::set_var("TERRAFORM_DIR", project_directory.to_string_lossy().to_string());
+        self.active_project = Some(name.to_string());
+        logging::info(&format!("Switched to project '{}' in {}", name, project_directory.display()));
+
+        Ok(self
+            .list_projects()
+            .into_iter()
+            .find(|info| info.name == name)
+            .expect("switched project is configured"))
+    }
+
+    fn projects(&self) -> impl Iterator<Item = (&String, &ProjectConfig)> {
+        self.config.terraform.projects.iter().flatten()
+    }
+
     // プロジェクトディレクトリを変更するメソッド
     pub fn change_project_directory(&mut self, new_directory: String) -> anyhow::Result<()> {
         let dir_path = PathBuf::from(new_directory);
@@ -355,6 +473,9 @@ resource "local_file" "example" {
         // TerraformServiceのプロジェクトディレクトリを変更
         match self.terraform_service.change_project_directory(project_directory.clone()) {
             Ok(_) => {
+                // 名前付きプロジェクトの設定は引き継がない
+                self.terraform_service.set_project_options(ProjectOptions::default());
+                self.active_project = None;
                 // 環境変数も更新
                 std::env::set_var("TERRAFORM_DIR", project_directory.to_string_lossy().to_string());
                 logging::info(&format!("Successfully changed project directory to: {}", project_directory.display()));
@@ -372,3 +493,103 @@ resource "local_file" "example" {
         self.terraform_service.get_project_directory().clone()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    /// Creates two configured projects and a fake terraform that echoes its arguments
+    fn tfmcp_with_projects() -> (tempfile::TempDir, TfMcp) {
+        let dir = tempfile::tempdir().unwrap();
+        for project in ["default", "staging", "production"] {
+            let project_dir = dir.path().join(project);
+            std::fs::create_dir(&project_dir).unwrap();
+            std::fs::write(project_dir.join("main.tf"), "resource \"null_resource\" \"a\" {}\n").unwrap();
+        }
+
+        let terraform = dir.path().join("terraform");
+        std::fs::write(&terraform, "#!/bin/sh\necho \"$@\"\n").unwrap();
+        std::fs::set_permissions(&terraform, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let config = serde_json::json!({
+            "terraform": {
+                "executable_path": terraform,
+                "audit_log_path": dir.path().join("audit.log"),
+                "projects": {
+                    "staging": {
+                        "directory": dir.path().join("staging"),
+                        "backend_config": "backend/staging.hcl",
+                        "var_files": ["staging.tfvars"]
+                    },
+                    "production": {
+                        "directory": dir.path().join("production")
+                    }
+                }
+            },
+            "mcp": { "tools": [] }
+        });
+        let config_path = dir.path().join("tfmcp.json");
+        std::fs::write(&config_path, config.to_string()).unwrap();
+
+        let tfmcp = TfMcp::new(
+            Some(config_path.to_string_lossy().to_string()),
+            Some(dir.path().join("default").to_string_lossy().to_string()),
+        )
+        .unwrap();
+        (dir, tfmcp)
+    }
+
+    #[test]
+    fn test_list_projects() {
+        let (dir, tfmcp) = tfmcp_with_projects();
+
+        let projects = tfmcp.list_projects();
+        let names: Vec<&str> = projects.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, vec!["production", "staging"]);
+        assert_eq!(projects[1].directory, dir.path().join("staging").to_string_lossy());
+        assert_eq!(projects[1].backend_config.as_deref(), Some("backend/staging.hcl"));
+        assert_eq!(projects[1].var_files, vec!["staging.tfvars"]);
+        assert!(projects.iter().all(|p| !p.active));
+        assert_eq!(tfmcp.active_project(), None);
+    }
+
+    #[tokio::test]
+    async fn test_switch_project_uses_project_settings() {
+        let (dir, mut tfmcp) = tfmcp_with_projects();
+
+        let project = tfmcp.switch_project("staging").unwrap();
+        assert!(project.active);
+        assert_eq!(tfmcp.active_project(), Some("staging"));
+        assert_eq!(tfmcp.get_project_directory(), dir.path().join("staging"));
+
+        let plan = tfmcp.get_terraform_plan().await.unwrap();
+        assert_eq!(plan.trim(), "plan -no-color -var-file=staging.tfvars");
+        let init = tfmcp.init_terraform().await.unwrap();
+        assert_eq!(init.trim(), "init -no-color -backend-config=backend/staging.hcl");
+
+        // ディレクトリを直接指定するとプロジェクトの設定は外れる
+        tfmcp
+            .change_project_directory(dir.path().join("default").to_string_lossy().to_string())
+            .unwrap();
+        assert_eq!(tfmcp.active_project(), None);
+        let plan = tfmcp.get_terraform_plan().await.unwrap();
+        assert_eq!(plan.trim(), "plan -no-color");
+    }
+
+    #[test]
+    fn test_switch_to_unknown_project_is_rejected() {
+        let (dir, mut tfmcp) = tfmcp_with_projects();
+
+        let err = tfmcp.switch_project("qa").unwrap_err();
+        match err.downcast_ref::<TfMcpError>() {
+            Some(TfMcpError::UnknownProject { name, available }) => {
+                assert_eq!(name, "qa");
+                assert_eq!(available, "production, staging");
+            }
+            other => panic!("unexpected error: {:?}", other),
+        }
+        assert_eq!(tfmcp.active_project(), None);
+        assert_eq!(tfmcp.get_project_directory(), dir.path().join("default"));
+    }
+}
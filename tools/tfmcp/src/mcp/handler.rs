@@ -1,11 +1,18 @@
 use crate::core::tfmcp::{JsonRpcErrorCode, TfMcp, TfMcpError};
+use crate::mcp::resources::{
+    ResultStore, ToolOutput, DEFAULT_INLINE_LIMIT, DEFAULT_RESULT_CAPACITY,
+};
 use crate::mcp::stdio::{Message, StdioTransport, Transport};
-use crate::terraform::model::TerraformAnalysis;
+use crate::terraform::model::{StartupCheck, TerraformAnalysis};
+use crate::terraform::service::{ApplyOptions, TerraformError};
 use futures::StreamExt;
 use serde_json::{json, Value};
 use std::path::PathBuf;
 use crate::shared::logging;
 
+/// Number of audit entries returned by `get_command_history` when no limit is given
+const DEFAULT_HISTORY_LIMIT: usize = 20;
+
 const TOOLS_JSON: &str = r#"{
   "tools": [
     {
@@ -78,7 +85,12 @@ const TOOLS_JSON: &str = r#"{
       "description": "Execute 'terraform plan' and return the output",
       "inputSchema": {
         "type": "object",
-        "properties": {}
+        "properties": {
+          "plan_file": {
+            "type": "string",
+            "description": "Save the plan to this file (terraform plan -out) so apply_terraform can apply exactly this plan"
+          }
+        }
       },
       "outputSchema": {
         "type": "object",
@@ -100,6 +112,28 @@ const TOOLS_JSON: &str = r#"{
           "auto_approve": {
             "type": "boolean",
             "description": "Whether to auto-approve changes without confirmation"
+          },
+          "dry_run": {
+            "type": "boolean",
+            "description": "Return the command that would be executed and the change summary recorded by the matching plan, without running terraform"
+          },
+          "variables": {
+            "type": "object",
+            "additionalProperties": { "type": "string" },
+            "description": "Input variables passed as -var name=value"
+          },
+          "targets": {
+            "type": "array",
+            "items": { "type": "string" },
+            "description": "Resource addresses passed as -target"
+          },
+          "workspace": {
+            "type": "string",
+            "description": "Terraform workspace to run in (sets TF_WORKSPACE)"
+          },
+          "plan_file": {
+            "type": "string",
+            "description": "Apply a plan saved by get_terraform_plan; rejected if the configuration changed since the plan was generated"
           }
         }
       },
@@ -154,6 +188,72 @@ const TOOLS_JSON: &str = r#"{
         "required": ["state"]
       }
     },
+    {
+      "name": "estimate_cost",
+      "description": "Estimate the monthly cost delta of a saved plan's resource changes (advisory; unknown resource types are reported as unpriced)",
+      "inputSchema": {
+        "type": "object",
+        "properties": {
+          "plan_file": {
+            "type": "string",
+            "description": "Saved plan file created by get_terraform_plan with plan_file"
+          }
+        },
+        "required": ["plan_file"]
+      },
+      "outputSchema": {
+        "type": "object",
+        "properties": {
+          "currency": { "type": "string" },
+          "monthly_delta": {
+            "type": "number",
+            "description": "Estimated change of the monthly cost over the priced resources"
+          },
+          "items": {
+            "type": "array",
+            "items": {
+              "type": "object",
+              "properties": {
+                "address": { "type": "string" },
+                "resource_type": { "type": "string" },
+                "action": { "type": "string", "enum": ["create", "update", "delete", "replace"] },
+                "monthly_delta": { "type": ["number", "null"] }
+              }
+            }
+          },
+          "unpriced": {
+            "type": "array",
+            "items": { "type": "string" },
+            "description": "Addresses of changed resources without a known price"
+          }
+        },
+        "required": ["currency", "monthly_delta", "items", "unpriced"]
+      }
+    },
+    {
+      "name": "terraform_console",
+      "description": "Evaluate a Terraform expression (e.g. a computed attribute or a function call) with terraform console against the current state",
+      "inputSchema": {
+        "type": "object",
+        "properties": {
+          "expression": {
+            "type": "string",
+            "description": "Expression to evaluate, e.g. aws_instance.web.private_ip"
+          }
+        },
+        "required": ["expression"]
+      },
+      "outputSchema": {
+        "type": "object",
+        "properties": {
+          "result": {
+            "type": "string",
+            "description": "The evaluated value as printed by terraform console"
+          }
+        },
+        "required": ["result"]
+      }
+    },
     {
       "name": "init_terraform",
       "description": "Initialize a Terraform project",
@@ -172,6 +272,40 @@ const TOOLS_JSON: &str = r#"{
         "required": ["output"]
       }
     },
+    {
+      "name": "get_command_history",
+      "description": "Get the audit trail of terraform commands executed by tfmcp, most recent first",
+      "inputSchema": {
+        "type": "object",
+        "properties": {
+          "limit": {
+            "type": "integer",
+            "description": "Maximum number of entries to return (default 20)"
+          }
+        }
+      },
+      "outputSchema": {
+        "type": "object",
+        "properties": {
+          "history": {
+            "type": "array",
+            "items": {
+              "type": "object",
+              "properties": {
+                "timestamp": { "type": "string" },
+                "subcommand": { "type": "string" },
+                "args": { "type": "array", "items": { "type": "string" } },
+                "exit_code": { "type": ["integer", "null"] },
+                "duration_ms": { "type": "integer" },
+                "working_dir": { "type": "string" }
+              }
+            },
+            "description": "Executed terraform commands with variable values redacted"
+          }
+        },
+        "required": ["history"]
+      }
+    },
     {
       "name": "set_terraform_directory",
       "description": "Change the current Terraform project directory",
@@ -203,6 +337,58 @@ const TOOLS_JSON: &str = r#"{
         },
         "required": ["success", "directory", "message"]
       }
+    },
+    {
+      "name": "list_projects",
+      "description": "List the named Terraform projects from the tfmcp configuration",
+      "inputSchema": {
+        "type": "object",
+        "properties": {}
+      },
+      "outputSchema": {
+        "type": "object",
+        "properties": {
+          "projects": {
+            "type": "array",
+            "items": {
+              "type": "object",
+              "properties": {
+                "name": { "type": "string" },
+                "directory": { "type": "string" },
+                "backend_config": { "type": ["string", "null"] },
+                "var_files": { "type": "array", "items": { "type": "string" } },
+                "active": { "type": "boolean" }
+              }
+            },
+            "description": "Configured projects sorted by name"
+          }
+        },
+        "required": ["projects"]
+      }
+    },
+    {
+      "name": "switch_project",
+      "description": "Switch to a named Terraform project (directory, backend config and var files) from the tfmcp configuration",
+      "inputSchema": {
+        "type": "object",
+        "properties": {
+          "name": {
+            "type": "string",
+            "description": "Project name as listed by list_projects"
+          }
+        },
+        "required": ["name"]
+      },
+      "outputSchema": {
+        "type": "object",
+        "properties": {
+          "project": {
+            "type": "object",
+            "description": "The now active project"
+          }
+        },
+        "required": ["project"]
+      }
     }
   ]
 }"#;
@@ -210,13 +396,26 @@ const TOOLS_JSON: &str = r#"{
 pub struct McpHandler<'a> {
     tfmcp: &'a mut TfMcp,
     initialized: bool,
+    result_store: ResultStore,
 }
 
 impl<'a> McpHandler<'a> {
     pub fn new(tfmcp: &'a mut TfMcp) -> Self {
+        let inline_limit = tfmcp
+            .config()
+            .mcp
+            .inline_result_limit
+            .unwrap_or(DEFAULT_INLINE_LIMIT);
+        let result_capacity = tfmcp
+            .config()
+            .mcp
+            .stored_result_limit
+            .unwrap_or(DEFAULT_RESULT_CAPACITY);
+
         Self {
             tfmcp,
             initialized: false,
+            result_store: ResultStore::new(inline_limit).with_capacity(result_capacity),
         }
     }
 
@@ -226,6 +425,21 @@ impl<'a> McpHandler<'a> {
         logging::info("MCP stdio transport server started. Waiting for JSON messages on stdin...");
         logging::send_log_message(transport, logging::LogLevel::Info, "tfmcp server initialized and ready").await?;
 
+        // Opt-in because it runs two plans, which can be slow for large projects
+        if self.tfmcp.config().terraform.startup_check.unwrap_or(false) {
+            match self.tfmcp.startup_check().await {
+                Ok(check) => report_startup_check(transport, &check).await?,
+                Err(e) => {
+                    logging::log_both(
+                        transport,
+                        logging::LogLevel::Warning,
+                        &format!("Startup check failed: {}", e),
+                    )
+                    .await?;
+                }
+            }
+        }
+
         while let Some(msg_result) = stream.next().await {
             match msg_result {
                 Ok(Message::Request {
@@ -310,6 +524,7 @@ impl<'a> McpHandler<'a> {
                 }
             }
             "resources/list" => self.handle_resources_list(transport, id).await?,
+            "resources/read" => self.handle_resources_read(transport, id, params).await?,
             "prompts/list" => self.handle_prompts_list(transport, id).await?,
             _ => {
                 self.send_error_response(
@@ -401,7 +616,7 @@ impl<'a> McpHandler<'a> {
                 self.handle_analyze_terraform(transport, id, &params_val).await?;
             }
             "get_terraform_plan" => {
-                self.handle_get_terraform_plan(transport, id).await?;
+                self.handle_get_terraform_plan(transport, id, &params_val).await?;
             }
             "apply_terraform" => {
                 self.handle_apply_terraform(transport, id, &params_val).await?;
@@ -412,12 +627,27 @@ impl<'a> McpHandler<'a> {
             "get_terraform_state" => {
                 self.handle_get_terraform_state(transport, id).await?;
             }
+            "estimate_cost" => {
+                self.handle_estimate_cost(transport, id, &params_val).await?;
+            }
+            "terraform_console" => {
+                self.handle_terraform_console(transport, id, &params_val).await?;
+            }
             "init_terraform" => {
                 self.handle_init_terraform(transport, id).await?;
             }
+            "get_command_history" => {
+                self.handle_get_command_history(transport, id, &params_val).await?;
+            }
             "set_terraform_directory" => {
                 self.handle_set_terraform_directory(transport, id, &params_val).await?;
             }
+            "list_projects" => {
+                self.handle_list_projects(transport, id).await?;
+            }
+            "switch_project" => {
+                self.handle_switch_project(transport, id, &params_val).await?;
+            }
             _ => {
                 self.send_error_response(
                     transport,
@@ -494,12 +724,22 @@ impl<'a> McpHandler<'a> {
         &self,
         transport: &StdioTransport,
         id: u64,
+        params_val: &serde_json::Value,
     ) -> anyhow::Result<()> {
-        match self.tfmcp.get_terraform_plan().await {
+        let plan = match parse_plan_file(params_val) {
+            Some(plan_file) => {
+                let options = ApplyOptions {
+                    plan_file: Some(plan_file),
+                    ..ApplyOptions::default()
+                };
+                self.tfmcp.get_terraform_plan_with(&options).await
+            }
+            None => self.tfmcp.get_terraform_plan().await,
+        };
+        match plan {
             Ok(plan) => {
-                let result_json = json!({ "plan": plan });
-                let obj_as_str = serde_json::to_string(&result_json)?;
-                self.send_text_response(transport, id, &obj_as_str).await?;
+                self.send_tool_output(transport, id, "get_terraform_plan", "plan", plan)
+                    .await?;
             }
             Err(err) => {
                 self.send_error_response(
@@ -521,16 +761,21 @@ impl<'a> McpHandler<'a> {
         id: u64,
         params_val: &serde_json::Value,
     ) -> anyhow::Result<()> {
-        let auto_approve = params_val
-            .pointer("/arguments/auto_approve")
+        let options = parse_apply_options(params_val);
+        let dry_run = params_val
+            .pointer("/arguments/dry_run")
             .and_then(Value::as_bool)
             .unwrap_or(false);
 
-        match self.tfmcp.apply_terraform(auto_approve).await {
+        if dry_run {
+            let obj_as_str = serde_json::to_string(&self.apply_dry_run(&options))?;
+            return self.send_text_response(transport, id, &obj_as_str).await;
+        }
+
+        match self.tfmcp.apply_terraform(&options).await {
             Ok(result) => {
-                let result_json = json!({ "result": result });
-                let obj_as_str = serde_json::to_string(&result_json)?;
-                self.send_text_response(transport, id, &obj_as_str).await?;
+                self.send_tool_output(transport, id, "apply_terraform", "result", result)
+                    .await?;
             }
             Err(err) => {
                 self.send_error_response(
@@ -546,6 +791,22 @@ impl<'a> McpHandler<'a> {
         Ok(())
     }
 
+    /// Dry-run result of `apply_terraform`; terraform itself is never run
+    ///
+    /// The plan summary is the one recorded by the last matching `plan`.
+    fn apply_dry_run(&self, options: &ApplyOptions) -> Value {
+        let preview = self.tfmcp.preview_apply(options);
+        let mut result = json!({
+            "dry_run": true,
+            "command": preview.command,
+            "plan_summary": preview.plan_summary
+        });
+        if let Some(plan_error) = preview.plan_error {
+            result["plan_error"] = json!(plan_error);
+        }
+        result
+    }
+
     async fn handle_validate_terraform(
         &self,
         transport: &StdioTransport,
@@ -583,9 +844,8 @@ impl<'a> McpHandler<'a> {
     ) -> anyhow::Result<()> {
         match self.tfmcp.get_state().await {
             Ok(state) => {
-                let result_json = json!({ "state": state });
-                let obj_as_str = serde_json::to_string(&result_json)?;
-                self.send_text_response(transport, id, &obj_as_str).await?;
+                self.send_tool_output(transport, id, "get_terraform_state", "state", state)
+                    .await?;
             }
             Err(err) => {
                 self.send_error_response(
@@ -601,6 +861,73 @@ impl<'a> McpHandler<'a> {
         Ok(())
     }
 
+    async fn handle_estimate_cost(
+        &self,
+        transport: &StdioTransport,
+        id: u64,
+        params_val: &serde_json::Value,
+    ) -> anyhow::Result<()> {
+        let Some(plan_file) = parse_plan_file(params_val) else {
+            return self
+                .send_error_response(
+                    transport,
+                    id,
+                    JsonRpcErrorCode::InvalidParams,
+                    "Missing required parameter: plan_file".to_string(),
+                )
+                .await;
+        };
+
+        match self.tfmcp.estimate_cost(&plan_file).await {
+            Ok(estimate) => {
+                let obj_as_str = serde_json::to_string(&estimate)?;
+                self.send_text_response(transport, id, &obj_as_str).await
+            }
+            Err(err) => {
+                let code = match err.downcast_ref::<TerraformError>() {
+                    Some(TerraformError::PlanFileNotFound(_)) => JsonRpcErrorCode::InvalidParams,
+                    _ => JsonRpcErrorCode::InternalError,
+                };
+                self.send_error_response(transport, id, code, format!("Failed to estimate cost: {}", err))
+                    .await
+            }
+        }
+    }
+
+    async fn handle_terraform_console(
+        &self,
+        transport: &StdioTransport,
+        id: u64,
+        params_val: &serde_json::Value,
+    ) -> anyhow::Result<()> {
+        let Some(expression) = params_val.pointer("/arguments/expression").and_then(Value::as_str) else {
+            return self
+                .send_error_response(
+                    transport,
+                    id,
+                    JsonRpcErrorCode::InvalidParams,
+                    "Missing required parameter: expression".to_string(),
+                )
+                .await;
+        };
+
+        match self.tfmcp.terraform_console(expression).await {
+            Ok(result) => {
+                let result_json = json!({ "result": result });
+                let obj_as_str = serde_json::to_string(&result_json)?;
+                self.send_text_response(transport, id, &obj_as_str).await
+            }
+            Err(err) => {
+                let code = match err.downcast_ref::<TerraformError>() {
+                    Some(TerraformError::EmptyExpression) => JsonRpcErrorCode::InvalidParams,
+                    _ => JsonRpcErrorCode::InternalError,
+                };
+                self.send_error_response(transport, id, code, format!("Failed to evaluate expression: {}", err))
+                    .await
+            }
+        }
+    }
+
     async fn handle_init_terraform(
         &self,
         transport: &StdioTransport,
@@ -626,15 +953,88 @@ impl<'a> McpHandler<'a> {
         Ok(())
     }
 
+    async fn handle_get_command_history(
+        &self,
+        transport: &StdioTransport,
+        id: u64,
+        params_val: &serde_json::Value,
+    ) -> anyhow::Result<()> {
+        let limit = params_val
+            .pointer("/arguments/limit")
+            .and_then(Value::as_u64)
+            .map(|limit| limit as usize)
+            .unwrap_or(DEFAULT_HISTORY_LIMIT);
+
+        let result_json = json!({ "history": self.tfmcp.command_history(limit) });
+        let obj_as_str = serde_json::to_string(&result_json)?;
+        self.send_text_response(transport, id, &obj_as_str).await
+    }
+
+    async fn handle_list_projects(&self, transport: &StdioTransport, id: u64) -> anyhow::Result<()> {
+        let result_json = json!({
+            "projects": self.tfmcp.list_projects(),
+            "active_project": self.tfmcp.active_project(),
+        });
+        let obj_as_str = serde_json::to_string(&result_json)?;
+        self.send_text_response(transport, id, &obj_as_str).await
+    }
+
+    async fn handle_switch_project(
+        &mut self,
+        transport: &StdioTransport,
+        id: u64,
+        params_val: &serde_json::Value,
+    ) -> anyhow::Result<()> {
+        let Some(name) = params_val.pointer("/arguments/name").and_then(Value::as_str) else {
+            return self
+                .send_error_response(
+                    transport,
+                    id,
+                    JsonRpcErrorCode::InvalidParams,
+                    "Missing required parameter: name".to_string(),
+                )
+                .await;
+        };
+
+        match self.tfmcp.switch_project(name) {
+            Ok(project) => {
+                let result_json = json!({ "project": project });
+                let obj_as_str = serde_json::to_string(&result_json)?;
+                self.send_text_response(transport, id, &obj_as_str).await
+            }
+            Err(err) => {
+                let code = match err.downcast_ref::<TfMcpError>() {
+                    Some(TfMcpError::UnknownProject { .. }) => JsonRpcErrorCode::InvalidParams,
+                    _ => JsonRpcErrorCode::InternalError,
+                };
+                self.send_error_response(transport, id, code, format!("Failed to switch project: {}", err))
+                    .await
+            }
+        }
+    }
+
     async fn handle_resources_list(&self, transport: &StdioTransport, id: u64) -> anyhow::Result<()> {
         logging::info("Handling resources/list request");
         
-        // Create a response with an empty resources list
+        // List tool results that were too large to return inline
+        let resources: Vec<Value> = self
+            .result_store
+            .list()
+            .into_iter()
+            .map(|stored| {
+                json!({
+                    "uri": stored.uri,
+                    "name": stored.name,
+                    "mimeType": "text/plain"
+                })
+            })
+            .collect();
+
         let response = Message::Response {
             jsonrpc: "2.0".to_string(),
             id,
             result: Some(json!({
-                "resources": []
+                "resources": resources
             })),
             error: None,
         };
@@ -657,6 +1057,71 @@ impl<'a> McpHandler<'a> {
         }
     }
 
+    async fn handle_resources_read(
+        &self,
+        transport: &StdioTransport,
+        id: u64,
+        params: Option<serde_json::Value>,
+    ) -> anyhow::Result<()> {
+        logging::info("Handling resources/read request");
+
+        let uri = match params
+            .as_ref()
+            .and_then(|p| p.get("uri"))
+            .and_then(Value::as_str)
+        {
+            Some(uri) => uri.to_string(),
+            None => {
+                return self
+                    .send_error_response(
+                        transport,
+                        id,
+                        JsonRpcErrorCode::InvalidParams,
+                        "Missing required parameter: uri".to_string(),
+                    )
+                    .await;
+            }
+        };
+
+        let stored = match self.result_store.get(&uri) {
+            Some(stored) => stored,
+            None => {
+                return self
+                    .send_error_response(
+                        transport,
+                        id,
+                        JsonRpcErrorCode::InvalidParams,
+                        format!("Resource not found: {}", uri),
+                    )
+                    .await;
+            }
+        };
+
+        let response = Message::Response {
+            jsonrpc: "2.0".to_string(),
+            id,
+            result: Some(json!({
+                "contents": [{
+                    "uri": stored.uri,
+                    "mimeType": "text/plain",
+                    "text": stored.content
+                }]
+            })),
+            error: None,
+        };
+
+        match transport.send(response).await {
+            Ok(_) => {
+                logging::info("Resources read response sent successfully");
+                Ok(())
+            },
+            Err(e) => {
+                logging::error(&format!("Failed to send resources/read response: {}", e));
+                Err(e.into())
+            }
+        }
+    }
+
     async fn handle_prompts_list(&self, transport: &StdioTransport, id: u64) -> anyhow::Result<()> {
         logging::info("Handling prompts/list request");
         
@@ -688,6 +1153,36 @@ impl<'a> McpHandler<'a> {
         }
     }
 
+    /// Sends a tool result, replacing large outputs with a resource reference
+    async fn send_tool_output(
+        &self,
+        transport: &StdioTransport,
+        id: u64,
+        tool_name: &str,
+        key: &str,
+        output: String,
+    ) -> anyhow::Result<()> {
+        let result_json = match self.result_store.store_if_large(tool_name, output) {
+            ToolOutput::Inline(text) => json!({ key: text }),
+            ToolOutput::Resource { uri, size } => {
+                logging::info(&format!(
+                    "Output of {} is {} bytes, stored as resource {}",
+                    tool_name, size, uri
+                ));
+                json!({
+                    "resource_uri": uri,
+                    "size_bytes": size,
+                    "message": format!(
+                        "Output exceeds {} bytes; fetch it with resources/read",
+                        self.result_store.inline_limit()
+                    )
+                })
+            }
+        };
+        let obj_as_str = serde_json::to_string(&result_json)?;
+        self.send_text_response(transport, id, &obj_as_str).await
+    }
+
     async fn send_text_response(
         &self,
         transport: &StdioTransport,
@@ -833,3 +1328,238 @@ impl<'a> McpHandler<'a> {
         }
     }
 }
+
+/// Sends the startup check result to the client so the agent has immediate context
+async fn report_startup_check<T: Transport>(
+    transport: &T,
+    check: &StartupCheck,
+) -> Result<(), crate::mcp::stdio::Error> {
+    if check.drift_detected {
+        logging::log_both(
+            transport,
+            logging::LogLevel::Warning,
+            "Startup check: drift detected, resources were changed outside of Terraform (see `terraform plan -refresh-only`)",
+        )
+        .await?;
+    }
+
+    if check.has_pending_changes {
+        let summary = match &check.pending_changes {
+            Some(changes) => format!(
+                "{} to add, {} to change, {} to destroy",
+                changes.add, changes.change, changes.destroy
+            ),
+            None => "see get_terraform_plan".to_string(),
+        };
+        logging::log_both(
+            transport,
+            logging::LogLevel::Warning,
+            &format!("Startup check: configuration has pending changes ({})", summary),
+        )
+        .await?;
+    }
+
+    if !check.drift_detected && !check.has_pending_changes {
+        logging::log_both(
+            transport,
+            logging::LogLevel::Info,
+            "Startup check: infrastructure matches the configuration",
+        )
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// Reads apply/plan options from tool call arguments
+fn parse_apply_options(params_val: &serde_json::Value) -> ApplyOptions {
+    let auto_approve = params_val
+        .pointer("/arguments/auto_approve")
+        .and_then(Value::as_bool)
+        .unwrap_or(false);
+
+    let variables = params_val
+        .pointer("/arguments/variables")
+        .and_then(Value::as_object)
+        .map(|vars| {
+            vars.iter()
+                .map(|(name, value)| {
+                    let value = match value {
+                        Value::String(s) => s.clone(),
+                        other => other.to_string(),
+                    };
+                    (name.clone(), value)
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let targets = params_val
+        .pointer("/arguments/targets")
+        .and_then(Value::as_array)
+        .map(|targets| {
+            targets
+                .iter()
+                .filter_map(Value::as_str)
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let workspace = params_val
+        .pointer("/arguments/workspace")
+        .and_then(Value::as_str)
+        .map(str::to_string);
+
+    ApplyOptions {
+        auto_approve,
+        variables,
+        targets,
+        workspace,
+        plan_file: parse_plan_file(params_val),
+    }
+}
+
+fn parse_plan_file(params_val: &serde_json::Value) -> Option<PathBuf> {
+    params_val
+        .pointer("/arguments/plan_file")
+        .and_then(Value::as_str)
+        .filter(|path| !path.is_empty())
+        .map(PathBuf::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::terraform::model::TerraformChanges;
+    use async_trait::async_trait;
+    use futures::Stream;
+    use std::pin::Pin;
+    use std::sync::Mutex;
+
+    /// Transport that records everything sent to the client
+    #[derive(Default)]
+    struct RecordingTransport {
+        sent: Mutex<Vec<Message>>,
+    }
+
+    impl RecordingTransport {
+        fn logs(&self) -> Vec<(String, String)> {
+            self.sent
+                .lock()
+                .unwrap()
+                .iter()
+                .filter_map(|message| match message {
+                    Message::Notification { method, params: Some(params), .. } if method == "$/log" => Some((
+                        params["level"].as_str().unwrap_or_default().to_string(),
+                        params["message"].as_str().unwrap_or_default().to_string(),
+                    )),
+                    _ => None,
+                })
+                .collect()
+        }
+    }
+
+    #[async_trait]
+    impl Transport for RecordingTransport {
+        async fn send(&self, message: Message) -> Result<(), crate::mcp::stdio::Error> {
+            self.sent.lock().unwrap().push(message);
+            Ok(())
+        }
+
+        fn receive(&self) -> Pin<Box<dyn Stream<Item = Result<Message, crate::mcp::stdio::Error>> + Send>> {
+            Box::pin(futures::stream::empty())
+        }
+
+        async fn close(&self) -> Result<(), crate::mcp::stdio::Error> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_startup_check_with_drift_emits_warnings() {
+        let transport = RecordingTransport::default();
+        let check = StartupCheck {
+            drift_detected: true,
+            has_pending_changes: true,
+            pending_changes: Some(TerraformChanges { add: 1, change: 0, destroy: 2 }),
+        };
+
+        report_startup_check(&transport, &check).await.unwrap();
+
+        let logs = transport.logs();
+        assert_eq!(logs.len(), 2);
+        assert_eq!(logs[0].0, "warning");
+        assert!(logs[0].1.contains("drift detected"));
+        assert_eq!(logs[1].0, "warning");
+        assert!(logs[1].1.contains("1 to add, 0 to change, 2 to destroy"));
+    }
+
+    #[tokio::test]
+    async fn test_startup_check_without_drift_is_informational() {
+        let transport = RecordingTransport::default();
+        let check = StartupCheck {
+            drift_detected: false,
+            has_pending_changes: false,
+            pending_changes: None,
+        };
+
+        report_startup_check(&transport, &check).await.unwrap();
+
+        let logs = transport.logs();
+        assert_eq!(logs.len(), 1);
+        assert_eq!(logs[0].0, "info");
+    }
+
+    #[tokio::test]
+    async fn test_apply_dry_run_returns_command_without_running_terraform() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("main.tf"), "resource \"null_resource\" \"a\" {}\n").unwrap();
+        let marker = dir.path().join("invoked");
+        let terraform = dir.path().join("terraform");
+        std::fs::write(
+            &terraform,
+            format!(
+                "#!/bin/sh\ntouch {}\necho 'Plan: 1 to add, 0 to change, 2 to destroy.'\n",
+                marker.display()
+            ),
+        )
+        .unwrap();
+        std::fs::set_permissions(&terraform, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let config_path = dir.path().join("tfmcp.json");
+        let config = json!({
+            "terraform": { "executable_path": terraform },
+            "mcp": { "tools": [] }
+        });
+        std::fs::write(&config_path, config.to_string()).unwrap();
+        let mut tfmcp = TfMcp::new(
+            Some(config_path.to_string_lossy().to_string()),
+            Some(dir.path().to_string_lossy().to_string()),
+        )
+        .unwrap();
+
+        let params = json!({
+            "arguments": {
+                "dry_run": true,
+                "variables": { "region": "us-east-1" },
+                "workspace": "staging"
+            }
+        });
+        let options = parse_apply_options(&params);
+        tfmcp.get_terraform_plan_with(&options).await.unwrap();
+        std::fs::remove_file(&marker).unwrap();
+        let history = tfmcp.command_history(10).len();
+
+        let handler = McpHandler::new(&mut tfmcp);
+        let result = handler.apply_dry_run(&options);
+
+        assert_eq!(result["dry_run"], json!(true));
+        assert_eq!(result["command"]["args"], json!(["apply", "-no-color", "-var=region=us-east-1"]));
+        assert_eq!(result["command"]["env"]["TF_WORKSPACE"], json!("staging"));
+        assert_eq!(result["plan_summary"], json!({ "add": 1, "change": 0, "destroy": 2 }));
+        assert!(!marker.exists());
+        assert_eq!(handler.tfmcp.command_history(10).len(), history);
+    }
+}
@@ -1,2 +1,3 @@
 pub mod handler;
+pub mod resources;
 pub mod stdio;
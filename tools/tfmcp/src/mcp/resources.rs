@@ -0,0 +1,199 @@
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+pub const RESULT_URI_PREFIX: &str = "terraform://results/";
+
+/// Default size (in bytes) above which tool output is stored as a resource
+pub const DEFAULT_INLINE_LIMIT: usize = 64 * 1024;
+
+/// Default number of stored results kept before the least recently used is evicted
+pub const DEFAULT_RESULT_CAPACITY: usize = 32;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ToolOutput {
+    Inline(String),
+    Resource { uri: String, size: usize },
+}
+
+#[derive(Debug, Clone)]
+pub struct StoredResult {
+    pub uri: String,
+    pub name: String,
+    pub content: String,
+}
+
+/// Keeps large tool outputs so clients can fetch them via `resources/read`
+/// instead of receiving them inline in the tool response.
+///
+/// At most `capacity` results are kept; storing another one evicts the result
+/// that was stored or read least recently, so a long-running server does not
+/// grow without bound.
+pub struct ResultStore {
+    inline_limit: usize,
+    capacity: usize,
+    results: Mutex<Results>,
+}
+
+#[derive(Default)]
+struct Results {
+    next_id: u64,
+    /// Incremented on every store and read, to order entries by last use
+    clock: u64,
+    entries: BTreeMap<String, (u64, StoredResult)>,
+}
+
+impl Results {
+    fn tick(&mut self) -> u64 {
+        self.clock += 1;
+        self.clock
+    }
+}
+
+impl ResultStore {
+    pub fn new(inline_limit: usize) -> Self {
+        Self {
+            inline_limit,
+            capacity: DEFAULT_RESULT_CAPACITY,
+            results: Mutex::new(Results {
+                next_id: 1,
+                ..Results::default()
+            }),
+        }
+    }
+
+    /// Sets how many results are kept (at least one)
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity.max(1);
+        self
+    }
+
+    pub fn inline_limit(&self) -> usize {
+        self.inline_limit
+    }
+
+    /// Returns the text unchanged if it is small, otherwise stores it and
+    /// returns a `terraform://results/<id>` reference.
+    pub fn store_if_large(&self, name: &str, text: String) -> ToolOutput {
+        if text.len() <= self.inline_limit {
+            return ToolOutput::Inline(text);
+        }
+
+        let mut results = self.results.lock().unwrap();
+        let id = results.next_id;
+        results.next_id += 1;
+
+        while results.entries.len() >= self.capacity {
+            let oldest = results
+                .entries
+                .iter()
+                .min_by_key(|(_, (last_used, _))| *last_used)
+                .map(|(uri, _)| uri.clone());
+            match oldest {
+                Some(uri) => results.entries.remove(&uri),
+                None => break,
+            };
+        }
+
+        let uri = format!("{}{}", RESULT_URI_PREFIX, id);
+        let size = text.len();
+        let last_used = results.tick();
+        results.entries.insert(
+            uri.clone(),
+            (
+                last_used,
+                StoredResult {
+                    uri: uri.clone(),
+                    name: format!("{} result #{}", name, id),
+                    content: text,
+                },
+            ),
+        );
+
+        ToolOutput::Resource { uri, size }
+    }
+
+    pub fn get(&self, uri: &str) -> Option<StoredResult> {
+        let mut results = self.results.lock().unwrap();
+        let now = results.tick();
+        let (last_used, result) = results.entries.get_mut(uri)?;
+        *last_used = now;
+        Some(result.clone())
+    }
+
+    pub fn list(&self) -> Vec<StoredResult> {
+        self.results
+            .lock()
+            .unwrap()
+            .entries
+            .values()
+            .map(|(_, result)| result.clone())
+            .collect()
+    }
+}
+
+impl Default for ResultStore {
+    fn default() -> Self {
+        Self::new(DEFAULT_INLINE_LIMIT)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_small_output_stays_inline() {
+        let store = ResultStore::new(1024);
+        let output = store.store_if_large("plan", "No changes.".to_string());
+        assert_eq!(output, ToolOutput::Inline("No changes.".to_string()));
+        assert!(store.list().is_empty());
+    }
+
+    #[test]
+    fn test_large_plan_is_stored_as_resource() {
+        let store = ResultStore::new(1024);
+        let plan: String = (0..500)
+            .map(|i| format!("  + resource \"local_file\" \"f{}\" {{}}\n", i))
+            .collect();
+
+        let output = store.store_if_large("get_terraform_plan", plan.clone());
+        let uri = match output {
+            ToolOutput::Resource { uri, size } => {
+                assert_eq!(size, plan.len());
+                uri
+            }
+            ToolOutput::Inline(_) => panic!("large plan should not be inlined"),
+        };
+        assert!(uri.starts_with(RESULT_URI_PREFIX));
+
+        let stored = store.get(&uri).expect("stored result should be readable");
+        assert_eq!(stored.content, plan);
+        assert_eq!(store.list().len(), 1);
+    }
+
+    #[test]
+    fn test_least_recently_used_result_is_evicted() {
+        let store = ResultStore::new(4).with_capacity(2);
+        let uri = |output| match output {
+            ToolOutput::Resource { uri, .. } => uri,
+            ToolOutput::Inline(_) => panic!("output should be stored"),
+        };
+
+        let first = uri(store.store_if_large("plan", "first".to_string()));
+        let second = uri(store.store_if_large("plan", "second".to_string()));
+        // Reading the first result makes the second one the least recently used
+        assert!(store.get(&first).is_some());
+        let third = uri(store.store_if_large("plan", "third".to_string()));
+
+        assert_eq!(store.list().len(), 2);
+        assert!(store.get(&second).is_none());
+        assert_eq!(store.get(&first).unwrap().content, "first");
+        assert_eq!(store.get(&third).unwrap().content, "third");
+    }
+
+    #[test]
+    fn test_unknown_uri_returns_none() {
+        let store = ResultStore::default();
+        assert!(store.get("terraform://results/42").is_none());
+    }
+}
@@ -0,0 +1,164 @@
+use crate::terraform::model::AuditEntry;
+use std::collections::VecDeque;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Number of entries kept in memory for `get_command_history`
+pub const DEFAULT_HISTORY_CAPACITY: usize = 200;
+
+const REDACTED: &str = "<redacted>";
+
+/// Records every terraform invocation made by tfmcp.
+///
+/// Recent entries are kept in memory; when a log path is configured each entry
+/// is also appended to it as a JSON line so the trail survives restarts.
+pub struct AuditLog {
+    capacity: usize,
+    path: Option<PathBuf>,
+    entries: Mutex<VecDeque<AuditEntry>>,
+}
+
+impl AuditLog {
+    pub fn new(capacity: usize, path: Option<PathBuf>) -> Self {
+        Self {
+            capacity,
+            path,
+            entries: Mutex::new(VecDeque::with_capacity(capacity)),
+        }
+    }
+
+    pub fn record(&self, entry: AuditEntry) {
+        if let Some(path) = &self.path {
+            // A broken audit file must not make terraform commands fail
+            if let Err(e) = append_line(path, &entry) {
+                eprintln!(
+                    "[ERROR] Failed to write audit log {}: {}",
+                    path.display(),
+                    e
+                );
+            }
+        }
+
+        let mut entries = self.entries.lock().unwrap();
+        if entries.len() == self.capacity {
+            entries.pop_front();
+        }
+        entries.push_back(entry);
+    }
+
+    /// Returns up to `limit` entries, most recent first
+    pub fn recent(&self, limit: usize) -> Vec<AuditEntry> {
+        let entries = self.entries.lock().unwrap();
+        entries.iter().rev().take(limit).cloned().collect()
+    }
+}
+
+fn append_line(path: &Path, entry: &AuditEntry) -> anyhow::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+    writeln!(file, "{}", serde_json::to_string(entry)?)?;
+    Ok(())
+}
+
+/// Hides variable values, which commonly carry credentials
+pub fn redact_args(args: &[String]) -> Vec<String> {
+    let mut redacted = Vec::with_capacity(args.len());
+    let mut value_follows = false;
+
+    for arg in args {
+        if value_follows {
+            redacted.push(redact_assignment(arg));
+            value_follows = false;
+        } else if let Some(assignment) = arg.strip_prefix("-var=") {
+            redacted.push(format!("-var={}", redact_assignment(assignment)));
+        } else {
+            value_follows = arg == "-var";
+            redacted.push(arg.clone());
+        }
+    }
+
+    redacted
+}
+
+fn redact_assignment(assignment: &str) -> String {
+    match assignment.split_once('=') {
+        Some((name, _)) => format!("{}={}", name, REDACTED),
+        None => REDACTED.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(subcommand: &str) -> AuditEntry {
+        AuditEntry {
+            timestamp: "2025-01-01T00:00:00+00:00".to_string(),
+            subcommand: subcommand.to_string(),
+            args: vec![subcommand.to_string()],
+            exit_code: Some(0),
+            duration_ms: 1,
+            working_dir: "/tmp".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_redact_args() {
+        let args: Vec<String> = [
+            "plan",
+            "-var=password=hunter2",
+            "-var",
+            "token=abc",
+            "-target=aws_instance.web",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+
+        assert_eq!(
+            redact_args(&args),
+            vec![
+                "plan",
+                "-var=password=<redacted>",
+                "-var",
+                "token=<redacted>",
+                "-target=aws_instance.web",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_recent_is_bounded_and_newest_first() {
+        let log = AuditLog::new(2, None);
+        log.record(entry("init"));
+        log.record(entry("plan"));
+        log.record(entry("apply"));
+
+        let recent: Vec<String> = log.recent(10).into_iter().map(|e| e.subcommand).collect();
+        assert_eq!(recent, vec!["apply", "plan"]);
+        assert_eq!(log.recent(1).len(), 1);
+    }
+
+    #[test]
+    fn test_entries_are_appended_to_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("audit").join("audit.log");
+        let log = AuditLog::new(10, Some(path.clone()));
+        log.record(entry("init"));
+        log.record(entry("plan"));
+
+        let content = std::fs::read_to_string(path).unwrap();
+        let lines: Vec<AuditEntry> = content
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[1].subcommand, "plan");
+    }
+}
@@ -0,0 +1,216 @@
+use crate::terraform::model::{ChangeAction, CostEstimate, CostItem, ResourceChange};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// Attribute that selects the size (and so the price) of a resource type
+const SIZE_ATTRIBUTES: &[(&str, &str)] = &[
+    ("aws_instance", "instance_type"),
+    ("aws_db_instance", "instance_class"),
+    ("aws_elasticache_cluster", "node_type"),
+    ("google_compute_instance", "machine_type"),
+    ("azurerm_linux_virtual_machine", "size"),
+];
+
+/// Built-in monthly on-demand prices in USD (730 hours, us-east-1 or equivalent).
+///
+/// Keys are either a resource type, or `type:size` for types listed in
+/// `SIZE_ATTRIBUTES`. Resources that cost nothing by themselves are priced at
+/// zero so they are not reported as unpriced.
+const BUILTIN_PRICES: &[(&str, f64)] = &[
+    ("aws_instance:t3.nano", 3.80),
+    ("aws_instance:t3.micro", 7.59),
+    ("aws_instance:t3.small", 15.18),
+    ("aws_instance:t3.medium", 30.37),
+    ("aws_instance:t3.large", 60.74),
+    ("aws_instance:m5.large", 70.08),
+    ("aws_instance:m5.xlarge", 140.16),
+    ("aws_db_instance:db.t3.micro", 12.41),
+    ("aws_db_instance:db.t3.small", 24.82),
+    ("aws_db_instance:db.t3.medium", 49.64),
+    ("aws_elasticache_cluster:cache.t3.micro", 12.41),
+    ("aws_nat_gateway", 32.85),
+    ("aws_lb", 16.43),
+    ("aws_eip", 3.65),
+    ("google_compute_instance:e2-micro", 6.11),
+    ("google_compute_instance:e2-small", 12.23),
+    ("google_compute_instance:e2-medium", 24.46),
+    ("azurerm_linux_virtual_machine:Standard_B1s", 7.59),
+    ("aws_vpc", 0.0),
+    ("aws_subnet", 0.0),
+    ("aws_security_group", 0.0),
+    ("aws_iam_role", 0.0),
+    ("aws_iam_policy", 0.0),
+    ("null_resource", 0.0),
+    ("local_file", 0.0),
+    ("random_id", 0.0),
+];
+
+/// Source of the prices used by `estimate_cost`
+pub trait PricingProvider: Send + Sync {
+    /// Monthly cost of one resource with the given attributes, or None if unknown
+    fn monthly_price(&self, resource_type: &str, attributes: &Value) -> Option<f64>;
+
+    fn currency(&self) -> &str {
+        "USD"
+    }
+}
+
+/// Fixed price table, looked up by `type:size` first and then by type
+#[derive(Debug, Clone)]
+pub struct StaticPriceTable {
+    prices: BTreeMap<String, f64>,
+}
+
+impl Default for StaticPriceTable {
+    /// The built-in prices for common resource types
+    fn default() -> Self {
+        Self {
+            prices: BUILTIN_PRICES
+                .iter()
+                .map(|(key, price)| (key.to_string(), *price))
+                .collect(),
+        }
+    }
+}
+
+impl StaticPriceTable {
+    /// Adds or replaces prices (keyed like the built-in table)
+    pub fn with_prices(mut self, prices: impl IntoIterator<Item = (String, f64)>) -> Self {
+        self.prices.extend(prices);
+        self
+    }
+}
+
+impl PricingProvider for StaticPriceTable {
+    fn monthly_price(&self, resource_type: &str, attributes: &Value) -> Option<f64> {
+        let size = SIZE_ATTRIBUTES
+            .iter()
+            .find(|(ty, _)| *ty == resource_type)
+            .and_then(|(_, attribute)| attributes.get(*attribute))
+            .and_then(Value::as_str);
+        size.and_then(|size| self.prices.get(&format!("{}:{}", resource_type, size)))
+            .or_else(|| self.prices.get(resource_type))
+            .copied()
+    }
+}
+
+/// Estimates the monthly cost delta of a plan's resource changes
+///
+/// Creates add the price of the new resource, deletes subtract the price of the
+/// old one, and updates and replacements add the difference (e.g. a changed
+/// instance type). A change is unpriced if any price it needs is unknown; it is
+/// left out of the total and listed in `unpriced`.
+pub fn estimate_cost(changes: &[ResourceChange], pricing: &dyn PricingProvider) -> CostEstimate {
+    let mut items = Vec::with_capacity(changes.len());
+    let mut unpriced = Vec::new();
+    let mut monthly_delta = 0.0;
+    for change in changes {
+        let price = |attributes: &Value| pricing.monthly_price(&change.resource_type, attributes);
+        let delta = match change.action {
+            ChangeAction::Create => price(&change.after),
+            ChangeAction::Delete => price(&change.before).map(|before| -before),
+            ChangeAction::Update | ChangeAction::Replace => {
+                price(&change.after).zip(price(&change.before)).map(|(after, before)| after - before)
+            }
+        };
+        match delta {
+            Some(delta) => monthly_delta += delta,
+            None => unpriced.push(change.address.clone()),
+        }
+        items.push(CostItem {
+            address: change.address.clone(),
+            resource_type: change.resource_type.clone(),
+            action: change.action,
+            monthly_delta: delta,
+        });
+    }
+
+    CostEstimate {
+        currency: pricing.currency().to_string(),
+        // Rounded to cents so float noise does not show up in the total
+        monthly_delta: (monthly_delta * 100.0).round() / 100.0,
+        items,
+        unpriced,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::terraform::service::parse_resource_changes;
+
+    const PLAN_JSON: &str = r#"{
+      "format_version": "1.2",
+      "resource_changes": [
+        {
+          "address": "aws_instance.web",
+          "type": "aws_instance",
+          "change": {
+            "actions": ["create"],
+            "before": null,
+            "after": { "ami": "ami-123", "instance_type": "t3.micro" }
+          }
+        },
+        {
+          "address": "aws_security_group.web",
+          "type": "aws_security_group",
+          "change": { "actions": ["no-op"], "before": {}, "after": {} }
+        }
+      ]
+    }"#;
+
+    #[test]
+    fn test_estimate_for_added_priced_resource() {
+        let changes = parse_resource_changes(PLAN_JSON).unwrap();
+        let estimate = estimate_cost(&changes, &StaticPriceTable::default());
+
+        assert_eq!(estimate.currency, "USD");
+        assert_eq!(estimate.monthly_delta, 7.59);
+        assert_eq!(
+            estimate.items,
+            vec![CostItem {
+                address: "aws_instance.web".to_string(),
+                resource_type: "aws_instance".to_string(),
+                action: ChangeAction::Create,
+                monthly_delta: Some(7.59),
+            }]
+        );
+        assert!(estimate.unpriced.is_empty());
+    }
+
+    #[test]
+    fn test_estimate_for_updates_destroys_and_unknown_types() {
+        let change = |address: &str, resource_type: &str, action, before: Value, after: Value| ResourceChange {
+            address: address.to_string(),
+            resource_type: resource_type.to_string(),
+            action,
+            before,
+            after,
+        };
+        let changes = vec![
+            change(
+                "aws_instance.app",
+                "aws_instance",
+                ChangeAction::Update,
+                serde_json::json!({ "instance_type": "t3.micro" }),
+                serde_json::json!({ "instance_type": "t3.medium" }),
+            ),
+            change("aws_nat_gateway.main", "aws_nat_gateway", ChangeAction::Delete, serde_json::json!({}), Value::Null),
+            change("custom_widget.a", "custom_widget", ChangeAction::Create, Value::Null, serde_json::json!({})),
+            // Size not known until apply
+            change("aws_instance.tmp", "aws_instance", ChangeAction::Create, Value::Null, serde_json::json!({})),
+        ];
+
+        let estimate = estimate_cost(&changes, &StaticPriceTable::default());
+
+        // (30.37 - 7.59) - 32.85
+        assert_eq!(estimate.monthly_delta, -10.07);
+        assert_eq!(estimate.unpriced, vec!["custom_widget.a", "aws_instance.tmp"]);
+        assert_eq!(estimate.items[2].monthly_delta, None);
+
+        let overridden = StaticPriceTable::default().with_prices([("custom_widget".to_string(), 5.0)]);
+        let estimate = estimate_cost(&changes, &overridden);
+        assert_eq!(estimate.unpriced, vec!["aws_instance.tmp"]);
+        assert_eq!(estimate.items[2].monthly_delta, Some(5.0));
+    }
+}
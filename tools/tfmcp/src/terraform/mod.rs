@@ -1,2 +1,4 @@
+pub mod audit;
+pub mod cost;
 pub mod service;
 pub mod model;
@@ -6,6 +6,7 @@ pub struct TerraformAnalysis {
     pub file_count: usize,
     pub resources: Vec<TerraformResource>,
     pub variables: Vec<String>,
+    pub variable_validations: Vec<TerraformVariableValidation>,
     pub outputs: Vec<String>,
     pub providers: Vec<String>,
 }
@@ -17,19 +18,84 @@ pub struct TerraformResource {
     pub file: String,
 }
 
+/// A `validation` block declared inside a variable
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct TerraformVariableValidation {
+    pub variable: String,
+    /// The condition expression, as written
+    pub condition: String,
+    pub error_message: String,
+    pub file: String,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct TerraformPlan {
     pub changes: TerraformChanges,
     pub raw_output: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct TerraformChanges {
     pub add: usize,
     pub change: usize,
     pub destroy: usize,
 }
 
+/// What a plan does to a resource
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeAction {
+    Create,
+    Update,
+    Delete,
+    /// Destroyed and created again (`delete` and `create` in either order)
+    Replace,
+}
+
+/// A resource change from a saved plan (`terraform show -json`)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ResourceChange {
+    pub address: String,
+    pub resource_type: String,
+    pub action: ChangeAction,
+    /// Attributes before the change (null for creates)
+    pub before: serde_json::Value,
+    /// Attributes after the change (null for deletes; values known only after apply are omitted)
+    pub after: serde_json::Value,
+}
+
+/// Estimated effect of one resource change on the monthly bill
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CostItem {
+    pub address: String,
+    pub resource_type: String,
+    pub action: ChangeAction,
+    /// None if the pricing provider has no price for the resource
+    pub monthly_delta: Option<f64>,
+}
+
+/// Advisory monthly cost delta of a plan, as returned by `estimate_cost`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CostEstimate {
+    pub currency: String,
+    /// Sum over the priced changes only
+    pub monthly_delta: f64,
+    pub items: Vec<CostItem>,
+    /// Addresses of changed resources that could not be priced
+    pub unpriced: Vec<String>,
+}
+
+/// Result of the optional startup sanity check
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct StartupCheck {
+    /// A refresh-only plan found resources changed outside of Terraform
+    pub drift_detected: bool,
+    /// A regular plan would change infrastructure
+    pub has_pending_changes: bool,
+    /// Summary of the pending changes, when it could be parsed
+    pub pending_changes: Option<TerraformChanges>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct TerraformState {
     pub resources: Vec<TerraformStateResource>,
@@ -49,3 +115,48 @@ pub struct TerraformResourceInstance {
     pub id: String,
     pub attributes: serde_json::Value,
 }
+
+/// A terraform invocation as it would be executed, without running it
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CommandPreview {
+    pub program: String,
+    pub args: Vec<String>,
+    pub env: std::collections::BTreeMap<String, String>,
+    pub working_dir: String,
+}
+
+/// What `apply` would do, as reported by a dry run
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ApplyPreview {
+    pub command: CommandPreview,
+    /// Changes recorded by the matching `plan`, if the configuration is unchanged since
+    pub plan_summary: Option<TerraformChanges>,
+    /// Why no plan summary is available
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub plan_error: Option<String>,
+}
+
+/// A named project from the configuration, as reported by `list_projects`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ProjectInfo {
+    pub name: String,
+    pub directory: String,
+    pub backend_config: Option<String>,
+    pub var_files: Vec<String>,
+    /// Whether this is the project currently selected with `switch_project`
+    pub active: bool,
+}
+
+/// A terraform invocation recorded for auditing
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AuditEntry {
+    /// RFC 3339 time the command was started
+    pub timestamp: String,
+    pub subcommand: String,
+    /// Full argument list with variable values redacted
+    pub args: Vec<String>,
+    /// None if terraform could not be started or was killed by a signal
+    pub exit_code: Option<i32>,
+    pub duration_ms: u64,
+    pub working_dir: String,
+}
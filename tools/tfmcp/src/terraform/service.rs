@@ -1,6 +1,16 @@
-use crate::terraform::model::{TerraformAnalysis, TerraformResource};
+use crate::terraform::audit::{redact_args, AuditLog, DEFAULT_HISTORY_CAPACITY};
+use crate::terraform::model::{
+    ApplyPreview, AuditEntry, ChangeAction, CommandPreview, ResourceChange, StartupCheck,
+    TerraformAnalysis, TerraformChanges,
+    TerraformVariableValidation,
+    TerraformResource,
+};
+use std::collections::BTreeMap;
+use std::io::Write;
 use std::path::{Path, PathBuf};
-use std::process::Command;
+use std::process::{Command, Output, Stdio};
+use std::sync::Mutex;
+use std::time::Instant;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -19,11 +29,95 @@ pub enum TerraformError {
     
     #[error("Failed to parse Terraform output: {0}")]
     ParseError(String),
+    
+    #[error("Saved plan file not found: {0}")]
+    PlanFileNotFound(String),
+    
+    #[error("Saved plan is stale: {0}")]
+    StalePlan(String),
+    
+    #[error("A saved plan file is required to apply (run plan with plan_file first)")]
+    PlanFileRequired,
+    
+    #[error("No plan summary available: {0}")]
+    NoPlanSummary(String),
+    
+    #[error("Console expression must not be empty")]
+    EmptyExpression,
+}
+
+/// Suffix of the file next to a saved plan that records the configuration fingerprint
+const PLAN_FINGERPRINT_SUFFIX: &str = ".tfmcp-fingerprint";
+
+/// Suffix of the file next to a saved plan that records its change summary
+const PLAN_SUMMARY_SUFFIX: &str = ".tfmcp-summary";
+
+/// Options shared by plan and apply invocations
+#[derive(Debug, Clone, Default)]
+pub struct ApplyOptions {
+    pub auto_approve: bool,
+    pub variables: Vec<(String, String)>,
+    pub targets: Vec<String>,
+    pub workspace: Option<String>,
+    /// Saved plan file (relative to the project directory): `plan` writes it with
+    /// `-out`, `apply` applies exactly that plan
+    pub plan_file: Option<PathBuf>,
+}
+
+impl ApplyOptions {
+    fn common_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        for (name, value) in &self.variables {
+            args.push(format!("-var={}={}", name, value));
+        }
+        for target in &self.targets {
+            args.push(format!("-target={}", target));
+        }
+        args
+    }
+
+    fn env(&self) -> BTreeMap<String, String> {
+        let mut env = BTreeMap::new();
+        if let Some(workspace) = &self.workspace {
+            env.insert("TF_WORKSPACE".to_string(), workspace.clone());
+        }
+        env
+    }
+}
+
+/// Per-project arguments added to terraform commands
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ProjectOptions {
+    /// Passed to `init` as `-backend-config`
+    pub backend_config: Option<PathBuf>,
+    /// Passed to plan and apply as `-var-file`, before any `-var`
+    pub var_files: Vec<PathBuf>,
+}
+
+impl ProjectOptions {
+    fn var_file_args(&self) -> Vec<String> {
+        self.var_files
+            .iter()
+            .map(|file| format!("-var-file={}", file.display()))
+            .collect()
+    }
+}
+
+/// Summary of the last plan run without a plan file, kept for apply dry runs
+struct RecordedPlan {
+    fingerprint: u64,
+    args: Vec<String>,
+    env: BTreeMap<String, String>,
+    summary: TerraformChanges,
 }
 
 pub struct TerraformService {
     terraform_path: PathBuf,
     project_directory: PathBuf,
+    project_options: ProjectOptions,
+    audit: AuditLog,
+    require_plan_file: bool,
+    last_plan: Mutex<Option<RecordedPlan>>,
 }
 
 impl TerraformService {
@@ -58,9 +152,72 @@ impl TerraformService {
         Ok(Self {
             terraform_path,
             project_directory,
+            project_options: ProjectOptions::default(),
+            audit: AuditLog::new(DEFAULT_HISTORY_CAPACITY, None),
+            require_plan_file: false,
+            last_plan: Mutex::new(None),
         })
     }
     
+    /// Also append audit entries to the given file as JSON lines
+    pub fn with_audit_log_path(mut self, path: PathBuf) -> Self {
+        self.audit = AuditLog::new(DEFAULT_HISTORY_CAPACITY, Some(path));
+        self
+    }
+    
+    /// Refuse to apply unless a saved plan file is given
+    pub fn with_require_plan_file(mut self, require: bool) -> Self {
+        self.require_plan_file = require;
+        self
+    }
+    
+    /// Most recent terraform invocations, newest first
+    pub fn command_history(&self, limit: usize) -> Vec<AuditEntry> {
+        self.audit.recent(limit)
+    }
+    
+    /// Runs terraform in the project directory and records it in the audit log
+    fn run(&self, args: &[String], env: &BTreeMap<String, String>) -> std::io::Result<Output> {
+        self.run_with_input(args, env, None)
+    }
+    
+    /// Like `run`, but writes `input` to terraform's stdin and then closes it
+    fn run_with_input(
+        &self,
+        args: &[String],
+        env: &BTreeMap<String, String>,
+        input: Option<&[u8]>,
+    ) -> std::io::Result<Output> {
+        let timestamp = chrono::Utc::now().to_rfc3339();
+        let started = Instant::now();
+        
+        let mut command = Command::new(&self.terraform_path);
+        command
+            .args(args)
+            .envs(env)
+            .current_dir(&self.project_directory);
+        let result = match input {
+            Some(input) => output_with_input(command, input),
+            None => command.output(),
+        };
+        
+        self.audit.record(AuditEntry {
+            timestamp,
+            subcommand: args.first().cloned().unwrap_or_default(),
+            args: redact_args(args),
+            exit_code: result.as_ref().ok().and_then(|output| output.status.code()),
+            duration_ms: started.elapsed().as_millis() as u64,
+            working_dir: self.project_directory.to_string_lossy().to_string(),
+        });
+        
+        result
+    }
+    
+    fn run_args(&self, args: &[&str]) -> std::io::Result<Output> {
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        self.run(&args, &BTreeMap::new())
+    }
+    
     pub fn change_project_directory(&mut self, new_directory: PathBuf) -> Result<(), TerraformError> {
         // Validate new project directory
         if !new_directory.exists() || !new_directory.is_dir() {
@@ -84,6 +241,7 @@ impl TerraformService {
         
         // 新しいディレクトリに変更
         self.project_directory = new_directory;
+        *self.last_plan.get_mut().unwrap() = None;
         
         Ok(())
     }
@@ -92,11 +250,14 @@ impl TerraformService {
         &self.project_directory
     }
     
+    /// Backend config and var files used for the current project
+    pub fn set_project_options(&mut self, options: ProjectOptions) {
+        self.project_options = options;
+        *self.last_plan.get_mut().unwrap() = None;
+    }
+    
     pub async fn get_version(&self) -> anyhow::Result<String> {
-        let output = Command::new(&self.terraform_path)
-            .arg("version")
-            .current_dir(&self.project_directory)
-            .output()?;
+        let output = self.run_args(&["version"])?;
         
         if !output.status.success() {
             return Err(TerraformError::CommandFailed(
@@ -108,10 +269,11 @@ impl TerraformService {
     }
     
     pub async fn init(&self) -> anyhow::Result<String> {
-        let output = Command::new(&self.terraform_path)
-            .args(["init", "-no-color"])
-            .current_dir(&self.project_directory)
-            .output()?;
+        let mut args = vec!["init".to_string(), "-no-color".to_string()];
+        if let Some(backend_config) = &self.project_options.backend_config {
+            args.push(format!("-backend-config={}", backend_config.display()));
+        }
+        let output = self.run(&args, &BTreeMap::new())?;
         
         if !output.status.success() {
             return Err(TerraformError::CommandFailed(
@@ -123,11 +285,24 @@ impl TerraformService {
     }
     
     pub async fn get_plan(&self) -> anyhow::Result<String> {
+        self.plan(&ApplyOptions::default()).await
+    }
+    
+    /// Runs terraform plan; with `plan_file` the plan is saved for a later apply
+    ///
+    /// A fingerprint of the configuration and the change summary are stored next
+    /// to the saved plan so `apply` can tell whether the configuration changed
+    /// since. Without a plan file the summary is kept in memory for dry runs.
+    pub async fn plan(&self, options: &ApplyOptions) -> anyhow::Result<String> {
+        let mut args = vec!["plan".to_string(), "-no-color".to_string()];
+        if let Some(plan_file) = &options.plan_file {
+            args.push(format!("-out={}", plan_file.display()));
+        }
+        args.extend(self.project_options.var_file_args());
+        args.extend(options.common_args());
+        
         // Run terraform plan and capture output
-        let output = Command::new(&self.terraform_path)
-            .args(["plan", "-no-color"])
-            .current_dir(&self.project_directory)
-            .output()?;
+        let output = self.run(&args, &options.env())?;
         
         if !output.status.success() {
             return Err(TerraformError::CommandFailed(
@@ -135,19 +310,120 @@ impl TerraformService {
             ).into());
         }
         
-        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+        let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+        let fingerprint = self.config_fingerprint()?;
+        let summary = parse_plan_summary(&stdout);
+        match &options.plan_file {
+            Some(plan_file) => {
+                std::fs::write(
+                    self.fingerprint_path(plan_file),
+                    format!("{:016x}\n", fingerprint),
+                )?;
+                let summary_path = self.summary_path(plan_file);
+                match &summary {
+                    Some(summary) => std::fs::write(summary_path, serde_json::to_string(summary)?)?,
+                    None => match std::fs::remove_file(summary_path) {
+                        Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+                        _ => {}
+                    },
+                }
+            }
+            None => {
+                *self.last_plan.lock().unwrap() = summary.map(|summary| RecordedPlan {
+                    fingerprint,
+                    args: options.common_args(),
+                    env: options.env(),
+                    summary,
+                });
+            }
+        }
+        
+        Ok(stdout)
+    }
+    
+    /// Runs a refresh-only plan and a regular plan to detect drift and pending changes
+    ///
+    /// Both use `-detailed-exitcode`, where exit code 2 means "changes present".
+    pub async fn startup_check(&self) -> anyhow::Result<StartupCheck> {
+        let var_files = self.project_options.var_file_args();
+        let plan_args = |extra: &[&str]| -> Vec<String> {
+            let mut args = vec!["plan".to_string()];
+            args.extend(extra.iter().map(|arg| arg.to_string()));
+            args.extend(["-detailed-exitcode", "-input=false", "-no-color"].map(String::from));
+            args.extend(var_files.iter().cloned());
+            args
+        };
+        
+        let refresh = self.run(&plan_args(&["-refresh-only"]), &BTreeMap::new())?;
+        let drift_detected = detailed_exit_code(&refresh)?;
+        
+        let plan = self.run(&plan_args(&[]), &BTreeMap::new())?;
+        let has_pending_changes = detailed_exit_code(&plan)?;
+        let pending_changes = if has_pending_changes {
+            parse_plan_summary(&String::from_utf8_lossy(&plan.stdout))
+        } else {
+            None
+        };
+        
+        Ok(StartupCheck {
+            drift_detected,
+            has_pending_changes,
+            pending_changes,
+        })
+    }
+    
+    /// Describes what `apply` would do without running terraform
+    ///
+    /// The plan summary is the one `plan` recorded: next to the saved plan, or
+    /// in memory for the last plan run with the same variables, targets and
+    /// workspace. It is only reported while the configuration is unchanged.
+    pub fn preview_apply(&self, options: &ApplyOptions) -> ApplyPreview {
+        let (plan_summary, plan_error) = match self.recorded_plan_summary(options) {
+            Ok(summary) => (Some(summary), None),
+            Err(err) => (None, Some(err.to_string())),
+        };
+        
+        ApplyPreview {
+            command: self.apply_command(options),
+            plan_summary,
+            plan_error,
+        }
     }
     
-    pub async fn apply(&self, auto_approve: bool) -> anyhow::Result<String> {
-        let mut args = vec!["apply", "-no-color"];
-        if auto_approve {
-            args.push("-auto-approve");
+    /// Builds the apply command line
+    ///
+    /// With a saved plan, variables and targets are already part of the plan
+    /// and terraform applies it without prompting.
+    fn apply_command(&self, options: &ApplyOptions) -> CommandPreview {
+        let mut args = vec!["apply".to_string(), "-no-color".to_string()];
+        if let Some(plan_file) = &options.plan_file {
+            args.push(plan_file.display().to_string());
+        } else {
+            if options.auto_approve {
+                args.push("-auto-approve".to_string());
+            }
+            args.extend(self.project_options.var_file_args());
+            args.extend(options.common_args());
         }
         
-        let output = Command::new(&self.terraform_path)
-            .args(&args)
-            .current_dir(&self.project_directory)
-            .output()?;
+        CommandPreview {
+            program: self.terraform_path.to_string_lossy().to_string(),
+            args,
+            env: options.env(),
+            working_dir: self.project_directory.to_string_lossy().to_string(),
+        }
+    }
+    
+    pub async fn apply(&self, options: &ApplyOptions) -> anyhow::Result<String> {
+        match &options.plan_file {
+            Some(plan_file) => self.check_plan_is_current(plan_file)?,
+            None if self.require_plan_file => return Err(TerraformError::PlanFileRequired.into()),
+            None => {}
+        }
+        
+        let command = self.apply_command(options);
+        
+        let output = self.run(&command.args, &command.env)?;
         
         if !output.status.success() {
             return Err(TerraformError::CommandFailed(
@@ -158,11 +434,134 @@ impl TerraformService {
         Ok(String::from_utf8_lossy(&output.stdout).to_string())
     }
     
+    /// Rejects a saved plan whose configuration has changed since it was generated
+    ///
+    /// Plans saved through `plan` are checked against the recorded configuration
+    /// fingerprint; plans saved elsewhere fall back to comparing modification times.
+    fn check_plan_is_current(&self, plan_file: &Path) -> Result<(), TerraformError> {
+        let plan_path = self.project_directory.join(plan_file);
+        let plan_modified = match std::fs::metadata(&plan_path) {
+            Ok(metadata) => metadata.modified()?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Err(TerraformError::PlanFileNotFound(plan_path.display().to_string()));
+            }
+            Err(e) => return Err(e.into()),
+        };
+        
+        match std::fs::read_to_string(self.fingerprint_path(plan_file)) {
+            Ok(recorded) => {
+                let current = format!("{:016x}", self.config_fingerprint()?);
+                if recorded.trim() != current {
+                    return Err(TerraformError::StalePlan(format!(
+                        "configuration changed since {} was generated; run plan again",
+                        plan_file.display()
+                    )));
+                }
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                for path in self.config_files()? {
+                    if std::fs::metadata(&path)?.modified()? > plan_modified {
+                        return Err(TerraformError::StalePlan(format!(
+                            "{} was modified after {} was generated; run plan again",
+                            path.display(),
+                            plan_file.display()
+                        )));
+                    }
+                }
+            }
+            Err(e) => return Err(e.into()),
+        }
+        
+        Ok(())
+    }
+    
+    /// Change summary recorded by `plan`, read without running terraform
+    fn recorded_plan_summary(&self, options: &ApplyOptions) -> Result<TerraformChanges, TerraformError> {
+        if let Some(plan_file) = &options.plan_file {
+            self.check_plan_is_current(plan_file)?;
+            return match std::fs::read_to_string(self.summary_path(plan_file)) {
+                Ok(recorded) => {
+                    serde_json::from_str(&recorded).map_err(|e| TerraformError::ParseError(e.to_string()))
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => Err(TerraformError::NoPlanSummary(
+                    format!("{} was not saved by plan", plan_file.display()),
+                )),
+                Err(e) => Err(e.into()),
+            };
+        }
+        
+        let fingerprint = self.config_fingerprint()?;
+        match self.last_plan.lock().unwrap().as_ref() {
+            Some(plan)
+                if plan.fingerprint == fingerprint
+                    && plan.args == options.common_args()
+                    && plan.env == options.env() =>
+            {
+                Ok(plan.summary.clone())
+            }
+            _ => Err(TerraformError::NoPlanSummary(
+                "run plan with the same options first".to_string(),
+            )),
+        }
+    }
+    
+    fn fingerprint_path(&self, plan_file: &Path) -> PathBuf {
+        self.plan_sidecar_path(plan_file, PLAN_FINGERPRINT_SUFFIX)
+    }
+    
+    fn summary_path(&self, plan_file: &Path) -> PathBuf {
+        self.plan_sidecar_path(plan_file, PLAN_SUMMARY_SUFFIX)
+    }
+    
+    fn plan_sidecar_path(&self, plan_file: &Path, suffix: &str) -> PathBuf {
+        let mut path = self.project_directory.join(plan_file).into_os_string();
+        path.push(suffix);
+        PathBuf::from(path)
+    }
+    
+    /// Configuration files that affect a plan, sorted by path
+    fn config_files(&self) -> Result<Vec<PathBuf>, TerraformError> {
+        let mut files: Vec<PathBuf> = std::fs::read_dir(&self.project_directory)?
+            .filter_map(Result::ok)
+            .map(|entry| entry.path())
+            .filter(|path| {
+                let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+                path.is_file()
+                    && (name.ends_with(".tf")
+                        || name.ends_with(".tf.json")
+                        || name.ends_with(".tfvars")
+                        || name.ends_with(".tfvars.json")
+                        || name == ".terraform.lock.hcl")
+            })
+            .collect();
+        files.sort();
+        Ok(files)
+    }
+    
+    /// FNV-1a hash over the names and contents of the configuration files
+    fn config_fingerprint(&self) -> Result<u64, TerraformError> {
+        const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+        const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+        
+        let mut hash = FNV_OFFSET;
+        let mut feed = |bytes: &[u8]| {
+            for byte in bytes {
+                hash ^= u64::from(*byte);
+                hash = hash.wrapping_mul(FNV_PRIME);
+            }
+        };
+        for path in self.config_files()? {
+            let name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+            let content = std::fs::read(&path)?;
+            feed(name.as_bytes());
+            feed(&(content.len() as u64).to_le_bytes());
+            feed(&content);
+        }
+        Ok(hash)
+    }
+    
     pub async fn get_state(&self) -> anyhow::Result<String> {
-        let output = Command::new(&self.terraform_path)
-            .args(["show", "-no-color"])
-            .current_dir(&self.project_directory)
-            .output()?;
+        let output = self.run_args(&["show", "-no-color"])?;
         
         if !output.status.success() {
             return Err(TerraformError::CommandFailed(
@@ -173,11 +572,51 @@ impl TerraformService {
         Ok(String::from_utf8_lossy(&output.stdout).to_string())
     }
     
+    /// Evaluates an expression with `terraform console` against the current state
+    ///
+    /// The expression is written to the console's stdin followed by EOF, so the
+    /// console prints the result and exits instead of waiting for more input.
+    pub async fn console(&self, expression: &str) -> anyhow::Result<String> {
+        let expression = expression.trim();
+        if expression.is_empty() {
+            return Err(TerraformError::EmptyExpression.into());
+        }
+        
+        let mut args = vec!["console".to_string()];
+        args.extend(self.project_options.var_file_args());
+        let input = format!("{}\n", expression);
+        let output = self.run_with_input(&args, &BTreeMap::new(), Some(input.as_bytes()))?;
+        
+        if !output.status.success() {
+            return Err(TerraformError::CommandFailed(
+                String::from_utf8_lossy(&output.stderr).to_string(),
+            ).into());
+        }
+        
+        Ok(String::from_utf8_lossy(&output.stdout).trim_end().to_string())
+    }
+    
+    /// Reads the resource changes of a saved plan with `terraform show -json`
+    pub async fn resource_changes(&self, plan_file: &Path) -> anyhow::Result<Vec<ResourceChange>> {
+        let plan_path = self.project_directory.join(plan_file);
+        if !plan_path.is_file() {
+            return Err(TerraformError::PlanFileNotFound(plan_path.display().to_string()).into());
+        }
+        
+        let args = vec!["show".to_string(), "-json".to_string(), plan_file.display().to_string()];
+        let output = self.run(&args, &BTreeMap::new())?;
+        
+        if !output.status.success() {
+            return Err(TerraformError::CommandFailed(
+                String::from_utf8_lossy(&output.stderr).to_string(),
+            ).into());
+        }
+        
+        Ok(parse_resource_changes(&String::from_utf8_lossy(&output.stdout))?)
+    }
+    
     pub async fn list_resources(&self) -> anyhow::Result<Vec<String>> {
-        let output = Command::new(&self.terraform_path)
-            .args(["state", "list"])
-            .current_dir(&self.project_directory)
-            .output()?;
+        let output = self.run_args(&["state", "list"])?;
         
         if !output.status.success() {
             return Err(TerraformError::CommandFailed(
@@ -194,10 +633,7 @@ impl TerraformService {
     }
     
     pub async fn validate(&self) -> anyhow::Result<String> {
-        let output = Command::new(&self.terraform_path)
-            .args(["validate", "-no-color"])
-            .current_dir(&self.project_directory)
-            .output()?;
+        let output = self.run_args(&["validate", "-no-color"])?;
         
         if !output.status.success() {
             return Err(TerraformError::CommandFailed(
@@ -238,6 +674,7 @@ impl TerraformService {
             file_count: tf_files.len(),
             resources: Vec::new(),
             variables: Vec::new(),
+            variable_validations: Vec::new(),
             outputs: Vec::new(),
             providers: Vec::new(),
         };
@@ -300,6 +737,17 @@ impl TerraformService {
             }
         }
         
+        eprintln!("[DEBUG] Parsing variable validations in {}", file_path.display());
+        for (variable, condition, error_message) in parse_variable_validations(&content) {
+            eprintln!("[DEBUG] Found validation on variable: {}", variable);
+            analysis.variable_validations.push(TerraformVariableValidation {
+                variable,
+                condition,
+                error_message,
+                file: file_name.to_string(),
+            });
+        }
+        
         eprintln!("[DEBUG] Parsing outputs in {}", file_path.display());
         // Find outputs
         let output_regex = regex::Regex::new(r#"output\s+"([^"]+)"#).unwrap();
@@ -328,3 +776,613 @@ impl TerraformService {
         Ok(())
     }
 }
+
+/// Runs `command` with `input` on stdin (closed afterwards) and collects its output
+fn output_with_input(mut command: Command, input: &[u8]) -> std::io::Result<Output> {
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    
+    // Write from another thread so a child that answers before reading all of
+    // its input cannot deadlock on a full stdout pipe
+    std::thread::scope(|scope| {
+        let writer = scope.spawn(move || stdin.write_all(input));
+        let output = child.wait_with_output()?;
+        match writer.join().expect("stdin writer panicked") {
+            // The child exited without reading everything; its output says why
+            Err(err) if err.kind() != std::io::ErrorKind::BrokenPipe => Err(err),
+            _ => Ok(output),
+        }
+    })
+}
+
+/// Interprets `terraform plan -detailed-exitcode`: 0 = no changes, 2 = changes, else failure
+fn detailed_exit_code(output: &Output) -> Result<bool, TerraformError> {
+    match output.status.code() {
+        Some(0) => Ok(false),
+        Some(2) => Ok(true),
+        _ => Err(TerraformError::CommandFailed(
+            String::from_utf8_lossy(&output.stderr).to_string(),
+        )),
+    }
+}
+
+/// Extracts the "Plan: X to add, Y to change, Z to destroy." summary line
+pub fn parse_plan_summary(plan_output: &str) -> Option<TerraformChanges> {
+    if plan_output.contains("No changes.") {
+        return Some(TerraformChanges { add: 0, change: 0, destroy: 0 });
+    }
+    
+    let summary_regex =
+        regex::Regex::new(r"Plan: (\d+) to add, (\d+) to change, (\d+) to destroy").unwrap();
+    let captures = summary_regex.captures(plan_output)?;
+    
+    Some(TerraformChanges {
+        add: captures[1].parse().ok()?,
+        change: captures[2].parse().ok()?,
+        destroy: captures[3].parse().ok()?,
+    })
+}
+
+/// Extracts the resource changes from `terraform show -json` output of a saved plan
+///
+/// Changes that leave the infrastructure as it is (`no-op`, `read`) are skipped.
+pub fn parse_resource_changes(plan_json: &str) -> Result<Vec<ResourceChange>, TerraformError> {
+    let plan: serde_json::Value =
+        serde_json::from_str(plan_json).map_err(|e| TerraformError::ParseError(e.to_string()))?;
+    let Some(resource_changes) = plan.get("resource_changes").and_then(|v| v.as_array()) else {
+        return Ok(Vec::new());
+    };
+    
+    let mut changes = Vec::new();
+    for resource in resource_changes {
+        let field = |name: &str| {
+            resource
+                .get(name)
+                .and_then(|v| v.as_str())
+                .map(str::to_string)
+                .ok_or_else(|| TerraformError::ParseError(format!("resource change without {}", name)))
+        };
+        let change = resource.get("change").cloned().unwrap_or_default();
+        let actions: Vec<&str> = change
+            .get("actions")
+            .and_then(|v| v.as_array())
+            .map(|actions| actions.iter().filter_map(|a| a.as_str()).collect())
+            .unwrap_or_default();
+        let action = match actions.as_slice() {
+            ["create"] => ChangeAction::Create,
+            ["update"] => ChangeAction::Update,
+            ["delete"] => ChangeAction::Delete,
+            ["delete", "create"] | ["create", "delete"] => ChangeAction::Replace,
+            _ => continue,
+        };
+        
+        changes.push(ResourceChange {
+            address: field("address")?,
+            resource_type: field("type")?,
+            action,
+            before: change.get("before").cloned().unwrap_or_default(),
+            after: change.get("after").cloned().unwrap_or_default(),
+        });
+    }
+    Ok(changes)
+}
+
+/// Extracts `(variable, condition, error_message)` for every `validation` block
+/// inside `variable` blocks
+fn parse_variable_validations(content: &str) -> Vec<(String, String, String)> {
+    let variable_regex = regex::Regex::new(r#"variable\s+"([^"]+)"\s*\{"#).unwrap();
+    let validation_regex = regex::Regex::new(r"(?m)^\s*validation\s*\{").unwrap();
+    
+    let mut validations = Vec::new();
+    for captures in variable_regex.captures_iter(content) {
+        let open = captures.get(0).unwrap().end() - 1;
+        let Some(variable_body) = block_body(content, open) else {
+            continue;
+        };
+        
+        for found in validation_regex.find_iter(variable_body) {
+            let Some(validation_body) = block_body(variable_body, found.end() - 1) else {
+                continue;
+            };
+            let condition = attribute_expr(validation_body, "condition").unwrap_or_default();
+            let error_message = attribute_expr(validation_body, "error_message")
+                .map(|message| unquote(&message))
+                .unwrap_or_default();
+            validations.push((captures[1].to_string(), condition, error_message));
+        }
+    }
+    validations
+}
+
+/// Returns the text between the `{` at `open` and its matching `}`,
+/// ignoring braces inside strings and comments
+fn block_body(content: &str, open: usize) -> Option<&str> {
+    let bytes = content.as_bytes();
+    let mut depth = 0;
+    let mut in_string = false;
+    let mut escaped = false;
+    let mut i = open;
+    
+    while i < bytes.len() {
+        let b = bytes[i];
+        if in_string {
+            match b {
+                _ if escaped => escaped = false,
+                b'\\' => escaped = true,
+                b'"' => in_string = false,
+                _ => {}
+            }
+        } else {
+            match b {
+                b'"' => in_string = true,
+                b'#' => i = skip_line(bytes, i),
+                b'/' if bytes.get(i + 1) == Some(&b'/') => i = skip_line(bytes, i),
+                b'{' => depth += 1,
+                b'}' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Some(&content[open + 1..i]);
+                    }
+                }
+                _ => {}
+            }
+        }
+        i += 1;
+    }
+    None
+}
+
+fn skip_line(bytes: &[u8], from: usize) -> usize {
+    bytes[from..]
+        .iter()
+        .position(|&b| b == b'\n')
+        .map_or(bytes.len(), |offset| from + offset)
+}
+
+/// Returns the expression assigned to `name`, which may span lines while brackets are open
+fn attribute_expr(body: &str, name: &str) -> Option<String> {
+    let attribute_regex = regex::Regex::new(&format!(r"(?m)^\s*{}\s*=", name)).unwrap();
+    let rest = &body[attribute_regex.find(body)?.end()..];
+    
+    let mut depth = 0;
+    let mut in_string = false;
+    let mut escaped = false;
+    for (i, b) in rest.bytes().enumerate() {
+        if in_string {
+            match b {
+                _ if escaped => escaped = false,
+                b'\\' => escaped = true,
+                b'"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match b {
+            b'"' => in_string = true,
+            b'(' | b'[' | b'{' => depth += 1,
+            b')' | b']' | b'}' => depth -= 1,
+            b'\n' if depth <= 0 => return Some(rest[..i].trim().to_string()),
+            _ => {}
+        }
+    }
+    Some(rest.trim().to_string())
+}
+
+/// Strips the quotes from a plain HCL string literal; other expressions are returned as-is
+fn unquote(expr: &str) -> String {
+    match expr.strip_prefix('"').and_then(|s| s.strip_suffix('"')) {
+        Some(inner) => inner.replace("\\\"", "\"").replace("\\\\", "\\"),
+        None => expr.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+    
+    /// Creates a project with a fake terraform that leaves a marker file when run
+    fn fake_project() -> (tempfile::TempDir, TerraformService) {
+        let dir = tempfile::tempdir().unwrap();
+        let marker = dir.path().join("invoked");
+        project_with_script(dir, &format!("touch {}", marker.display()))
+    }
+    
+    /// Creates a project whose fake terraform runs the given shell script
+    fn project_with_script(dir: tempfile::TempDir, script: &str) -> (tempfile::TempDir, TerraformService) {
+        std::fs::write(dir.path().join("main.tf"), "resource \"null_resource\" \"a\" {}\n").unwrap();
+        
+        let terraform = dir.path().join("terraform");
+        std::fs::write(&terraform, format!("#!/bin/sh\n{}\n", script)).unwrap();
+        std::fs::set_permissions(&terraform, std::fs::Permissions::from_mode(0o755)).unwrap();
+        
+        let service = TerraformService::new(terraform, dir.path().to_path_buf()).unwrap();
+        (dir, service)
+    }
+    
+    #[test]
+    fn test_preview_apply_builds_command_without_running_terraform() {
+        let (dir, service) = fake_project();
+        let options = ApplyOptions {
+            auto_approve: true,
+            variables: vec![("region".to_string(), "us-east-1".to_string())],
+            targets: vec!["aws_instance.web".to_string()],
+            workspace: Some("staging".to_string()),
+            plan_file: None,
+        };
+        
+        let preview = service.preview_apply(&options).command;
+        
+        assert_eq!(
+            preview.args,
+            vec![
+                "apply",
+                "-no-color",
+                "-auto-approve",
+                "-var=region=us-east-1",
+                "-target=aws_instance.web",
+            ]
+        );
+        assert_eq!(preview.env.get("TF_WORKSPACE").map(String::as_str), Some("staging"));
+        assert_eq!(preview.program, dir.path().join("terraform").to_string_lossy());
+        assert_eq!(preview.working_dir, dir.path().to_string_lossy());
+        assert!(!dir.path().join("invoked").exists());
+    }
+    
+    #[test]
+    fn test_project_var_files_come_before_variables() {
+        let (_dir, mut service) = fake_project();
+        service.set_project_options(ProjectOptions {
+            backend_config: Some(PathBuf::from("backend/staging.hcl")),
+            var_files: vec![PathBuf::from("staging.tfvars")],
+        });
+        let options = ApplyOptions {
+            variables: vec![("region".to_string(), "us-east-1".to_string())],
+            ..ApplyOptions::default()
+        };
+        
+        let preview = service.preview_apply(&options).command;
+        assert_eq!(
+            preview.args,
+            vec!["apply", "-no-color", "-var-file=staging.tfvars", "-var=region=us-east-1"]
+        );
+    }
+    
+    #[test]
+    fn test_preview_apply_defaults() {
+        let (_dir, service) = fake_project();
+        let preview = service.preview_apply(&ApplyOptions::default());
+        assert_eq!(preview.command.args, vec!["apply", "-no-color"]);
+        assert!(preview.command.env.is_empty());
+        assert_eq!(preview.plan_summary, None);
+        assert!(preview.plan_error.is_some());
+    }
+    
+    #[tokio::test]
+    async fn test_completed_command_is_recorded_in_audit_log() {
+        let (dir, service) = fake_project();
+        let options = ApplyOptions {
+            variables: vec![("db_password".to_string(), "hunter2".to_string())],
+            ..ApplyOptions::default()
+        };
+        
+        service.plan(&options).await.unwrap();
+        
+        let history = service.command_history(10);
+        assert_eq!(history.len(), 1);
+        let entry = &history[0];
+        assert_eq!(entry.subcommand, "plan");
+        assert_eq!(entry.args, vec!["plan", "-no-color", "-var=db_password=<redacted>"]);
+        assert_eq!(entry.exit_code, Some(0));
+        assert_eq!(entry.working_dir, dir.path().to_string_lossy());
+        assert!(chrono::DateTime::parse_from_rfc3339(&entry.timestamp).is_ok());
+    }
+    
+    #[tokio::test]
+    async fn test_startup_check_detects_drift_and_pending_changes() {
+        // refresh-only plan reports drift, regular plan reports changes
+        let script = r#"case "$*" in
+  *-refresh-only*) echo "Terraform detected the following changes made outside of Terraform"; exit 2 ;;
+  *) echo "Plan: 1 to add, 0 to change, 2 to destroy."; exit 2 ;;
+esac"#;
+        let (_dir, service) = project_with_script(tempfile::tempdir().unwrap(), script);
+        
+        let check = service.startup_check().await.unwrap();
+        assert_eq!(
+            check,
+            StartupCheck {
+                drift_detected: true,
+                has_pending_changes: true,
+                pending_changes: Some(TerraformChanges { add: 1, change: 0, destroy: 2 }),
+            }
+        );
+        assert_eq!(service.command_history(10).len(), 2);
+    }
+    
+    #[tokio::test]
+    async fn test_startup_check_fails_on_terraform_error() {
+        let (_dir, service) =
+            project_with_script(tempfile::tempdir().unwrap(), "echo 'no backend' >&2; exit 1");
+        assert!(service.startup_check().await.is_err());
+    }
+    
+    #[tokio::test]
+    async fn test_console_pipes_expression_and_returns_result() {
+        let dir = tempfile::tempdir().unwrap();
+        let script = format!(
+            "echo \"$@\" > {args}\ncat > {stdin}\necho '\"t3.micro\"'",
+            args = dir.path().join("args").display(),
+            stdin = dir.path().join("stdin").display(),
+        );
+        let (dir, service) = project_with_script(dir, &script);
+        
+        let result = service.console("  aws_instance.web.instance_type ").await.unwrap();
+        
+        assert_eq!(result, "\"t3.micro\"");
+        assert_eq!(std::fs::read_to_string(dir.path().join("args")).unwrap(), "console\n");
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("stdin")).unwrap(),
+            "aws_instance.web.instance_type\n"
+        );
+        assert_eq!(service.command_history(1)[0].subcommand, "console");
+    }
+    
+    #[tokio::test]
+    async fn test_console_reports_evaluation_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let (_dir, service) = project_with_script(dir, "cat > /dev/null\necho 'Error: Reference to undeclared resource' >&2\nexit 1");
+        
+        let err = service.console("aws_instance.missing.id").await.unwrap_err();
+        assert!(err.to_string().contains("Reference to undeclared resource"));
+        
+        let err = service.console("   ").await.unwrap_err();
+        assert!(matches!(err.downcast_ref::<TerraformError>(), Some(TerraformError::EmptyExpression)));
+    }
+    
+    #[test]
+    fn test_parse_plan_summary() {
+        let output = "Terraform will perform the following actions:\n\nPlan: 2 to add, 1 to change, 0 to destroy.\n";
+        assert_eq!(
+            parse_plan_summary(output),
+            Some(TerraformChanges { add: 2, change: 1, destroy: 0 })
+        );
+        assert_eq!(
+            parse_plan_summary("No changes. Your infrastructure matches the configuration."),
+            Some(TerraformChanges { add: 0, change: 0, destroy: 0 })
+        );
+        assert_eq!(parse_plan_summary("garbage"), None);
+    }
+    
+    #[tokio::test]
+    async fn test_analysis_includes_variable_validations() {
+        let (dir, service) = fake_project();
+        std::fs::write(
+            dir.path().join("variables.tf"),
+            r#"
+variable "instance_count" {
+  type    = number
+  default = 2
+
+  validation {
+    condition     = var.instance_count > 0
+    error_message = "instance_count must be positive."
+  }
+
+  # Braces in comments and strings must not confuse the parser: }
+  validation {
+    condition = contains(
+      [1, 2, 4],
+      var.instance_count
+    )
+    error_message = "instance_count must be one of {1, 2, 4}, got \"${var.instance_count}\"."
+  }
+}
+
+variable "region" {
+  type = string
+}
+"#,
+        )
+        .unwrap();
+        
+        let analysis = service.analyze_configurations().await.unwrap();
+        
+        assert_eq!(analysis.variables, vec!["instance_count", "region"]);
+        assert_eq!(
+            analysis.variable_validations,
+            vec![
+                TerraformVariableValidation {
+                    variable: "instance_count".to_string(),
+                    condition: "var.instance_count > 0".to_string(),
+                    error_message: "instance_count must be positive.".to_string(),
+                    file: "variables.tf".to_string(),
+                },
+                TerraformVariableValidation {
+                    variable: "instance_count".to_string(),
+                    condition: "contains(\n      [1, 2, 4],\n      var.instance_count\n    )".to_string(),
+                    error_message:
+                        "instance_count must be one of {1, 2, 4}, got \"${var.instance_count}\"."
+                            .to_string(),
+                    file: "variables.tf".to_string(),
+                },
+            ]
+        );
+    }
+    
+    /// Fake terraform that writes the `-out` plan file and marks when apply runs
+    fn plan_file_project() -> (tempfile::TempDir, TerraformService) {
+        let dir = tempfile::tempdir().unwrap();
+        let applied = dir.path().join("applied");
+        let script = format!(
+            r#"for arg in "$@"; do
+  case "$arg" in -out=*) echo saved-plan > "${{arg#-out=}}" ;; esac
+done
+[ "$1" = apply ] && touch {}
+exit 0"#,
+            applied.display()
+        );
+        project_with_script(dir, &script)
+    }
+    
+    #[tokio::test]
+    async fn test_apply_with_saved_plan() {
+        let (dir, service) = plan_file_project();
+        let options = ApplyOptions {
+            plan_file: Some(PathBuf::from("release.tfplan")),
+            variables: vec![("region".to_string(), "us-east-1".to_string())],
+            ..ApplyOptions::default()
+        };
+        
+        service.plan(&options).await.unwrap();
+        assert!(dir.path().join("release.tfplan").exists());
+        
+        // Variables are baked into the saved plan
+        assert_eq!(service.preview_apply(&options).command.args, vec!["apply", "-no-color", "release.tfplan"]);
+        service.apply(&options).await.unwrap();
+        assert!(dir.path().join("applied").exists());
+    }
+    
+    #[tokio::test]
+    async fn test_apply_rejects_stale_plan_after_config_edit() {
+        let (dir, service) = plan_file_project();
+        let options = ApplyOptions {
+            plan_file: Some(PathBuf::from("release.tfplan")),
+            ..ApplyOptions::default()
+        };
+        
+        service.plan(&options).await.unwrap();
+        std::fs::write(
+            dir.path().join("main.tf"),
+            "resource \"null_resource\" \"a\" {}\nresource \"null_resource\" \"b\" {}\n",
+        )
+        .unwrap();
+        
+        let err = service.apply(&options).await.unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<TerraformError>(),
+            Some(TerraformError::StalePlan(_))
+        ));
+        assert!(!dir.path().join("applied").exists());
+    }
+    
+    #[tokio::test]
+    async fn test_apply_rejects_plan_older_than_config_without_fingerprint() {
+        let (dir, service) = plan_file_project();
+        // Plan generated outside tfmcp, before the last config edit
+        std::fs::write(dir.path().join("external.tfplan"), "saved-plan").unwrap();
+        let past = std::time::SystemTime::now() - std::time::Duration::from_secs(60);
+        std::fs::File::options()
+            .write(true)
+            .open(dir.path().join("external.tfplan"))
+            .unwrap()
+            .set_modified(past)
+            .unwrap();
+        
+        let options = ApplyOptions {
+            plan_file: Some(PathBuf::from("external.tfplan")),
+            ..ApplyOptions::default()
+        };
+        let err = service.apply(&options).await.unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<TerraformError>(),
+            Some(TerraformError::StalePlan(_))
+        ));
+        
+        let missing = ApplyOptions {
+            plan_file: Some(PathBuf::from("missing.tfplan")),
+            ..ApplyOptions::default()
+        };
+        let err = service.apply(&missing).await.unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<TerraformError>(),
+            Some(TerraformError::PlanFileNotFound(_))
+        ));
+        assert!(!dir.path().join("applied").exists());
+    }
+    
+    /// Fake terraform whose plan reports one change per `-var` and saves `-out` plans
+    fn summary_project() -> (tempfile::TempDir, TerraformService) {
+        let script = r#"[ "$1" = plan ] || exit 1
+add=0
+for arg in "$@"; do
+  case "$arg" in
+    -out=*) echo saved-plan > "${arg#-out=}" ;;
+    -var=*) add=$((add + 1)) ;;
+  esac
+done
+echo "Plan: $add to add, 0 to change, 1 to destroy.""#;
+        project_with_script(tempfile::tempdir().unwrap(), script)
+    }
+    
+    #[tokio::test]
+    async fn test_preview_apply_reports_saved_plan_summary() {
+        let (dir, service) = summary_project();
+        let plan_file = PathBuf::from("release.tfplan");
+        let options = ApplyOptions {
+            plan_file: Some(plan_file.clone()),
+            variables: vec![("region".to_string(), "us-east-1".to_string())],
+            ..ApplyOptions::default()
+        };
+        service.plan(&options).await.unwrap();
+        let plan_path = dir.path().join("release.tfplan");
+        let fingerprint_path = service.fingerprint_path(&plan_file);
+        let saved = (std::fs::metadata(&plan_path).unwrap().modified().unwrap(), std::fs::read(&fingerprint_path).unwrap());
+        let history = service.command_history(10).len();
+        
+        let preview = service.preview_apply(&options);
+        assert_eq!(preview.plan_summary, Some(TerraformChanges { add: 1, change: 0, destroy: 1 }));
+        assert_eq!(preview.plan_error, None);
+        assert_eq!(
+            (std::fs::metadata(&plan_path).unwrap().modified().unwrap(), std::fs::read(&fingerprint_path).unwrap()),
+            saved
+        );
+        
+        // A stale plan is reported, not regenerated
+        std::fs::write(dir.path().join("main.tf"), "resource \"null_resource\" \"b\" {}\n").unwrap();
+        let preview = service.preview_apply(&options);
+        assert_eq!(preview.plan_summary, None);
+        assert!(preview.plan_error.unwrap().contains("stale"));
+        assert_eq!(service.command_history(10).len(), history);
+    }
+    
+    #[tokio::test]
+    async fn test_preview_apply_reports_last_plan_with_same_options() {
+        let (dir, service) = summary_project();
+        let options = ApplyOptions {
+            variables: vec![("region".to_string(), "us-east-1".to_string())],
+            ..ApplyOptions::default()
+        };
+        service.plan(&options).await.unwrap();
+        let history = service.command_history(10).len();
+        
+        let preview = service.preview_apply(&options);
+        assert_eq!(preview.plan_summary, Some(TerraformChanges { add: 1, change: 0, destroy: 1 }));
+        
+        let other = ApplyOptions {
+            variables: vec![("region".to_string(), "eu-west-1".to_string())],
+            ..ApplyOptions::default()
+        };
+        assert_eq!(service.preview_apply(&other).plan_summary, None);
+        
+        std::fs::write(dir.path().join("main.tf"), "resource \"null_resource\" \"b\" {}\n").unwrap();
+        assert_eq!(service.preview_apply(&options).plan_summary, None);
+        assert_eq!(service.command_history(10).len(), history);
+    }
+    
+    #[tokio::test]
+    async fn test_apply_without_plan_file_when_required() {
+        let (dir, service) = plan_file_project();
+        let service = service.with_require_plan_file(true);
+        
+        let err = service.apply(&ApplyOptions::default()).await.unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<TerraformError>(),
+            Some(TerraformError::PlanFileRequired)
+        ));
+        assert!(!dir.path().join("applied").exists());
+    }
+}
@@ -31,6 +31,8 @@ async fn search_books(
             query: "Rust".to_string(),
             page_size: 10,
             page_number: 1,
+            fuzzy: false,
+            min_similarity: 0.0,
         }))
         .await?;
 
@@ -0,0 +1,143 @@
+// 書籍ごとの貸出可否の変化を購読者に配信する
+//
+// 書籍IDごとにbroadcastチャネルを持ち、購読者がいなくなったチャネルは削除する。
+// 購読者のいない書籍への通知は何もしない。
+// サーバー停止時は `close` ですべてのチャネルを閉じ、購読中のストリームを終わらせる。
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+use tokio::sync::broadcast;
+
+use crate::BookAvailabilityEvent;
+
+// 購読者ごとに溜められる未読イベントの数（超えた分は古いものから捨てる）
+const EVENT_BUFFER: usize = 16;
+
+#[derive(Default)]
+pub struct AvailabilityWatchers {
+    channels: Mutex<HashMap<String, broadcast::Sender<BookAvailabilityEvent>>>,
+    closed: AtomicBool,
+}
+
+impl AvailabilityWatchers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // `close` の後に購読すると、すぐに終わるレシーバーを返す
+    pub fn subscribe(&self, book_id: &str) -> broadcast::Receiver<BookAvailabilityEvent> {
+        let mut channels = self.channels.lock().unwrap();
+        if self.closed.load(Ordering::SeqCst) {
+            return broadcast::channel(1).1;
+        }
+        channels
+            .entry(book_id.to_string())
+            .or_insert_with(|| broadcast::channel(EVENT_BUFFER).0)
+            .subscribe()
+    }
+
+    // 購読を終えた後に呼ぶ。最後の購読者だったら書籍のチャネルを削除する
+    //
+    // `receiver` を受け取ってロック中に破棄し、同時に購読を始めた相手と競合しないようにする
+    pub fn unsubscribe(&self, book_id: &str, receiver: broadcast::Receiver<BookAvailabilityEvent>) {
+        let mut channels = self.channels.lock().unwrap();
+        drop(receiver);
+        if channels
+            .get(book_id)
+            .is_some_and(|sender| sender.receiver_count() == 0)
+        {
+            channels.remove(book_id);
+        }
+    }
+
+    pub fn publish(&self, event: BookAvailabilityEvent) {
+        if let Some(sender) = self.channels.lock().unwrap().get(&event.book_id) {
+            // 購読者が全員切断していても送信エラーになるだけなので無視する
+            let _ = sender.send(event);
+        }
+    }
+
+    // すべてのチャネルを破棄し、購読中のストリームを終わらせる（サーバー停止時に呼ぶ）
+    //
+    // 送信側が破棄されると、購読者は未読のイベントを受け取った後に `Closed` を受け取る
+    pub fn close(&self) {
+        let mut channels = self.channels.lock().unwrap();
+        self.closed.store(true, Ordering::SeqCst);
+        channels.clear();
+    }
+
+    #[cfg(test)]
+    pub fn watched_books(&self) -> usize {
+        self.channels.lock().unwrap().len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(book_id: &str, available: bool) -> BookAvailabilityEvent {
+        BookAvailabilityEvent {
+            book_id: book_id.to_string(),
+            available,
+            changed_at: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn events_are_delivered_only_to_watchers_of_the_book() {
+        let watchers = AvailabilityWatchers::new();
+        let mut first = watchers.subscribe("1");
+        let mut second = watchers.subscribe("1");
+        let mut other = watchers.subscribe("2");
+
+        watchers.publish(event("1", true));
+        watchers.publish(event("3", true));
+
+        assert_eq!(first.recv().await.unwrap(), event("1", true));
+        assert_eq!(second.recv().await.unwrap(), event("1", true));
+        assert!(other.try_recv().is_err());
+    }
+
+    #[test]
+    fn channel_is_removed_with_the_last_watcher() {
+        let watchers = AvailabilityWatchers::new();
+        let first = watchers.subscribe("1");
+        let second = watchers.subscribe("1");
+        assert_eq!(watchers.watched_books(), 1);
+
+        watchers.unsubscribe("1", first);
+        assert_eq!(watchers.watched_books(), 1);
+        watchers.unsubscribe("1", second);
+        assert_eq!(watchers.watched_books(), 0);
+
+        // 購読者がいなければ通知しても何も残らない
+        watchers.publish(event("1", false));
+        assert_eq!(watchers.watched_books(), 0);
+    }
+
+    #[tokio::test]
+    async fn close_ends_current_and_future_subscriptions() {
+        let watchers = AvailabilityWatchers::new();
+        let mut current = watchers.subscribe("1");
+        watchers.publish(event("1", true));
+
+        watchers.close();
+        assert_eq!(watchers.watched_books(), 0);
+
+        // 閉じる前に届いたイベントは読める
+        assert_eq!(current.recv().await.unwrap(), event("1", true));
+        assert_eq!(
+            current.recv().await.unwrap_err(),
+            broadcast::error::RecvError::Closed
+        );
+
+        let mut late = watchers.subscribe("1");
+        assert_eq!(
+            late.recv().await.unwrap_err(),
+            broadcast::error::RecvError::Closed
+        );
+        assert_eq!(watchers.watched_books(), 0);
+    }
+}
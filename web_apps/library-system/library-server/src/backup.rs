@@ -0,0 +1,178 @@
+// ユーザー・書籍・貸出をまとめたバックアップ（障害時の復旧用）
+//
+// 書き出しは1つの読み込みトランザクション内で行うので、途中で貸出や返却が
+// コミットされても、貸出と書籍の貸出可否が食い違ったスナップショットにはならない。
+// 形式は人が読めて差分も取りやすいJSONにしている。
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, Sqlite};
+
+use crate::database::ImmediateTransaction;
+use crate::{BookRow, LoanRow, UserRow};
+
+// バックアップ形式のバージョン（互換性のない変更をしたら上げる）
+pub const BACKUP_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Backup {
+    pub version: u32,
+    pub exported_at: DateTime<Utc>,
+    pub users: Vec<UserRow>,
+    pub books: Vec<BookRow>,
+    pub loans: Vec<LoanRow>,
+}
+
+// バックアップの読み込み・復元時のエラー
+#[derive(Debug)]
+pub enum BackupError {
+    // ユーザーか貸出が既にあり、forceが指定されていない
+    NotEmpty,
+    UnsupportedVersion(u32),
+    Invalid(serde_json::Error),
+    Database(sqlx::Error),
+}
+
+impl std::fmt::Display for BackupError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BackupError::NotEmpty => {
+                write!(
+                    f,
+                    "database already contains users or loans (use force to overwrite)"
+                )
+            }
+            BackupError::UnsupportedVersion(version) => write!(
+                f,
+                "unsupported backup version {} (expected {})",
+                version, BACKUP_VERSION
+            ),
+            BackupError::Invalid(e) => write!(f, "invalid backup: {}", e),
+            BackupError::Database(e) => write!(f, "database error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for BackupError {}
+
+impl From<sqlx::Error> for BackupError {
+    fn from(e: sqlx::Error) -> Self {
+        BackupError::Database(e)
+    }
+}
+
+impl Backup {
+    pub fn to_json(&self) -> Vec<u8> {
+        serde_json::to_vec_pretty(self).expect("backup rows always serialize")
+    }
+
+    pub fn from_json(bytes: &[u8]) -> Result<Self, BackupError> {
+        let backup: Backup = serde_json::from_slice(bytes).map_err(BackupError::Invalid)?;
+        if backup.version != BACKUP_VERSION {
+            return Err(BackupError::UnsupportedVersion(backup.version));
+        }
+        Ok(backup)
+    }
+}
+
+// 全テーブルを1つのトランザクション内で読み出す
+pub async fn export(pool: &Pool<Sqlite>) -> Result<Backup, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    let users = sqlx::query_as::<_, UserRow>("SELECT id, name, email FROM users ORDER BY id")
+        .fetch_all(&mut *tx)
+        .await?;
+    let books = sqlx::query_as::<_, BookRow>(
+        "SELECT id, title, author, isbn, available FROM books ORDER BY id",
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+    let loans = sqlx::query_as::<_, LoanRow>(
+        r#"
+        SELECT id, book_id, user_id, loan_date, due_date, return_date, status
+        FROM loans
+        ORDER BY id
+        "#,
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+
+    // 読み込みだけなので、ロールバックしても結果は同じ
+    tx.rollback().await?;
+
+    Ok(Backup {
+        version: BACKUP_VERSION,
+        exported_at: Utc::now(),
+        users,
+        books,
+        loans,
+    })
+}
+
+// バックアップの内容でデータベースを置き換える
+//
+// 書籍の目録はマイグレーションのサンプルデータで埋まっているため、ユーザーと
+// 貸出がなければ空のデータベースとみなす。そうでなければforceが必要。
+// 既存の行を消してから挿入するまでを1つのトランザクションで行うので、
+// 途中で失敗しても元のデータが残る。
+pub async fn import(pool: &Pool<Sqlite>, backup: &Backup, force: bool) -> Result<(), BackupError> {
+    let mut tx = ImmediateTransaction::begin(pool).await?;
+
+    let existing = sqlx::query_scalar::<_, i64>(
+        "SELECT (SELECT COUNT(*) FROM users) + (SELECT COUNT(*) FROM loans)",
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+    if existing > 0 && !force {
+        return Err(BackupError::NotEmpty);
+    }
+
+    // 外部キーで参照されている貸出から消す
+    for table in ["loans", "users", "books"] {
+        sqlx::query(&format!("DELETE FROM {}", table))
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    for user in &backup.users {
+        sqlx::query("INSERT INTO users (id, name, email) VALUES (?, ?, ?)")
+            .bind(&user.id)
+            .bind(&user.name)
+            .bind(&user.email)
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    for book in &backup.books {
+        sqlx::query(
+            "INSERT INTO books (id, title, author, isbn, available) VALUES (?, ?, ?, ?, ?)",
+        )
+        .bind(&book.id)
+        .bind(&book.title)
+        .bind(&book.author)
+        .bind(&book.isbn)
+        .bind(book.available)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    for loan in &backup.loans {
+        sqlx::query(
+            r#"
+            INSERT INTO loans (id, book_id, user_id, loan_date, due_date, return_date, status)
+            VALUES (?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(&loan.id)
+        .bind(&loan.book_id)
+        .bind(&loan.user_id)
+        .bind(loan.loan_date)
+        .bind(loan.due_date)
+        .bind(loan.return_date)
+        .bind(loan.status)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    tx.commit().await?;
+    Ok(())
+}
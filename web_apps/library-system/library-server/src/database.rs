@@ -0,0 +1,140 @@
+// SQLiteの接続設定（プラグマとコネクションプール）
+//
+// SQLiteは同時に1つの接続しか書き込めないため、デフォルト設定のままでは
+// 同時に貸出処理が走ると "database is locked" ですぐに失敗する。
+// デフォルトでは次の設定にしている。
+//
+// - journal_mode = WAL: 書き込み中も読み込みをブロックしない
+// - busy_timeout = 5秒: ロックが解放されるまで待ってから書き込む（即座に失敗しない）
+// - synchronous = NORMAL: WALではコミットごとのfsyncを省いても電源断以外で壊れない
+// - 最大接続数 = 5: 書き込みは直列化されるので、多くしてもロック待ちが増えるだけ
+// - 接続取得のタイムアウト = 10秒: busy_timeoutより長くし、ロック待ちの接続を待てるようにする
+//
+// busy_timeoutで待てるのは、トランザクションの最初に書き込みロックを取る場合だけ。
+// 読み込んでから書き込むトランザクションは ImmediateTransaction で始める。
+use std::str::FromStr;
+use std::time::Duration;
+
+use sqlx::pool::PoolConnection;
+use sqlx::sqlite::{
+    SqliteConnectOptions, SqliteConnection, SqliteJournalMode, SqlitePool, SqlitePoolOptions,
+    SqliteSynchronous,
+};
+use sqlx::Sqlite;
+
+pub const DEFAULT_BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+pub const DEFAULT_MAX_CONNECTIONS: u32 = 5;
+pub const DEFAULT_ACQUIRE_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone)]
+pub struct DatabaseConfig {
+    pub journal_mode: SqliteJournalMode,
+    pub busy_timeout: Duration,
+    pub synchronous: SqliteSynchronous,
+    pub max_connections: u32,
+    pub acquire_timeout: Duration,
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        Self {
+            journal_mode: SqliteJournalMode::Wal,
+            busy_timeout: DEFAULT_BUSY_TIMEOUT,
+            synchronous: SqliteSynchronous::Normal,
+            max_connections: DEFAULT_MAX_CONNECTIONS,
+            acquire_timeout: DEFAULT_ACQUIRE_TIMEOUT,
+        }
+    }
+}
+
+impl DatabaseConfig {
+    // 環境変数で指定された項目だけデフォルトを上書きする
+    //
+    // SQLITE_JOURNAL_MODE（wal, delete など）、SQLITE_BUSY_TIMEOUT_MS、
+    // SQLITE_SYNCHRONOUS（off, normal, full, extra）、DB_MAX_CONNECTIONS、DB_ACQUIRE_TIMEOUT_SECS
+    pub fn from_env() -> Result<Self, Box<dyn std::error::Error>> {
+        let mut config = Self::default();
+        if let Ok(mode) = std::env::var("SQLITE_JOURNAL_MODE") {
+            config.journal_mode = SqliteJournalMode::from_str(&mode)?;
+        }
+        if let Ok(millis) = std::env::var("SQLITE_BUSY_TIMEOUT_MS") {
+            config.busy_timeout = Duration::from_millis(millis.parse()?);
+        }
+        if let Ok(level) = std::env::var("SQLITE_SYNCHRONOUS") {
+            config.synchronous = SqliteSynchronous::from_str(&level)?;
+        }
+        if let Ok(connections) = std::env::var("DB_MAX_CONNECTIONS") {
+            config.max_connections = connections.parse()?;
+        }
+        if let Ok(secs) = std::env::var("DB_ACQUIRE_TIMEOUT_SECS") {
+            config.acquire_timeout = Duration::from_secs(secs.parse()?);
+        }
+        Ok(config)
+    }
+
+    // プラグマはプールが新しい接続を開くたびに設定される
+    pub async fn connect(&self, database_url: &str) -> Result<SqlitePool, sqlx::Error> {
+        let options = SqliteConnectOptions::from_str(database_url)?
+            .journal_mode(self.journal_mode)
+            .busy_timeout(self.busy_timeout)
+            .synchronous(self.synchronous);
+
+        SqlitePoolOptions::new()
+            .max_connections(self.max_connections)
+            .acquire_timeout(self.acquire_timeout)
+            .connect_with(options)
+            .await
+    }
+}
+
+// BEGIN IMMEDIATE で始める書き込みトランザクション
+//
+// pool.begin() は BEGIN（DEFERRED）なので、SELECTの後の書き込みで他の書き込みと
+// 重なると、WALではbusy_timeoutを待たずにすぐ "database is locked" になる
+// （読み込んだスナップショットが古くなり、待っても書き込めないため）。
+// 最初に書き込みロックを取っておけば、ロック待ちはbusy_timeoutまで待つ。
+//
+// コミットせずに破棄された場合（エラーやリクエストのキャンセル）は、接続をプールに
+// 戻さずに閉じてロールバックさせる。
+pub struct ImmediateTransaction {
+    conn: Option<PoolConnection<Sqlite>>,
+}
+
+impl ImmediateTransaction {
+    pub async fn begin(pool: &SqlitePool) -> Result<Self, sqlx::Error> {
+        let mut conn = pool.acquire().await?;
+        sqlx::query("BEGIN IMMEDIATE").execute(&mut *conn).await?;
+        Ok(Self { conn: Some(conn) })
+    }
+
+    pub async fn commit(mut self) -> Result<(), sqlx::Error> {
+        let conn = self.conn.as_mut().expect("transaction already finished");
+        sqlx::query("COMMIT").execute(&mut **conn).await?;
+        // コミットできた接続だけプールに戻す
+        self.conn.take();
+        Ok(())
+    }
+}
+
+impl std::ops::Deref for ImmediateTransaction {
+    type Target = SqliteConnection;
+
+    fn deref(&self) -> &SqliteConnection {
+        self.conn.as_ref().expect("transaction already finished")
+    }
+}
+
+impl std::ops::DerefMut for ImmediateTransaction {
+    fn deref_mut(&mut self) -> &mut SqliteConnection {
+        self.conn.as_mut().expect("transaction already finished")
+    }
+}
+
+impl Drop for ImmediateTransaction {
+    fn drop(&mut self) {
+        if let Some(conn) = self.conn.take() {
+            // 閉じた接続の未コミットの変更はSQLiteがロールバックする
+            drop(conn.detach());
+        }
+    }
+}
@@ -1,23 +1,39 @@
 use chrono::{DateTime, Duration, Utc};
 use prost_types::Timestamp;
-use sqlx::{sqlite::SqlitePool, Pool, Sqlite};
+use sqlx::{Pool, Sqlite};
+use std::collections::HashSet;
+use std::future::Future;
+use std::sync::Arc;
+use tokio::net::TcpListener;
+use tokio::sync::{broadcast, mpsc};
+use tokio_stream::wrappers::{ReceiverStream, TcpListenerStream};
 use tonic::{transport::Server, Request, Response, Status};
 use uuid::Uuid;
 
+mod availability;
+mod backup;
+mod database;
+mod rate_limit;
+
+use availability::AvailabilityWatchers;
+use backup::{Backup, BackupError};
+use database::{DatabaseConfig, ImmediateTransaction};
+use rate_limit::{RateLimit, RateLimitConfig, RateLimitLayer};
+
 // 生成されたコードを直接インポート
 tonic::include_proto!("library.v1");
 
 use library_service_server::{LibraryService, LibraryServiceServer};
 
-// SQLXのクエリ結果を受け取るための構造体
-#[derive(sqlx::FromRow)]
+// SQLXのクエリ結果を受け取るための構造体（バックアップのJSONにもそのまま使う）
+#[derive(sqlx::FromRow, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 struct UserRow {
     id: String,
     name: String,
     email: String,
 }
 
-#[derive(sqlx::FromRow)]
+#[derive(sqlx::FromRow, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 struct BookRow {
     id: String,
     title: String,
@@ -26,7 +42,7 @@ struct BookRow {
     available: bool,
 }
 
-#[derive(sqlx::FromRow)]
+#[derive(sqlx::FromRow, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 #[allow(dead_code)] // 未使用のフィールドがあるため警告を抑制
 struct LoanRow {
     id: String,
@@ -38,15 +54,139 @@ struct LoanRow {
     status: i32,
 }
 
+impl LoanRow {
+    // 返却されていない期限切れの貸出はOVERDUEとして扱う
+    fn effective_status(&self, now: DateTime<Utc>) -> i32 {
+        if self.status == LoanStatus::Active as i32 && self.return_date.is_none() && self.due_date < now {
+            LoanStatus::Overdue as i32
+        } else {
+            self.status
+        }
+    }
+
+    // 貸出日から返却日（未返却なら現在）までの日数
+    fn days_borrowed(&self, now: DateTime<Utc>) -> i64 {
+        (self.return_date.unwrap_or(now) - self.loan_date).num_days()
+    }
+
+    fn to_loan(&self, now: DateTime<Utc>) -> Loan {
+        Loan {
+            id: self.id.clone(),
+            book_id: self.book_id.clone(),
+            user_id: self.user_id.clone(),
+            loan_date: Some(LibraryServiceImpl::datetime_to_timestamp(self.loan_date)),
+            due_date: Some(LibraryServiceImpl::datetime_to_timestamp(self.due_date)),
+            return_date: self.return_date.map(LibraryServiceImpl::datetime_to_timestamp),
+            status: self.effective_status(now),
+        }
+    }
+}
+
+// 貸出履歴の問い合わせ結果
+struct LoanHistory {
+    entries: Vec<LoanHistoryEntry>,
+    total_count: i32,
+    total_pages: i32,
+    total_days_borrowed: i64,
+}
+
+// 貸出期間のデフォルト（日数）
+pub const DEFAULT_LOAN_PERIOD_DAYS: i64 = 14;
+
+// あいまい検索で類似度のしきい値が指定されなかった場合の値
+pub const DEFAULT_FUZZY_THRESHOLD: f32 = 0.3;
+
+// クライアントごとのSearchBooksの上限のデフォルト（1分あたりのリクエスト数）
+pub const DEFAULT_SEARCH_BOOKS_RATE_LIMIT: u32 = 60;
+
+// 単語ごとに前後を空白で埋めた文字トライグラムの集合（pg_trgmと同じ分割方法）
+fn trigrams(text: &str) -> HashSet<[char; 3]> {
+    text.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .flat_map(|word| {
+            let padded: Vec<char> = "  "
+                .chars()
+                .chain(word.chars())
+                .chain(" ".chars())
+                .collect();
+            padded
+                .windows(3)
+                .map(|w| [w[0], w[1], w[2]])
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+// 2つの文字列のトライグラム類似度（共通トライグラム数 / 全トライグラム数、0〜1）
+pub fn trigram_similarity(a: &str, b: &str) -> f32 {
+    let a = trigrams(a);
+    let b = trigrams(b);
+    let union = a.union(&b).count();
+    if union == 0 {
+        return 0.0;
+    }
+    a.intersection(&b).count() as f32 / union as f32
+}
+
+// 貸出期間が0以下で設定された場合のエラー
+#[derive(Debug)]
+pub struct InvalidLoanPeriod(pub i64);
+
+impl std::fmt::Display for InvalidLoanPeriod {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "loan period must be positive, got {} days", self.0)
+    }
+}
+
+impl std::error::Error for InvalidLoanPeriod {}
+
+// 貸出開始日（または延長前の返却期限）から返却期限を計算する
+pub fn compute_due_date(from: DateTime<Utc>, loan_period: Duration) -> DateTime<Utc> {
+    from + loan_period
+}
+
 pub struct LibraryServiceImpl {
     pool: Pool<Sqlite>,
+    loan_period: Duration,
+    watchers: Arc<AvailabilityWatchers>,
 }
 
 impl LibraryServiceImpl {
     pub async fn new(database_url: &str) -> Result<Self, sqlx::Error> {
-        let pool = SqlitePool::connect(database_url).await?;
+        Self::connect_with(database_url, &DatabaseConfig::default()).await
+    }
+
+    // プラグマとコネクションプールの設定を指定して接続する
+    pub async fn connect_with(
+        database_url: &str,
+        config: &DatabaseConfig,
+    ) -> Result<Self, sqlx::Error> {
+        let pool = config.connect(database_url).await?;
         sqlx::migrate!("./migrations").run(&pool).await?;
-        Ok(Self { pool })
+        Ok(Self {
+            pool,
+            loan_period: Duration::days(DEFAULT_LOAN_PERIOD_DAYS),
+            watchers: Arc::new(AvailabilityWatchers::new()),
+        })
+    }
+
+    // 貸出・延長で使う貸出期間を設定する
+    pub fn with_loan_period_days(mut self, days: i64) -> Result<Self, InvalidLoanPeriod> {
+        if days <= 0 {
+            return Err(InvalidLoanPeriod(days));
+        }
+        self.loan_period = Duration::days(days);
+        Ok(self)
+    }
+
+    pub fn loan_period(&self) -> Duration {
+        self.loan_period
+    }
+
+    // シャットダウン後にクローズするためのコネクションプール
+    pub fn pool(&self) -> Pool<Sqlite> {
+        self.pool.clone()
     }
 
     fn datetime_to_timestamp(dt: DateTime<Utc>) -> Timestamp {
@@ -55,10 +195,137 @@ impl LibraryServiceImpl {
             nanos: dt.timestamp_subsec_nanos() as i32,
         }
     }
+
+    // コミット済みの貸出可否の変化を購読者に通知する
+    fn notify_availability(&self, book_id: &str, available: bool) {
+        self.watchers.publish(BookAvailabilityEvent {
+            book_id: book_id.to_string(),
+            available,
+            changed_at: Some(Self::datetime_to_timestamp(Utc::now())),
+        });
+    }
+
+    // タイトル・著者との類似度でしきい値以上の書籍を順位付けし、ページ分割する
+    async fn fuzzy_search_books(
+        &self,
+        req: SearchBooksRequest,
+    ) -> Result<SearchBooksResponse, Status> {
+        if req.page_size <= 0 || req.page_number <= 0 {
+            return Err(Status::invalid_argument(
+                "page_size and page_number must be positive",
+            ));
+        }
+        let threshold = if req.min_similarity > 0.0 {
+            req.min_similarity
+        } else {
+            DEFAULT_FUZZY_THRESHOLD
+        };
+
+        let rows =
+            sqlx::query_as::<_, BookRow>("SELECT id, title, author, isbn, available FROM books")
+                .fetch_all(&self.pool)
+                .await
+                .map_err(|e| Status::internal(e.to_string()))?;
+
+        let mut matches: Vec<(f32, BookRow)> = rows
+            .into_iter()
+            .map(|row| {
+                let score = trigram_similarity(&req.query, &row.title)
+                    .max(trigram_similarity(&req.query, &row.author));
+                (score, row)
+            })
+            .filter(|(score, _)| *score >= threshold)
+            .collect();
+        // 類似度の高い順、同点はタイトル順
+        matches.sort_by(|a, b| b.0.total_cmp(&a.0).then_with(|| a.1.title.cmp(&b.1.title)));
+
+        let total_count = matches.len() as i32;
+        let total_pages = (total_count as f64 / req.page_size as f64).ceil() as i32;
+        let offset = ((req.page_number - 1) * req.page_size) as usize;
+
+        let (scores, books) = matches
+            .into_iter()
+            .skip(offset)
+            .take(req.page_size as usize)
+            .map(|(score, b)| {
+                (
+                    score,
+                    Book {
+                        id: b.id,
+                        title: b.title,
+                        author: b.author,
+                        isbn: b.isbn,
+                        available: b.available,
+                    },
+                )
+            })
+            .unzip();
+
+        Ok(SearchBooksResponse {
+            books,
+            total_count,
+            total_pages,
+            scores,
+        })
+    }
+
+    // 書籍またはユーザーに紐づく全貸出を貸出日順に取得し、ページ分割する
+    async fn loan_history(
+        &self,
+        column: &str,
+        id: &str,
+        page_size: i32,
+        page_number: i32,
+    ) -> Result<LoanHistory, Status> {
+        if page_size <= 0 || page_number <= 0 {
+            return Err(Status::invalid_argument(
+                "page_size and page_number must be positive",
+            ));
+        }
+
+        let rows = sqlx::query_as::<_, LoanRow>(&format!(
+            r#"
+            SELECT id, book_id, user_id, loan_date, due_date, return_date, status
+            FROM loans
+            WHERE {} = ?
+            ORDER BY loan_date ASC, id ASC
+            "#,
+            column
+        ))
+        .bind(id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| Status::internal(e.to_string()))?;
+
+        let now = Utc::now();
+        let total_count = rows.len() as i32;
+        let total_pages = (total_count as f64 / page_size as f64).ceil() as i32;
+        let total_days_borrowed = rows.iter().map(|row| row.days_borrowed(now)).sum();
+
+        let offset = ((page_number - 1) * page_size) as usize;
+        let entries = rows
+            .iter()
+            .skip(offset)
+            .take(page_size as usize)
+            .map(|row| LoanHistoryEntry {
+                loan: Some(row.to_loan(now)),
+                days_borrowed: row.days_borrowed(now),
+            })
+            .collect();
+
+        Ok(LoanHistory {
+            entries,
+            total_count,
+            total_pages,
+            total_days_borrowed,
+        })
+    }
 }
 
 #[tonic::async_trait]
 impl LibraryService for LibraryServiceImpl {
+    type WatchBookAvailabilityStream = ReceiverStream<Result<BookAvailabilityEvent, Status>>;
+
     async fn create_user(
         &self,
         request: Request<CreateUserRequest>,
@@ -110,6 +377,10 @@ impl LibraryService for LibraryServiceImpl {
         request: Request<SearchBooksRequest>,
     ) -> Result<Response<SearchBooksResponse>, Status> {
         let req = request.into_inner();
+        if req.fuzzy {
+            return self.fuzzy_search_books(req).await.map(Response::new);
+        }
+
         let offset = (req.page_number - 1) * req.page_size;
         let query = format!("%{}%", req.query);
 
@@ -161,17 +432,56 @@ impl LibraryService for LibraryServiceImpl {
             books,
             total_count,
             total_pages,
+            scores: Vec::new(),
         }))
     }
 
+    async fn watch_book_availability(
+        &self,
+        request: Request<WatchBookAvailabilityRequest>,
+    ) -> Result<Response<Self::WatchBookAvailabilityStream>, Status> {
+        let req = request.into_inner();
+
+        sqlx::query_scalar::<_, String>("SELECT id FROM books WHERE id = ?")
+            .bind(&req.book_id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?
+            .ok_or_else(|| Status::not_found("Book not found"))?;
+
+        let watchers = self.watchers.clone();
+        let mut events = watchers.subscribe(&req.book_id);
+        let (tx, rx) = mpsc::channel(4);
+
+        // クライアントが切断するとレスポンスのストリームが破棄され、txがクローズされる
+        tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    _ = tx.closed() => break,
+                    event = events.recv() => match event {
+                        Ok(event) => {
+                            if tx.send(Ok(event)).await.is_err() {
+                                break;
+                            }
+                        }
+                        // 取りこぼしても、最新の状態はその後のイベントで届く
+                        Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                        Err(broadcast::error::RecvError::Closed) => break,
+                    },
+                }
+            }
+            watchers.unsubscribe(&req.book_id, events);
+        });
+
+        Ok(Response::new(ReceiverStream::new(rx)))
+    }
+
     async fn create_loan(
         &self,
         request: Request<CreateLoanRequest>,
     ) -> Result<Response<CreateLoanResponse>, Status> {
         let req = request.into_inner();
-        let mut tx = self
-            .pool
-            .begin()
+        let mut tx = ImmediateTransaction::begin(&self.pool)
             .await
             .map_err(|e| Status::internal(e.to_string()))?;
 
@@ -193,7 +503,7 @@ impl LibraryService for LibraryServiceImpl {
             .map_err(|e| Status::internal(e.to_string()))?;
 
         let now = Utc::now();
-        let due_date = now + Duration::days(14);
+        let due_date = compute_due_date(now, self.loan_period);
         let loan_id = Uuid::new_v4().to_string();
 
         let loan = Loan {
@@ -225,6 +535,7 @@ impl LibraryService for LibraryServiceImpl {
         tx.commit()
             .await
             .map_err(|e| Status::internal(e.to_string()))?;
+        self.notify_availability(&loan.book_id, false);
 
         Ok(Response::new(CreateLoanResponse { loan: Some(loan) }))
     }
@@ -234,9 +545,7 @@ impl LibraryService for LibraryServiceImpl {
         request: Request<ReturnBookRequest>,
     ) -> Result<Response<ReturnBookResponse>, Status> {
         let req = request.into_inner();
-        let mut tx = self
-            .pool
-            .begin()
+        let mut tx = ImmediateTransaction::begin(&self.pool)
             .await
             .map_err(|e| Status::internal(e.to_string()))?;
 
@@ -289,12 +598,133 @@ impl LibraryService for LibraryServiceImpl {
         tx.commit()
             .await
             .map_err(|e| Status::internal(e.to_string()))?;
+        self.notify_availability(&updated_loan.book_id, true);
 
         Ok(Response::new(ReturnBookResponse {
             success: true,
             loan: Some(updated_loan),
         }))
     }
+
+    async fn renew_loan(
+        &self,
+        request: Request<RenewLoanRequest>,
+    ) -> Result<Response<RenewLoanResponse>, Status> {
+        let req = request.into_inner();
+        let mut tx = ImmediateTransaction::begin(&self.pool)
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?;
+
+        let loan = sqlx::query_as::<_, LoanRow>(
+            r#"
+            SELECT id, book_id, user_id, loan_date, due_date, return_date, status
+            FROM loans
+            WHERE id = ? AND status = ?
+            "#,
+        )
+        .bind(&req.loan_id)
+        .bind(LoanStatus::Active as i32)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|e| Status::internal(e.to_string()))?
+        .ok_or_else(|| Status::not_found("Active loan not found"))?;
+
+        // 現在の返却期限から貸出期間分だけ延長する
+        let due_date = compute_due_date(loan.due_date, self.loan_period);
+
+        sqlx::query("UPDATE loans SET due_date = ? WHERE id = ?")
+            .bind(due_date)
+            .bind(&loan.id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?;
+
+        tx.commit()
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?;
+
+        let renewed = LoanRow { due_date, ..loan };
+        Ok(Response::new(RenewLoanResponse {
+            loan: Some(renewed.to_loan(Utc::now())),
+        }))
+    }
+
+    async fn get_book_history(
+        &self,
+        request: Request<GetBookHistoryRequest>,
+    ) -> Result<Response<GetBookHistoryResponse>, Status> {
+        let req = request.into_inner();
+        let history = self
+            .loan_history("book_id", &req.book_id, req.page_size, req.page_number)
+            .await?;
+
+        Ok(Response::new(GetBookHistoryResponse {
+            entries: history.entries,
+            total_count: history.total_count,
+            total_pages: history.total_pages,
+            total_days_borrowed: history.total_days_borrowed,
+        }))
+    }
+
+    async fn get_user_history(
+        &self,
+        request: Request<GetUserHistoryRequest>,
+    ) -> Result<Response<GetUserHistoryResponse>, Status> {
+        let req = request.into_inner();
+        let history = self
+            .loan_history("user_id", &req.user_id, req.page_size, req.page_number)
+            .await?;
+
+        Ok(Response::new(GetUserHistoryResponse {
+            entries: history.entries,
+            total_count: history.total_count,
+            total_pages: history.total_pages,
+            total_days_borrowed: history.total_days_borrowed,
+        }))
+    }
+
+    async fn export_backup(
+        &self,
+        _request: Request<ExportBackupRequest>,
+    ) -> Result<Response<ExportBackupResponse>, Status> {
+        let backup = backup::export(&self.pool)
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?;
+
+        Ok(Response::new(ExportBackupResponse {
+            backup: backup.to_json(),
+            user_count: backup.users.len() as i32,
+            book_count: backup.books.len() as i32,
+            loan_count: backup.loans.len() as i32,
+        }))
+    }
+
+    async fn import_backup(
+        &self,
+        request: Request<ImportBackupRequest>,
+    ) -> Result<Response<ImportBackupResponse>, Status> {
+        let req = request.into_inner();
+        let backup = Backup::from_json(&req.backup).map_err(backup_status)?;
+        backup::import(&self.pool, &backup, req.force)
+            .await
+            .map_err(backup_status)?;
+
+        Ok(Response::new(ImportBackupResponse {
+            user_count: backup.users.len() as i32,
+            book_count: backup.books.len() as i32,
+            loan_count: backup.loans.len() as i32,
+        }))
+    }
+}
+
+fn backup_status(e: BackupError) -> Status {
+    match e {
+        BackupError::NotEmpty => Status::failed_precondition(e.to_string()),
+        BackupError::UnsupportedVersion(_) | BackupError::Invalid(_) => {
+            Status::invalid_argument(e.to_string())
+        }
+        BackupError::Database(_) => Status::internal(e.to_string()),
+    }
 }
 
 #[tokio::main]
@@ -302,15 +732,789 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let database_url =
         std::env::var("DATABASE_URL").unwrap_or_else(|_| "sqlite:library.db".to_string());
 
-    let service = LibraryServiceImpl::new(&database_url).await?;
-    let addr = "[::1]:50051".parse()?;
+    let loan_period_days = match std::env::var("LOAN_PERIOD_DAYS") {
+        Ok(days) => days.parse()?,
+        Err(_) => DEFAULT_LOAN_PERIOD_DAYS,
+    };
+
+    let search_books_rate_limit = match std::env::var("SEARCH_BOOKS_RATE_LIMIT") {
+        Ok(requests) => requests.parse()?,
+        Err(_) => DEFAULT_SEARCH_BOOKS_RATE_LIMIT,
+    };
+    let rate_limits = RateLimitConfig::new().with_method_limit(
+        "SearchBooks",
+        RateLimit::per_minute(search_books_rate_limit),
+    );
+
+    let database_config = DatabaseConfig::from_env()?;
+
+    let service = LibraryServiceImpl::connect_with(&database_url, &database_config)
+        .await?
+        .with_loan_period_days(loan_period_days)?;
+    let addr: std::net::SocketAddr = "[::1]:50051".parse()?;
+    let listener = TcpListener::bind(addr).await?;
 
     println!("LibraryService listening on {}", addr);
 
-    Server::builder()
-        .add_service(LibraryServiceServer::new(service))
-        .serve(addr)
-        .await?;
+    serve(service, rate_limits, listener, shutdown_signal()).await?;
 
+    println!("LibraryService stopped");
     Ok(())
 }
+
+// SIGINT（Ctrl+C）またはSIGTERMを受け取ると完了する
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("failed to install Ctrl+C handler");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("failed to install SIGTERM handler")
+            .recv()
+            .await;
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+    println!("Shutdown signal received, draining in-flight requests");
+}
+
+// `shutdown` が完了するまでリクエストを受け付ける
+//
+// シャットダウン開始後は新しい接続を受け付けず、貸出可否の購読ストリームを閉じ、
+// 処理中のRPCが完了するのを待ってから
+// SQLiteのプールをクローズする（書き込み途中のトランザクションを壊さないため）
+pub async fn serve<F>(
+    service: LibraryServiceImpl,
+    rate_limits: RateLimitConfig,
+    listener: TcpListener,
+    shutdown: F,
+) -> Result<(), tonic::transport::Error>
+where
+    F: Future<Output = ()>,
+{
+    let pool = service.pool();
+    let watchers = service.watchers.clone();
+    // 購読中のストリームは自分からは終わらないので、シャットダウン開始時に閉じる
+    let shutdown = async move {
+        shutdown.await;
+        watchers.close();
+    };
+
+    let result = Server::builder()
+        .layer(RateLimitLayer::new(rate_limits))
+        .add_service(LibraryServiceServer::new(service))
+        .serve_with_incoming_shutdown(TcpListenerStream::new(listener), shutdown)
+        .await;
+
+    pool.close().await;
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    async fn setup() -> LibraryServiceImpl {
+        let service = LibraryServiceImpl::new("sqlite::memory:").await.unwrap();
+
+        for (id, email) in [("u1", "u1@example.com"), ("u2", "u2@example.com")] {
+            sqlx::query("INSERT INTO users (id, name, email) VALUES (?, ?, ?)")
+                .bind(id)
+                .bind(id)
+                .bind(email)
+                .execute(&service.pool)
+                .await
+                .unwrap();
+        }
+
+        service
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn seed_loan(
+        service: &LibraryServiceImpl,
+        id: &str,
+        book_id: &str,
+        user_id: &str,
+        loan_date: DateTime<Utc>,
+        due_date: DateTime<Utc>,
+        return_date: Option<DateTime<Utc>>,
+        status: LoanStatus,
+    ) {
+        sqlx::query(
+            r#"
+            INSERT INTO loans (id, book_id, user_id, loan_date, due_date, return_date, status)
+            VALUES (?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(id)
+        .bind(book_id)
+        .bind(user_id)
+        .bind(loan_date)
+        .bind(due_date)
+        .bind(return_date)
+        .bind(status as i32)
+        .execute(&service.pool)
+        .await
+        .unwrap();
+    }
+
+    // 返却済み2件・期限切れ1件・貸出中1件を登録する
+    async fn seed_history(service: &LibraryServiceImpl) {
+        let now = Utc::now();
+        let jan = Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap();
+        let feb = Utc.with_ymd_and_hms(2025, 2, 1, 0, 0, 0).unwrap();
+
+        // 挿入順と貸出日順をずらして並び替えを確認する
+        seed_loan(service, "l3", "1", "u1", now - Duration::days(20), now - Duration::days(6), None, LoanStatus::Active).await;
+        seed_loan(service, "l2", "1", "u2", feb, feb + Duration::days(14), Some(feb + Duration::days(3)), LoanStatus::Returned).await;
+        seed_loan(service, "l1", "1", "u1", jan, jan + Duration::days(14), Some(jan + Duration::days(10)), LoanStatus::Returned).await;
+        seed_loan(service, "l4", "2", "u1", now - Duration::days(2), now + Duration::days(12), None, LoanStatus::Active).await;
+    }
+
+    fn ids_and_statuses(entries: &[LoanHistoryEntry]) -> Vec<(String, i32)> {
+        entries
+            .iter()
+            .map(|e| {
+                let loan = e.loan.as_ref().unwrap();
+                (loan.id.clone(), loan.status)
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn book_history_is_ordered_by_loan_date_with_all_statuses() {
+        let service = setup().await;
+        seed_history(&service).await;
+
+        let response = service
+            .get_book_history(Request::new(GetBookHistoryRequest {
+                book_id: "1".to_string(),
+                page_size: 10,
+                page_number: 1,
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+
+        assert_eq!(
+            ids_and_statuses(&response.entries),
+            vec![
+                ("l1".to_string(), LoanStatus::Returned as i32),
+                ("l2".to_string(), LoanStatus::Returned as i32),
+                ("l3".to_string(), LoanStatus::Overdue as i32),
+            ]
+        );
+        let days: Vec<i64> = response.entries.iter().map(|e| e.days_borrowed).collect();
+        assert_eq!(days, vec![10, 3, 20]);
+        assert_eq!(response.total_count, 3);
+        assert_eq!(response.total_pages, 1);
+        assert_eq!(response.total_days_borrowed, 33);
+    }
+
+    #[tokio::test]
+    async fn user_history_is_paginated() {
+        let service = setup().await;
+        seed_history(&service).await;
+
+        let first = service
+            .get_user_history(Request::new(GetUserHistoryRequest {
+                user_id: "u1".to_string(),
+                page_size: 2,
+                page_number: 1,
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+
+        assert_eq!(
+            ids_and_statuses(&first.entries),
+            vec![
+                ("l1".to_string(), LoanStatus::Returned as i32),
+                ("l3".to_string(), LoanStatus::Overdue as i32),
+            ]
+        );
+        assert_eq!(first.total_count, 3);
+        assert_eq!(first.total_pages, 2);
+        assert_eq!(first.total_days_borrowed, 32);
+
+        let second = service
+            .get_user_history(Request::new(GetUserHistoryRequest {
+                user_id: "u1".to_string(),
+                page_size: 2,
+                page_number: 2,
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+
+        assert_eq!(
+            ids_and_statuses(&second.entries),
+            vec![("l4".to_string(), LoanStatus::Active as i32)]
+        );
+    }
+
+    #[tokio::test]
+    async fn history_rejects_invalid_pagination() {
+        let service = setup().await;
+
+        let status = service
+            .get_user_history(Request::new(GetUserHistoryRequest {
+                user_id: "u1".to_string(),
+                page_size: 0,
+                page_number: 1,
+            }))
+            .await
+            .unwrap_err();
+
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+    }
+
+    #[test]
+    fn due_date_is_start_plus_loan_period() {
+        let start = Utc.with_ymd_and_hms(2025, 3, 1, 12, 0, 0).unwrap();
+        assert_eq!(
+            compute_due_date(start, Duration::days(7)),
+            Utc.with_ymd_and_hms(2025, 3, 8, 12, 0, 0).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn shutdown_waits_for_in_flight_request() {
+        use library_service_client::LibraryServiceClient;
+        use sqlx::Connection;
+
+        // 別接続で書き込みロックを握り、CreateUser を処理中のまま止めておく
+        let db_path = std::env::temp_dir().join(format!("library-{}.db", Uuid::new_v4()));
+        let database_url = format!("sqlite:{}?mode=rwc", db_path.display());
+        let service = LibraryServiceImpl::new(&database_url).await.unwrap();
+        let pool = service.pool();
+
+        let mut blocker = sqlx::SqliteConnection::connect(&database_url).await.unwrap();
+        sqlx::query("BEGIN IMMEDIATE").execute(&mut blocker).await.unwrap();
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (shutdown_tx, shutdown_rx) = tokio::sync::oneshot::channel::<()>();
+        let server = tokio::spawn(serve(service, RateLimitConfig::new(), listener, async {
+            let _ = shutdown_rx.await;
+        }));
+
+        let mut client = LibraryServiceClient::connect(format!("http://{}", addr))
+            .await
+            .unwrap();
+        let request = tokio::spawn(async move {
+            client
+                .create_user(CreateUserRequest {
+                    name: "Alice".to_string(),
+                    email: "alice@example.com".to_string(),
+                })
+                .await
+        });
+
+        // リクエストがロック待ちになってからシャットダウンを開始する
+        tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+        shutdown_tx.send(()).unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+        assert!(!server.is_finished(), "server stopped before the request completed");
+        assert!(!request.is_finished());
+
+        // ロックを解放すると処理中のリクエストが完了し、その後サーバーが止まる
+        sqlx::query("COMMIT").execute(&mut blocker).await.unwrap();
+        let response = request.await.unwrap().unwrap().into_inner();
+        assert_eq!(response.user.unwrap().name, "Alice");
+
+        server.await.unwrap().unwrap();
+        assert!(pool.is_closed());
+
+        drop(blocker);
+        let _ = std::fs::remove_file(&db_path);
+    }
+
+    #[tokio::test]
+    async fn shutdown_ends_open_watch_streams() {
+        use library_service_client::LibraryServiceClient;
+        use tokio_stream::StreamExt;
+
+        let service = setup().await;
+        let pool = service.pool();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (shutdown_tx, shutdown_rx) = tokio::sync::oneshot::channel::<()>();
+        let server = tokio::spawn(serve(service, RateLimitConfig::new(), listener, async {
+            let _ = shutdown_rx.await;
+        }));
+
+        let mut client = LibraryServiceClient::connect(format!("http://{}", addr))
+            .await
+            .unwrap();
+        let mut events = client
+            .watch_book_availability(WatchBookAvailabilityRequest {
+                book_id: "3".to_string(),
+            })
+            .await
+            .unwrap()
+            .into_inner();
+
+        shutdown_tx.send(()).unwrap();
+        let end = tokio::time::timeout(std::time::Duration::from_secs(5), events.next())
+            .await
+            .expect("watch stream was not closed on shutdown");
+        assert!(end.is_none(), "unexpected item: {:?}", end);
+        drop(events);
+        drop(client);
+
+        tokio::time::timeout(std::time::Duration::from_secs(5), server)
+            .await
+            .expect("server did not stop with an open watch stream")
+            .unwrap()
+            .unwrap();
+        assert!(pool.is_closed());
+    }
+
+    // 別接続で書き込みロックを握ったまま、同時に複数の書き込みを投げる
+    async fn contended_writes(
+        config: DatabaseConfig,
+    ) -> Vec<Result<Response<CreateUserResponse>, Status>> {
+        use sqlx::Connection;
+
+        let db_path = std::env::temp_dir().join(format!("library-{}.db", Uuid::new_v4()));
+        let database_url = format!("sqlite:{}?mode=rwc", db_path.display());
+        let service = Arc::new(
+            LibraryServiceImpl::connect_with(&database_url, &config)
+                .await
+                .unwrap(),
+        );
+
+        let mut blocker = sqlx::SqliteConnection::connect(&database_url).await.unwrap();
+        sqlx::query("BEGIN IMMEDIATE").execute(&mut blocker).await.unwrap();
+
+        let writes: Vec<_> = (0..8)
+            .map(|i| {
+                let service = service.clone();
+                tokio::spawn(async move {
+                    service
+                        .create_user(Request::new(CreateUserRequest {
+                            name: format!("user{}", i),
+                            email: format!("user{}@example.com", i),
+                        }))
+                        .await
+                })
+            })
+            .collect();
+
+        tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+        sqlx::query("COMMIT").execute(&mut blocker).await.unwrap();
+
+        let mut results = Vec::new();
+        for write in writes {
+            results.push(write.await.unwrap());
+        }
+
+        drop(blocker);
+        service.pool().close().await;
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{}", db_path.display(), suffix));
+        }
+        results
+    }
+
+    #[tokio::test]
+    async fn default_pragmas_are_applied() {
+        let db_path = std::env::temp_dir().join(format!("library-{}.db", Uuid::new_v4()));
+        let database_url = format!("sqlite:{}?mode=rwc", db_path.display());
+        let service = LibraryServiceImpl::new(&database_url).await.unwrap();
+
+        let journal_mode: String = sqlx::query_scalar("PRAGMA journal_mode")
+            .fetch_one(&service.pool)
+            .await
+            .unwrap();
+        let busy_timeout: i64 = sqlx::query_scalar("PRAGMA busy_timeout")
+            .fetch_one(&service.pool)
+            .await
+            .unwrap();
+        // 0: OFF, 1: NORMAL, 2: FULL, 3: EXTRA
+        let synchronous: i64 = sqlx::query_scalar("PRAGMA synchronous")
+            .fetch_one(&service.pool)
+            .await
+            .unwrap();
+        assert_eq!(journal_mode, "wal");
+        assert_eq!(busy_timeout, 5000);
+        assert_eq!(synchronous, 1);
+
+        service.pool().close().await;
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{}", db_path.display(), suffix));
+        }
+    }
+
+    #[tokio::test]
+    async fn concurrent_writes_wait_for_lock_with_busy_timeout() {
+        let results = contended_writes(DatabaseConfig::default()).await;
+        assert_eq!(results.len(), 8);
+        for result in results {
+            let user = result.unwrap().into_inner().user.unwrap();
+            assert!(user.email.ends_with("@example.com"));
+        }
+    }
+
+    #[tokio::test]
+    async fn concurrent_writes_fail_without_busy_timeout() {
+        // busy_timeoutがなければロック中の書き込みは待たずに失敗する
+        let config = DatabaseConfig {
+            busy_timeout: std::time::Duration::ZERO,
+            ..DatabaseConfig::default()
+        };
+        let results = contended_writes(config).await;
+        let locked = results
+            .iter()
+            .filter(|result| {
+                matches!(result, Err(status) if status.message().contains("database is locked"))
+            })
+            .count();
+        assert_eq!(locked, results.len());
+    }
+
+    #[tokio::test]
+    async fn concurrent_loans_of_different_books_all_succeed() {
+        // 読み込んでから書き込む貸出が重なっても、すぐに "database is locked" にならずに待つ
+        let db_path = std::env::temp_dir().join(format!("library-{}.db", Uuid::new_v4()));
+        let database_url = format!("sqlite:{}?mode=rwc", db_path.display());
+        let service = Arc::new(LibraryServiceImpl::new(&database_url).await.unwrap());
+
+        sqlx::query("INSERT INTO users (id, name, email) VALUES ('u1', 'u1', 'u1@example.com')")
+            .execute(&service.pool)
+            .await
+            .unwrap();
+        let book_ids: Vec<String> = (0..16).map(|i| format!("c{}", i)).collect();
+        for id in &book_ids {
+            sqlx::query("INSERT INTO books (id, title, author, isbn) VALUES (?, ?, 'author', ?)")
+                .bind(id)
+                .bind(id)
+                .bind(format!("isbn-{}", id))
+                .execute(&service.pool)
+                .await
+                .unwrap();
+        }
+
+        let loans: Vec<_> = book_ids
+            .iter()
+            .map(|book_id| {
+                let service = service.clone();
+                let book_id = book_id.clone();
+                tokio::spawn(async move {
+                    service
+                        .create_loan(Request::new(CreateLoanRequest {
+                            book_id,
+                            user_id: "u1".to_string(),
+                        }))
+                        .await
+                })
+            })
+            .collect();
+        for loan in loans {
+            loan.await.unwrap().unwrap();
+        }
+
+        let on_loan: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM books WHERE id LIKE 'c%' AND available = false",
+        )
+        .fetch_one(&service.pool)
+        .await
+        .unwrap();
+        assert_eq!(on_loan, book_ids.len() as i64);
+
+        service.pool().close().await;
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{}", db_path.display(), suffix));
+        }
+    }
+
+    async fn export_json(service: &LibraryServiceImpl) -> Vec<u8> {
+        service
+            .export_backup(Request::new(ExportBackupRequest {}))
+            .await
+            .unwrap()
+            .into_inner()
+            .backup
+    }
+
+    async fn import_json(
+        service: &LibraryServiceImpl,
+        backup: Vec<u8>,
+        force: bool,
+    ) -> Result<ImportBackupResponse, Status> {
+        service
+            .import_backup(Request::new(ImportBackupRequest { backup, force }))
+            .await
+            .map(Response::into_inner)
+    }
+
+    #[tokio::test]
+    async fn backup_round_trips_into_fresh_database() {
+        let source = setup().await;
+        seed_history(&source).await;
+        sqlx::query("UPDATE books SET available = false WHERE id = '1'")
+            .execute(&source.pool)
+            .await
+            .unwrap();
+
+        let exported = export_json(&source).await;
+        let fresh = LibraryServiceImpl::new("sqlite::memory:").await.unwrap();
+        let imported = import_json(&fresh, exported.clone(), false).await.unwrap();
+        assert_eq!(
+            (imported.user_count, imported.book_count, imported.loan_count),
+            (2, 3, 4)
+        );
+
+        let original = Backup::from_json(&exported).unwrap();
+        let restored = Backup::from_json(&export_json(&fresh).await).unwrap();
+        assert_eq!(restored.users, original.users);
+        assert_eq!(restored.books, original.books);
+        assert_eq!(restored.loans, original.loans);
+        assert!(!restored.books.iter().find(|b| b.id == "1").unwrap().available);
+    }
+
+    #[tokio::test]
+    async fn import_refuses_to_overwrite_data_unless_forced() {
+        let source = setup().await;
+        seed_history(&source).await;
+        let exported = export_json(&source).await;
+
+        // ユーザーがいるデータベースは上書きしない
+        let target = setup().await;
+        let err = import_json(&target, exported.clone(), false).await.unwrap_err();
+        assert_eq!(err.code(), tonic::Code::FailedPrecondition);
+        assert_eq!(Backup::from_json(&export_json(&target).await).unwrap().loans.len(), 0);
+
+        import_json(&target, exported, true).await.unwrap();
+        assert_eq!(Backup::from_json(&export_json(&target).await).unwrap().loans.len(), 4);
+
+        let err = import_json(&target, b"not json".to_vec(), true).await.unwrap_err();
+        assert_eq!(err.code(), tonic::Code::InvalidArgument);
+    }
+
+    #[tokio::test]
+    async fn loan_period_must_be_positive() {
+        let err = setup().await.with_loan_period_days(0).err().unwrap();
+        assert_eq!(err.to_string(), "loan period must be positive, got 0 days");
+        assert!(setup().await.with_loan_period_days(-3).is_err());
+    }
+
+    fn timestamp_to_datetime(ts: &Timestamp) -> DateTime<Utc> {
+        Utc.timestamp_opt(ts.seconds, ts.nanos as u32).unwrap()
+    }
+
+    #[tokio::test]
+    async fn configured_loan_period_is_used_for_loans_and_renewals() {
+        let service = setup().await.with_loan_period_days(7).unwrap();
+
+        let loan = service
+            .create_loan(Request::new(CreateLoanRequest {
+                book_id: "3".to_string(),
+                user_id: "u1".to_string(),
+            }))
+            .await
+            .unwrap()
+            .into_inner()
+            .loan
+            .unwrap();
+
+        let loan_date = timestamp_to_datetime(loan.loan_date.as_ref().unwrap());
+        let due_date = timestamp_to_datetime(loan.due_date.as_ref().unwrap());
+        assert_eq!(due_date - loan_date, Duration::days(7));
+
+        let renewed = service
+            .renew_loan(Request::new(RenewLoanRequest {
+                loan_id: loan.id.clone(),
+            }))
+            .await
+            .unwrap()
+            .into_inner()
+            .loan
+            .unwrap();
+
+        let renewed_due = timestamp_to_datetime(renewed.due_date.as_ref().unwrap());
+        assert_eq!(renewed_due - due_date, Duration::days(7));
+        assert_eq!(renewed.status, LoanStatus::Active as i32);
+    }
+
+    async fn fuzzy_search(service: &LibraryServiceImpl, query: &str) -> SearchBooksResponse {
+        service
+            .search_books(Request::new(SearchBooksRequest {
+                query: query.to_string(),
+                page_size: 10,
+                page_number: 1,
+                fuzzy: true,
+                min_similarity: 0.0,
+            }))
+            .await
+            .unwrap()
+            .into_inner()
+    }
+
+    #[tokio::test]
+    async fn fuzzy_search_finds_misspelled_title() {
+        let service = setup().await;
+        sqlx::query(
+            "INSERT INTO books (id, title, author, isbn, available) VALUES (?, ?, ?, ?, ?)",
+        )
+        .bind("4")
+        .bind("Rust Programming")
+        .bind("Steve Klabnik")
+        .bind("978-4-wwww-wwww-w")
+        .bind(true)
+        .execute(&service.pool)
+        .await
+        .unwrap();
+
+        let response = fuzzy_search(&service, "Rost programming").await;
+        assert_eq!(response.books[0].id, "4");
+        assert_eq!(response.scores.len(), response.books.len());
+        assert!(response.scores[0] >= DEFAULT_FUZZY_THRESHOLD);
+        assert!(response.scores.windows(2).all(|w| w[0] >= w[1]));
+
+        let unrelated = fuzzy_search(&service, "Cooking for beginners").await;
+        assert!(unrelated.books.is_empty());
+        assert_eq!(unrelated.total_count, 0);
+    }
+
+    #[test]
+    fn trigram_similarity_is_case_insensitive_and_bounded() {
+        assert_eq!(trigram_similarity("Rust", "rust"), 1.0);
+        assert_eq!(trigram_similarity("", "rust"), 0.0);
+        let score = trigram_similarity("Rost programming", "Rust Programming");
+        assert!(score > 0.5 && score < 1.0, "score: {}", score);
+    }
+
+    #[tokio::test]
+    async fn returning_a_book_notifies_watchers() {
+        use tokio_stream::StreamExt;
+
+        let service = setup().await;
+        let mut events = service
+            .watch_book_availability(Request::new(WatchBookAvailabilityRequest {
+                book_id: "3".to_string(),
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+
+        let loan = service
+            .create_loan(Request::new(CreateLoanRequest {
+                book_id: "3".to_string(),
+                user_id: "u1".to_string(),
+            }))
+            .await
+            .unwrap()
+            .into_inner()
+            .loan
+            .unwrap();
+        let borrowed = events.next().await.unwrap().unwrap();
+        assert_eq!(borrowed.book_id, "3");
+        assert!(!borrowed.available);
+
+        service
+            .return_book(Request::new(ReturnBookRequest { loan_id: loan.id }))
+            .await
+            .unwrap();
+        let returned = tokio::time::timeout(std::time::Duration::from_secs(1), events.next())
+            .await
+            .expect("no availability event after return")
+            .unwrap()
+            .unwrap();
+        assert_eq!(returned.book_id, "3");
+        assert!(returned.available);
+        assert!(returned.changed_at.is_some());
+
+        // ストリームを破棄すると購読が片付けられる
+        drop(events);
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        assert_eq!(service.watchers.watched_books(), 0);
+        assert_eq!(Arc::strong_count(&service.watchers), 1);
+    }
+
+    #[tokio::test]
+    async fn watching_unknown_book_is_not_found() {
+        let status = setup()
+            .await
+            .watch_book_availability(Request::new(WatchBookAvailabilityRequest {
+                book_id: "missing".to_string(),
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::NotFound);
+    }
+
+    // 接続元アドレスを指定してサーバーに接続する（127.0.0.0/8はすべてループバック）
+    async fn client_from(
+        source: &str,
+        addr: std::net::SocketAddr,
+    ) -> library_service_client::LibraryServiceClient<tonic::transport::Channel> {
+        let source = std::net::SocketAddr::new(source.parse().unwrap(), 0);
+        let channel = tonic::transport::Endpoint::from_shared(format!("http://{}", addr))
+            .unwrap()
+            .connect_with_connector(tower::service_fn(
+                move |_: tonic::transport::Uri| async move {
+                    let socket = tokio::net::TcpSocket::new_v4()?;
+                    socket.bind(source)?;
+                    socket.connect(addr).await
+                },
+            ))
+            .await
+            .unwrap();
+        library_service_client::LibraryServiceClient::new(channel)
+    }
+
+    #[tokio::test]
+    async fn search_rate_limit_rejects_only_the_abusive_client() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let rate_limits =
+            RateLimitConfig::new().with_method_limit("SearchBooks", RateLimit::per_minute(3));
+        let (shutdown_tx, shutdown_rx) = tokio::sync::oneshot::channel::<()>();
+        let server = tokio::spawn(serve(setup().await, rate_limits, listener, async {
+            let _ = shutdown_rx.await;
+        }));
+
+        let search = || SearchBooksRequest {
+            query: "Rust".to_string(),
+            page_size: 10,
+            page_number: 1,
+            fuzzy: false,
+            min_similarity: 0.0,
+        };
+
+        let mut abusive = client_from("127.0.0.1", addr).await;
+        for _ in 0..3 {
+            abusive.search_books(search()).await.unwrap();
+        }
+        let status = abusive.search_books(search()).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::ResourceExhausted);
+
+        // 制限のないメソッドは引き続き使える
+        abusive
+            .create_user(CreateUserRequest {
+                name: "Mallory".to_string(),
+                email: "mallory@example.com".to_string(),
+            })
+            .await
+            .unwrap();
+
+        let mut compliant = client_from("127.0.0.2", addr).await;
+        compliant.search_books(search()).await.unwrap();
+
+        shutdown_tx.send(()).unwrap();
+        server.await.unwrap().unwrap();
+    }
+}
@@ -0,0 +1,262 @@
+// クライアントごとのレート制限を行うtower Layer
+//
+// クライアントは接続元のIPアドレスで識別する（ポートは接続し直すたびに変わるので使わない）。
+// 上限はメソッドごとに設定し、設定のないメソッドは制限しない。
+// 上限を超えたリクエストはハンドラに渡さず `RESOURCE_EXHAUSTED` を返す。
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use tonic::body::BoxBody;
+use tonic::codegen::http::{Request, Response};
+use tonic::codegen::BoxFuture;
+use tonic::transport::server::TcpConnectInfo;
+use tonic::Status;
+use tower::{Layer, Service};
+
+// 使われなくなったクライアントの状態を掃除する間隔
+const SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+// `per` あたり `requests` 回までリクエストを受け付ける（同数までのバーストを許す）
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimit {
+    pub requests: u32,
+    pub per: Duration,
+}
+
+impl RateLimit {
+    pub fn per_minute(requests: u32) -> Self {
+        Self {
+            requests,
+            per: Duration::from_secs(60),
+        }
+    }
+}
+
+// メソッド名（例: "SearchBooks"）ごとのレート制限の設定
+#[derive(Debug, Clone, Default)]
+pub struct RateLimitConfig {
+    methods: HashMap<String, RateLimit>,
+}
+
+impl RateLimitConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_method_limit(mut self, method: &str, limit: RateLimit) -> Self {
+        self.methods.insert(method.to_string(), limit);
+        self
+    }
+
+    fn limit_for(&self, method: &str) -> Option<RateLimit> {
+        self.methods.get(method).copied()
+    }
+}
+
+// トークンバケット。`updated` 時点で `tokens` 回分のリクエストが残っている
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+struct RateLimiter {
+    config: RateLimitConfig,
+    buckets: HashMap<(IpAddr, String), Bucket>,
+    last_sweep: Instant,
+}
+
+impl RateLimiter {
+    fn new(config: RateLimitConfig) -> Self {
+        Self {
+            config,
+            buckets: HashMap::new(),
+            last_sweep: Instant::now(),
+        }
+    }
+
+    // リクエストを受け付けてよいか判定する。拒否する場合は次に受け付けられるまでの時間を返す
+    fn check(&mut self, client: IpAddr, method: &str, now: Instant) -> Result<(), Duration> {
+        let Some(limit) = self.config.limit_for(method) else {
+            return Ok(());
+        };
+        if limit.requests == 0 {
+            return Err(limit.per);
+        }
+        self.sweep(now);
+
+        let capacity = limit.requests as f64;
+        let rate = capacity / limit.per.as_secs_f64();
+        let bucket = self
+            .buckets
+            .entry((client, method.to_string()))
+            .or_insert(Bucket {
+                tokens: capacity,
+                updated: now,
+            });
+
+        let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * rate).min(capacity);
+        bucket.updated = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - bucket.tokens) / rate))
+        }
+    }
+
+    // `per` 以上使われていないバケットは満タンに戻っており、作り直しても同じなので削除する
+    fn sweep(&mut self, now: Instant) {
+        if now.saturating_duration_since(self.last_sweep) < SWEEP_INTERVAL {
+            return;
+        }
+        self.last_sweep = now;
+
+        let config = &self.config;
+        self.buckets
+            .retain(|(_, method), bucket| match config.limit_for(method) {
+                Some(limit) => now.saturating_duration_since(bucket.updated) < limit.per,
+                None => false,
+            });
+    }
+}
+
+// `Server::builder().layer(...)` に渡すレート制限のLayer
+#[derive(Clone)]
+pub struct RateLimitLayer {
+    limiter: Arc<Mutex<RateLimiter>>,
+}
+
+impl RateLimitLayer {
+    pub fn new(config: RateLimitConfig) -> Self {
+        Self {
+            limiter: Arc::new(Mutex::new(RateLimiter::new(config))),
+        }
+    }
+}
+
+impl<S> Layer<S> for RateLimitLayer {
+    type Service = RateLimitService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RateLimitService {
+            inner,
+            limiter: self.limiter.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct RateLimitService<S> {
+    inner: S,
+    limiter: Arc<Mutex<RateLimiter>>,
+}
+
+impl<S, B> Service<Request<B>> for RateLimitService<S>
+where
+    S: Service<Request<B>, Response = Response<BoxBody>>,
+    S::Future: Send + 'static,
+{
+    type Response = Response<BoxBody>;
+    type Error = S::Error;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<B>) -> Self::Future {
+        // TCP以外（接続元が分からない）のリクエストは制限しない
+        let client = request
+            .extensions()
+            .get::<TcpConnectInfo>()
+            .and_then(|info| info.remote_addr())
+            .map(|addr| addr.ip());
+
+        if let Some(client) = client {
+            // パスは "/library.v1.LibraryService/SearchBooks" の形式
+            let method = request.uri().path().rsplit('/').next().unwrap_or_default();
+            let checked = self
+                .limiter
+                .lock()
+                .unwrap()
+                .check(client, method, Instant::now());
+
+            if let Err(retry_after) = checked {
+                let status = Status::resource_exhausted(format!(
+                    "rate limit exceeded for {}, retry after {}ms",
+                    method,
+                    retry_after.as_millis()
+                ));
+                return Box::pin(async move { Ok(status.to_http()) });
+            }
+        }
+
+        Box::pin(self.inner.call(request))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limiter() -> RateLimiter {
+        RateLimiter::new(
+            RateLimitConfig::new().with_method_limit("SearchBooks", RateLimit::per_minute(2)),
+        )
+    }
+
+    #[test]
+    fn limits_each_client_and_method_separately() {
+        let mut limiter = limiter();
+        let now = Instant::now();
+        let abusive: IpAddr = "10.0.0.1".parse().unwrap();
+        let other: IpAddr = "10.0.0.2".parse().unwrap();
+
+        assert!(limiter.check(abusive, "SearchBooks", now).is_ok());
+        assert!(limiter.check(abusive, "SearchBooks", now).is_ok());
+        assert_eq!(
+            limiter.check(abusive, "SearchBooks", now),
+            Err(Duration::from_secs(30))
+        );
+
+        // 他のクライアントや制限のないメソッドには影響しない
+        assert!(limiter.check(other, "SearchBooks", now).is_ok());
+        assert!(limiter.check(abusive, "GetUser", now).is_ok());
+
+        // 時間の経過とともに回復する
+        assert!(limiter
+            .check(abusive, "SearchBooks", now + Duration::from_secs(30))
+            .is_ok());
+    }
+
+    #[test]
+    fn idle_clients_are_swept() {
+        let mut limiter = limiter();
+        let now = Instant::now();
+        let idle: IpAddr = "10.0.0.1".parse().unwrap();
+        let active: IpAddr = "10.0.0.2".parse().unwrap();
+
+        limiter.check(idle, "SearchBooks", now).unwrap();
+        limiter
+            .check(active, "SearchBooks", now + Duration::from_secs(50))
+            .unwrap();
+        assert_eq!(limiter.buckets.len(), 2);
+
+        limiter
+            .check(
+                active,
+                "SearchBooks",
+                now + SWEEP_INTERVAL + Duration::from_secs(5),
+            )
+            .unwrap();
+        assert_eq!(limiter.buckets.len(), 1);
+        assert!(limiter
+            .buckets
+            .contains_key(&(active, "SearchBooks".to_string())));
+    }
+}